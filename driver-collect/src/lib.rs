@@ -6,28 +6,231 @@
 extern crate cfg_if;
 
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 pub use serial_frame::SerialDriver;
 
+/// The kernel virtual address of the default serial and the layout of its registers, the kernel
+/// moves them to the UART of the device tree with [set_serial]
+static SERIAL_BASE: AtomicUsize = AtomicUsize::new(DEFAULT_SERIAL_BASE);
+static SERIAL_REG_SHIFT: AtomicUsize = AtomicUsize::new(0);
+static SERIAL_REG_IO_WIDTH: AtomicUsize = AtomicUsize::new(1);
+
+/// Move the default serial to the UART at `base`, with registers `1 << reg_shift` bytes apart and
+/// accessed `reg_io_width` bytes at a time, one of [SERIAL_REG_IO_WIDTHS].
+///
+/// Nothing may be printed from here until the kernel has mapped the UART and called [init].
+pub fn set_serial(base: usize, reg_shift: usize, reg_io_width: usize) {
+    SERIAL_BASE.store(base, Ordering::Relaxed);
+    SERIAL_REG_SHIFT.store(reg_shift, Ordering::Relaxed);
+    SERIAL_REG_IO_WIDTH.store(reg_io_width, Ordering::Relaxed);
+}
+
+/// The base of the default serial, `None` while there is no UART.
+#[allow(unused)]
+fn serial_base() -> Option<NonNull<usize>> {
+    NonNull::new(SERIAL_BASE.load(Ordering::Relaxed) as _)
+}
+
+#[allow(unused)]
+fn serial_reg_shift() -> usize {
+    SERIAL_REG_SHIFT.load(Ordering::Relaxed)
+}
+
+#[allow(unused)]
+fn serial_reg_io_width() -> usize {
+    SERIAL_REG_IO_WIDTH.load(Ordering::Relaxed)
+}
+
 cfg_if! {
-    if #[cfg(target_arch = "aarch64")] {
+    if #[cfg(all(feature = "SERIAL_NS16550", target_arch = "riscv64"))] {
+        use serial_impl_ns16550::Ns16550Uart;
+        use serial_impl_sbi::SerialSBI;
+
+        /// The compatible strings of the UARTs the default serial drives
+        pub const SERIAL_COMPATIBLES: &[&str] = &["ns16550a", "ns16550"];
+        /// The register access widths, in bytes, the default serial drives
+        pub const SERIAL_REG_IO_WIDTHS: &[usize] = Ns16550Uart::REG_IO_WIDTHS;
+        /// There is no UART mapped until the kernel has read the device tree
+        const DEFAULT_SERIAL_BASE: usize = 0;
+
+        /// The default serial of RISC-V, the SBI console until the kernel has mapped the UART
+        pub enum DefaultSerial {
+            Sbi(SerialSBI),
+            Ns16550(Ns16550Uart),
+        }
+
+        impl SerialDriver for DefaultSerial {
+            fn new(addr: NonNull<usize>) -> Self {
+                DefaultSerial::Ns16550(Ns16550Uart::with_reg_layout(addr, serial_reg_shift(), serial_reg_io_width()))
+            }
+
+            fn init(&self) {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.init(),
+                    DefaultSerial::Ns16550(serial) => serial.init(),
+                }
+            }
+
+            fn putchar(&self, c: u8) {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.putchar(c),
+                    DefaultSerial::Ns16550(serial) => serial.putchar(c),
+                }
+            }
+
+            fn getchar(&self) -> Option<u8> {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.getchar(),
+                    DefaultSerial::Ns16550(serial) => serial.getchar(),
+                }
+            }
+
+            fn set_rx_interrupt(&self, enable: bool) {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.set_rx_interrupt(enable),
+                    DefaultSerial::Ns16550(serial) => serial.set_rx_interrupt(enable),
+                }
+            }
+
+            fn is_rx_interrupt(&self) -> bool {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.is_rx_interrupt(),
+                    DefaultSerial::Ns16550(serial) => serial.is_rx_interrupt(),
+                }
+            }
+
+            fn ack_interrupts(&self) {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.ack_interrupts(),
+                    DefaultSerial::Ns16550(serial) => serial.ack_interrupts(),
+                }
+            }
+
+            fn rx_fifo_empty(&self) -> bool {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.rx_fifo_empty(),
+                    DefaultSerial::Ns16550(serial) => serial.rx_fifo_empty(),
+                }
+            }
+
+            fn tx_fifo_full(&self) -> bool {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.tx_fifo_full(),
+                    DefaultSerial::Ns16550(serial) => serial.tx_fifo_full(),
+                }
+            }
+
+            fn write(&self, buf: &[u8]) -> usize {
+                match self {
+                    DefaultSerial::Sbi(serial) => serial.write(buf),
+                    DefaultSerial::Ns16550(serial) => serial.write(buf),
+                }
+            }
+        }
+
+        /// Initialize Default Serial Driver
+        pub fn default_serial() -> impl SerialDriver {
+            match serial_base() {
+                Some(base) => DefaultSerial::new(base),
+                None => DefaultSerial::Sbi(SerialSBI),
+            }
+        }
+    } else if #[cfg(all(feature = "SERIAL_NS16550", target_arch = "aarch64"))] {
+        use serial_impl_ns16550::Ns16550Uart;
+
+        /// The compatible strings of the UARTs the default serial drives
+        pub const SERIAL_COMPATIBLES: &[&str] = &["ns16550a", "ns16550"];
+        /// The register access widths, in bytes, the default serial drives
+        pub const SERIAL_REG_IO_WIDTHS: &[usize] = Ns16550Uart::REG_IO_WIDTHS;
+        #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+        const DEFAULT_SERIAL_BASE: usize = 0xffffffffffe00000;
+        /// The kernel device window of an EL2 kernel is in the lower half
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        const DEFAULT_SERIAL_BASE: usize = 0x000000ffffe00000;
+
+        /// Initialize Default Serial Driver
+        pub fn default_serial() -> impl SerialDriver {
+            Ns16550Uart::with_reg_layout(
+                serial_base().unwrap(),
+                serial_reg_shift(),
+                serial_reg_io_width(),
+            )
+        }
+    } else if #[cfg(target_arch = "aarch64")] {
         /// Use cfg(driver = "pl011") in the future.
         use serial_impl_pl011::Pl011Uart;
-        /// Initialize Default Serial Driver
-        // pub static DEFAULT_SERIAL: &dyn SerialDriver = Pl011Uart::new(unsafe { NonNull::new_unchecked(0x900_0000 as _) });
 
+        /// The compatible strings of the UARTs the default serial drives
+        pub const SERIAL_COMPATIBLES: &[&str] = &["arm,pl011"];
+        /// The registers of the PL011 are 32 bits wide
+        pub const SERIAL_REG_IO_WIDTHS: &[usize] = &[4];
+        #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+        const DEFAULT_SERIAL_BASE: usize = 0xffffffffffe00000;
+        /// The kernel device window of an EL2 kernel is in the lower half
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        const DEFAULT_SERIAL_BASE: usize = 0x000000ffffe00000;
+
+        /// Initialize Default Serial Driver
         pub fn default_serial() -> impl SerialDriver {
-            // Pl011Uart::new(unsafe { NonNull::new_unchecked(0x900_0000 as _) })
-            Pl011Uart::new(unsafe { NonNull::new_unchecked(0xffffffffffe00000usize as _) })
+            Pl011Uart::new(serial_base().unwrap())
         }
     } else if #[cfg(target_arch = "riscv64")] {
         use serial_impl_sbi::SerialSBI;
 
+        /// The SBI console is not a UART of the device tree
+        pub const SERIAL_COMPATIBLES: &[&str] = &[];
+        pub const SERIAL_REG_IO_WIDTHS: &[usize] = &[];
+        const DEFAULT_SERIAL_BASE: usize = 0;
+
         /// Initialize Default Serial Driver
         pub fn default_serial() -> impl SerialDriver {
             // 0xf is a random number, the argument of this function will never be used
             SerialSBI::new(unsafe { NonNull::new_unchecked(0xf as _) })
         }
+    } else {
+        /// The serial of the host backend used by the unit tests, the output is dropped
+        pub struct HostSerial;
+
+        impl SerialDriver for HostSerial {
+            fn new(_addr: NonNull<usize>) -> Self {
+                HostSerial
+            }
+
+            fn init(&self) {}
+
+            fn putchar(&self, _c: u8) {}
+
+            fn getchar(&self) -> Option<u8> {
+                None
+            }
+
+            fn set_rx_interrupt(&self, _enable: bool) {}
+
+            fn is_rx_interrupt(&self) -> bool {
+                false
+            }
+
+            fn ack_interrupts(&self) {}
+
+            fn rx_fifo_empty(&self) -> bool {
+                true
+            }
+
+            fn tx_fifo_full(&self) -> bool {
+                false
+            }
+        }
+
+        /// The output of the host backend goes nowhere
+        pub const SERIAL_COMPATIBLES: &[&str] = &[];
+        pub const SERIAL_REG_IO_WIDTHS: &[usize] = &[];
+        const DEFAULT_SERIAL_BASE: usize = 0;
+
+        /// Initialize Default Serial Driver
+        pub fn default_serial() -> impl SerialDriver {
+            HostSerial::new(NonNull::dangling())
+        }
     }
 }
 
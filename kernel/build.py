@@ -25,6 +25,10 @@ def parse_args():
     parser.add_argument('-p', '--platform', dest='platform', default='spike', help="set-platform")
     parser.add_argument('-c', '--cpu', dest="cpu_nums", type=int,
                         help="kernel & qemu cpu nums", default=1)
+    parser.add_argument('--track-kernel-entries', dest="track_kernel_entries", action="store_true",
+                        help="record the kernel entries in the benchmark log buffer")
+    parser.add_argument('--track-utilisation', dest="track_utilisation", action="store_true",
+                        help="account the CPU utilisation of the threads")
     args = parser.parse_args()
     return args
 
@@ -43,8 +47,13 @@ if __name__ == "__main__":
     progname = sys.argv[0]
 
     target = ""
+    features = []
     if args.platform == "spike":
         target = "riscv64imac-unknown-none-elf"
+    elif args.platform == "qemu-riscv-virt":
+        target = "riscv64imac-unknown-none-elf"
+        features.append("PLAT_QEMU_RISCV_VIRT")
+        features.append("SERIAL_NS16550")
     elif args.platform == "qemu-arm-virt":
         target = "aarch64-unknown-none-softfloat"
     
@@ -58,13 +67,15 @@ if __name__ == "__main__":
             sys.exit(-1)
     else:
         if args.cpu_nums > 1:
-            if not exec_shell(f"cargo build --release --target {target} --features ENABLE_SMP"):
-                clean_config()
-                sys.exit(-1)
-        else:
-            if not exec_shell(f"cargo build --release --target {target}"):
-                clean_config()
-                sys.exit(-1)
+            features.append("ENABLE_SMP")
+        if args.track_kernel_entries:
+            features.append("BENCHMARK_TRACK_KERNEL_ENTRIES")
+        if args.track_utilisation:
+            features.append("BENCHMARK_TRACK_UTILISATION")
+        feature_args = f" --features {','.join(features)}" if features else ""
+        if not exec_shell(f"cargo build --release --target {target}{feature_args}"):
+            clean_config()
+            sys.exit(-1)
     
     if args.cpu_nums > 1:
         shell_command = f"cd ./build && ../../init-build.sh  -DPLATFORM={args.platform} -DSIMULATION=TRUE -DSMP=TRUE && ninja"
@@ -1,8 +1,16 @@
 // pub const GIC_V2_PPTR: usize = 0x8000000;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 pub const GIC_V2_PPTR: usize = 0xffffffffffe00000;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const GIC_V2_PPTR: usize = 0x000000ffffe00000;
 pub const GIC_V2_DISTRIBUTOR_PPTR: usize = GIC_V2_PPTR + 0x1000;
 pub const GIC_V2_CONTROLLER_PPTR: usize = GIC_V2_PPTR + 0x2000;
+/// The virtual interface control of the GIC, the hypervisor view of the list registers
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const GIC_V2_VCPUCTRL_PPTR: usize = GIC_V2_PPTR + 0x3000;
 
 pub const IRQ_SET_ALL: u32 = 0xffffffff;
 pub const IRQ_MASK: u32 = (1 << (10)) - 1;
 pub const IRQ_NONE: u32 = 1023;
+/// The ids from here on are special, 1022 and 1023 are both spurious
+pub const SPECIAL_IRQ_START: u32 = 1020;
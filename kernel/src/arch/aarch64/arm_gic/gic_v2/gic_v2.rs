@@ -1,10 +1,17 @@
 use super::consts::*;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use super::Gic_VCpu_Ctrl_Map;
 use super::{Gic_Cpu_Iface_Map, Gic_Dist_Map};
+use crate::arch::arm_gic::GicDriver;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::arm_gic::VGicDriver;
 use aarch64_cpu::registers::Readable;
 use tock_registers::interfaces::Writeable;
 
 static GIC_DIST: Gic_Dist_Map = Gic_Dist_Map::new(GIC_V2_DISTRIBUTOR_PPTR as *mut u8);
 static GIC_CPUIFACE: Gic_Cpu_Iface_Map = Gic_Cpu_Iface_Map::new(GIC_V2_CONTROLLER_PPTR as *mut u8);
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+static GIC_VCPUCTRL: Gic_VCpu_Ctrl_Map = Gic_VCpu_Ctrl_Map::new(GIC_V2_VCPUCTRL_PPTR as *mut u8);
 
 // This is for aarch64 only
 pub fn cpu_iface_init() {
@@ -41,6 +48,13 @@ pub fn irq_enable(irq: usize) {
     GIC_DIST.regs().enable_set[word].set(1 << bits);
 }
 
+/// Disable the IRQ
+pub fn irq_disable(irq: usize) {
+    let word = irq >> 5;
+    let bits = (irq & 0x1f) as u32;
+    GIC_DIST.regs().enable_clr[word].set(1 << bits);
+}
+
 /// Get the current interrupt number
 pub fn gic_int_ack() -> usize {
     GIC_CPUIFACE.regs().int_ack.get() as usize
@@ -51,6 +65,31 @@ pub fn ack_irq(irq: usize) {
     GIC_CPUIFACE.regs().eoi.set(irq as _);
 }
 
+/// Check the highest priority pending interrupt, reading GICC_HPPIR doesn't acknowledge it.
+/// GICC_HPPIR reads 1022 for a pending group 1 interrupt the kernel can't take.
+pub fn is_irq_pending() -> bool {
+    (GIC_CPUIFACE.regs().hi_pend.get() & IRQ_MASK) < SPECIAL_IRQ_START
+}
+
+/// Route the SPI to the CPU interface of the given core
+pub fn set_irq_target(irq: usize, cpu: usize) {
+    if irq < 32 {
+        log::warn!("GICv2: local interrupt {} can't be routed", irq);
+        return;
+    }
+    let shift = (irq & 0x3) * 8;
+    let targets = GIC_DIST.regs().targets[irq >> 2].get() & !(0xff << shift);
+    GIC_DIST.regs().targets[irq >> 2].set(targets | ((1 << cpu) << shift));
+}
+
+/// Send the SGI to every core in `target_mask`
+pub fn send_sgi(irq: usize, target_mask: usize) {
+    GIC_DIST
+        .regs()
+        .sgi_control
+        .set((((target_mask & 0xff) << 16) | (irq & 0xf)) as u32);
+}
+
 pub fn dist_init() {
     let nirqs = 32 * ((GIC_DIST.regs().ic_type.get() & 0x1f) + 1) as usize;
 
@@ -169,3 +208,130 @@ fn infer_cpu_gic_id(nirqs: usize) -> u8 {
 fn TARGET_CPU_ALLINT(CPU: u8) -> u32 {
     ((CPU as u32) << 0) | ((CPU as u32) << 8) | ((CPU as u32) << 16) | ((CPU as u32) << 24)
 }
+
+pub struct GicV2;
+
+impl GicDriver for GicV2 {
+    const IRQ_MASK: usize = IRQ_MASK as usize;
+
+    fn dist_init() {
+        dist_init();
+    }
+
+    fn cpu_iface_init() {
+        cpu_initLocalIRQController();
+    }
+
+    fn get_active_irq() -> usize {
+        gic_int_ack()
+    }
+
+    fn ack_irq(irq: usize) {
+        ack_irq(irq);
+    }
+
+    fn is_irq_pending() -> bool {
+        is_irq_pending()
+    }
+
+    fn mask_irq(disable: bool, irq: usize) {
+        if disable {
+            irq_disable(irq);
+        } else {
+            irq_enable(irq);
+        }
+    }
+
+    fn set_irq_target(irq: usize, cpu: usize) {
+        set_irq_target(irq, cpu);
+    }
+
+    fn send_sgi(irq: usize, target_mask: usize) {
+        send_sgi(irq, target_mask);
+    }
+}
+
+/// GICH_VTR.ListRegs, the number of list registers minus one
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VGIC_VTR_NLISTREGS_MASK: u32 = 0x3f;
+/// GICH_LR.VirtualID
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_VID_MASK: u64 = 0x3ff;
+/// GICH_LR.EOI, the bit 9 of PhysicalID when GICH_LR.HW is clear
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_EOI_IRQ_EN: u64 = 1 << 19;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_PRIORITY_SHIFT: usize = 23;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_SHIFT: usize = 28;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_MASK: u64 = 0x3;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_PENDING: u64 = 0x1;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_ACTIVE: u64 = 0x2;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_GROUP_SHIFT: usize = 30;
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+impl VGicDriver for GicV2 {
+    fn num_list_regs() -> usize {
+        ((GIC_VCPUCTRL.regs().vtr.get() & VGIC_VTR_NLISTREGS_MASK) + 1) as usize
+    }
+
+    fn get_hcr() -> u32 {
+        GIC_VCPUCTRL.regs().hcr.get()
+    }
+
+    fn set_hcr(hcr: u32) {
+        GIC_VCPUCTRL.regs().hcr.set(hcr);
+    }
+
+    fn get_vmcr() -> u32 {
+        GIC_VCPUCTRL.regs().vmcr.get()
+    }
+
+    fn set_vmcr(vmcr: u32) {
+        GIC_VCPUCTRL.regs().vmcr.set(vmcr);
+    }
+
+    fn get_apr() -> u32 {
+        GIC_VCPUCTRL.regs().apr.get()
+    }
+
+    fn set_apr(apr: u32) {
+        GIC_VCPUCTRL.regs().apr.set(apr);
+    }
+
+    fn get_misr() -> u32 {
+        GIC_VCPUCTRL.regs().misr.get()
+    }
+
+    fn get_eisr() -> u64 {
+        GIC_VCPUCTRL.regs().eisr0.get() as u64 | (GIC_VCPUCTRL.regs().eisr1.get() as u64) << 32
+    }
+
+    fn get_lr(index: usize) -> u64 {
+        GIC_VCPUCTRL.regs().lr[index].get() as u64
+    }
+
+    fn set_lr(index: usize, lr: u64) {
+        GIC_VCPUCTRL.regs().lr[index].set(lr as u32);
+    }
+
+    fn virq_pending_new(group: usize, priority: usize, vid: usize) -> u64 {
+        ((group as u64 & 0x1) << VIRQ_GROUP_SHIFT)
+            | (VIRQ_STATE_PENDING << VIRQ_STATE_SHIFT)
+            | ((priority as u64 & 0x1f) << VIRQ_PRIORITY_SHIFT)
+            | VIRQ_EOI_IRQ_EN
+            | (vid as u64 & VIRQ_VID_MASK)
+    }
+
+    fn virq_is_active(lr: u64) -> bool {
+        (lr >> VIRQ_STATE_SHIFT) & VIRQ_STATE_MASK == VIRQ_STATE_ACTIVE
+    }
+
+    fn virq_clear_eoi_irq(lr: u64) -> u64 {
+        lr & !VIRQ_EOI_IRQ_EN
+    }
+}
@@ -2,7 +2,6 @@ pub mod consts;
 pub mod gic_v2;
 
 use core::ptr::NonNull;
-pub use gic_v2::irq_enable;
 use tock_registers::register_structs;
 use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
 
@@ -85,6 +84,29 @@ register_structs! {
     }
 }
 
+register_structs! {
+    /// GIC virtual interface control registers.
+    #[allow(non_snake_case)]
+    pub Gic_VCpu_Ctrl_Map_Regs {
+        (0x0000 => hcr: ReadWrite<u32>),
+        (0x0004 => vtr: ReadOnly<u32>),
+        (0x0008 => vmcr: ReadWrite<u32>),
+        (0x000c => _reserved_1),
+        (0x0010 => misr: ReadOnly<u32>),
+        (0x0014 => _reserved_2),
+        (0x0020 => eisr0: ReadOnly<u32>),
+        (0x0024 => eisr1: ReadOnly<u32>),
+        (0x0028 => _reserved_3),
+        (0x0030 => elsr0: ReadOnly<u32>),
+        (0x0034 => elsr1: ReadOnly<u32>),
+        (0x0038 => _reserved_4),
+        (0x00f0 => apr: ReadWrite<u32>),
+        (0x00f4 => _reserved_5),
+        (0x0100 => lr: [ReadWrite<u32>; 64]),
+        (0x0200 => @END),
+    }
+}
+
 pub struct Gic_Dist_Map {
     base: NonNull<Gic_Dist_Map_Regs>,
 }
@@ -99,6 +121,30 @@ unsafe impl Sync for Gic_Dist_Map {}
 unsafe impl Send for Gic_Cpu_Iface_Map {}
 unsafe impl Sync for Gic_Cpu_Iface_Map {}
 
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub struct Gic_VCpu_Ctrl_Map {
+    base: NonNull<Gic_VCpu_Ctrl_Map_Regs>,
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+unsafe impl Send for Gic_VCpu_Ctrl_Map {}
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+unsafe impl Sync for Gic_VCpu_Ctrl_Map {}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+impl Gic_VCpu_Ctrl_Map {
+    /// Construct a new GIC virtual interface control instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &Gic_VCpu_Ctrl_Map_Regs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
 impl Gic_Dist_Map {
     /// Construct a new GIC distributor instance from the base address.
     pub const fn new(base: *mut u8) -> Self {
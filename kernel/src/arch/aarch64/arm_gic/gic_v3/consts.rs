@@ -1 +1,55 @@
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub const GIC_V3_PPTR: usize = 0xffffffffffe00000;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const GIC_V3_PPTR: usize = 0x000000ffffe00000;
+/// The distributor takes 64KiB of the kernel device window
+pub const GIC_V3_DISTRIBUTOR_PPTR: usize = GIC_V3_PPTR + 0x1000;
+/// Only the first page of the RD_base and SGI_base frames of each core is mapped,
+/// so the redistributors are `GIC_V3_REDIST_STRIDE` apart in the device window.
+pub const GIC_V3_REDISTRIBUTOR_PPTR: usize = GIC_V3_DISTRIBUTOR_PPTR + 0x10000;
+pub const GIC_V3_REDIST_STRIDE: usize = 0x2000;
+pub const GIC_V3_SGI_BASE_OFFSET: usize = 0x1000;
 
+pub const IRQ_SET_ALL: u32 = 0xffffffff;
+pub const IRQ_MASK: u32 = (1 << 16) - 1;
+pub const IRQ_NONE: u32 = 1023;
+
+/// SGIs and PPIs are banked per core in the redistributor
+pub const NR_GIC_LOCAL_IRQS: usize = 32;
+pub const NR_GIC_SGI: usize = 16;
+pub const GIC_PRI_IRQ: u32 = 0xa0;
+pub const GIC_PRI_ALL_IRQ: u32 =
+    (GIC_PRI_IRQ << 24) | (GIC_PRI_IRQ << 16) | (GIC_PRI_IRQ << 8) | GIC_PRI_IRQ;
+pub const DEFAULT_PMR_VALUE: usize = 0xff;
+
+pub const GICD_CTLR_RWP: u32 = 1 << 31;
+pub const GICD_CTLR_ARE_NS: u32 = 1 << 4;
+pub const GICD_CTLR_ENABLE_G1NS: u32 = 1 << 1;
+pub const GICD_CTLR_ENABLE_G0: u32 = 1 << 0;
+pub const GICD_TYPE_LINESNR: u32 = 0x1f;
+
+pub const GICR_CTLR_RWP: u32 = 1 << 3;
+pub const GICR_WAKER_PROCESSOR_SLEEP: u32 = 1 << 1;
+pub const GICR_WAKER_CHILDREN_ASLEEP: u32 = 1 << 2;
+pub const GICR_TYPER_LAST: u64 = 1 << 4;
+
+pub const ICC_SRE_EL1_SRE: usize = 1 << 0;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const ICC_SRE_EL2_SRE: usize = 1 << 0;
+/// Let EL1 use ICC_SRE_EL1
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const ICC_SRE_EL2_ENABLE: usize = 1 << 3;
+pub const ICC_CTLR_EL1_EOIMODE_DROP: usize = 1 << 1;
+
+pub const ICC_SGI1R_TARGET_LIST_MASK: usize = 0xffff;
+pub const ICC_SGI1R_AFFINITY1_SHIFT: usize = 16;
+pub const ICC_SGI1R_INT_ID_SHIFT: usize = 24;
+pub const ICC_SGI1R_AFFINITY2_SHIFT: usize = 32;
+pub const ICC_SGI1R_RS_SHIFT: usize = 44;
+pub const ICC_SGI1R_AFFINITY3_SHIFT: usize = 48;
+
+/// Aff3, Aff2, Aff1 and Aff0 fields of MPIDR_EL1, which is also the GICD_IROUTER layout
+pub const MPIDR_AFFINITY_MASK: usize = 0xff_00ff_ffff;
+
+/// Max number of loops waiting for a register write to be observed by the GIC
+pub const GIC_RWP_TIMEOUT: usize = 0x100000;
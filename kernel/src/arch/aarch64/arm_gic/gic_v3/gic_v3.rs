@@ -1 +1,408 @@
+use super::consts::*;
+use super::{Gicd_Map, Gicr_Rd_Map, Gicr_Sgi_Map};
+use crate::arch::arm_gic::GicDriver;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::arm_gic::VGicDriver;
+use aarch64_cpu::registers::MPIDR_EL1;
+use core::arch::asm;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+use sel4_common::BIT;
+use tock_registers::interfaces::{Readable, Writeable};
+use tock_registers::registers::ReadWrite;
 
+static GIC_DIST: Gicd_Map = Gicd_Map::new(GIC_V3_DISTRIBUTOR_PPTR as *mut u8);
+
+/// The RD_base of the redistributor used by each core
+static mut GIC_RDIST_PPTR: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+/// The affinity of each core in the GICD_IROUTER layout, used for SGIs and routing
+static mut GIC_MPIDR_MAP: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+macro_rules! read_sysreg {
+    ($reg:literal) => {{
+        let value: usize;
+        unsafe { asm!(concat!("mrs {}, ", $reg), out(reg) value) };
+        value
+    }};
+}
+
+macro_rules! write_sysreg {
+    ($reg:literal, $value:expr) => {{
+        let value: usize = $value;
+        unsafe { asm!(concat!("msr ", $reg, ", {}"), in(reg) value) };
+    }};
+}
+
+#[inline]
+fn isb() {
+    unsafe { asm!("isb") };
+}
+
+#[inline]
+fn gic_rdist() -> Gicr_Rd_Map {
+    Gicr_Rd_Map::new(unsafe { GIC_RDIST_PPTR[cpu_id()] } as *mut u8)
+}
+
+#[inline]
+fn gic_rdist_sgi() -> Gicr_Sgi_Map {
+    Gicr_Sgi_Map::new(unsafe { GIC_RDIST_PPTR[cpu_id()] + GIC_V3_SGI_BASE_OFFSET } as *mut u8)
+}
+
+/// Wait until the GIC has observed the previous writes to the control register
+fn wait_for_rwp(ctlr: &ReadWrite<u32>, rwp: u32) {
+    for _ in 0..GIC_RWP_TIMEOUT {
+        if ctlr.get() & rwp == 0 {
+            return;
+        }
+    }
+    log::warn!("GICv3: RWP timeout");
+}
+
+/// Get the affinity of the current core in the GICD_IROUTER layout
+fn mpidr_to_gic_affinity() -> usize {
+    MPIDR_EL1.get() as usize & MPIDR_AFFINITY_MASK
+}
+
+/// Convert an affinity in the GICD_IROUTER layout to the Aff3.Aff2.Aff1.Aff0 layout of GICR_TYPER
+fn gicr_typer_affinity(affinity: usize) -> u64 {
+    ((affinity & 0xff_ffff) | (((affinity >> 32) & 0xff) << 24)) as u64
+}
+
+/// Find the redistributor of the current core among the mapped ones
+fn gicr_locate_interface() {
+    let affinity = gicr_typer_affinity(mpidr_to_gic_affinity());
+    for i in 0..CONFIG_MAX_NUM_NODES {
+        let pptr = GIC_V3_REDISTRIBUTOR_PPTR + i * GIC_V3_REDIST_STRIDE;
+        let typer = Gicr_Rd_Map::new(pptr as *mut u8).regs().typer.get();
+        if typer >> 32 == affinity {
+            unsafe {
+                GIC_RDIST_PPTR[cpu_id()] = pptr;
+            }
+            return;
+        }
+        if typer & GICR_TYPER_LAST != 0 {
+            break;
+        }
+    }
+    panic!(
+        "GICv3: no redistributor for core {} (affinity {:#x})",
+        cpu_id(),
+        affinity
+    );
+}
+
+/// Wake up and initialise the redistributor of the current core
+fn gicr_init() {
+    gicr_locate_interface();
+
+    let rdist = gic_rdist();
+    let waker = rdist.regs().waker.get();
+    rdist.regs().waker.set(waker & !GICR_WAKER_PROCESSOR_SLEEP);
+    wait_for_rwp(&rdist.regs().waker, GICR_WAKER_CHILDREN_ASLEEP);
+
+    let sgi = gic_rdist_sgi();
+    // Deactivate SGIs and PPIs
+    sgi.regs().icactiver0.set(IRQ_SET_ALL);
+    for i in (0..NR_GIC_LOCAL_IRQS).step_by(4) {
+        sgi.regs().ipriorityr[i >> 2].set(GIC_PRI_ALL_IRQ);
+    }
+    sgi.regs().igroupr0.set(IRQ_SET_ALL);
+    // Disable all PPIs and enable all SGIs
+    sgi.regs().icenabler0.set(0xffff0000);
+    sgi.regs().isenabler0.set(0x0000ffff);
+    // PPIs are level triggered
+    sgi.regs().icfgr1.set(0);
+    wait_for_rwp(&rdist.regs().ctlr, GICR_CTLR_RWP);
+}
+
+/// Enable the system register interface of the current core
+fn cpu_iface_init() {
+    let sre = read_sysreg!("icc_sre_el1");
+    write_sysreg!("icc_sre_el1", sre | ICC_SRE_EL1_SRE);
+    // the guests reach their virtual CPU interface through the system registers too
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    {
+        let sre = read_sysreg!("icc_sre_el2");
+        write_sysreg!("icc_sre_el2", sre | ICC_SRE_EL2_SRE | ICC_SRE_EL2_ENABLE);
+    }
+    isb();
+
+    // No priority grouping
+    write_sysreg!("icc_bpr1_el1", 0);
+    write_sysreg!("icc_pmr_el1", DEFAULT_PMR_VALUE);
+    // EOI drops the priority and deactivates the interrupt
+    let ctlr = read_sysreg!("icc_ctlr_el1");
+    write_sysreg!("icc_ctlr_el1", ctlr & !ICC_CTLR_EL1_EOIMODE_DROP);
+    write_sysreg!("icc_igrpen1_el1", 1);
+    isb();
+
+    unsafe {
+        GIC_MPIDR_MAP[cpu_id()] = mpidr_to_gic_affinity();
+    }
+}
+
+pub fn cpu_initLocalIRQController() {
+    gicr_init();
+    cpu_iface_init();
+}
+
+pub fn dist_init() {
+    let nirqs = 32 * ((GIC_DIST.regs().typer.get() & GICD_TYPE_LINESNR) + 1) as usize;
+
+    GIC_DIST.regs().ctlr.set(0);
+    wait_for_rwp(&GIC_DIST.regs().ctlr, GICD_CTLR_RWP);
+
+    // Level triggered
+    for i in (NR_GIC_LOCAL_IRQS..nirqs).step_by(16) {
+        GIC_DIST.regs().icfgr[i >> 4].set(0);
+    }
+
+    for i in (NR_GIC_LOCAL_IRQS..nirqs).step_by(4) {
+        GIC_DIST.regs().ipriorityr[i >> 2].set(GIC_PRI_ALL_IRQ);
+    }
+
+    for i in (NR_GIC_LOCAL_IRQS..nirqs).step_by(32) {
+        GIC_DIST.regs().icenabler[i >> 5].set(IRQ_SET_ALL);
+        GIC_DIST.regs().icpendr[i >> 5].set(IRQ_SET_ALL);
+        GIC_DIST.regs().igroupr[i >> 5].set(IRQ_SET_ALL);
+    }
+
+    GIC_DIST
+        .regs()
+        .ctlr
+        .set(GICD_CTLR_ARE_NS | GICD_CTLR_ENABLE_G1NS | GICD_CTLR_ENABLE_G0);
+    wait_for_rwp(&GIC_DIST.regs().ctlr, GICD_CTLR_RWP);
+
+    // Route all the global interrupts to the boot core
+    let affinity = mpidr_to_gic_affinity() as u64;
+    for i in NR_GIC_LOCAL_IRQS..nirqs.min(GIC_DIST.regs().irouter.len()) {
+        GIC_DIST.regs().irouter[i].set(affinity);
+    }
+}
+
+/// Enable the interrupt
+pub fn irq_enable(irq: usize) {
+    let bit = 1 << (irq & 0x1f);
+    if irq < NR_GIC_LOCAL_IRQS {
+        gic_rdist_sgi().regs().isenabler0.set(bit);
+    } else {
+        GIC_DIST.regs().isenabler[irq >> 5].set(bit);
+    }
+}
+
+/// Disable the interrupt, it won't be signalled once this returns
+pub fn irq_disable(irq: usize) {
+    let bit = 1 << (irq & 0x1f);
+    if irq < NR_GIC_LOCAL_IRQS {
+        gic_rdist_sgi().regs().icenabler0.set(bit);
+        wait_for_rwp(&gic_rdist().regs().ctlr, GICR_CTLR_RWP);
+    } else {
+        GIC_DIST.regs().icenabler[irq >> 5].set(bit);
+        wait_for_rwp(&GIC_DIST.regs().ctlr, GICD_CTLR_RWP);
+    }
+}
+
+/// Get the current interrupt number
+pub fn gic_int_ack() -> usize {
+    read_sysreg!("icc_iar1_el1")
+}
+
+/// Acknowledge the interrupt
+pub fn ack_irq(irq: usize) {
+    write_sysreg!("icc_eoir1_el1", irq);
+    isb();
+}
+
+/// Check the highest priority pending group 1 interrupt, reading ICC_HPPIR1_EL1 doesn't
+/// acknowledge it
+pub fn is_irq_pending() -> bool {
+    (read_sysreg!("icc_hppir1_el1") as u32 & IRQ_MASK) != IRQ_NONE
+}
+
+/// Route the SPI to the given core
+pub fn set_irq_target(irq: usize, cpu: usize) {
+    if irq < NR_GIC_LOCAL_IRQS {
+        log::warn!("GICv3: local interrupt {} can't be routed", irq);
+        return;
+    }
+    let affinity = unsafe { GIC_MPIDR_MAP[cpu] };
+    GIC_DIST.regs().irouter[irq].set(affinity as u64);
+}
+
+/// Send the SGI to every core in `target_mask`
+pub fn send_sgi(irq: usize, target_mask: usize) {
+    assert!(irq < NR_GIC_SGI);
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        if target_mask & BIT!(cpu) == 0 {
+            continue;
+        }
+        let affinity = unsafe { GIC_MPIDR_MAP[cpu] };
+        let aff0 = affinity & 0xff;
+        let sgi1r = (((affinity >> 32) & 0xff) << ICC_SGI1R_AFFINITY3_SHIFT)
+            | (((affinity >> 16) & 0xff) << ICC_SGI1R_AFFINITY2_SHIFT)
+            | (((affinity >> 8) & 0xff) << ICC_SGI1R_AFFINITY1_SHIFT)
+            | ((aff0 >> 4) << ICC_SGI1R_RS_SHIFT)
+            | (irq << ICC_SGI1R_INT_ID_SHIFT)
+            | (BIT!(aff0 & 0xf) & ICC_SGI1R_TARGET_LIST_MASK);
+        write_sysreg!("icc_sgi1r_el1", sgi1r);
+    }
+    isb();
+}
+
+pub struct GicV3;
+
+impl GicDriver for GicV3 {
+    const IRQ_MASK: usize = IRQ_MASK as usize;
+
+    fn dist_init() {
+        dist_init();
+    }
+
+    fn cpu_iface_init() {
+        cpu_initLocalIRQController();
+    }
+
+    fn get_active_irq() -> usize {
+        gic_int_ack()
+    }
+
+    fn ack_irq(irq: usize) {
+        ack_irq(irq);
+    }
+
+    fn is_irq_pending() -> bool {
+        is_irq_pending()
+    }
+
+    fn mask_irq(disable: bool, irq: usize) {
+        if disable {
+            irq_disable(irq);
+        } else {
+            irq_enable(irq);
+        }
+    }
+
+    fn set_irq_target(irq: usize, cpu: usize) {
+        set_irq_target(irq, cpu);
+    }
+
+    fn send_sgi(irq: usize, target_mask: usize) {
+        send_sgi(irq, target_mask);
+    }
+}
+
+/// ICH_VTR_EL2.ListRegs, the number of list registers minus one
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VGIC_VTR_NLISTREGS_MASK: usize = 0x1f;
+/// ICH_LR<n>_EL2.vINTID
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_VID_MASK: u64 = 0xffff_ffff;
+/// ICH_LR<n>_EL2.EOI, when ICH_LR<n>_EL2.HW is clear
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_EOI_IRQ_EN: u64 = 1 << 41;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_PRIORITY_SHIFT: usize = 48;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_GROUP_SHIFT: usize = 60;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_SHIFT: usize = 62;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_MASK: u64 = 0x3;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_PENDING: u64 = 0x1;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VIRQ_STATE_ACTIVE: u64 = 0x2;
+
+/// Each list register is a system register of its own
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+macro_rules! ich_lr {
+    ($index:expr, $op:ident $(, $value:expr)?) => {
+        match $index {
+            0 => $op!("ich_lr0_el2" $(, $value)?),
+            1 => $op!("ich_lr1_el2" $(, $value)?),
+            2 => $op!("ich_lr2_el2" $(, $value)?),
+            3 => $op!("ich_lr3_el2" $(, $value)?),
+            4 => $op!("ich_lr4_el2" $(, $value)?),
+            5 => $op!("ich_lr5_el2" $(, $value)?),
+            6 => $op!("ich_lr6_el2" $(, $value)?),
+            7 => $op!("ich_lr7_el2" $(, $value)?),
+            8 => $op!("ich_lr8_el2" $(, $value)?),
+            9 => $op!("ich_lr9_el2" $(, $value)?),
+            10 => $op!("ich_lr10_el2" $(, $value)?),
+            11 => $op!("ich_lr11_el2" $(, $value)?),
+            12 => $op!("ich_lr12_el2" $(, $value)?),
+            13 => $op!("ich_lr13_el2" $(, $value)?),
+            14 => $op!("ich_lr14_el2" $(, $value)?),
+            15 => $op!("ich_lr15_el2" $(, $value)?),
+            _ => panic!("GICv3: no list register {}", $index),
+        }
+    };
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+impl VGicDriver for GicV3 {
+    fn num_list_regs() -> usize {
+        (read_sysreg!("ich_vtr_el2") & VGIC_VTR_NLISTREGS_MASK) + 1
+    }
+
+    fn get_hcr() -> u32 {
+        read_sysreg!("ich_hcr_el2") as u32
+    }
+
+    fn set_hcr(hcr: u32) {
+        write_sysreg!("ich_hcr_el2", hcr as usize);
+        isb();
+    }
+
+    fn get_vmcr() -> u32 {
+        read_sysreg!("ich_vmcr_el2") as u32
+    }
+
+    fn set_vmcr(vmcr: u32) {
+        write_sysreg!("ich_vmcr_el2", vmcr as usize);
+        isb();
+    }
+
+    fn get_apr() -> u32 {
+        read_sysreg!("ich_ap1r0_el2") as u32
+    }
+
+    fn set_apr(apr: u32) {
+        write_sysreg!("ich_ap1r0_el2", apr as usize);
+        isb();
+    }
+
+    fn get_misr() -> u32 {
+        read_sysreg!("ich_misr_el2") as u32
+    }
+
+    fn get_eisr() -> u64 {
+        read_sysreg!("ich_eisr_el2") as u64
+    }
+
+    fn get_lr(index: usize) -> u64 {
+        ich_lr!(index, read_sysreg) as u64
+    }
+
+    fn set_lr(index: usize, lr: u64) {
+        ich_lr!(index, write_sysreg, lr as usize);
+        isb();
+    }
+
+    fn virq_pending_new(group: usize, priority: usize, vid: usize) -> u64 {
+        (VIRQ_STATE_PENDING << VIRQ_STATE_SHIFT)
+            | ((group as u64 & 0x1) << VIRQ_GROUP_SHIFT)
+            | ((priority as u64 & 0xff) << VIRQ_PRIORITY_SHIFT)
+            | VIRQ_EOI_IRQ_EN
+            | (vid as u64 & VIRQ_VID_MASK)
+    }
+
+    fn virq_is_active(lr: u64) -> bool {
+        (lr >> VIRQ_STATE_SHIFT) & VIRQ_STATE_MASK == VIRQ_STATE_ACTIVE
+    }
+
+    fn virq_clear_eoi_irq(lr: u64) -> u64 {
+        lr & !VIRQ_EOI_IRQ_EN
+    }
+}
@@ -1,2 +1,131 @@
 pub mod consts;
 pub mod gic_v3;
+
+use core::ptr::NonNull;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite};
+
+register_structs! {
+    /// GICv3 Distributor registers.
+    #[allow(non_snake_case)]
+    pub Gicd_Map_Regs {
+        (0x0000 => ctlr: ReadWrite<u32>),
+        (0x0004 => typer: ReadOnly<u32>),
+        (0x0008 => iidr: ReadOnly<u32>),
+        (0x000c => _reserved_1),
+        (0x0080 => igroupr: [ReadWrite<u32>; 0x20]),
+        (0x0100 => isenabler: [ReadWrite<u32>; 0x20]),
+        (0x0180 => icenabler: [ReadWrite<u32>; 0x20]),
+        (0x0200 => ispendr: [ReadWrite<u32>; 0x20]),
+        (0x0280 => icpendr: [ReadWrite<u32>; 0x20]),
+        (0x0300 => isactiver: [ReadWrite<u32>; 0x20]),
+        (0x0380 => icactiver: [ReadWrite<u32>; 0x20]),
+        (0x0400 => ipriorityr: [ReadWrite<u32>; 0xff]),
+        (0x07fc => _reserved_2),
+        (0x0c00 => icfgr: [ReadWrite<u32>; 0x40]),
+        (0x0d00 => igrpmodr: [ReadWrite<u32>; 0x20]),
+        (0x0d80 => _reserved_3),
+        /// The first 32 entries are reserved for the local interrupts
+        (0x6000 => irouter: [ReadWrite<u64>; 1020]),
+        (0x7fe0 => _reserved_4),
+        (0x10000 => @END),
+    }
+}
+
+register_structs! {
+    /// GICv3 Redistributor registers, the first page of the RD_base frame.
+    #[allow(non_snake_case)]
+    pub Gicr_Rd_Map_Regs {
+        (0x0000 => ctlr: ReadWrite<u32>),
+        (0x0004 => iidr: ReadOnly<u32>),
+        (0x0008 => typer: ReadOnly<u64>),
+        (0x0010 => statusr: ReadWrite<u32>),
+        (0x0014 => waker: ReadWrite<u32>),
+        (0x0018 => _reserved_1),
+        (0x1000 => @END),
+    }
+}
+
+register_structs! {
+    /// GICv3 Redistributor registers, the first page of the SGI_base frame.
+    #[allow(non_snake_case)]
+    pub Gicr_Sgi_Map_Regs {
+        (0x0000 => _reserved_1),
+        (0x0080 => igroupr0: ReadWrite<u32>),
+        (0x0084 => _reserved_2),
+        (0x0100 => isenabler0: ReadWrite<u32>),
+        (0x0104 => _reserved_3),
+        (0x0180 => icenabler0: ReadWrite<u32>),
+        (0x0184 => _reserved_4),
+        (0x0200 => ispendr0: ReadWrite<u32>),
+        (0x0204 => _reserved_5),
+        (0x0280 => icpendr0: ReadWrite<u32>),
+        (0x0284 => _reserved_6),
+        (0x0300 => isactiver0: ReadWrite<u32>),
+        (0x0304 => _reserved_7),
+        (0x0380 => icactiver0: ReadWrite<u32>),
+        (0x0384 => _reserved_8),
+        (0x0400 => ipriorityr: [ReadWrite<u32>; 0x8]),
+        (0x0420 => _reserved_9),
+        (0x0c00 => icfgr0: ReadWrite<u32>),
+        (0x0c04 => icfgr1: ReadWrite<u32>),
+        (0x0c08 => _reserved_10),
+        (0x0d00 => igrpmodr0: ReadWrite<u32>),
+        (0x0d04 => _reserved_11),
+        (0x1000 => @END),
+    }
+}
+
+pub struct Gicd_Map {
+    base: NonNull<Gicd_Map_Regs>,
+}
+
+pub struct Gicr_Rd_Map {
+    base: NonNull<Gicr_Rd_Map_Regs>,
+}
+
+pub struct Gicr_Sgi_Map {
+    base: NonNull<Gicr_Sgi_Map_Regs>,
+}
+
+unsafe impl Send for Gicd_Map {}
+unsafe impl Sync for Gicd_Map {}
+
+impl Gicd_Map {
+    /// Construct a new GIC distributor instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &Gicd_Map_Regs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl Gicr_Rd_Map {
+    /// Construct a new GIC redistributor RD_base instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &Gicr_Rd_Map_Regs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl Gicr_Sgi_Map {
+    /// Construct a new GIC redistributor SGI_base instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &Gicr_Sgi_Map_Regs {
+        unsafe { self.base.as_ref() }
+    }
+}
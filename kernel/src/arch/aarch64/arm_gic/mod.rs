@@ -1,2 +1,92 @@
 pub mod gic_v2;
 pub mod gic_v3;
+
+#[cfg(not(feature = "GIC_V3"))]
+pub use gic_v2::gic_v2::GicV2 as Gic;
+#[cfg(feature = "GIC_V3")]
+pub use gic_v3::gic_v3::GicV3 as Gic;
+
+/// The maintenance interrupt of the virtual CPU interface
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const INTERRUPT_VGIC_MAINTENANCE: usize = 25;
+/// The virtual timer, which is handed to the guest of the current VCPU
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const INTERRUPT_VTIMER_EVENT: usize = 27;
+
+/// VGIC_HCR.EN, GICH_HCR and ICH_HCR_EL2 agree on it
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const VGIC_HCR_EN: u32 = 1 << 0;
+/// VGIC_MISR.EOI, GICH_MISR and ICH_MISR_EL2 agree on it
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const VGIC_MISR_EOI: u32 = 1 << 0;
+
+/// The interrupt controller interface used by the kernel. Both GIC versions implement it,
+/// and the `GIC_V3` feature selects the one exported as [`Gic`].
+pub trait GicDriver {
+    /// Mask of the interrupt number in the value returned by `get_active_irq`
+    const IRQ_MASK: usize;
+
+    /// Initialise the distributor, called once by the boot core
+    fn dist_init();
+
+    /// Initialise the CPU interface of the current core
+    fn cpu_iface_init();
+
+    /// Acknowledge the highest priority pending interrupt and get its raw interrupt id
+    fn get_active_irq() -> usize;
+
+    /// Signal the end of the interrupt, given by the raw interrupt id of `get_active_irq`
+    fn ack_irq(irq: usize);
+
+    /// Check if an interrupt is pending for the current core, without acknowledging it
+    fn is_irq_pending() -> bool;
+
+    /// Disable or enable the interrupt
+    fn mask_irq(disable: bool, irq: usize);
+
+    /// Route the shared interrupt to the given core
+    fn set_irq_target(irq: usize, cpu: usize);
+
+    /// Send the software generated interrupt to every core in `target_mask`
+    fn send_sgi(irq: usize, target_mask: usize);
+}
+
+/// The virtual CPU interface control of the GIC, through which the kernel feeds the virtual
+/// interrupts of the VCPUs. A list register is kept as the raw word of the GIC version.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub trait VGicDriver {
+    /// Get the number of list registers
+    fn num_list_regs() -> usize;
+
+    fn get_hcr() -> u32;
+
+    fn set_hcr(hcr: u32);
+
+    fn get_vmcr() -> u32;
+
+    fn set_vmcr(vmcr: u32);
+
+    fn get_apr() -> u32;
+
+    fn set_apr(apr: u32);
+
+    /// Get the maintenance interrupt status
+    fn get_misr() -> u32;
+
+    /// Get the list registers that have had an EOI, one bit for each
+    fn get_eisr() -> u64;
+
+    fn get_lr(index: usize) -> u64;
+
+    fn set_lr(index: usize, lr: u64);
+
+    /// Make the list register of a pending virtual interrupt, which raises the maintenance
+    /// interrupt on its EOI
+    fn virq_pending_new(group: usize, priority: usize, vid: usize) -> u64;
+
+    /// Check if the virtual interrupt of the list register is active
+    fn virq_is_active(lr: u64) -> bool;
+
+    /// Stop the list register from raising the maintenance interrupt on an EOI again
+    fn virq_clear_eoi_irq(lr: u64) -> u64;
+}
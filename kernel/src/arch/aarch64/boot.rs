@@ -2,15 +2,19 @@ use log::debug;
 use sel4_common::arch::config::KERNEL_ELF_BASE;
 use sel4_common::{sel4_config::PAGE_BITS, BIT};
 use sel4_task::create_idle_thread;
-use sel4_vspace::{kpptr_to_paddr, rust_map_kernel_window};
+use sel4_vspace::{
+    activate_kernel_vspace, kpptr_to_paddr, map_kernel_devices, rust_map_kernel_window,
+};
 
-use crate::arch::aarch64::platform::{cleanInvalidateL1Caches, init_cpu, invalidateLocalTLB};
+use crate::arch::aarch64::platform::{
+    cleanInvalidateL1Caches, init_cpu, init_fdt_devices, invalidateLocalTLB,
+};
 
 use crate::{
     arch::init_freemem,
     boot::{
         bi_finalise, calculate_extra_bi_size_bits, create_untypeds, init_core_state, init_dtb,
-        ksNumCPUs, ndks_boot, paddr_to_pptr_reg, root_server_init,
+        init_fdt, ksNumCPUs, log_fdt_info, ndks_boot, paddr_to_pptr_reg, root_server_init,
     },
     config::{BI_FRAME_SIZE_BITS, USER_TOP},
     structures::{p_region_t, seL4_SlotRegion, v_region_t},
@@ -51,12 +55,20 @@ pub fn try_init_kernel(
 
     // Map kernel window area
     rust_map_kernel_window();
+    // the device tree is read through the kernel window, it decides where the devices are
+    activate_kernel_vspace();
+    let fdt_info = init_fdt(dtb_phys_addr, dtb_size);
+    if let Some(info) = fdt_info {
+        init_fdt_devices(info);
+    }
+    map_kernel_devices();
 
     // Initialize cpu
     let inited = init_cpu();
     // Initialize the drivers used by the kernel.
     driver_collect::init();
     log::debug!("init_cpu: {}", inited);
+    log_fdt_info(fdt_info);
 
     // Initialize platform
     // sel4_common::ffi_call!(init_plat);
@@ -116,11 +128,13 @@ pub fn try_init_kernel(
         *ksNumCPUs.lock() = 1;
         #[cfg(feature = "ENABLE_SMP")]
         {
-            unsafe {
-                clh_lock_init();
-                release_secondary_cores();
-                clh_lock_acquire(cpu_id(), false);
-            }
+            use crate::boot::release_secondary_cores;
+            use crate::smp::{clh_lock_acquire, clh_lock_init};
+            use sel4_common::utils::cpu_id;
+
+            clh_lock_init();
+            release_secondary_cores();
+            clh_lock_acquire(cpu_id(), false);
         }
 
         debug!("Booting all finished, dropped to user space");
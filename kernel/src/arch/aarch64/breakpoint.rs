@@ -0,0 +1,117 @@
+//! Context switching of the hardware breakpoints, and the debug exceptions of the threads.
+//!
+//! The breakpoint registers of a core are only rewritten for a thread which uses them and whose
+//! breakpoints are not already loaded. A thread which uses none runs with MDSCR_EL1.MDE clear, so
+//! the registers left by another thread never match.
+
+use super::instruction::{get_far, get_mdscr, set_mdscr, unlock_os_lock};
+use sel4_common::arch::{
+    seL4_DataBreakpoint, seL4_FirstWatchpoint, seL4_InstructionBreakpoint, seL4_SingleStep,
+    seL4_SoftwareBreakRequest, ArchReg,
+};
+use sel4_common::fault::seL4_Fault_t;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+use sel4_task::tcb_t;
+
+/// MDSCR_EL1.SS, the software step is enabled
+const MDSCR_SS: usize = 1 << 0;
+/// MDSCR_EL1.MDE, the breakpoints and watchpoints are enabled
+const MDSCR_MDE: usize = 1 << 15;
+
+const ESR_EC_SHIFT: usize = 26;
+const ESR_EC_BREAKPOINT: usize = 0x30;
+const ESR_EC_SOFTWARE_STEP: usize = 0x32;
+const ESR_EC_WATCHPOINT: usize = 0x34;
+const ESR_EC_BRK: usize = 0x3c;
+
+/// The TCB whose breakpoints are loaded in the debug registers of each core, 0 if there is none
+static mut ksActiveBreakpointState: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+/// Load the breakpoints and the single-stepping of the thread going back to EL0, called by
+/// [restore_user_context](super::restore_user_context) and the fastpath.
+pub fn restore_breakpoint_state(thread: &mut tcb_t) {
+    let used = thread.tcbArch.breakpoints.is_used();
+    #[allow(unused_mut)]
+    let mut stepping = thread.tcbArch.breakpoints.is_single_stepping();
+    #[cfg(feature = "ENABLE_GDB")]
+    {
+        stepping |= crate::kernel::gdb::gdb_is_stepping(thread);
+    }
+    let cpu = cpu_id();
+    unsafe {
+        if used && ksActiveBreakpointState[cpu] != thread.get_ptr() {
+            thread.tcbArch.breakpoints.load();
+            ksActiveBreakpointState[cpu] = thread.get_ptr();
+        }
+    }
+    if stepping {
+        thread.tcbArch.set_software_step(true);
+    }
+    let mdscr = get_mdscr();
+    let mut new_mdscr = mdscr & !(MDSCR_MDE | MDSCR_SS);
+    if used {
+        new_mdscr |= MDSCR_MDE;
+    }
+    if stepping {
+        new_mdscr |= MDSCR_SS;
+    }
+    if new_mdscr != mdscr {
+        unlock_os_lock();
+        set_mdscr(new_mdscr);
+    }
+}
+
+/// Forget the breakpoints of the thread loaded in the cores, after they have been changed.
+pub fn breakpoint_state_changed(thread: &tcb_t) {
+    unsafe {
+        for active in ksActiveBreakpointState.iter_mut() {
+            if *active == thread.get_ptr() {
+                *active = 0;
+            }
+        }
+    }
+}
+
+/// Get the fault of a user exception of the thread, from its ESR, a debug exception for the
+/// exceptions of the debug architecture.
+///
+/// Returns None for a step exception when the thread has instructions left to step.
+pub fn user_level_fault(thread: &mut tcb_t, esr: usize, code: usize) -> Option<seL4_Fault_t> {
+    let fault_ip = thread.tcbArch.get_register(ArchReg::FaultIP);
+    let breakpoints = &mut thread.tcbArch.breakpoints;
+    match esr >> ESR_EC_SHIFT {
+        ESR_EC_BREAKPOINT => Some(seL4_Fault_t::new_debug_exception(
+            fault_ip,
+            breakpoints.find_breakpoint(fault_ip).unwrap_or(0),
+            seL4_InstructionBreakpoint,
+        )),
+        ESR_EC_WATCHPOINT => {
+            let addr = get_far();
+            Some(seL4_Fault_t::new_debug_exception(
+                addr,
+                breakpoints
+                    .find_watchpoint(addr)
+                    .unwrap_or(seL4_FirstWatchpoint),
+                seL4_DataBreakpoint,
+            ))
+        }
+        // the step exceptions of a thread not stepped by the debug API are left to the GDB stub
+        ESR_EC_SOFTWARE_STEP if breakpoints.is_single_stepping() => {
+            if !breakpoints.single_step_done() {
+                return None;
+            }
+            Some(seL4_Fault_t::new_debug_exception(
+                fault_ip,
+                0,
+                seL4_SingleStep,
+            ))
+        }
+        ESR_EC_BRK => Some(seL4_Fault_t::new_debug_exception(
+            fault_ip,
+            0,
+            seL4_SoftwareBreakRequest,
+        )),
+        _ => Some(seL4_Fault_t::new_user_exeception(esr, code)),
+    }
+}
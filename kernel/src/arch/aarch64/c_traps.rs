@@ -1,70 +1,89 @@
+use super::fpu::{handle_fpu_fault, lazy_fpu_restore};
 use crate::interrupt::handler::handleInterruptEntry;
-use crate::syscall::slowpath;
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+use crate::kernel::benchmark::benchmark_track_syscall;
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+use crate::kernel::benchmark::{benchmark_track_exit, benchmark_track_start};
+use crate::kernel::fastpath::fastpath_signal;
+use crate::syscall::{slowpath, SysSend};
 use core::arch::asm;
 
 #[cfg(feature = "ENABLE_SMP")]
 use crate::{
-    deps::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
     interrupt::getActiveIRQ,
+    smp::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
 };
 
 #[cfg(feature = "ENABLE_SMP")]
 use sel4_common::utils::cpu_id;
 use sel4_task::get_currenct_thread;
 
+/// Load the return address and state of the thread, kept in x22 and x23, into the exception
+/// registers of the level the kernel runs at
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[macro_export]
+macro_rules! msr_return_state {
+    () => {
+        "msr     elr_el1, x22               \nmsr     spsr_el1, x23              \n"
+    };
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[macro_export]
+macro_rules! msr_return_state {
+    () => {
+        "msr     elr_el2, x22               \nmsr     spsr_el2, x23              \n"
+    };
+}
+
 #[no_mangle]
 pub fn restore_user_context() {
-    // NODE_UNLOCK_IF_HELD;
-
-    // this is just a empty "do {} while (0)", I think it is only meaningfully under multi core case
-    // at that case the micro NODE_UNLOCK_IF_HELD is
-    // do {                         \
-    //     if(clh_is_self_in_queue()) {                         \
-    //         NODE_UNLOCK;                                     \
-    //     }                                                    \
-    // } while(0)
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_exit();
+    #[cfg(feature = "ENABLE_SMP")]
+    if clh_is_self_in_queue() {
+        clh_lock_release(cpu_id());
+    }
 
-    // c_exit_hook();
     get_currenct_thread().tcbArch.load_thread_local();
 
-    // #ifdef CONFIG_HAVE_FPU
-    //     lazyFPURestore(NODE_STATE(ksCurThread));
-    // #endif /* CONFIG_HAVE_FPU */
+    lazy_fpu_restore(get_currenct_thread());
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    super::vcpu::vcpu_switch(get_currenct_thread().tcbArch.tcbVCPU);
+    // the software step of the GDB stub is taken over by the breakpoint state
+    #[cfg(all(feature = "ENABLE_GDB", not(feature = "HARDWARE_DEBUG_API")))]
+    super::gdb::prepare_resume(crate::kernel::gdb::gdb_is_stepping(get_currenct_thread()));
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    super::breakpoint::restore_breakpoint_state(get_currenct_thread());
     unsafe {
         asm!(
-                "mov     sp, {}                     \n",
+            "mov     sp, {}                     \n",
 
-                /* Restore thread's SPSR, LR, and SP */
-                "ldp     x21, x22, [sp, #31 * 8] \n",
-                "ldr     x23, [sp, #33 * 8]    \n",
-                "msr     sp_el0, x21                \n",
-        // #ifdef CONFIG_ARM_HYPERVISOR_SUPPORT
-        //         "msr     elr_el2, x22               \n"
-        //         "msr     spsr_el2, x23              \n"
-        // #else
-                "msr     elr_el1, x22               \n",
-                "msr     spsr_el1, x23              \n",
-        // #endif
-                /* Restore remaining registers */
-                "ldp     x0,  x1,  [sp, #16 * 0]    \n",
-                "ldp     x2,  x3,  [sp, #16 * 1]    \n",
-                "ldp     x4,  x5,  [sp, #16 * 2]    \n",
-                "ldp     x6,  x7,  [sp, #16 * 3]    \n",
-                "ldp     x8,  x9,  [sp, #16 * 4]    \n",
-                "ldp     x10, x11, [sp, #16 * 5]    \n",
-                "ldp     x12, x13, [sp, #16 * 6]    \n",
-                "ldp     x14, x15, [sp, #16 * 7]    \n",
-                "ldp     x16, x17, [sp, #16 * 8]    \n",
-                "ldp     x18, x19, [sp, #16 * 9]    \n",
-                "ldp     x20, x21, [sp, #16 * 10]   \n",
-                "ldp     x22, x23, [sp, #16 * 11]   \n",
-                "ldp     x24, x25, [sp, #16 * 12]   \n",
-                "ldp     x26, x27, [sp, #16 * 13]   \n",
-                "ldp     x28, x29, [sp, #16 * 14]   \n",
-                "ldr     x30, [sp, #30 * 8]          \n",
-                "eret",
-                in(reg) get_currenct_thread().tcbArch.raw_ptr()
-            );
+            /* Restore thread's SPSR, LR, and SP */
+            "ldp     x21, x22, [sp, #31 * 8] \n",
+            "ldr     x23, [sp, #33 * 8]    \n",
+            "msr     sp_el0, x21                \n",
+            crate::msr_return_state!(),
+            /* Restore remaining registers */
+            "ldp     x0,  x1,  [sp, #16 * 0]    \n",
+            "ldp     x2,  x3,  [sp, #16 * 1]    \n",
+            "ldp     x4,  x5,  [sp, #16 * 2]    \n",
+            "ldp     x6,  x7,  [sp, #16 * 3]    \n",
+            "ldp     x8,  x9,  [sp, #16 * 4]    \n",
+            "ldp     x10, x11, [sp, #16 * 5]    \n",
+            "ldp     x12, x13, [sp, #16 * 6]    \n",
+            "ldp     x14, x15, [sp, #16 * 7]    \n",
+            "ldp     x16, x17, [sp, #16 * 8]    \n",
+            "ldp     x18, x19, [sp, #16 * 9]    \n",
+            "ldp     x20, x21, [sp, #16 * 10]   \n",
+            "ldp     x22, x23, [sp, #16 * 11]   \n",
+            "ldp     x24, x25, [sp, #16 * 12]   \n",
+            "ldp     x26, x27, [sp, #16 * 13]   \n",
+            "ldp     x28, x29, [sp, #16 * 14]   \n",
+            "ldr     x30, [sp, #30 * 8]          \n",
+            "eret",
+            in(reg) get_currenct_thread().tcbArch.raw_ptr()
+        );
     }
     panic!("unreachable")
 }
@@ -81,9 +100,7 @@ pub fn c_handle_interrupt() {
     {
         use crate::config::INTERRUPT_IPI_0;
         if getActiveIRQ() != INTERRUPT_IPI_0 {
-            unsafe {
-                clh_lock_acquire(cpu_id(), true);
-            }
+            clh_lock_acquire(cpu_id(), true);
         }
     }
     // debug!("c_handle_interrupt");
@@ -94,10 +111,14 @@ pub fn c_handle_interrupt() {
 #[no_mangle]
 pub fn c_handle_syscall(_cptr: usize, _msgInfo: usize, syscall: usize) {
     #[cfg(feature = "ENABLE_SMP")]
-    unsafe {
-        clh_lock_acquire(cpu_id(), false);
-    }
+    clh_lock_acquire(cpu_id(), false);
     entry_hook();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_syscall(_cptr, _msgInfo, syscall as isize, false);
+    // seL4_Signal is the common Send, it tries the fastpath first
+    if syscall as isize == SysSend {
+        fastpath_signal(_cptr, _msgInfo);
+    }
     // if hart_id() == 0 {
     //     debug!("c_handle_syscall: syscall: {},", syscall as isize);
     // }
@@ -105,9 +126,31 @@ pub fn c_handle_syscall(_cptr: usize, _msgInfo: usize, syscall: usize) {
     // debug!("c_handle_syscall complete");
 }
 
+#[no_mangle]
+pub fn c_handle_enfp() {
+    #[cfg(feature = "ENABLE_SMP")]
+    clh_lock_acquire(cpu_id(), false);
+    entry_hook();
+    handle_fpu_fault();
+    restore_user_context();
+}
+
+/// The guest of the current VCPU trapped into the kernel, `hsr` is its syndrome.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[no_mangle]
+pub fn c_handle_vcpu_fault(hsr: usize) {
+    #[cfg(feature = "ENABLE_SMP")]
+    clh_lock_acquire(cpu_id(), false);
+    entry_hook();
+    super::vcpu::handle_vcpu_fault(hsr);
+    restore_user_context();
+}
+
 /// This function should be the first thing called from after entry.
-/// This function Save TPIDR(TLS) in aarch64.
+/// This function Save TPIDR(TLS) in aarch64, and timestamps the entry for the benchmark log.
 #[inline]
 pub fn entry_hook() {
     get_currenct_thread().tcbArch.save_thread_local();
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
 }
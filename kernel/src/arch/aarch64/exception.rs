@@ -2,28 +2,44 @@ use crate::arch::aarch64::consts::ARMDataAbort;
 use crate::arch::aarch64::consts::ARMPrefetchAbort;
 use crate::compatibility::lookupIPCBuffer;
 use crate::halt;
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+use crate::kernel::benchmark::{benchmark_track_entry, kernel_entry_t};
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+use crate::kernel::benchmark::{benchmark_track_start, handle_benchmark_syscall};
 use crate::kernel::boot::current_fault;
+use crate::kernel::capdl::debug_capdl_snapshot;
+use crate::kernel::debug::debug_dump_scheduler;
 use crate::object::lookupCapAndSlot;
 use crate::strnlen;
 use crate::syscall::handle_fault;
 use crate::syscall::{
-    SysDebugCapIdentify, SysDebugDumpScheduler, SysDebugHalt, SysDebugNameThread, SysDebugPutChar,
-    SysDebugSnapshot, SysGetClock,
+    SysDebugCapIdentify, SysDebugDumpScheduler, SysDebugGetChar, SysDebugHalt, SysDebugNameThread,
+    SysDebugPutChar, SysDebugSnapshot, SysGetClock,
 };
 
-use aarch64_cpu::registers::Readable;
-use aarch64_cpu::registers::TTBR0_EL1;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+use aarch64_cpu::registers::{Readable, TTBR0_EL1};
+use core::mem::size_of;
 use log::debug;
 use sel4_common::arch::ArchReg::*;
 use sel4_common::fault::seL4_Fault_t;
-use sel4_common::print;
 use sel4_common::sel4_config::seL4_MsgMaxLength;
 use sel4_common::structures::exception_t;
 use sel4_common::utils::global_read;
+use sel4_common::{print, println};
 use sel4_cspace::arch::CapTag;
 use sel4_task::{activateThread, get_currenct_thread, get_current_domain, schedule};
 
 use super::instruction::*;
+use super::{read_time, read_time_freq};
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use sel4_common::{sel4_config::seL4_PageBits, MASK};
+
+/// HPFAR_EL2.FIPA, bits [47:12] of the faulting IPA
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const HPFAR_FIPA_MASK: usize = 0xfff_ffff_fff0;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const HPFAR_FIPA_SHIFT: usize = 8;
 
 #[no_mangle]
 pub fn handleUnknownSyscall(w: isize) -> exception_t {
@@ -32,19 +48,31 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
         print!("{}", thread.tcbArch.get_register(Cap) as u8 as char);
         return exception_t::EXCEPTION_NONE;
     }
+    if w == SysDebugGetChar {
+        // -1 tells the thread that no character has been received
+        let c = sel4_common::console::getchar().map_or(usize::MAX, |c| c as usize);
+        thread.tcbArch.set_register(Cap, c);
+        return exception_t::EXCEPTION_NONE;
+    }
     if w == SysDebugDumpScheduler {
-        // unimplement debug
-        // println!("debug dump scheduler");
+        debug_dump_scheduler();
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysDebugHalt {
-        // unimplement debug
-        // println!("debug halt");
-        return exception_t::EXCEPTION_NONE;
+        println!(
+            "Debug halt syscall from user thread {:#x} \"{}\"",
+            thread.get_ptr(),
+            thread.get_name()
+        );
+        halt();
     }
     if w == SysDebugSnapshot {
-        // unimplement debug
-        // println!("debug snapshot");
+        println!(
+            "Debug snapshot syscall from user thread {:#x} \"{}\"",
+            thread.get_ptr(),
+            thread.get_name()
+        );
+        debug_capdl_snapshot();
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysDebugCapIdentify {
@@ -65,11 +93,13 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
             debug!("SysDebugNameThread: cap is not a TCB, halting");
             halt();
         }
-        let name = lookupIPCBuffer(true, thread) + 1;
-        if name == 0 {
+        let buffer = lookupIPCBuffer(true, thread);
+        if buffer == 0 {
             debug!("SysDebugNameThread: Failed to lookup IPC buffer, halting");
             halt();
         }
+        // the name starts at the first message register, right after the tag
+        let name = buffer + size_of::<usize>();
 
         let len = strnlen(name as *const u8, seL4_MsgMaxLength * 8);
         if len == seL4_MsgMaxLength * 8 {
@@ -77,13 +107,31 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
             halt();
         }
 
-        // setThreadName(TCB_PTR(cap_thread_cap_get_capTCBPtr(lu_ret.cap)), name);
+        #[cfg(feature = "DEBUG_BUILD")]
+        {
+            let tcb = sel4_common::utils::convert_to_mut_type_ref::<sel4_task::tcb_t>(
+                lu_ret.cap.get_tcb_ptr(),
+            );
+            tcb.set_name(unsafe { core::slice::from_raw_parts(name as *const u8, len) });
+        }
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysGetClock {
-        /*no implementation of aarch64 get clock*/
+        // The frequency is returned in the first message register to convert the count to time
+        thread.tcbArch.set_register(Cap, read_time());
+        thread.tcbArch.set_register(Msg(0), read_time_freq());
         return exception_t::EXCEPTION_NONE;
     }
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    if let Some(ret) = handle_benchmark_syscall(w, thread) {
+        return ret;
+    }
+    debug!(
+        "Unknown syscall {} from thread {:#x} \"{}\"",
+        w,
+        thread.get_ptr(),
+        thread.get_name()
+    );
     unsafe {
         current_fault = seL4_Fault_t::new_unknown_syscall_fault(w as usize);
         handle_fault(get_currenct_thread());
@@ -95,9 +143,20 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
 
 #[no_mangle]
 pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
-    unsafe {
-        current_fault = seL4_Fault_t::new_user_exeception(w_a, w_b);
-        handle_fault(get_currenct_thread());
+    // the fault vectors of aarch64 enter the kernel here from the C traps
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_entry(kernel_entry_t::new_user_level_fault_entry(w_a));
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    let fault = super::breakpoint::user_level_fault(get_currenct_thread(), w_a, w_b);
+    #[cfg(not(feature = "HARDWARE_DEBUG_API"))]
+    let fault = Some(seL4_Fault_t::new_user_exeception(w_a, w_b));
+    if let Some(fault) = fault {
+        unsafe {
+            current_fault = fault;
+            handle_fault(get_currenct_thread());
+        }
     }
     schedule();
     activateThread();
@@ -106,6 +165,11 @@ pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
 
 #[no_mangle]
 pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
+    // the aborts enter the kernel here from the C traps
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_entry(kernel_entry_t::new_vm_fault_entry(vm_faultType));
     let status = handle_vm_fault(vm_faultType);
     if status != exception_t::EXCEPTION_NONE {
         handle_fault(get_currenct_thread());
@@ -148,14 +212,26 @@ pub fn handle_vm_fault(type_: usize) -> exception_t {
     */
     // ARMDataAbort = seL4_DataFault,               0
     // ARMPrefetchAbort = seL4_InstructionFault     1
+    let thread = get_currenct_thread();
     log::debug!(
-        "Handle VM fault: {}  domain: {}",
+        "Handle VM fault: {} from thread {:#x} \"{}\" domain: {}",
         type_,
+        thread.get_ptr(),
+        thread.get_name(),
         get_current_domain()
     );
     match type_ {
         ARMDataAbort => {
+            #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
             let addr = get_far();
+            // a guest reports the IPA, its FAR holds the address of its own stage-1
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            let addr = if super::vcpu::vcpu_active() {
+                ((get_hpfar() & HPFAR_FIPA_MASK) << HPFAR_FIPA_SHIFT)
+                    | (get_far() & MASK!(seL4_PageBits))
+            } else {
+                get_far()
+            };
             let fault = get_esr();
             log::debug!("fault addr: {:#x} esr: {:#x}", addr, fault);
             unsafe {
@@ -165,12 +241,13 @@ pub fn handle_vm_fault(type_: usize) -> exception_t {
             exception_t::EXCEPTION_FAULT
         }
         ARMPrefetchAbort => {
-            let pc = get_currenct_thread().tcbArch.get_register(FaultIP);
+            let pc = thread.tcbArch.get_register(FaultIP);
             let fault = get_esr();
             unsafe {
                 current_fault = seL4_Fault_t::new_vm_fault(pc, fault, 1);
             }
 
+            #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
             log::debug!("ttbr0_el1: {:#x?}", TTBR0_EL1.get());
 
             log::debug!("fault pc: {:#x}  fault: {:#x}", pc, fault);
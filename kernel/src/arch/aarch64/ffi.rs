@@ -1,3 +0,0 @@
-extern "C" {
-    pub fn initTimer();
-}
@@ -0,0 +1,194 @@
+//! Lazy FPU context switching.
+//!
+//! The FPU is disabled for EL0 unless the current thread owns it. The first FP/SIMD access of
+//! another thread traps into `handle_fpu_fault`, which saves the registers of the owner and loads
+//! the ones of the current thread.
+//!
+//! The kernel in EL2 traps the accesses with CPTR_EL2 instead, which covers the guests of the
+//! VCPUs as well as the native threads.
+
+use core::arch::asm;
+use core::intrinsics::{likely, unlikely};
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::{convert_to_option_mut_type_ref, cpu_id};
+use sel4_task::{get_currenct_thread, tcb_t};
+
+/// Give the FPU up if its owner didn't touch it for this many kernel exits
+const CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH: usize = 64;
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+const CPACR_EL1_FPEN_SHIFT: usize = 20;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+const CPACR_EL1_FPEN_MASK: usize = 0x3;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+const CPACR_EL1_FPEN_TRAP_EL0: usize = 0x1;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+const CPACR_EL1_FPEN_TRAP_NONE: usize = 0x3;
+
+/// CPTR_EL2.TFP
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const CPTR_EL2_TFP: usize = 1 << 10;
+
+/// The TCB whose registers are loaded in the FPU of each core, 0 if there is none
+static mut ksActiveFPUState: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+/// The number of kernel exits since the FPU owner of each core was switched
+static mut ksFPURestoresSinceSwitch: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+fn set_cpacr_fpen(fpen: usize) {
+    let mut cpacr: usize;
+    unsafe {
+        asm!("mrs {}, cpacr_el1", out(reg) cpacr);
+        cpacr &= !(CPACR_EL1_FPEN_MASK << CPACR_EL1_FPEN_SHIFT);
+        cpacr |= fpen << CPACR_EL1_FPEN_SHIFT;
+        asm!("msr cpacr_el1, {}", in(reg) cpacr);
+        asm!("isb");
+    }
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+fn set_cptr_tfp(trap: bool) {
+    let mut cptr: usize;
+    unsafe {
+        asm!("mrs {}, cptr_el2", out(reg) cptr);
+        if trap {
+            cptr |= CPTR_EL2_TFP;
+        } else {
+            cptr &= !CPTR_EL2_TFP;
+        }
+        asm!("msr cptr_el2, {}", in(reg) cptr);
+        asm!("isb");
+    }
+}
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+/// Allow FP/SIMD accesses from EL0 and EL1.
+fn enable_fpu() {
+    set_cpacr_fpen(CPACR_EL1_FPEN_TRAP_NONE);
+}
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+/// Trap FP/SIMD accesses from EL0.
+fn disable_fpu() {
+    set_cpacr_fpen(CPACR_EL1_FPEN_TRAP_EL0);
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+/// Allow FP/SIMD accesses from EL0, EL1 and EL2.
+fn enable_fpu() {
+    set_cptr_tfp(false);
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+/// Trap FP/SIMD accesses to EL2.
+fn disable_fpu() {
+    set_cptr_tfp(true);
+}
+
+/// Initialise the FPU of the current core, no thread owns it until its first access.
+pub fn fpsimd_init() {
+    disable_fpu();
+    unsafe {
+        ksActiveFPUState[cpu_id()] = 0;
+        ksFPURestoresSinceSwitch[cpu_id()] = 0;
+    }
+}
+
+#[inline]
+/// Check if the FPU of the core of the thread holds the registers of the thread.
+fn native_thread_using_fpu(thread: &tcb_t) -> bool {
+    unsafe { ksActiveFPUState[thread.get_cpu()] == thread.get_ptr() }
+}
+
+/// Save the FPU registers of the current owner and load the ones of `new_owner`.
+/// The FPU is left disabled if `new_owner` is 0.
+fn switch_local_fpu_owner(new_owner: usize) {
+    enable_fpu();
+    let cpu = cpu_id();
+    let old_owner = unsafe { ksActiveFPUState[cpu] };
+    if let Some(owner) = convert_to_option_mut_type_ref::<tcb_t>(old_owner) {
+        owner.tcbArch.save_fpu_state();
+    }
+    if let Some(thread) = convert_to_option_mut_type_ref::<tcb_t>(new_owner) {
+        unsafe {
+            ksFPURestoresSinceSwitch[cpu] = 0;
+        }
+        thread.tcbArch.load_fpu_state();
+    } else {
+        disable_fpu();
+    }
+    unsafe {
+        ksActiveFPUState[cpu] = new_owner;
+    }
+}
+
+/// Give the FPU to the current thread after it trapped on a FP/SIMD access.
+pub fn handle_fpu_fault() {
+    let thread = get_currenct_thread();
+    assert!(!native_thread_using_fpu(thread));
+    switch_local_fpu_owner(thread.get_ptr());
+}
+
+/// Enable the FPU if the thread about to run owns it, and disable it otherwise.
+pub fn lazy_fpu_restore(thread: &tcb_t) {
+    let cpu = cpu_id();
+    unsafe {
+        if unlikely(ksActiveFPUState[cpu] != 0) {
+            if unlikely(ksFPURestoresSinceSwitch[cpu] > CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH) {
+                // The owner hasn't used the FPU for a while, save it now to avoid toggling it
+                switch_local_fpu_owner(0);
+                ksFPURestoresSinceSwitch[cpu] = 0;
+            } else {
+                if likely(native_thread_using_fpu(thread)) {
+                    enable_fpu();
+                } else {
+                    disable_fpu();
+                }
+                ksFPURestoresSinceSwitch[cpu] += 1;
+            }
+        }
+    }
+}
+
+/// Release the FPU owned by a thread that is being deleted.
+pub fn fpu_thread_delete(thread: &tcb_t) {
+    if !native_thread_using_fpu(thread) {
+        return;
+    }
+    let cpu = thread.get_cpu();
+    if cpu == cpu_id() {
+        switch_local_fpu_owner(0);
+    } else {
+        // the FPU and its owner are only touched by their own core
+        #[cfg(feature = "ENABLE_SMP")]
+        crate::smp::do_remote_op(
+            crate::smp::IpiRemoteCall::FunctionCall(|_| switch_local_fpu_owner(0), 0),
+            cpu,
+        );
+    }
+}
+
+/// Release the FPU of the core of a thread moving to another core, saving its registers there.
+#[cfg(feature = "ENABLE_SMP")]
+pub fn fpu_thread_migrate(thread: &tcb_t) {
+    use crate::smp::{do_remote_op, IpiRemoteCall};
+    if !native_thread_using_fpu(thread) {
+        return;
+    }
+    let cpu = thread.get_cpu();
+    if cpu == cpu_id() {
+        switch_local_fpu_owner(0);
+    } else {
+        do_remote_op(
+            IpiRemoteCall::FunctionCall(|_| switch_local_fpu_owner(0), 0),
+            cpu,
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The aarch64 part of the GDB stub.
+//!
+//! The threads are stepped with the software step of the debug architecture. MDSCR_EL1.SS is
+//! set on the way back to the stepped thread only, [is_stray_fault] takes care of a thread
+//! which gets back to EL0 by the fastpath while it is still set.
+
+use super::instruction::{get_mdscr, set_mdscr, unlock_os_lock};
+use crate::kernel::gdb::{context_reg, GDB_SIGNAL_BUS, GDB_SIGNAL_ILL, GDB_SIGNAL_TRAP};
+use sel4_common::arch::ArchReg;
+use sel4_common::fault::{seL4_Fault_t, FaultType};
+use sel4_common::sel4_config::tcbVTable;
+use sel4_common::utils::ptr_to_mut;
+use sel4_common::MASK;
+use sel4_task::tcb_t;
+use sel4_vspace::{clean_by_va_pou, dsb, invalidate_i_pou, paddr_to_pptr, PTE};
+
+/// x0-x30, sp, pc and cpsr
+pub const GDB_REG_NUM: usize = 34;
+/// The GDB register number of the pc
+pub const GDB_PC_REGISTER: usize = 32;
+
+/// The index of SPSR_EL1 in the saved context
+const SPSR_EL1: usize = 33;
+/// SPSR_EL1.SS, the thread executes one instruction before the step exception
+const SPSR_SS: usize = 1 << 21;
+/// MDSCR_EL1.SS, the software step is enabled
+const MDSCR_SS: usize = 1 << 0;
+
+const ESR_EC_SHIFT: usize = 26;
+const ESR_EC_UNKNOWN: usize = 0x00;
+const ESR_EC_PC_ALIGN: usize = 0x22;
+const ESR_EC_SP_ALIGN: usize = 0x26;
+const ESR_EC_SOFTWARE_STEP: usize = 0x32;
+const ESR_EC_BRK: usize = 0x3c;
+
+/// brk #0
+const BRK: [u8; 4] = [0x00, 0x00, 0x20, 0xd4];
+
+/// Get the size in bytes of the GDB register `n`, 0 for the registers the stub does not have.
+pub fn register_size(n: usize) -> usize {
+    match n {
+        0..=32 => 8,
+        33 => 4,
+        _ => 0,
+    }
+}
+
+pub fn read_register(tcb: &tcb_t, n: usize) -> usize {
+    match n {
+        0..=30 => tcb.tcbArch.get_register(context_reg(n)),
+        31 => tcb.tcbArch.get_register(ArchReg::SP),
+        32 => tcb.tcbArch.get_register(ArchReg::NextIP),
+        _ => tcb.tcbArch.get_register(context_reg(SPSR_EL1)),
+    }
+}
+
+pub fn write_register(tcb: &mut tcb_t, n: usize, value: usize) {
+    match n {
+        0..=30 => tcb.tcbArch.set_register(context_reg(n), value),
+        31 => tcb.tcbArch.set_register(ArchReg::SP, value),
+        32 => {
+            tcb.tcbArch.set_register(ArchReg::FaultIP, value);
+            tcb.tcbArch.set_register(ArchReg::NextIP, value);
+        }
+        // only the condition flags, the rest of the cpsr would let the thread out of EL0
+        _ => {
+            let spsr = tcb.tcbArch.get_register(context_reg(SPSR_EL1));
+            let spsr = (spsr & !(0xf << 28)) | (value & (0xf << 28));
+            tcb.tcbArch.set_register(context_reg(SPSR_EL1), spsr);
+        }
+    }
+}
+
+/// Translate the user address `vaddr` of the thread to the kernel address of its byte.
+pub fn lookup_user_addr(tcb: &mut tcb_t, vaddr: usize) -> Option<usize> {
+    let cap = &tcb.get_cspace(tcbVTable).cap;
+    if !cap.is_valid_native_root() {
+        return None;
+    }
+    let lu_ret = PTE::new_from_pte(cap.get_vs_base_ptr()).lookup_pt_slot(vaddr);
+    let slot = ptr_to_mut(lu_ret.ptSlot);
+    if !slot.pte_is_page_type() {
+        return None;
+    }
+    Some(paddr_to_pptr(
+        slot.get_page_base_address() + (vaddr & MASK!(lu_ret.ptBitsLeft)),
+    ))
+}
+
+/// Make the instruction fetch see the code written by the stub.
+pub fn sync_icache(kaddr: usize) {
+    clean_by_va_pou(kaddr, 0);
+    dsb();
+    invalidate_i_pou();
+}
+
+/// Get the breakpoint instruction for the breakpoint kind of GDB, its size in bytes.
+pub fn breakpoint_instruction(kind: usize) -> Option<&'static [u8]> {
+    match kind {
+        4 => Some(&BRK),
+        _ => None,
+    }
+}
+
+/// Get the GDB signal of a user exception, from its ESR.
+pub fn user_exception_signal(number: usize) -> u8 {
+    match number >> ESR_EC_SHIFT {
+        ESR_EC_BRK | ESR_EC_SOFTWARE_STEP => GDB_SIGNAL_TRAP,
+        ESR_EC_UNKNOWN => GDB_SIGNAL_ILL,
+        ESR_EC_PC_ALIGN | ESR_EC_SP_ALIGN => GDB_SIGNAL_BUS,
+        _ => GDB_SIGNAL_TRAP,
+    }
+}
+
+/// Let the thread execute one instruction on its way back to EL0, or not.
+pub fn set_single_step(tcb: &mut tcb_t, enable: bool) -> bool {
+    let spsr = tcb.tcbArch.get_register(context_reg(SPSR_EL1));
+    let spsr = if enable {
+        spsr | SPSR_SS
+    } else {
+        spsr & !SPSR_SS
+    };
+    tcb.tcbArch.set_register(context_reg(SPSR_EL1), spsr);
+    true
+}
+
+/// Enable the software step for the thread going back to EL0, called by
+/// [restore_user_context](super::restore_user_context).
+pub fn prepare_resume(stepping: bool) {
+    let mdscr = get_mdscr();
+    if stepping && mdscr & MDSCR_SS == 0 {
+        unlock_os_lock();
+        set_mdscr(mdscr | MDSCR_SS);
+    } else if !stepping && mdscr & MDSCR_SS != 0 {
+        set_mdscr(mdscr & !MDSCR_SS);
+    }
+}
+
+/// Whether the fault of a thread which is not debugged was caused by the stub.
+///
+/// That is a step exception of a thread which got back to EL0 with MDSCR_EL1.SS still set, it is
+/// cleared and the thread goes on.
+pub fn is_stray_fault(fault: &seL4_Fault_t) -> bool {
+    if fault.get_fault_type() == FaultType::UserException
+        && fault.user_exeception_get_number() >> ESR_EC_SHIFT == ESR_EC_SOFTWARE_STEP
+    {
+        prepare_resume(false);
+        return true;
+    }
+    false
+}
@@ -0,0 +1,132 @@
+//! ARM generic timer driver, the kernel tick is driven by the EL1 virtual timer.
+//! A kernel running in EL2 leaves the virtual timer to its guests and takes the EL2
+//! physical timer instead.
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+use aarch64_cpu::registers::Writeable;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+use aarch64_cpu::registers::CNTV_CTL_EL0;
+#[cfg(all(not(feature = "KERNEL_MCS"), not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+use aarch64_cpu::registers::CNTV_TVAL_EL0;
+use aarch64_cpu::registers::{Readable, CNTFRQ_EL0, CNTPCT_EL0};
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+use aarch64_cpu::registers::{CNTVCT_EL0, CNTV_CVAL_EL0};
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use core::arch::asm;
+
+#[cfg(not(feature = "KERNEL_MCS"))]
+use crate::config::CONFIG_TIMER_TICK_MS;
+use crate::config::TIMER_CLOCK_HZ;
+#[cfg(not(feature = "KERNEL_MCS"))]
+use sel4_common::utils::ticks_to_cycles;
+
+/// CNTV_CTL_EL0.ENABLE
+const CNT_CTL_ENABLE: u64 = 1 << 0;
+
+/// The timer cycles of one kernel tick
+#[cfg(not(feature = "KERNEL_MCS"))]
+pub const TIMER_RELOAD: usize = ticks_to_cycles(TIMER_CLOCK_HZ, CONFIG_TIMER_TICK_MS);
+
+// CNTV_TVAL_EL0 is a signed 32-bit down counter
+#[cfg(not(feature = "KERNEL_MCS"))]
+const _: () = assert!(TIMER_RELOAD > 0 && TIMER_RELOAD <= i32::MAX as usize);
+
+/// Initialize the generic timer of the current core and start the first tick.
+/// The MCS kernel has no tick, the timer stays quiet until the first deadline is set.
+pub fn init_generic_timer() {
+    let freq = read_time_freq();
+    if freq != 0 && freq != TIMER_CLOCK_HZ {
+        log::warn!("gpt_cntfrq {}, expected {}", freq, TIMER_CLOCK_HZ);
+    }
+    #[cfg(not(feature = "KERNEL_MCS"))]
+    reset_timer();
+    #[cfg(feature = "KERNEL_MCS")]
+    ack_deadline_irq();
+}
+
+/// Reload the timer for the next kernel tick.
+#[cfg(not(feature = "KERNEL_MCS"))]
+#[inline]
+pub fn reset_timer() {
+    set_tval(TIMER_RELOAD as u64);
+    set_ctl(CNT_CTL_ENABLE);
+}
+
+#[cfg(all(not(feature = "KERNEL_MCS"), not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+#[inline]
+fn set_tval(tval: u64) {
+    CNTV_TVAL_EL0.set(tval);
+}
+
+#[cfg(all(not(feature = "KERNEL_MCS"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+fn set_tval(tval: u64) {
+    unsafe { asm!("msr cnthp_tval_el2, {}", in(reg) tval) };
+}
+
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+fn set_ctl(ctl: u64) {
+    CNTV_CTL_EL0.set(ctl);
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+fn set_ctl(ctl: u64) {
+    unsafe { asm!("msr cnthp_ctl_el2, {}", in(reg) ctl) };
+}
+
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+#[inline]
+fn set_cval(cval: u64) {
+    CNTV_CVAL_EL0.set(cval);
+}
+
+#[cfg(all(feature = "KERNEL_MCS", feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+fn set_cval(cval: u64) {
+    unsafe { asm!("msr cnthp_cval_el2, {}", in(reg) cval) };
+}
+
+/// Read the physical count of the generic timer
+#[inline]
+pub fn read_time() -> usize {
+    CNTPCT_EL0.get() as usize
+}
+
+/// Read the virtual count the kernel timer compares against
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+#[inline]
+pub fn read_virtual_time() -> usize {
+    CNTVCT_EL0.get() as usize
+}
+
+/// Read the count the kernel timer compares against, the EL2 physical timer uses the
+/// physical count
+#[cfg(all(feature = "KERNEL_MCS", feature = "ARM_HYPERVISOR_SUPPORT"))]
+#[inline]
+pub fn read_virtual_time() -> usize {
+    read_time()
+}
+
+/// Raise the timer interrupt when the virtual count reaches `deadline`
+#[cfg(feature = "KERNEL_MCS")]
+#[inline]
+pub fn set_deadline(deadline: usize) {
+    set_cval(deadline as u64);
+    set_ctl(CNT_CTL_ENABLE);
+}
+
+/// Push the compare value out of reach to clear the timer interrupt
+#[cfg(feature = "KERNEL_MCS")]
+#[inline]
+pub fn ack_deadline_irq() {
+    set_cval(u64::MAX);
+    set_ctl(CNT_CTL_ENABLE);
+}
+
+/// Read the frequency of the generic timer in Hz
+#[inline]
+pub fn read_time_freq() -> usize {
+    CNTFRQ_EL0.get() as usize
+}
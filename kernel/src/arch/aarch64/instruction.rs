@@ -31,12 +31,80 @@ macro_rules! msr {
 }
 
 /// Get the value of the FAR register.
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn get_far() -> usize {
     mrs!("far_el1")
 }
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn get_esr() -> usize {
     mrs!("esr_el1")
 }
+
+/// Get the value of the FAR register, the faults of the threads are taken to EL2.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn get_far() -> usize {
+    mrs!("far_el2")
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn get_esr() -> usize {
+    mrs!("esr_el2")
+}
+
+/// Get the value of the HPFAR register, the IPA page of a stage-2 abort.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn get_hpfar() -> usize {
+    mrs!("hpfar_el2")
+}
+
+/// Get the value of the MDSCR register, the debug control of the core.
+#[cfg(any(feature = "ENABLE_GDB", feature = "HARDWARE_DEBUG_API"))]
+#[inline]
+pub fn get_mdscr() -> usize {
+    mrs!("mdscr_el1")
+}
+
+/// Set the value of the MDSCR register, it takes effect by the next eret.
+#[cfg(any(feature = "ENABLE_GDB", feature = "HARDWARE_DEBUG_API"))]
+#[inline]
+pub fn set_mdscr(mdscr: usize) {
+    msr!("mdscr_el1", mdscr);
+}
+
+/// Clear the OS lock, which holds the debug exceptions of the core after reset.
+#[cfg(any(feature = "ENABLE_GDB", feature = "HARDWARE_DEBUG_API"))]
+#[inline]
+pub fn unlock_os_lock() {
+    unsafe {
+        core::arch::asm!("msr oslar_el1, xzr");
+    }
+}
+
+/// PMCR_EL0.E and PMCR_EL0.C, enable the counters and reset the cycle counter
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+const PMCR_ENABLE_RESET_CCNT: usize = (1 << 0) | (1 << 2);
+/// PMCNTENSET_EL0.C
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+const PMCNTENSET_CCNT: usize = 1 << 31;
+
+/// Start the PMU cycle counter of the current core.
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub fn init_cycle_counter() {
+    let pmcr = mrs!("pmcr_el0") | PMCR_ENABLE_RESET_CCNT;
+    msr!("pmcr_el0", pmcr);
+    let pmcntenset = PMCNTENSET_CCNT;
+    msr!("pmcntenset_el0", pmcntenset);
+}
+
+/// Read the cycle counter used to timestamp the kernel entries.
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+#[inline]
+pub fn read_cycle_counter() -> usize {
+    mrs!("pmccntr_el0")
+}
@@ -1,30 +1,62 @@
 mod boot;
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+mod breakpoint;
 mod c_traps;
 mod consts;
 mod exception;
-mod ffi;
+mod fpu;
+#[cfg(feature = "ENABLE_GDB")]
+pub mod gdb;
+mod generic_timer;
 pub(self) mod instruction;
 mod pg;
 mod platform;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub mod vcpu;
 
 pub mod arm_gic;
 
-use aarch64_cpu::registers::{Writeable, CNTV_CTL_EL0, CNTV_TVAL_EL0};
 pub use boot::try_init_kernel;
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub use breakpoint::{breakpoint_state_changed, restore_breakpoint_state};
 pub use c_traps::restore_user_context;
 pub use exception::handleUnknownSyscall;
+#[cfg(feature = "ENABLE_SMP")]
+pub use fpu::fpu_thread_migrate;
+pub use fpu::{fpu_thread_delete, lazy_fpu_restore};
+pub use generic_timer::{read_time, read_time_freq};
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub use instruction::read_cycle_counter;
 pub(crate) use pg::set_vm_root_for_flush;
+#[cfg(feature = "ENABLE_SMP")]
+pub use platform::init_cpu;
 pub use platform::init_freemem;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub use vcpu::vcpu_switch;
 
 /// Reset the current Timer
+#[cfg(not(feature = "KERNEL_MCS"))]
 #[no_mangle]
 pub fn resetTimer() {
-    /*
-        SYSTEM_WRITE_WORD(CNT_TVAL, TIMER_RELOAD);
-        SYSTEM_WRITE_WORD(CNT_CTL, BIT(0));
-    */
-    const TIMER_CLOCK_HZ: u64 = 62500000;
-    // TODO: Set a proper timer clock
-    CNTV_TVAL_EL0.set(TIMER_CLOCK_HZ / 1000 * 10);
-    CNTV_CTL_EL0.set(1);
+    generic_timer::reset_timer();
+}
+
+/// Get the current time of the kernel timer in ticks
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn getCurrentTime() -> usize {
+    generic_timer::read_virtual_time()
+}
+
+/// Raise the timer interrupt when the time reaches `deadline`
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn setDeadline(deadline: usize) {
+    generic_timer::set_deadline(deadline);
+}
+
+/// Clear the pending timer interrupt until the next deadline is set
+#[cfg(feature = "KERNEL_MCS")]
+pub fn ackDeadlineIRQ() {
+    generic_timer::ack_deadline_irq();
 }
@@ -71,6 +71,10 @@ pub fn set_vm_root_for_flush(vspace: usize, asid: asid_t) -> bool {
         return false;
     }
 
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    let Some(asid) = sel4_vspace::get_hw_asid(asid, vspace) else {
+        return false;
+    };
     setCurrentUserVSpaceRoot(ttbr_new(asid, vspace));
     true
 }
@@ -6,18 +6,20 @@ use sel4_common::ffi::kernel_stack_alloc;
 use sel4_common::ffi_addr;
 use sel4_common::sel4_config::{wordBits, CONFIG_KERNEL_STACK_BITS};
 
-use super::ffi::*;
+use super::fpu::fpsimd_init;
+use super::generic_timer::init_generic_timer;
 use crate::boot::{
     avail_p_regs_addr, avail_p_regs_size, paddr_to_pptr_reg, res_reg, reserve_region,
-    rust_init_freemem,
+    rust_init_freemem, FdtDevice, FdtInfo,
 };
 use crate::config::*;
+use crate::interrupt::ksSerialIRQ;
 use crate::structures::*;
 use crate::utils::{fpsimd_HWCapTest, setVTable};
 use log::debug;
 use sel4_vspace::*;
 
-use super::arm_gic::gic_v2::gic_v2::{cpu_initLocalIRQController, dist_init};
+use super::arm_gic::{Gic, GicDriver};
 
 #[allow(unused)]
 pub fn init_cpu() -> bool {
@@ -39,21 +41,73 @@ pub fn init_cpu() -> bool {
         setVTable(ffi_addr!(arm_vector_table));
     }
     TPIDR_EL1.set(stack_top);
+    // the vectors of the kernel in EL2 find the stack there
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    unsafe {
+        asm!("msr tpidr_el2, {}", in(reg) stack_top);
+    }
 
     let haveHWFPU = fpsimd_HWCapTest();
+    if haveHWFPU {
+        fpsimd_init();
+    }
 
     // initLocalIRQController
-    cpu_initLocalIRQController();
+    Gic::cpu_iface_init();
+
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    super::vcpu::vcpu_boot_init();
 
     // armv_init_user_access
     armv_init_user_access();
 
-    unsafe {
-        initTimer();
-    }
+    init_generic_timer();
     true
 }
 
+/// The type of a shared peripheral interrupt in the device tree
+const GIC_SPI: u32 = 0;
+/// The type of a private peripheral interrupt in the device tree
+const GIC_PPI: u32 = 1;
+
+/// Place the kernel devices where the device tree puts them, before they are mapped.
+pub fn init_fdt_devices(info: &FdtInfo) {
+    if let Some(uart) = info.uart.filter(FdtDevice::is_default_serial) {
+        // nothing is printed until the UART is mapped at its new place
+        driver_collect::set_serial(
+            set_uart_paddr(uart.reg[0].start),
+            uart.reg_shift,
+            uart.reg_io_width.unwrap_or(1),
+        );
+        // a GIC interrupt is the type, the number and the flags
+        let irq = match (uart.interrupt_cell(0), uart.interrupt_cell(1)) {
+            (Some(GIC_SPI), Some(number)) => number as usize + 32,
+            (Some(GIC_PPI), Some(number)) => number as usize + 16,
+            _ => irqInvalid,
+        };
+        if irq <= maxIRQ {
+            unsafe {
+                ksSerialIRQ = irq;
+            }
+        }
+    }
+    if let Some(gic) = info.interrupt_controller {
+        // the GIC the kernel is built for
+        #[cfg(not(feature = "GIC_V3"))]
+        let compatible = ["arm,cortex-a15-gic", "arm,gic-400"];
+        #[cfg(feature = "GIC_V3")]
+        let compatible = ["arm,gic-v3"];
+        if compatible.iter().any(|c| gic.is_compatible(c)) {
+            set_gic_paddr(gic.reg[0].start, gic.reg[1].start);
+            // the GICv2 puts the virtual interface control after the CPU interface
+            #[cfg(all(not(feature = "GIC_V3"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+            if gic.reg[2].start != 0 {
+                set_gic_vcpuctrl_paddr(gic.reg[2].start);
+            }
+        }
+    }
+}
+
 pub fn init_freemem(ui_p_reg: p_region_t, dtb_p_reg: p_region_t) -> bool {
     unsafe {
         res_reg[0].start = paddr_to_pptr(kpptr_to_paddr(KERNEL_ELF_BASE));
@@ -110,7 +164,10 @@ pub fn cleanInvalidateL1Caches() {
 pub fn invalidateLocalTLB() {
     unsafe {
         asm!("dsb sy;"); // DSB SY
+        #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
         asm!("tlbi vmalle1;");
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        asm!("tlbi alle2; tlbi vmalls12e1;");
         asm!("dsb sy;"); // DSB SY
         asm!("isb;"); // ISB SY
     }
@@ -190,8 +247,10 @@ fn readCacheSize(level: usize) -> usize {
 
 fn armv_init_user_access() {
     CNTKCTL_EL1.set(0);
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    super::instruction::init_cycle_counter();
 }
 
 pub fn initIRQController() {
-    dist_init();
+    Gic::dist_init();
 }
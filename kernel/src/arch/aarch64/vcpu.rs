@@ -0,0 +1,590 @@
+//! The VCPU objects of the kernel running in EL2.
+//!
+//! A VCPU holds the EL1 system registers, the virtual timer and the virtual GIC interface of a
+//! guest. The thread bound to it runs the guest at EL1, with its VSpace as the stage-2 tables.
+//! Each core keeps the VCPU loaded in its hardware until another one needs it: a native thread
+//! only disables the loaded VCPU, so going back to the guest just enables it again.
+
+use super::arm_gic::{
+    Gic, GicDriver, VGicDriver, INTERRUPT_VGIC_MAINTENANCE, INTERRUPT_VTIMER_EVENT, VGIC_HCR_EN,
+    VGIC_MISR_EOI,
+};
+use super::fpu::handle_fpu_fault;
+use crate::kernel::boot::current_fault;
+use crate::syscall::handle_fault;
+use core::arch::asm;
+use sel4_common::arch::*;
+use sel4_common::fault::seL4_Fault_t;
+use sel4_common::message_info::seL4_MessageInfo_t;
+use sel4_common::sel4_config::{seL4_VCPUBits, CONFIG_MAX_NUM_NODES};
+use sel4_common::structures::exception_t;
+use sel4_common::utils::{convert_to_mut_type_ref, convert_to_option_mut_type_ref, cpu_id};
+use sel4_common::BIT;
+use sel4_task::{
+    activateThread, get_currenct_thread, schedule, set_thread_state, tcb_t, ThreadState,
+};
+
+const HCR_VM: usize = 1 << 0;
+const HCR_SWIO: usize = 1 << 1;
+const HCR_FMO: usize = 1 << 3;
+const HCR_IMO: usize = 1 << 4;
+const HCR_AMO: usize = 1 << 5;
+const HCR_DC: usize = 1 << 12;
+const HCR_TSC: usize = 1 << 19;
+const HCR_TAC: usize = 1 << 21;
+const HCR_TTLB: usize = 1 << 25;
+const HCR_TVM: usize = 1 << 26;
+const HCR_TGE: usize = 1 << 27;
+const HCR_RW: usize = 1 << 31;
+/// Stage-2 translation, AArch64 EL1 and the physical interrupts taken to EL2
+const HCR_COMMON: usize = HCR_VM | HCR_RW | HCR_AMO | HCR_IMO | HCR_FMO;
+/// The native threads run at EL0 with the EL1 regime turned off and their exceptions taken to EL2
+const HCR_NATIVE: usize =
+    HCR_COMMON | HCR_TGE | HCR_TVM | HCR_TTLB | HCR_DC | HCR_TAC | HCR_SWIO | HCR_TSC;
+const HCR_VCPU: usize = HCR_COMMON | HCR_TSC;
+
+/// The RES1 bits of SCTLR_EL1
+const SCTLR_EL1_RES: usize = 0x30d00800;
+const SCTLR_EL1_C: usize = 1 << 2;
+const SCTLR_EL1_CP15BEN: usize = 1 << 5;
+const SCTLR_EL1_I: usize = 1 << 12;
+const SCTLR_EL1_UTC: usize = 1 << 15;
+const SCTLR_EL1_NTWI: usize = 1 << 16;
+const SCTLR_EL1_NTWE: usize = 1 << 18;
+const SCTLR_EL1_UCI: usize = 1 << 26;
+const SCTLR_EL1: usize =
+    SCTLR_EL1_RES | SCTLR_EL1_CP15BEN | SCTLR_EL1_UTC | SCTLR_EL1_NTWI | SCTLR_EL1_NTWE;
+const SCTLR_EL1_NATIVE: usize = SCTLR_EL1 | SCTLR_EL1_C | SCTLR_EL1_I | SCTLR_EL1_UCI;
+/// A guest starts with its MMU and caches off
+const SCTLR_EL1_VM: usize = SCTLR_EL1 | SCTLR_EL1_UCI;
+
+const VTCR_EL2_RES1: usize = 1 << 31;
+/// VTCR_EL2.SL0, the walk of the 4-level stage-2 tables starts at level 0
+const VTCR_EL2_SL0_LEVEL0: usize = 2 << 6;
+const VTCR_EL2_IRGN0_WBWA: usize = 1 << 8;
+const VTCR_EL2_ORGN0_WBWA: usize = 1 << 10;
+const VTCR_EL2_SH0_INNER: usize = 3 << 12;
+const VTCR_EL2_PS_SHIFT: usize = 16;
+/// The IPA and PA sizes of ID_AA64MMFR0_EL1.PARange, the kernel tables reach 48 bits at most
+const PARANGE_BITS: [usize; 6] = [32, 36, 40, 42, 44, 48];
+
+/// CNTHCTL_EL2.EL1PCTEN and CNTHCTL_EL2.EL1PCEN, the physical counter and timer stay accessible
+const CNTHCTL_EL2_EL1PCTEN: usize = 1 << 0;
+const CNTHCTL_EL2_EL1PCEN: usize = 1 << 1;
+
+/// CPACR_EL1.FPEN, the FP/SIMD accesses are trapped by CPTR_EL2 instead
+const CPACR_EL1_FPEN_TRAP_NONE: usize = 0x3 << 20;
+
+const ESR_EC_SHIFT: usize = 26;
+const ESR_EC_MASK: usize = 0x3f;
+/// The guest touched the FPU while CPTR_EL2.TFP is set
+const ESR_EC_TFP: usize = 0x07;
+
+/// The most list registers of a GIC virtual interface
+const GIC_VCPU_MAX_NUM_LR: usize = 64;
+
+/// The state of the virtual CPU interface saved for a VCPU
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct gicVCpuIface_t {
+    pub hcr: u32,
+    pub vmcr: u32,
+    pub apr: u32,
+    pub lr: [u64; GIC_VCPU_MAX_NUM_LR],
+}
+
+/// The VCPU object
+#[repr(C)]
+pub struct vcpu_t {
+    /// The thread bound to the VCPU, 0 if there is none
+    pub vcpuTCB: usize,
+    pub vgic: gicVCpuIface_t,
+    pub regs: [usize; seL4_VCPUReg_Num],
+    /// The virtual timer is masked until the guest acknowledges its last event
+    pub vppi_masked: bool,
+}
+
+const _: () = assert!(core::mem::size_of::<vcpu_t>() <= BIT!(seL4_VCPUBits));
+
+impl vcpu_t {
+    #[inline]
+    pub fn get_ptr(&self) -> usize {
+        self as *const vcpu_t as usize
+    }
+
+    /// Check if the VCPU is loaded in the hardware of the current core
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe { armHSCurVCPU[cpu_id()] == self.get_ptr() }
+    }
+}
+
+/// The VCPU loaded in each core, 0 if there is none
+static mut armHSCurVCPU: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+/// Whether the loaded VCPU of each core is enabled, it is disabled while a native thread runs
+static mut armHSVCPUActive: [bool; CONFIG_MAX_NUM_NODES] = [false; CONFIG_MAX_NUM_NODES];
+
+/// The number of list registers of the GIC virtual interface
+static mut gic_vcpu_num_list_regs: usize = 0;
+
+/// Generate the accessors of the system registers a VCPU is made of
+macro_rules! vcpu_hw_regs {
+    ($($index:ident => $reg:literal),* $(,)?) => {
+        fn vcpu_hw_read_reg(reg: usize) -> usize {
+            let value: usize;
+            match reg {
+                $($index => unsafe { asm!(concat!("mrs {}, ", $reg), out(reg) value) },)*
+                seL4_VCPUReg_ISR => unsafe { asm!("mrs {}, isr_el1", out(reg) value) },
+                _ => panic!("invalid VCPU register {}", reg),
+            }
+            value
+        }
+
+        /// ISR_EL1 is read only, writing it does nothing
+        fn vcpu_hw_write_reg(reg: usize, value: usize) {
+            match reg {
+                $($index => unsafe { asm!(concat!("msr ", $reg, ", {}"), in(reg) value) },)*
+                seL4_VCPUReg_ISR => {}
+                _ => panic!("invalid VCPU register {}", reg),
+            }
+        }
+    };
+}
+
+vcpu_hw_regs! {
+    seL4_VCPUReg_SCTLR => "sctlr_el1",
+    seL4_VCPUReg_TTBR0 => "ttbr0_el1",
+    seL4_VCPUReg_TTBR1 => "ttbr1_el1",
+    seL4_VCPUReg_TCR => "tcr_el1",
+    seL4_VCPUReg_MAIR => "mair_el1",
+    seL4_VCPUReg_AMAIR => "amair_el1",
+    seL4_VCPUReg_CIDR => "contextidr_el1",
+    seL4_VCPUReg_ACTLR => "actlr_el1",
+    seL4_VCPUReg_CPACR => "cpacr_el1",
+    seL4_VCPUReg_AFSR0 => "afsr0_el1",
+    seL4_VCPUReg_AFSR1 => "afsr1_el1",
+    seL4_VCPUReg_ESR => "esr_el1",
+    seL4_VCPUReg_FAR => "far_el1",
+    seL4_VCPUReg_VBAR => "vbar_el1",
+    seL4_VCPUReg_TPIDR_EL1 => "tpidr_el1",
+    seL4_VCPUReg_SP_EL1 => "sp_el1",
+    seL4_VCPUReg_ELR_EL1 => "elr_el1",
+    seL4_VCPUReg_SPSR_EL1 => "spsr_el1",
+    seL4_VCPUReg_CNTV_CTL => "cntv_ctl_el0",
+    seL4_VCPUReg_CNTV_CVAL => "cntv_cval_el0",
+    seL4_VCPUReg_CNTVOFF => "cntvoff_el2",
+    seL4_VCPUReg_CNTKCTL_EL1 => "cntkctl_el1",
+}
+
+/// The registers that are saved when the VCPU is disabled rather than when it is switched out
+#[inline]
+fn vcpu_reg_saved_when_disabled(reg: usize) -> bool {
+    matches!(
+        reg,
+        seL4_VCPUReg_SCTLR
+            | seL4_VCPUReg_CNTV_CTL
+            | seL4_VCPUReg_CNTV_CVAL
+            | seL4_VCPUReg_CNTVOFF
+            | seL4_VCPUReg_CNTKCTL_EL1
+    )
+}
+
+#[inline]
+fn set_hcr(hcr: usize) {
+    unsafe {
+        asm!("msr hcr_el2, {}", in(reg) hcr);
+        asm!("isb");
+    }
+}
+
+#[inline]
+fn isb() {
+    unsafe { asm!("isb") };
+}
+
+#[inline]
+fn dsb() {
+    unsafe { asm!("dsb sy") };
+}
+
+/// The stage-2 translation of the user VSpaces, the IPA covers the PA of the core
+fn vtcr() -> usize {
+    let mmfr0: usize;
+    unsafe { asm!("mrs {}, id_aa64mmfr0_el1", out(reg) mmfr0) };
+    let parange = (mmfr0 & 0xf).min(PARANGE_BITS.len() - 1);
+    let ipa_bits = PARANGE_BITS[parange];
+    // a walk from level 0 needs T0SZ up to 24
+    assert!(ipa_bits >= 40, "VCPU: a {} bits IPA is too small", ipa_bits);
+    VTCR_EL2_RES1
+        | (parange << VTCR_EL2_PS_SHIFT)
+        | VTCR_EL2_SH0_INNER
+        | VTCR_EL2_ORGN0_WBWA
+        | VTCR_EL2_IRGN0_WBWA
+        | VTCR_EL2_SL0_LEVEL0
+        | (64 - ipa_bits)
+}
+
+/// Initialise the EL2 state of the current core, it starts with the native threads.
+pub fn vcpu_boot_init() {
+    unsafe {
+        gic_vcpu_num_list_regs = Gic::num_list_regs().min(GIC_VCPU_MAX_NUM_LR);
+        asm!("msr vtcr_el2, {}", in(reg) vtcr());
+        asm!("msr cnthctl_el2, {}", in(reg) CNTHCTL_EL2_EL1PCTEN | CNTHCTL_EL2_EL1PCEN);
+        asm!("msr cntvoff_el2, xzr");
+        asm!("msr cpacr_el1, {}", in(reg) CPACR_EL1_FPEN_TRAP_NONE);
+        asm!("msr sctlr_el1, {}", in(reg) SCTLR_EL1_NATIVE);
+    }
+    set_hcr(HCR_NATIVE);
+    Gic::set_hcr(0);
+    // the PPIs are banked, the boot core gets it again with the IRQ states
+    Gic::mask_irq(false, INTERRUPT_VGIC_MAINTENANCE);
+    unsafe {
+        armHSCurVCPU[cpu_id()] = 0;
+        armHSVCPUActive[cpu_id()] = false;
+    }
+}
+
+/// Initialise a new VCPU object.
+pub fn vcpu_init(vcpu: &mut vcpu_t) {
+    vcpu.regs[seL4_VCPUReg_SCTLR] = SCTLR_EL1_VM;
+    vcpu.vgic.hcr = VGIC_HCR_EN;
+}
+
+/// Check if the current core runs a guest.
+#[inline]
+pub fn vcpu_active() -> bool {
+    unsafe { armHSVCPUActive[cpu_id()] }
+}
+
+/// Get the number of list registers the guests can be given.
+#[inline]
+pub fn num_list_regs() -> usize {
+    unsafe { gic_vcpu_num_list_regs }
+}
+
+fn save_virt_timer(vcpu: &mut vcpu_t) {
+    vcpu.regs[seL4_VCPUReg_CNTV_CTL] = vcpu_hw_read_reg(seL4_VCPUReg_CNTV_CTL);
+    // keep the timer of the guest from firing while it is not running
+    vcpu_hw_write_reg(seL4_VCPUReg_CNTV_CTL, 0);
+    isb();
+    vcpu.regs[seL4_VCPUReg_CNTV_CVAL] = vcpu_hw_read_reg(seL4_VCPUReg_CNTV_CVAL);
+    vcpu.regs[seL4_VCPUReg_CNTVOFF] = vcpu_hw_read_reg(seL4_VCPUReg_CNTVOFF);
+    vcpu.regs[seL4_VCPUReg_CNTKCTL_EL1] = vcpu_hw_read_reg(seL4_VCPUReg_CNTKCTL_EL1);
+    Gic::mask_irq(true, INTERRUPT_VTIMER_EVENT);
+}
+
+fn restore_virt_timer(vcpu: &vcpu_t) {
+    vcpu_hw_write_reg(seL4_VCPUReg_CNTV_CVAL, vcpu.regs[seL4_VCPUReg_CNTV_CVAL]);
+    vcpu_hw_write_reg(seL4_VCPUReg_CNTVOFF, vcpu.regs[seL4_VCPUReg_CNTVOFF]);
+    vcpu_hw_write_reg(
+        seL4_VCPUReg_CNTKCTL_EL1,
+        vcpu.regs[seL4_VCPUReg_CNTKCTL_EL1],
+    );
+    vcpu_hw_write_reg(seL4_VCPUReg_CNTV_CTL, vcpu.regs[seL4_VCPUReg_CNTV_CTL]);
+    isb();
+    if !vcpu.vppi_masked {
+        Gic::mask_irq(false, INTERRUPT_VTIMER_EVENT);
+    }
+}
+
+/// Hand the core to the loaded VCPU.
+fn vcpu_enable(vcpu: &vcpu_t) {
+    vcpu_hw_write_reg(seL4_VCPUReg_SCTLR, vcpu.regs[seL4_VCPUReg_SCTLR]);
+    set_hcr(HCR_VCPU);
+    Gic::set_hcr(vcpu.vgic.hcr);
+    restore_virt_timer(vcpu);
+}
+
+/// Hand the core back to the native threads, the state of a VCPU which is left loaded is saved
+/// where the native threads overwrite it.
+fn vcpu_disable(vcpu: Option<&mut vcpu_t>) {
+    dsb();
+    if let Some(vcpu) = vcpu {
+        vcpu.vgic.hcr = Gic::get_hcr();
+        vcpu.regs[seL4_VCPUReg_SCTLR] = vcpu_hw_read_reg(seL4_VCPUReg_SCTLR);
+        save_virt_timer(vcpu);
+    }
+    Gic::set_hcr(0);
+    isb();
+    vcpu_hw_write_reg(seL4_VCPUReg_SCTLR, SCTLR_EL1_NATIVE);
+    isb();
+    set_hcr(HCR_NATIVE);
+}
+
+/// Save the loaded VCPU, `active` tells if the parts saved by `vcpu_disable` are still loaded.
+fn vcpu_save(vcpu: &mut vcpu_t, active: bool) {
+    dsb();
+    if active {
+        vcpu.regs[seL4_VCPUReg_SCTLR] = vcpu_hw_read_reg(seL4_VCPUReg_SCTLR);
+        vcpu.vgic.hcr = Gic::get_hcr();
+        save_virt_timer(vcpu);
+    }
+    vcpu.vgic.vmcr = Gic::get_vmcr();
+    vcpu.vgic.apr = Gic::get_apr();
+    for i in 0..num_list_regs() {
+        vcpu.vgic.lr[i] = Gic::get_lr(i);
+    }
+    for reg in seL4_VCPUReg_TTBR0..=seL4_VCPUReg_SPSR_EL1 {
+        vcpu.regs[reg] = vcpu_hw_read_reg(reg);
+    }
+    isb();
+}
+
+/// Load the VCPU into the core and enable it.
+fn vcpu_restore(vcpu: &mut vcpu_t) {
+    // the virtual interface is off while its state is rewritten
+    Gic::set_hcr(0);
+    isb();
+    Gic::set_vmcr(vcpu.vgic.vmcr);
+    Gic::set_apr(vcpu.vgic.apr);
+    for i in 0..num_list_regs() {
+        Gic::set_lr(i, vcpu.vgic.lr[i]);
+    }
+    for reg in seL4_VCPUReg_TTBR0..=seL4_VCPUReg_SPSR_EL1 {
+        vcpu_hw_write_reg(reg, vcpu.regs[reg]);
+    }
+    vcpu_enable(vcpu);
+}
+
+/// Switch the core to the VCPU of the thread going back to user level, 0 for a native thread.
+/// Called by [restore_user_context](super::restore_user_context) and the fastpath.
+pub fn vcpu_switch(new: usize) {
+    let cpu = cpu_id();
+    unsafe {
+        let cur = armHSCurVCPU[cpu];
+        if new == 0 {
+            // the VCPU stays loaded, the next switch back to it only enables it
+            if armHSVCPUActive[cpu] {
+                vcpu_disable(convert_to_option_mut_type_ref::<vcpu_t>(cur));
+                armHSVCPUActive[cpu] = false;
+            }
+        } else if new != cur {
+            if let Some(cur) = convert_to_option_mut_type_ref::<vcpu_t>(cur) {
+                vcpu_save(cur, armHSVCPUActive[cpu]);
+            }
+            vcpu_restore(convert_to_mut_type_ref::<vcpu_t>(new));
+            armHSCurVCPU[cpu] = new;
+            armHSVCPUActive[cpu] = true;
+        } else if !armHSVCPUActive[cpu] {
+            vcpu_enable(convert_to_mut_type_ref::<vcpu_t>(new));
+            armHSVCPUActive[cpu] = true;
+        }
+    }
+}
+
+/// Forget the VCPU loaded in the current core without saving it.
+fn vcpu_invalidate_active() {
+    let cpu = cpu_id();
+    unsafe {
+        if armHSVCPUActive[cpu] {
+            vcpu_disable(None);
+            armHSVCPUActive[cpu] = false;
+        }
+        armHSCurVCPU[cpu] = 0;
+    }
+}
+
+/// Save the VCPU loaded in the current core to its object and unload it.
+#[cfg(feature = "ENABLE_SMP")]
+fn vcpu_flush_local(_: usize) {
+    let cpu = cpu_id();
+    if let Some(cur) = convert_to_option_mut_type_ref::<vcpu_t>(unsafe { armHSCurVCPU[cpu] }) {
+        vcpu_save(cur, unsafe { armHSVCPUActive[cpu] });
+    }
+    vcpu_invalidate_active();
+}
+
+/// Make the object of the VCPU hold its state, unless the VCPU is loaded in the current core.
+/// A VCPU loaded in another core is saved and unloaded there.
+#[allow(unused_variables)]
+fn vcpu_bring_local(vcpu: &vcpu_t) {
+    #[cfg(feature = "ENABLE_SMP")]
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        if cpu != cpu_id() && unsafe { armHSCurVCPU[cpu] } == vcpu.get_ptr() {
+            use crate::smp::{do_remote_op, IpiRemoteCall};
+            do_remote_op(IpiRemoteCall::FunctionCall(vcpu_flush_local, 0), cpu);
+        }
+    }
+}
+
+fn dissociate_vcpu(vcpu: &mut vcpu_t, tcb: &mut tcb_t) {
+    assert_eq!(vcpu.vcpuTCB, tcb.get_ptr());
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        vcpu_invalidate_active();
+    }
+    tcb.tcbArch.tcbVCPU = 0;
+    vcpu.vcpuTCB = 0;
+    tcb.tcbArch.sanitise_spsr();
+}
+
+fn associate_vcpu(vcpu: &mut vcpu_t, tcb: &mut tcb_t) {
+    if let Some(old) = convert_to_option_mut_type_ref::<vcpu_t>(tcb.tcbArch.tcbVCPU) {
+        dissociate_vcpu(old, tcb);
+    }
+    if let Some(old) = convert_to_option_mut_type_ref::<tcb_t>(vcpu.vcpuTCB) {
+        dissociate_vcpu(vcpu, old);
+    }
+    tcb.tcbArch.tcbVCPU = vcpu.get_ptr();
+    vcpu.vcpuTCB = tcb.get_ptr();
+}
+
+/// Unbind the VCPU of a thread that is being deleted.
+pub fn dissociate_vcpu_tcb(tcb: &mut tcb_t) {
+    if let Some(vcpu) = convert_to_option_mut_type_ref::<vcpu_t>(tcb.tcbArch.tcbVCPU) {
+        dissociate_vcpu(vcpu, tcb);
+    }
+}
+
+/// Release a VCPU object that is being deleted.
+pub fn vcpu_finalise(vcpu: &mut vcpu_t) {
+    if let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(vcpu.vcpuTCB) {
+        dissociate_vcpu(vcpu, tcb);
+    } else {
+        vcpu_bring_local(vcpu);
+        if vcpu.is_current() {
+            vcpu_invalidate_active();
+        }
+    }
+}
+
+fn vcpu_read_reg(vcpu: &mut vcpu_t, reg: usize) -> usize {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() && (vcpu_active() || !vcpu_reg_saved_when_disabled(reg)) {
+        vcpu_hw_read_reg(reg)
+    } else {
+        vcpu.regs[reg]
+    }
+}
+
+fn vcpu_write_reg(vcpu: &mut vcpu_t, reg: usize, value: usize) {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() && (vcpu_active() || !vcpu_reg_saved_when_disabled(reg)) {
+        vcpu_hw_write_reg(reg, value);
+    } else {
+        vcpu.regs[reg] = value;
+    }
+}
+
+/// Get the list register of the VCPU, up to date with the hardware.
+pub fn vcpu_get_lr(vcpu: &mut vcpu_t, index: usize) -> u64 {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        vcpu.vgic.lr[index] = Gic::get_lr(index);
+    }
+    vcpu.vgic.lr[index]
+}
+
+pub fn invoke_vcpu_set_tcb(vcpu: &mut vcpu_t, tcb: &mut tcb_t) -> exception_t {
+    associate_vcpu(vcpu, tcb);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_read_reg(vcpu: &mut vcpu_t, reg: usize, call: bool) -> exception_t {
+    let thread = get_currenct_thread();
+    let value = vcpu_read_reg(vcpu, reg);
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = thread.set_mr(0, value);
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo_t::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_write_reg(vcpu: &mut vcpu_t, reg: usize, value: usize) -> exception_t {
+    vcpu_write_reg(vcpu, reg, value);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_inject_irq(vcpu: &mut vcpu_t, index: usize, virq: u64) -> exception_t {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        Gic::set_lr(index, virq);
+    }
+    vcpu.vgic.lr[index] = virq;
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_ack_vppi(vcpu: &mut vcpu_t, irq: usize) -> exception_t {
+    debug_assert_eq!(irq, INTERRUPT_VTIMER_EVENT);
+    vcpu.vppi_masked = false;
+    // the timer of a VCPU which is not running is unmasked by the switch back to it
+    if vcpu.is_current() && vcpu_active() {
+        Gic::mask_irq(false, irq);
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+/// A list register of the running VCPU had its EOI, or the virtual interface needs the VMM.
+pub fn handle_vgic_maintenance() {
+    let cpu = cpu_id();
+    let vcpu = match convert_to_option_mut_type_ref::<vcpu_t>(unsafe { armHSCurVCPU[cpu] }) {
+        Some(vcpu) if vcpu_active() => vcpu,
+        _ => {
+            log::debug!("Received VGIC maintenance without active VCPU!");
+            return;
+        }
+    };
+    let eisr = Gic::get_eisr();
+    let misr = Gic::get_misr();
+    let fault = if misr & VGIC_MISR_EOI != 0 {
+        // an empty EISR gives 64, which is never a list register
+        let index = eisr.trailing_zeros() as usize;
+        if index < num_list_regs() {
+            let lr = Gic::virq_clear_eoi_irq(Gic::get_lr(index));
+            Gic::set_lr(index, lr);
+            // InjectIRQ checks the saved list registers
+            vcpu.vgic.lr[index] = lr;
+            seL4_Fault_t::new_vgic_maintenance(index, 1)
+        } else {
+            seL4_Fault_t::new_vgic_maintenance(0, 0)
+        }
+    } else {
+        seL4_Fault_t::new_vgic_maintenance(0, 0)
+    };
+    let thread = get_currenct_thread();
+    if thread.is_runnable() {
+        unsafe {
+            current_fault = fault;
+        }
+        handle_fault(thread);
+    }
+}
+
+/// The virtual timer of the running VCPU fired, it stays masked until the VMM acknowledges it.
+pub fn handle_vppi_event(irq: usize) {
+    Gic::mask_irq(true, irq);
+    let cpu = cpu_id();
+    let vcpu = match convert_to_option_mut_type_ref::<vcpu_t>(unsafe { armHSCurVCPU[cpu] }) {
+        Some(vcpu) if vcpu_active() => vcpu,
+        _ => return,
+    };
+    vcpu.vppi_masked = true;
+    let thread = get_currenct_thread();
+    if thread.is_runnable() {
+        unsafe {
+            current_fault = seL4_Fault_t::new_vppi_event(irq);
+        }
+        handle_fault(thread);
+    }
+}
+
+/// A guest trapped into the kernel with the syndrome `hsr`, the first FPU access of the thread
+/// is handled by the kernel and the rest goes to the VMM.
+pub fn handle_vcpu_fault(hsr: usize) {
+    if (hsr >> ESR_EC_SHIFT) & ESR_EC_MASK == ESR_EC_TFP {
+        handle_fpu_fault();
+        return;
+    }
+    unsafe {
+        current_fault = seL4_Fault_t::new_vcpu_fault(hsr);
+    }
+    handle_fault(get_currenct_thread());
+    schedule();
+    activateThread();
+}
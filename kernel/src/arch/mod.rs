@@ -7,3 +7,27 @@ pub use aarch64::*;
 mod riscv;
 #[cfg(target_arch = "riscv64")]
 pub use riscv::*;
+
+#[cfg(feature = "KERNEL_MCS")]
+use crate::config::{TIMER_CLOCK_HZ, TIMER_OVERHEAD_TICKS, TIMER_PRECISION, US_IN_S};
+
+/// Convert microseconds to the ticks of the kernel timer
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn usToTicks(us: usize) -> usize {
+    (us as u128 * TIMER_CLOCK_HZ as u128 / US_IN_S as u128) as usize
+}
+
+/// Convert the ticks of the kernel timer to microseconds
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn ticksToUs(ticks: usize) -> usize {
+    (ticks as u128 * US_IN_S as u128 / TIMER_CLOCK_HZ as u128) as usize
+}
+
+/// How early a deadline is programmed, so that the interrupt is not raised after it
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn getTimerPrecision() -> usize {
+    usToTicks(TIMER_PRECISION) + TIMER_OVERHEAD_TICKS
+}
@@ -1,16 +1,17 @@
 use log::debug;
 use sel4_common::{arch::config::KERNEL_ELF_BASE, sel4_config::PAGE_BITS, BIT};
 use sel4_task::create_idle_thread;
-use sel4_vspace::{kpptr_to_paddr, rust_map_kernel_window};
+use sel4_vspace::{
+    activate_kernel_vspace, kpptr_to_paddr, map_kernel_devices, rust_map_kernel_window,
+};
 
 use crate::{
-    arch::{init_cpu, init_freemem},
+    arch::{init_cpu, init_fdt_devices, init_freemem, plic_init_controller},
     boot::{
         bi_finalise, calculate_extra_bi_size_bits, create_untypeds, init_core_state, init_dtb,
-        ksNumCPUs, ndks_boot, paddr_to_pptr_reg, root_server_init,
+        init_fdt, ksNumCPUs, log_fdt_info, ndks_boot, paddr_to_pptr_reg, root_server_init,
     },
     config::{BI_FRAME_SIZE_BITS, USER_TOP},
-    ffi::init_plat,
     structures::{p_region_t, seL4_SlotRegion, v_region_t},
 };
 
@@ -46,11 +47,18 @@ pub fn try_init_kernel(
     let bi_frame_vptr = ipcbuf_vptr + BIT!(PAGE_BITS);
     let extra_bi_frame_vptr = bi_frame_vptr + BIT!(BI_FRAME_SIZE_BITS);
     rust_map_kernel_window();
+    // the device tree is read through the kernel window, it decides where the devices are
+    activate_kernel_vspace();
+    let fdt_info = init_fdt(dtb_phys_addr, dtb_size);
+    if let Some(info) = fdt_info {
+        init_fdt_devices(info);
+    }
+    map_kernel_devices();
+    driver_collect::init();
     init_cpu();
+    log_fdt_info(fdt_info);
 
-    unsafe {
-        init_plat();
-    }
+    init_plat();
 
     let dtb_p_reg = init_dtb(dtb_size, dtb_phys_addr, &mut extra_bi_size);
     if dtb_p_reg.is_none() {
@@ -103,11 +111,13 @@ pub fn try_init_kernel(
         *ksNumCPUs.lock() = 1;
         #[cfg(feature = "ENABLE_SMP")]
         {
-            unsafe {
-                clh_lock_init();
-                release_secondary_cores();
-                clh_lock_acquire(cpu_id(), false);
-            }
+            use crate::boot::release_secondary_cores;
+            use crate::smp::{clh_lock_acquire, clh_lock_init};
+            use sel4_common::utils::cpu_id;
+
+            clh_lock_init();
+            release_secondary_cores();
+            clh_lock_acquire(cpu_id(), false);
         }
 
         debug!("Booting all finished, dropped to user space");
@@ -118,3 +128,7 @@ pub fn try_init_kernel(
 
     true
 }
+
+fn init_plat() {
+    plic_init_controller()
+}
@@ -6,18 +6,29 @@ use crate::{
         RISCVInstructionAccessFault, RISCVInstructionPageFault, RISCVLoadAccessFault,
         RISCVLoadPageFault, RISCVStoreAccessFault, RISCVStorePageFault,
     },
-    syscall::slowpath,
+    kernel::fastpath::fastpath_signal,
+    syscall::{slowpath, SysSend},
 };
 
 use sel4_task::*;
 
 use super::exception::{handleUserLevelFault, handleVMFaultEvent};
+#[cfg(feature = "HAVE_FPU")]
+use super::fpu::{handle_fpu_fault, lazy_fpu_restore};
+#[cfg(feature = "HAVE_FPU")]
+use crate::config::RISCVInstructionIllegal;
 use crate::interrupt::handler::handleInterruptEntry;
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+use crate::kernel::benchmark::benchmark_track_syscall;
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+use crate::kernel::benchmark::{benchmark_track_exit, benchmark_track_start};
+#[cfg(feature = "HAVE_FPU")]
+use sel4_common::arch::SSTATUS_FS_OFF;
 
 #[cfg(feature = "ENABLE_SMP")]
 use crate::{
-    deps::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
     interrupt::getActiveIRQ,
+    smp::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
 };
 
 #[cfg(feature = "ENABLE_SMP")]
@@ -25,8 +36,14 @@ use sel4_common::utils::cpu_id;
 
 #[no_mangle]
 pub fn restore_user_context() {
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_exit();
     unsafe {
         // debug!("restore_user_context");
+        #[cfg(feature = "HAVE_FPU")]
+        lazy_fpu_restore(get_currenct_thread());
+        #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+        super::vcpu::vcpu_switch(get_currenct_thread().tcbArch.tcbVCPU);
         let cur_thread_reg: usize = get_currenct_thread().tcbArch.raw_ptr();
         #[cfg(feature = "ENABLE_SMP")]
         {
@@ -34,7 +51,7 @@ pub fn restore_user_context() {
                 clh_lock_release(cpu_id());
             }
             // debug!("restore_user_context2");
-            let mut cur_sp: usize = 8;
+            let cur_sp: usize;
             asm!(
                 "csrr {}, sscratch",
                 out(reg) cur_sp,
@@ -102,11 +119,11 @@ pub fn c_handle_interrupt() {
     {
         use crate::config::INTERRUPT_IPI_0;
         if getActiveIRQ() != INTERRUPT_IPI_0 {
-            unsafe {
-                clh_lock_acquire(cpu_id(), true);
-            }
+            clh_lock_acquire(cpu_id(), true);
         }
     }
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
     // debug!("c_handle_interrupt");
     handleInterruptEntry();
     restore_user_context();
@@ -115,14 +132,21 @@ pub fn c_handle_interrupt() {
 #[no_mangle]
 pub fn c_handle_exception() {
     #[cfg(feature = "ENABLE_SMP")]
-    unsafe {
-        clh_lock_acquire(cpu_id(), false);
-    }
+    clh_lock_acquire(cpu_id(), false);
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
     // if hart_id() == 0 {
     //     debug!("c_handle_exception");
     // }
 
     let cause = read_scause();
+    // everything a guest raises goes to its VMM
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    if get_currenct_thread().tcbArch.tcbVCPU != 0 {
+        super::vcpu::handle_vcpu_fault(cause);
+        restore_user_context();
+        return;
+    }
     match cause {
         RISCVInstructionAccessFault
         | RISCVLoadAccessFault
@@ -132,6 +156,10 @@ pub fn c_handle_exception() {
         | RISCVInstructionPageFault => {
             handleVMFaultEvent(cause);
         }
+        #[cfg(feature = "HAVE_FPU")]
+        RISCVInstructionIllegal if get_currenct_thread().tcbArch.get_fs() == SSTATUS_FS_OFF => {
+            handle_fpu_fault();
+        }
         _ => {
             handleUserLevelFault(cause, 0);
         }
@@ -142,8 +170,14 @@ pub fn c_handle_exception() {
 #[no_mangle]
 pub fn c_handle_syscall(_cptr: usize, _msgInfo: usize, syscall: usize) {
     #[cfg(feature = "ENABLE_SMP")]
-    unsafe {
-        clh_lock_acquire(cpu_id(), false);
+    clh_lock_acquire(cpu_id(), false);
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_syscall(_cptr, _msgInfo, syscall as isize, false);
+    // seL4_Signal is the common Send, it tries the fastpath first
+    if syscall as isize == SysSend {
+        fastpath_signal(_cptr, _msgInfo);
     }
     // if hart_id() == 0 {
     //     debug!("c_handle_syscall: syscall: {},", syscall as isize);
@@ -2,20 +2,27 @@ use super::{read_stval, read_time};
 use crate::compatibility::lookupIPCBuffer;
 use crate::config::*;
 use crate::halt;
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+use crate::kernel::benchmark::handle_benchmark_syscall;
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+use crate::kernel::benchmark::{benchmark_track_entry, kernel_entry_t};
 use crate::kernel::boot::current_fault;
+use crate::kernel::capdl::debug_capdl_snapshot;
+use crate::kernel::debug::debug_dump_scheduler;
 use crate::object::lookupCapAndSlot;
 use crate::strnlen;
 use crate::syscall::handle_fault;
 use crate::syscall::{
-    SysDebugCapIdentify, SysDebugDumpScheduler, SysDebugHalt, SysDebugNameThread, SysDebugPutChar,
-    SysDebugSnapshot, SysGetClock,
+    SysDebugCapIdentify, SysDebugDumpScheduler, SysDebugGetChar, SysDebugHalt, SysDebugNameThread,
+    SysDebugPutChar, SysDebugSnapshot, SysGetClock,
 };
+use core::mem::size_of;
 use log::debug;
 use sel4_common::arch::ArchReg::*;
 use sel4_common::fault::seL4_Fault_t;
-use sel4_common::print;
 use sel4_common::sel4_config::seL4_MsgMaxLength;
 use sel4_common::structures::exception_t;
+use sel4_common::{print, println};
 use sel4_cspace::arch::CapTag;
 use sel4_task::{activateThread, get_currenct_thread, schedule};
 
@@ -26,19 +33,31 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
         print!("{}", thread.tcbArch.get_register(Cap) as u8 as char);
         return exception_t::EXCEPTION_NONE;
     }
+    if w == SysDebugGetChar {
+        // -1 tells the thread that no character has been received
+        let c = sel4_common::console::getchar().map_or(usize::MAX, |c| c as usize);
+        thread.tcbArch.set_register(Cap, c);
+        return exception_t::EXCEPTION_NONE;
+    }
     if w == SysDebugDumpScheduler {
-        // unimplement debug
-        // println!("debug dump scheduler");
+        debug_dump_scheduler();
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysDebugHalt {
-        // unimplement debug
-        // println!("debug halt");
-        return exception_t::EXCEPTION_NONE;
+        println!(
+            "Debug halt syscall from user thread {:#x} \"{}\"",
+            thread.get_ptr(),
+            thread.get_name()
+        );
+        halt();
     }
     if w == SysDebugSnapshot {
-        // unimplement debug
-        // println!("debug snap shot");
+        println!(
+            "Debug snapshot syscall from user thread {:#x} \"{}\"",
+            thread.get_ptr(),
+            thread.get_name()
+        );
+        debug_capdl_snapshot();
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysDebugCapIdentify {
@@ -57,11 +76,13 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
             debug!("SysDebugNameThread: cap is not a TCB, halting");
             halt();
         }
-        let name = lookupIPCBuffer(true, thread) + 1;
-        if name == 0 {
+        let buffer = lookupIPCBuffer(true, thread);
+        if buffer == 0 {
             debug!("SysDebugNameThread: Failed to lookup IPC buffer, halting");
             halt();
         }
+        // the name starts at the first message register, right after the tag
+        let name = buffer + size_of::<usize>();
 
         let len = strnlen(name as *const u8, seL4_MsgMaxLength * 8);
         if len == seL4_MsgMaxLength * 8 {
@@ -69,14 +90,31 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
             halt();
         }
 
-        // setThreadName(TCB_PTR(cap_thread_cap_get_capTCBPtr(lu_ret.cap)), name);
+        #[cfg(feature = "DEBUG_BUILD")]
+        {
+            let tcb = sel4_common::utils::convert_to_mut_type_ref::<sel4_task::tcb_t>(
+                lu_ret.cap.get_tcb_ptr(),
+            );
+            tcb.set_name(unsafe { core::slice::from_raw_parts(name as *const u8, len) });
+        }
         return exception_t::EXCEPTION_NONE;
     }
     if w == SysGetClock {
-        let current = read_time();
-        thread.tcbArch.set_register(Cap, current);
+        // The timebase frequency is returned in the first message register, as on aarch64
+        thread.tcbArch.set_register(Cap, read_time());
+        thread.tcbArch.set_register(Msg(0), TIMER_CLOCK_HZ);
         return exception_t::EXCEPTION_NONE;
     }
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    if let Some(ret) = handle_benchmark_syscall(w, thread) {
+        return ret;
+    }
+    debug!(
+        "Unknown syscall {} from thread {:#x} \"{}\"",
+        w,
+        thread.get_ptr(),
+        thread.get_name()
+    );
     unsafe {
         current_fault = seL4_Fault_t::new_unknown_syscall_fault(w as usize);
         handle_fault(get_currenct_thread());
@@ -88,6 +126,10 @@ pub fn handleUnknownSyscall(w: isize) -> exception_t {
 
 #[no_mangle]
 pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    {
+        benchmark_track_entry(kernel_entry_t::new_user_level_fault_entry(w_a));
+    }
     unsafe {
         current_fault = seL4_Fault_t::new_user_exeception(w_a, w_b);
         handle_fault(get_currenct_thread());
@@ -99,6 +141,10 @@ pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
 
 #[no_mangle]
 pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    {
+        benchmark_track_entry(kernel_entry_t::new_vm_fault_entry(vm_faultType));
+    }
     let status = handle_vm_fault(vm_faultType);
     if status != exception_t::EXCEPTION_NONE {
         handle_fault(get_currenct_thread());
@@ -110,6 +156,14 @@ pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
 
 pub fn handle_vm_fault(type_: usize) -> exception_t {
     let addr = read_stval();
+    let thread = get_currenct_thread();
+    debug!(
+        "Handle VM fault: {} at {:#x} from thread {:#x} \"{}\"",
+        type_,
+        addr,
+        thread.get_ptr(),
+        thread.get_name()
+    );
     match type_ {
         RISCVLoadPageFault | RISCVLoadAccessFault => {
             unsafe {
@@ -0,0 +1,121 @@
+//! Lazy FPU context switching.
+//!
+//! Threads run with sstatus.FS Off unless they own the FPU of their core. The first FP access of
+//! another thread raises an illegal instruction exception, and `handle_fpu_fault` then saves the
+//! registers of the owner if sstatus.FS says they are Dirty and loads the ones of the thread.
+
+use core::arch::asm;
+use sel4_common::arch::{ArchReg, SSTATUS_FS, SSTATUS_FS_CLEAN, SSTATUS_FS_DIRTY, SSTATUS_FS_OFF};
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::{convert_to_option_mut_type_ref, cpu_id};
+use sel4_task::{get_currenct_thread, tcb_t};
+
+/// The TCB whose registers are loaded in the FPU of each core, 0 if there is none
+static mut ksActiveFPUState: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+#[inline]
+/// Allow the kernel to access the FP registers, the FS of the thread is restored on sret.
+fn enable_kernel_fpu() {
+    unsafe {
+        asm!("csrs sstatus, {}", in(reg) SSTATUS_FS);
+    }
+}
+
+#[inline]
+/// Check if the FPU of the core of the thread holds the registers of the thread.
+fn native_thread_using_fpu(thread: &tcb_t) -> bool {
+    unsafe { ksActiveFPUState[thread.get_cpu()] == thread.get_ptr() }
+}
+
+/// Save the FPU registers of the current owner if they are Dirty and load the ones of
+/// `new_owner`, which can be 0 to leave the FPU without owner.
+fn switch_local_fpu_owner(new_owner: usize) {
+    enable_kernel_fpu();
+    let cpu = cpu_id();
+    let old_owner = unsafe { ksActiveFPUState[cpu] };
+    if let Some(owner) = convert_to_option_mut_type_ref::<tcb_t>(old_owner) {
+        if owner.tcbArch.get_fs() == SSTATUS_FS_DIRTY {
+            owner.tcbArch.save_fpu_state();
+        }
+        owner.tcbArch.set_fs(SSTATUS_FS_OFF);
+    }
+    if let Some(thread) = convert_to_option_mut_type_ref::<tcb_t>(new_owner) {
+        thread.tcbArch.load_fpu_state();
+        thread.tcbArch.set_fs(SSTATUS_FS_CLEAN);
+    }
+    unsafe {
+        ksActiveFPUState[cpu] = new_owner;
+    }
+}
+
+/// Give the FPU to the current thread after its FP access trapped with sstatus.FS Off.
+pub fn handle_fpu_fault() {
+    let thread = get_currenct_thread();
+    assert!(!native_thread_using_fpu(thread));
+    switch_local_fpu_owner(thread.get_ptr());
+    // Run the FP instruction again
+    thread.tcbArch.set_register(
+        ArchReg::NextIP,
+        thread.tcbArch.get_register(ArchReg::FaultIP),
+    );
+}
+
+/// Make sure the thread about to run traps on FP accesses unless it owns the FPU.
+#[inline]
+pub fn lazy_fpu_restore(thread: &mut tcb_t) {
+    if !native_thread_using_fpu(thread) {
+        thread.tcbArch.set_fs(SSTATUS_FS_OFF);
+    }
+}
+
+/// Write the FPU registers of the thread back to its TCB if it owns the FPU of the current core.
+/// The registers of a thread owning the FPU of another core are left as they were last saved.
+pub fn fpu_sync(thread: &mut tcb_t) {
+    if native_thread_using_fpu(thread)
+        && thread.get_cpu() == cpu_id()
+        && thread.tcbArch.get_fs() == SSTATUS_FS_DIRTY
+    {
+        enable_kernel_fpu();
+        thread.tcbArch.save_fpu_state();
+        thread.tcbArch.set_fs(SSTATUS_FS_CLEAN);
+    }
+}
+
+/// Take the FPU from the thread after writing the FPU registers saved in its TCB, so that they
+/// are loaded on its next FP access.
+pub fn fpu_release(thread: &mut tcb_t) {
+    if native_thread_using_fpu(thread) {
+        unsafe {
+            ksActiveFPUState[thread.get_cpu()] = 0;
+        }
+        thread.tcbArch.set_fs(SSTATUS_FS_OFF);
+    }
+}
+
+/// Release the FPU owned by a thread that is being deleted.
+pub fn fpu_thread_delete(thread: &tcb_t) {
+    if native_thread_using_fpu(thread) {
+        // The FS of the next owner is Off, so the registers are loaded before being used
+        unsafe {
+            ksActiveFPUState[thread.get_cpu()] = 0;
+        }
+    }
+}
+
+/// Release the FPU of the core of a thread moving to another core, saving its registers there.
+#[cfg(feature = "ENABLE_SMP")]
+pub fn fpu_thread_migrate(thread: &tcb_t) {
+    use crate::smp::{do_remote_op, IpiRemoteCall};
+    if !native_thread_using_fpu(thread) {
+        return;
+    }
+    let cpu = thread.get_cpu();
+    if cpu == cpu_id() {
+        switch_local_fpu_owner(0);
+    } else {
+        do_remote_op(
+            IpiRemoteCall::FunctionCall(|_| switch_local_fpu_owner(0), 0),
+            cpu,
+        );
+    }
+}
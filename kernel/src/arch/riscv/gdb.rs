@@ -0,0 +1,115 @@
+//! The RISC-V part of the GDB stub.
+//!
+//! RISC-V has no single-step in the supervisor mode, GDB steps the threads with its own
+//! breakpoints instead.
+
+use crate::kernel::gdb::{context_reg, GDB_SIGNAL_BUS, GDB_SIGNAL_ILL, GDB_SIGNAL_TRAP};
+use core::arch::asm;
+use sel4_common::arch::ArchReg;
+use sel4_common::fault::seL4_Fault_t;
+use sel4_common::sel4_config::{seL4_PageBits, tcbVTable};
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_common::MASK;
+use sel4_cspace::interface::CapTag;
+use sel4_task::tcb_t;
+use sel4_vspace::{paddr_to_pptr, PTE};
+
+/// x0-x31 and pc
+pub const GDB_REG_NUM: usize = 33;
+/// The GDB register number of the pc
+pub const GDB_PC_REGISTER: usize = 32;
+
+const RISCV_INSTRUCTION_MISALIGNED: usize = 0;
+const RISCV_INSTRUCTION_ILLEGAL: usize = 2;
+const RISCV_BREAKPOINT: usize = 3;
+const RISCV_LOAD_MISALIGNED: usize = 4;
+const RISCV_STORE_MISALIGNED: usize = 6;
+
+/// ebreak
+const EBREAK: [u8; 4] = [0x73, 0x00, 0x10, 0x00];
+/// c.ebreak
+const C_EBREAK: [u8; 2] = [0x02, 0x90];
+
+/// Get the size in bytes of the GDB register `n`, 0 for the registers the stub does not have.
+pub fn register_size(n: usize) -> usize {
+    if n < GDB_REG_NUM {
+        8
+    } else {
+        0
+    }
+}
+
+pub fn read_register(tcb: &tcb_t, n: usize) -> usize {
+    match n {
+        // x0 is not saved
+        0 => 0,
+        1..=31 => tcb.tcbArch.get_register(context_reg(n - 1)),
+        _ => tcb.tcbArch.get_register(ArchReg::NextIP),
+    }
+}
+
+pub fn write_register(tcb: &mut tcb_t, n: usize, value: usize) {
+    match n {
+        0 => {}
+        1..=31 => tcb.tcbArch.set_register(context_reg(n - 1), value),
+        _ => {
+            tcb.tcbArch.set_register(ArchReg::FaultIP, value);
+            tcb.tcbArch.set_register(ArchReg::NextIP, value);
+        }
+    }
+}
+
+/// Translate the user address `vaddr` of the thread to the kernel address of its byte.
+pub fn lookup_user_addr(tcb: &mut tcb_t, vaddr: usize) -> Option<usize> {
+    let cap = &tcb.get_cspace(tcbVTable).cap;
+    if cap.get_cap_type() != CapTag::CapPageTableCap || cap.get_pt_is_mapped() == 0 {
+        return None;
+    }
+    let lvl1pt = convert_to_mut_type_ref::<PTE>(cap.get_pt_base_ptr());
+    let lu_ret = lvl1pt.lookup_pt_slot(vaddr);
+    let slot = unsafe { &*lu_ret.ptSlot };
+    if slot.get_valid() == 0 || slot.is_pte_table() {
+        return None;
+    }
+    Some(paddr_to_pptr(
+        (slot.get_ppn() << seL4_PageBits) + (vaddr & MASK!(lu_ret.ptBitsLeft)),
+    ))
+}
+
+/// Make the instruction fetch see the code written by the stub.
+pub fn sync_icache(_kaddr: usize) {
+    unsafe {
+        asm!("fence.i");
+    }
+}
+
+/// Get the breakpoint instruction for the breakpoint kind of GDB, its size in bytes.
+pub fn breakpoint_instruction(kind: usize) -> Option<&'static [u8]> {
+    match kind {
+        2 => Some(&C_EBREAK),
+        4 => Some(&EBREAK),
+        _ => None,
+    }
+}
+
+/// Get the GDB signal of a user exception, from its scause.
+pub fn user_exception_signal(number: usize) -> u8 {
+    match number {
+        RISCV_BREAKPOINT => GDB_SIGNAL_TRAP,
+        RISCV_INSTRUCTION_ILLEGAL => GDB_SIGNAL_ILL,
+        RISCV_INSTRUCTION_MISALIGNED | RISCV_LOAD_MISALIGNED | RISCV_STORE_MISALIGNED => {
+            GDB_SIGNAL_BUS
+        }
+        _ => GDB_SIGNAL_TRAP,
+    }
+}
+
+/// Single-step is not supported, see the module documentation.
+pub fn set_single_step(_tcb: &mut tcb_t, _enable: bool) -> bool {
+    false
+}
+
+/// Whether the fault of a thread which is not debugged was caused by the stub.
+pub fn is_stray_fault(_fault: &seL4_Fault_t) -> bool {
+    false
+}
@@ -1,13 +1,28 @@
 mod boot;
 mod c_traps;
 mod exception;
+#[cfg(feature = "HAVE_FPU")]
+mod fpu;
+#[cfg(feature = "ENABLE_GDB")]
+pub mod gdb;
 mod platform;
+mod plic;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+pub mod vcpu;
 
 pub use boot::try_init_kernel;
 pub use c_traps::restore_user_context;
 use core::arch::asm;
-pub use platform::{init_cpu, init_freemem};
+#[cfg(all(feature = "HAVE_FPU", feature = "ENABLE_SMP"))]
+pub use fpu::fpu_thread_migrate;
+#[cfg(feature = "HAVE_FPU")]
+pub use fpu::{fpu_release, fpu_sync, fpu_thread_delete, lazy_fpu_restore};
+pub use platform::{init_cpu, init_fdt_devices, init_freemem};
+pub use plic::{plic_complete_claim, plic_get_claim, plic_init_controller, plic_mask_irq};
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+pub use vcpu::vcpu_switch;
 
+#[cfg(not(feature = "KERNEL_MCS"))]
 use crate::config::RESET_CYCLES;
 pub use exception::handleUnknownSyscall;
 use sel4_common::arch::set_timer;
@@ -38,6 +53,16 @@ pub fn read_time() -> usize {
     temp
 }
 
+/// Read the cycle counter used to timestamp the kernel entries
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub fn read_cycle_counter() -> usize {
+    let temp: usize;
+    unsafe {
+        asm!("rdcycle {}",out(reg)temp);
+    }
+    temp
+}
+
 pub fn read_scause() -> usize {
     let temp: usize;
     unsafe {
@@ -46,6 +71,7 @@ pub fn read_scause() -> usize {
     temp
 }
 
+#[cfg(not(feature = "KERNEL_MCS"))]
 #[no_mangle]
 pub fn resetTimer() {
     let mut target = read_time() + RESET_CYCLES;
@@ -55,3 +81,23 @@ pub fn resetTimer() {
         set_timer(target);
     }
 }
+
+/// Get the current time of the kernel timer in ticks
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn getCurrentTime() -> usize {
+    read_time()
+}
+
+/// Raise the timer interrupt when the time reaches `deadline`
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub fn setDeadline(deadline: usize) {
+    set_timer(deadline);
+}
+
+/// Clear the pending timer interrupt until the next deadline is set
+#[cfg(feature = "KERNEL_MCS")]
+pub fn ackDeadlineIRQ() {
+    set_timer(usize::MAX);
+}
@@ -1,17 +1,17 @@
 use riscv::register::{stvec, utvec::TrapMode};
-use sel4_common::{
-    arch::{config::KERNEL_ELF_BASE, get_time, set_timer},
-    BIT,
-};
+use sel4_common::{arch::config::KERNEL_ELF_BASE, BIT};
 use sel4_vspace::activate_kernel_vspace;
 
 use crate::boot::paddr_to_pptr_reg;
 use crate::boot::rust_init_freemem;
+use crate::boot::FdtInfo;
 use crate::boot::{avail_p_regs_addr, avail_p_regs_size, res_reg};
 use crate::config::*;
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+use crate::interrupt::ksSerialIRQ;
 use crate::structures::*;
 use crate::{
-    config::{RESET_CYCLES, SIE_SEIE, SIE_STIE},
+    config::{SIE_SEIE, SIE_STIE},
     interrupt::set_sie_mask,
 };
 use log::debug;
@@ -33,7 +33,42 @@ pub fn init_cpu() {
     {
         set_sie_mask(BIT!(SIE_SEIE) | BIT!(SIE_STIE));
     }
-    set_timer(get_time() + RESET_CYCLES);
+    super::plic::plic_init_hart();
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    super::vcpu::vcpu_boot_init();
+    // the MCS kernel sets the first deadline when it schedules the root thread
+    #[cfg(not(feature = "KERNEL_MCS"))]
+    {
+        use sel4_common::arch::{get_time, set_timer};
+        set_timer(get_time() + RESET_CYCLES);
+    }
+}
+
+/// Place the kernel devices where the device tree puts them, before they are mapped.
+pub fn init_fdt_devices(_info: &FdtInfo) {
+    #[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+    if let Some(plic) = _info.interrupt_controller {
+        if plic.is_compatible("riscv,plic0") || plic.is_compatible("sifive,plic-1.0.0") {
+            set_plic_paddr(plic.reg[0].start);
+        }
+    }
+    // the SBI console is used until the UART is mapped at its new place
+    #[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+    if let Some(uart) = _info.uart.filter(crate::boot::FdtDevice::is_default_serial) {
+        driver_collect::set_serial(
+            set_uart_paddr(uart.reg[0].start),
+            uart.reg_shift,
+            uart.reg_io_width.unwrap_or(1),
+        );
+        // a PLIC source is one cell
+        if let Some(irq) = uart.interrupt_cell(0).map(|irq| irq as usize) {
+            if irq != irqInvalid && irq <= PLIC_MAX_IRQ {
+                unsafe {
+                    ksSerialIRQ = irq;
+                }
+            }
+        }
+    }
 }
 
 pub fn init_freemem(ui_reg: region_t, dtb_p_reg: p_region_t) -> bool {
@@ -0,0 +1,113 @@
+//! Driver of the RISC-V Platform-Level Interrupt Controller.
+//!
+//! Every device interrupt is routed to the supervisor context of the hart that enabled it. The
+//! kernel claims the interrupt when `getActiveIRQ` sees `SIP_SEIP`, and the claim is completed by
+//! the `IRQAckIRQ` invocation of the handler. A platform without PLIC has `PLIC_MAX_NUM_INT` 0,
+//! and then never reaches the registers, which aren't mapped.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::config::{irqInvalid, PLIC_MAX_NUM_INT, PLIC_PPTR};
+
+const PLIC_PRIO: usize = 0x0;
+const PLIC_PRIO_PER_ID: usize = 0x4;
+const PLIC_EN: usize = 0x2000;
+const PLIC_EN_PER_HART: usize = 0x100;
+const PLIC_EN_PER_CONTEXT: usize = 0x80;
+const PLIC_THRES: usize = 0x200000;
+const PLIC_THRES_PER_HART: usize = 0x2000;
+const PLIC_THRES_PER_CONTEXT: usize = 0x1000;
+const PLIC_THRES_CLAIM: usize = 0x4;
+
+/// Each hart has a machine mode context followed by a supervisor mode context
+const PLIC_SVC_CONTEXT: usize = 1;
+
+#[inline]
+fn plic_read(offset: usize) -> u32 {
+    unsafe { read_volatile((PLIC_PPTR + offset) as *const u32) }
+}
+
+#[inline]
+fn plic_write(offset: usize, value: u32) {
+    unsafe { write_volatile((PLIC_PPTR + offset) as *mut u32, value) }
+}
+
+#[inline]
+fn plic_get_current_hart_id() -> usize {
+    #[cfg(feature = "ENABLE_SMP")]
+    {
+        sel4_common::smp::cpu_index_to_id(sel4_common::utils::cpu_id())
+    }
+    #[cfg(not(feature = "ENABLE_SMP"))]
+    {
+        0
+    }
+}
+
+#[inline]
+fn plic_enable_offset(hart_id: usize, context_id: usize) -> usize {
+    PLIC_EN + hart_id * PLIC_EN_PER_HART + context_id * PLIC_EN_PER_CONTEXT
+}
+
+#[inline]
+fn plic_thres_offset(hart_id: usize, context_id: usize) -> usize {
+    PLIC_THRES + hart_id * PLIC_THRES_PER_HART + context_id * PLIC_THRES_PER_CONTEXT
+}
+
+#[inline]
+fn plic_claim_offset(hart_id: usize, context_id: usize) -> usize {
+    plic_thres_offset(hart_id, context_id) + PLIC_THRES_CLAIM
+}
+
+/// Claim the highest priority pending interrupt of the current hart, `irqInvalid` if none.
+pub fn plic_get_claim() -> usize {
+    if PLIC_MAX_NUM_INT == 0 {
+        return irqInvalid;
+    }
+    plic_read(plic_claim_offset(
+        plic_get_current_hart_id(),
+        PLIC_SVC_CONTEXT,
+    )) as usize
+}
+
+/// Tell the PLIC the claimed interrupt was handled, so that it can be raised again.
+/// The PLIC ignores the completion of an interrupt disabled for the hart.
+pub fn plic_complete_claim(irq: usize) {
+    if PLIC_MAX_NUM_INT == 0 {
+        return;
+    }
+    plic_write(
+        plic_claim_offset(plic_get_current_hart_id(), PLIC_SVC_CONTEXT),
+        irq as u32,
+    );
+}
+
+/// Disable or enable the interrupt for the current hart.
+pub fn plic_mask_irq(disable: bool, irq: usize) {
+    assert!(irq != irqInvalid && irq <= PLIC_MAX_NUM_INT);
+    let offset = plic_enable_offset(plic_get_current_hart_id(), PLIC_SVC_CONTEXT) + (irq / 32) * 4;
+    let bit = 1u32 << (irq % 32);
+    let val = plic_read(offset);
+    plic_write(offset, if disable { val & !bit } else { val | bit });
+}
+
+/// Disable every interrupt for the current hart and let through any priority above 0.
+pub fn plic_init_hart() {
+    if PLIC_MAX_NUM_INT == 0 {
+        return;
+    }
+    for irq in 1..=PLIC_MAX_NUM_INT {
+        plic_mask_irq(true, irq);
+    }
+    plic_write(
+        plic_thres_offset(plic_get_current_hart_id(), PLIC_SVC_CONTEXT),
+        0,
+    );
+}
+
+/// Give every interrupt the same priority 1, called once by the boot hart.
+pub fn plic_init_controller() {
+    for irq in 1..=PLIC_MAX_NUM_INT {
+        plic_write(PLIC_PRIO + PLIC_PRIO_PER_ID * irq, 1);
+    }
+}
@@ -0,0 +1,380 @@
+//! The VCPU objects of the kernel running in HS-mode.
+//!
+//! A VCPU holds the VS-mode CSRs of a guest with its virtual interrupts and time offset. The
+//! thread bound to it runs the guest in VS-mode, with its VSpace as the G-stage tables: each
+//! core has a Sv48x4 root whose first entry points at the Sv39 root of the running thread, which
+//! covers the first 512 GiB of the guest physical addresses. The VS-mode CSRs only matter while
+//! V=1, so a native thread leaves the VCPU loaded and going back to the guest just sets SPV.
+
+#[cfg(feature = "HAVE_FPU")]
+use super::fpu::handle_fpu_fault;
+use super::read_stval;
+#[cfg(feature = "HAVE_FPU")]
+use crate::config::RISCVInstructionIllegal;
+use crate::config::{
+    RISCVInstructionGuestPageFault, RISCVLoadGuestPageFault, RISCVStoreGuestPageFault,
+};
+use crate::kernel::boot::current_fault;
+use crate::syscall::handle_fault;
+use core::arch::asm;
+use riscv::register::satp;
+use sel4_common::arch::*;
+use sel4_common::fault::seL4_Fault_t;
+use sel4_common::message_info::seL4_MessageInfo_t;
+use sel4_common::sel4_config::{seL4_VCPUBits, CONFIG_MAX_NUM_NODES};
+use sel4_common::structures::exception_t;
+use sel4_common::utils::{convert_to_mut_type_ref, convert_to_option_mut_type_ref, cpu_id};
+use sel4_common::BIT;
+use sel4_task::{
+    activateThread, get_currenct_thread, schedule, set_thread_state, tcb_t, ThreadState,
+};
+use sel4_vspace::{hfence_gvma, hfence_vvma, kpptr_to_paddr, PTEFlags, PTE};
+
+/// hstatus.SPV, sret goes back to the guest
+const HSTATUS_SPV: usize = 1 << 7;
+/// hstatus.VTW, the WFI of the guest traps as a virtual instruction
+const HSTATUS_VTW: usize = 1 << 21;
+
+/// The exceptions the guest handles itself: misaligned fetch, breakpoint, ecall from VU-mode and
+/// the page faults of its VS-stage
+const HEDELEG_GUEST: usize = BIT!(0) | BIT!(3) | BIT!(8) | BIT!(12) | BIT!(13) | BIT!(15);
+/// The virtual interrupts of VS-mode go to the guest
+const HIDELEG_GUEST: usize =
+    BIT!(seL4_VCPU_VSSoftwareIRQ) | BIT!(seL4_VCPU_VSTimerIRQ) | BIT!(seL4_VCPU_VSExternalIRQ);
+/// The guest reads the cycle, time and instret counters
+const HCOUNTEREN_GUEST: usize = 0x7;
+
+const HGATP_MODE_SHIFT: usize = 60;
+const HGATP_MODE_SV48X4: usize = 9;
+/// The root of Sv48x4 has 2 more bits of index and takes 4 pages
+const GSTAGE_ROOT_ENTRIES: usize = 2048;
+
+/// The VCPU object
+#[repr(C)]
+pub struct vcpu_t {
+    /// The thread bound to the VCPU, 0 if there is none
+    pub vcpuTCB: usize,
+    pub regs: [usize; seL4_VCPUReg_Num],
+}
+
+const _: () = assert!(core::mem::size_of::<vcpu_t>() <= BIT!(seL4_VCPUBits));
+
+impl vcpu_t {
+    #[inline]
+    pub fn get_ptr(&self) -> usize {
+        self as *const vcpu_t as usize
+    }
+
+    /// Check if the VCPU is loaded in the hardware of the current core
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe { riscvHSCurVCPU[cpu_id()] == self.get_ptr() }
+    }
+}
+
+/// The root of the G-stage tables of a core
+#[repr(C, align(16384))]
+#[derive(Clone, Copy)]
+struct gstage_root_t([usize; GSTAGE_ROOT_ENTRIES]);
+
+/// The VCPU loaded in each core, 0 if there is none
+static mut riscvHSCurVCPU: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+static mut riscvGStageRoot: [gstage_root_t; CONFIG_MAX_NUM_NODES] =
+    [gstage_root_t([0; GSTAGE_ROOT_ENTRIES]); CONFIG_MAX_NUM_NODES];
+
+/// Read a CSR of the hypervisor extension
+macro_rules! csr_read {
+    ($csr:literal) => {{
+        let value: usize;
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +h",
+                concat!("csrr {}, ", $csr),
+                ".option pop",
+                out(reg) value
+            )
+        };
+        value
+    }};
+}
+
+/// Write a CSR of the hypervisor extension
+macro_rules! csr_write {
+    ($csr:literal, $value:expr) => {{
+        let value: usize = $value;
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +h",
+                concat!("csrw ", $csr, ", {}"),
+                ".option pop",
+                in(reg) value
+            )
+        }
+    }};
+}
+
+/// Generate the accessors of the CSRs a VCPU is made of
+macro_rules! vcpu_hw_regs {
+    ($($index:ident => $csr:literal),* $(,)?) => {
+        fn vcpu_hw_read_reg(reg: usize) -> usize {
+            match reg {
+                $($index => csr_read!($csr),)*
+                _ => panic!("invalid VCPU register {}", reg),
+            }
+        }
+
+        fn vcpu_hw_write_reg(reg: usize, value: usize) {
+            match reg {
+                $($index => csr_write!($csr, value),)*
+                _ => panic!("invalid VCPU register {}", reg),
+            }
+        }
+    };
+}
+
+vcpu_hw_regs! {
+    seL4_VCPUReg_VSSTATUS => "vsstatus",
+    seL4_VCPUReg_VSIE => "vsie",
+    seL4_VCPUReg_VSTVEC => "vstvec",
+    seL4_VCPUReg_VSSCRATCH => "vsscratch",
+    seL4_VCPUReg_VSEPC => "vsepc",
+    seL4_VCPUReg_VSCAUSE => "vscause",
+    seL4_VCPUReg_VSTVAL => "vstval",
+    seL4_VCPUReg_VSATP => "vsatp",
+    seL4_VCPUReg_HVIP => "hvip",
+    seL4_VCPUReg_HTIMEDELTA => "htimedelta",
+}
+
+/// Point the G-stage of the current core at the VSpace of the running thread.
+fn gstage_set_vspace() {
+    let pte = PTE::new(satp::read().ppn(), PTEFlags::V);
+    let root = unsafe { &mut riscvGStageRoot[cpu_id()].0 };
+    if root[0] != pte.0 {
+        root[0] = pte.0;
+        hfence_gvma();
+    }
+}
+
+/// Initialise the HS-mode state of the current core, it starts with the native threads.
+pub fn vcpu_boot_init() {
+    let root = unsafe { kpptr_to_paddr(riscvGStageRoot[cpu_id()].0.as_ptr() as usize) };
+    let hgatp = (HGATP_MODE_SV48X4 << HGATP_MODE_SHIFT) | (root >> 12);
+    csr_write!("hgatp", hgatp);
+    // the mode is WARL, an unsupported one reads back as Bare
+    assert_eq!(
+        csr_read!("hgatp") >> HGATP_MODE_SHIFT,
+        HGATP_MODE_SV48X4,
+        "VCPU: the G-stage does not support Sv48x4"
+    );
+    csr_write!("hedeleg", HEDELEG_GUEST);
+    csr_write!("hideleg", HIDELEG_GUEST);
+    csr_write!("hcounteren", HCOUNTEREN_GUEST);
+    csr_write!("hvip", 0usize);
+    csr_write!("htimedelta", 0usize);
+    csr_write!(
+        "hstatus",
+        (csr_read!("hstatus") | HSTATUS_VTW) & !HSTATUS_SPV
+    );
+    hfence_gvma();
+    unsafe {
+        riscvHSCurVCPU[cpu_id()] = 0;
+    }
+}
+
+/// Initialise a new VCPU object, the guest starts with its MMU off and nothing pending.
+pub fn vcpu_init(vcpu: &mut vcpu_t) {
+    vcpu.vcpuTCB = 0;
+    vcpu.regs = [0; seL4_VCPUReg_Num];
+}
+
+/// Save the loaded VCPU to its object.
+fn vcpu_save(vcpu: &mut vcpu_t) {
+    for reg in 0..seL4_VCPUReg_Num {
+        vcpu.regs[reg] = vcpu_hw_read_reg(reg);
+    }
+}
+
+/// Load the VCPU into the core, the VS-stage translations of the previous one are dropped.
+fn vcpu_restore(vcpu: &vcpu_t) {
+    for reg in 0..seL4_VCPUReg_Num {
+        vcpu_hw_write_reg(reg, vcpu.regs[reg]);
+    }
+    hfence_vvma();
+}
+
+/// Switch the core to the VCPU of the thread going back to user level, 0 for a native thread.
+/// Called by [restore_user_context](super::restore_user_context) and the fastpath.
+pub fn vcpu_switch(new: usize) {
+    if new == 0 {
+        csr_write!("hstatus", csr_read!("hstatus") & !HSTATUS_SPV);
+        return;
+    }
+    let cpu = cpu_id();
+    unsafe {
+        let cur = riscvHSCurVCPU[cpu];
+        if new != cur {
+            if let Some(cur) = convert_to_option_mut_type_ref::<vcpu_t>(cur) {
+                vcpu_save(cur);
+            }
+            vcpu_restore(convert_to_mut_type_ref::<vcpu_t>(new));
+            riscvHSCurVCPU[cpu] = new;
+        }
+    }
+    gstage_set_vspace();
+    csr_write!("hstatus", csr_read!("hstatus") | HSTATUS_SPV);
+}
+
+/// Forget the VCPU loaded in the current core without saving it.
+fn vcpu_invalidate_active() {
+    unsafe {
+        riscvHSCurVCPU[cpu_id()] = 0;
+    }
+}
+
+/// Save the VCPU loaded in the current core to its object and unload it.
+#[cfg(feature = "ENABLE_SMP")]
+fn vcpu_flush_local(_: usize) {
+    if let Some(cur) = convert_to_option_mut_type_ref::<vcpu_t>(unsafe { riscvHSCurVCPU[cpu_id()] })
+    {
+        vcpu_save(cur);
+    }
+    vcpu_invalidate_active();
+}
+
+/// Make the object of the VCPU hold its state, unless the VCPU is loaded in the current core.
+/// A VCPU loaded in another core is saved and unloaded there.
+#[allow(unused_variables)]
+fn vcpu_bring_local(vcpu: &vcpu_t) {
+    #[cfg(feature = "ENABLE_SMP")]
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        if cpu != cpu_id() && unsafe { riscvHSCurVCPU[cpu] } == vcpu.get_ptr() {
+            use crate::smp::{do_remote_op, IpiRemoteCall};
+            do_remote_op(IpiRemoteCall::FunctionCall(vcpu_flush_local, 0), cpu);
+        }
+    }
+}
+
+fn dissociate_vcpu(vcpu: &mut vcpu_t, tcb: &mut tcb_t) {
+    assert_eq!(vcpu.vcpuTCB, tcb.get_ptr());
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        vcpu_invalidate_active();
+    }
+    tcb.tcbArch.tcbVCPU = 0;
+    vcpu.vcpuTCB = 0;
+    tcb.tcbArch.sanitise_sstatus();
+}
+
+fn associate_vcpu(vcpu: &mut vcpu_t, tcb: &mut tcb_t) {
+    if let Some(old) = convert_to_option_mut_type_ref::<vcpu_t>(tcb.tcbArch.tcbVCPU) {
+        dissociate_vcpu(old, tcb);
+    }
+    if let Some(old) = convert_to_option_mut_type_ref::<tcb_t>(vcpu.vcpuTCB) {
+        dissociate_vcpu(vcpu, old);
+    }
+    tcb.tcbArch.tcbVCPU = vcpu.get_ptr();
+    vcpu.vcpuTCB = tcb.get_ptr();
+    tcb.tcbArch.set_guest_mode();
+}
+
+/// Unbind the VCPU of a thread that is being deleted.
+pub fn dissociate_vcpu_tcb(tcb: &mut tcb_t) {
+    if let Some(vcpu) = convert_to_option_mut_type_ref::<vcpu_t>(tcb.tcbArch.tcbVCPU) {
+        dissociate_vcpu(vcpu, tcb);
+    }
+}
+
+/// Release a VCPU object that is being deleted.
+pub fn vcpu_finalise(vcpu: &mut vcpu_t) {
+    if let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(vcpu.vcpuTCB) {
+        dissociate_vcpu(vcpu, tcb);
+    } else {
+        vcpu_bring_local(vcpu);
+        if vcpu.is_current() {
+            vcpu_invalidate_active();
+        }
+    }
+}
+
+fn vcpu_read_reg(vcpu: &mut vcpu_t, reg: usize) -> usize {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        vcpu_hw_read_reg(reg)
+    } else {
+        vcpu.regs[reg]
+    }
+}
+
+fn vcpu_write_reg(vcpu: &mut vcpu_t, reg: usize, value: usize) {
+    vcpu_bring_local(vcpu);
+    if vcpu.is_current() {
+        vcpu_hw_write_reg(reg, value);
+    } else {
+        vcpu.regs[reg] = value;
+    }
+}
+
+pub fn invoke_vcpu_set_tcb(vcpu: &mut vcpu_t, tcb: &mut tcb_t) -> exception_t {
+    associate_vcpu(vcpu, tcb);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_read_reg(vcpu: &mut vcpu_t, reg: usize, call: bool) -> exception_t {
+    let thread = get_currenct_thread();
+    let value = vcpu_read_reg(vcpu, reg);
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = thread.set_mr(0, value);
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo_t::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_vcpu_write_reg(vcpu: &mut vcpu_t, reg: usize, value: usize) -> exception_t {
+    vcpu_write_reg(vcpu, reg, value);
+    exception_t::EXCEPTION_NONE
+}
+
+/// Raise or lower the virtual interrupt `irq` of the guest in its hvip.
+pub fn invoke_vcpu_inject_irq(vcpu: &mut vcpu_t, irq: usize, level: bool) -> exception_t {
+    let hvip = vcpu_read_reg(vcpu, seL4_VCPUReg_HVIP);
+    let hvip = if level {
+        hvip | BIT!(irq)
+    } else {
+        hvip & !BIT!(irq)
+    };
+    vcpu_write_reg(vcpu, seL4_VCPUReg_HVIP, hvip);
+    exception_t::EXCEPTION_NONE
+}
+
+/// A guest trapped into the kernel with the exception `cause`, the first FPU access of the
+/// thread is handled by the kernel and the rest goes to the VMM.
+pub fn handle_vcpu_fault(cause: usize) {
+    #[cfg(feature = "HAVE_FPU")]
+    if cause == RISCVInstructionIllegal && get_currenct_thread().tcbArch.get_fs() == SSTATUS_FS_OFF
+    {
+        handle_fpu_fault();
+        return;
+    }
+    let (addr, inst) = match cause {
+        // htval holds the guest physical address shifted right by 2
+        RISCVInstructionGuestPageFault | RISCVLoadGuestPageFault | RISCVStoreGuestPageFault => (
+            (csr_read!("htval") << 2) | (read_stval() & 0x3),
+            csr_read!("htinst"),
+        ),
+        _ => (read_stval(), 0),
+    };
+    unsafe {
+        current_fault = seL4_Fault_t::new_vcpu_fault(addr, inst, cause);
+    }
+    handle_fault(get_currenct_thread());
+    schedule();
+    activateThread();
+}
@@ -0,0 +1,558 @@
+//! A no_std parser of the flattened device tree handed over at boot.
+//!
+//! The kernel reads the memory, the reserved memory, `/chosen`, the interrupt controller, the
+//! timer frequency and the UART from it. The memory replaces the regions of the platform config
+//! given through `pRegsToR`, so the same kernel boots with any amount of RAM, and the devices
+//! are mapped where the device tree puts them.
+use core::str;
+
+use log::{debug, warn};
+use sel4_common::arch::config::{PADDR_BASE, PADDR_TOP};
+use sel4_common::sel4_config::seL4_PageBits;
+use sel4_vspace::paddr_to_pptr;
+
+use super::mm::{avail_p_regs_addr, avail_p_regs_size};
+use crate::config::MAX_NUM_FREEMEM_REG;
+use crate::structures::p_region_t;
+use crate::{BIT, ROUND_DOWN, ROUND_UP};
+
+const FDT_MAGIC: u32 = 0xd00dfeed;
+/// The header of version 17 has every field the parser reads
+const FDT_VERSION: u32 = 17;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+const FDT_END: u32 = 9;
+/// The deepest node the parser walks into
+const FDT_MAX_DEPTH: usize = 16;
+/// The number of reserved regions kept from the device tree
+pub const MAX_NUM_FDT_RESV_REG: usize = 16;
+
+fn be32(data: &[u8], off: usize) -> Option<u32> {
+    let bytes = data.get(off..off + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Read a number of `cells` 32 bit cells as one number, as `#address-cells` and `#size-cells`
+/// give them.
+fn read_cells(data: &[u8], off: usize, cells: usize) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..cells {
+        value = (value << 32) | be32(data, off + i * 4)? as u64;
+    }
+    Some(value)
+}
+
+/// Read the NUL terminated string at `off`.
+fn read_str(data: &[u8], off: usize) -> Option<&str> {
+    let bytes = data.get(off..)?;
+    let len = bytes.iter().position(|&b| b == 0)?;
+    str::from_utf8(&bytes[..len]).ok()
+}
+
+#[inline]
+fn align4(off: usize) -> usize {
+    (off + 3) & !3
+}
+
+/// A flattened device tree in memory
+#[derive(Clone, Copy)]
+pub struct Fdt<'a> {
+    data: &'a [u8],
+    struct_off: usize,
+    struct_end: usize,
+    strings_off: usize,
+    rsvmap_off: usize,
+}
+
+/// A node of the device tree, with the `#address-cells` and `#size-cells` of its parent
+#[derive(Clone, Copy)]
+pub struct FdtNode<'a> {
+    fdt: Fdt<'a>,
+    /// The name with the unit address, as `memory@80000000`
+    pub name: &'a str,
+    /// The name of the parent, empty for the root
+    pub parent: &'a str,
+    /// 0 for the root
+    pub depth: usize,
+    props: usize,
+    address_cells: usize,
+    size_cells: usize,
+}
+
+impl<'a> Fdt<'a> {
+    /// Check the header of the device tree in `data`, `None` if it is not one.
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        if be32(data, 0)? != FDT_MAGIC || be32(data, 20)? < FDT_VERSION {
+            return None;
+        }
+        let data = data.get(..be32(data, 4)? as usize)?;
+        let struct_off = be32(data, 8)? as usize;
+        let struct_end = struct_off + be32(data, 36)? as usize;
+        let strings_off = be32(data, 12)? as usize;
+        let strings_end = strings_off + be32(data, 32)? as usize;
+        if struct_end > data.len() || strings_end > data.len() {
+            return None;
+        }
+        Some(Self {
+            data,
+            struct_off,
+            struct_end,
+            strings_off,
+            rsvmap_off: be32(data, 16)? as usize,
+        })
+    }
+
+    /// The entries of the memory reservation block.
+    pub fn reservations(&self) -> impl Iterator<Item = p_region_t> + 'a {
+        let data = self.data;
+        let mut off = self.rsvmap_off;
+        core::iter::from_fn(move || {
+            let start = read_cells(data, off, 2)? as usize;
+            let size = read_cells(data, off + 8, 2)? as usize;
+            off += 16;
+            (size != 0).then_some(p_region_t {
+                start,
+                end: start + size,
+            })
+        })
+    }
+
+    /// Call `f` on every node in the order of the tree, `None` if the structure block is broken.
+    pub fn for_each_node(&self, mut f: impl FnMut(&FdtNode<'a>)) -> Option<()> {
+        // the cells of the nodes at each depth, as their parent gives them
+        let mut cells = [(2, 1); FDT_MAX_DEPTH + 1];
+        let mut names = [""; FDT_MAX_DEPTH + 1];
+        let mut depth = 0;
+        let mut off = self.struct_off;
+        while off < self.struct_end {
+            let token = be32(self.data, off)?;
+            off += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    if depth >= FDT_MAX_DEPTH {
+                        return None;
+                    }
+                    let name = read_str(self.data, off)?;
+                    off = align4(off + name.len() + 1);
+                    let node = FdtNode {
+                        fdt: *self,
+                        name,
+                        parent: if depth > 0 { names[depth - 1] } else { "" },
+                        depth,
+                        props: off,
+                        address_cells: cells[depth].0,
+                        size_cells: cells[depth].1,
+                    };
+                    f(&node);
+                    cells[depth + 1] = (
+                        node.u32_property("#address-cells")
+                            .map_or(2, |c| c as usize),
+                        node.u32_property("#size-cells").map_or(1, |c| c as usize),
+                    );
+                    names[depth] = name;
+                    depth += 1;
+                }
+                FDT_END_NODE => depth = depth.checked_sub(1)?,
+                FDT_PROP => off = align4(off + 8 + be32(self.data, off)? as usize),
+                FDT_NOP => {}
+                FDT_END => return Some(()),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Find the node at `path`, the unit addresses may be left out of it.
+    pub fn find_node(&self, path: &str) -> Option<FdtNode<'a>> {
+        let path = path.trim_matches('/');
+        let mut found = None;
+        // how deep the nodes walked so far match the path
+        let mut matched = 0;
+        self.for_each_node(|node| {
+            if found.is_some() || node.depth == 0 || node.depth > matched + 1 {
+                return;
+            }
+            matched = node.depth - 1;
+            match path.split('/').nth(matched) {
+                Some(component) if node.name == component || node.base_name() == component => {
+                    matched += 1;
+                    if matched == path.split('/').count() {
+                        found = Some(*node);
+                    }
+                }
+                _ => {}
+            }
+        })?;
+        if path.is_empty() {
+            return None;
+        }
+        found
+    }
+}
+
+impl<'a> FdtNode<'a> {
+    /// The name without the unit address.
+    pub fn base_name(&self) -> &'a str {
+        self.name.split('@').next().unwrap_or(self.name)
+    }
+
+    /// The properties of the node, as names and values.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a [u8])> + 'a {
+        let fdt = self.fdt;
+        let mut off = self.props;
+        core::iter::from_fn(move || loop {
+            match be32(fdt.data, off)? {
+                FDT_NOP => off += 4,
+                FDT_PROP => {
+                    let len = be32(fdt.data, off + 4)? as usize;
+                    let name = read_str(
+                        fdt.data,
+                        fdt.strings_off + be32(fdt.data, off + 8)? as usize,
+                    )?;
+                    let value = fdt.data.get(off + 12..off + 12 + len)?;
+                    off = align4(off + 12 + len);
+                    return Some((name, value));
+                }
+                _ => return None,
+            }
+        })
+    }
+
+    pub fn property(&self, name: &str) -> Option<&'a [u8]> {
+        self.properties()
+            .find(|(prop_name, _)| *prop_name == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn u32_property(&self, name: &str) -> Option<u32> {
+        be32(self.property(name)?, 0)
+    }
+
+    /// A property of one or two cells, as `linux,initrd-start`.
+    pub fn u64_property(&self, name: &str) -> Option<u64> {
+        let value = self.property(name)?;
+        read_cells(value, 0, value.len() / 4).filter(|_| value.len() == 4 || value.len() == 8)
+    }
+
+    /// A string property, the first one of a string list.
+    pub fn str_property(&self, name: &str) -> Option<&'a str> {
+        read_str(self.property(name)?, 0)
+    }
+
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.property("compatible").is_some_and(|value| {
+            value
+                .split(|&b| b == 0)
+                .any(|entry| entry == compatible.as_bytes())
+        })
+    }
+
+    /// The regions of the `reg` property.
+    pub fn reg(&self) -> impl Iterator<Item = p_region_t> + 'a {
+        let value = self.property("reg").unwrap_or(&[]);
+        let (address_cells, size_cells) = (self.address_cells, self.size_cells);
+        let entry_size = (address_cells + size_cells) * 4;
+        (0..value.len() / entry_size.max(1)).filter_map(move |i| {
+            let start = read_cells(value, i * entry_size, address_cells)? as usize;
+            let size = read_cells(value, i * entry_size + address_cells * 4, size_cells)? as usize;
+            Some(p_region_t {
+                start,
+                end: start + size,
+            })
+        })
+    }
+}
+
+/// A device found in the device tree
+#[derive(Clone, Copy)]
+pub struct FdtDevice {
+    /// The compatible strings, separated by NULs
+    pub compatible: &'static [u8],
+    /// The first regions of `reg`, as the distributor, the CPU interface and the virtual
+    /// interface control of a GIC
+    pub reg: [p_region_t; 3],
+    /// The spacing of the registers of a UART, `reg-shift`
+    pub reg_shift: usize,
+    /// The width in bytes of the register accesses of a UART, `reg-io-width`, `None` if absent
+    pub reg_io_width: Option<usize>,
+    /// The cells of `interrupts`, as the interrupt controller reads them
+    pub interrupts: &'static [u8],
+}
+
+impl FdtDevice {
+    /// Whether the default serial of `driver_collect` drives this UART, with the register access
+    /// width it asks for.
+    pub fn is_default_serial(&self) -> bool {
+        driver_collect::SERIAL_COMPATIBLES
+            .iter()
+            .any(|compatible| self.is_compatible(compatible))
+            && self.reg_io_width.map_or(true, |width| {
+                driver_collect::SERIAL_REG_IO_WIDTHS.contains(&width)
+            })
+    }
+
+    fn new(node: &FdtNode<'static>) -> Option<Self> {
+        let mut reg = [p_region_t { start: 0, end: 0 }; 3];
+        let mut count = 0;
+        for (slot, region) in reg.iter_mut().zip(node.reg()) {
+            *slot = region;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(Self {
+            compatible: node.property("compatible").unwrap_or(&[]),
+            reg,
+            reg_shift: node.u32_property("reg-shift").unwrap_or(0) as usize,
+            reg_io_width: node
+                .u32_property("reg-io-width")
+                .map(|width| width as usize),
+            interrupts: node.property("interrupts").unwrap_or(&[]),
+        })
+    }
+
+    /// The cell `index` of `interrupts`.
+    #[cfg(any(target_arch = "aarch64", feature = "PLAT_QEMU_RISCV_VIRT"))]
+    pub fn interrupt_cell(&self, index: usize) -> Option<u32> {
+        be32(self.interrupts, index * 4)
+    }
+
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.compatible
+            .split(|&b| b == 0)
+            .any(|entry| entry == compatible.as_bytes())
+    }
+}
+
+/// What the kernel learns from the device tree at boot
+#[derive(Clone, Copy)]
+pub struct FdtInfo {
+    /// The physical memory without the reserved memory, in order
+    pub memory: [p_region_t; MAX_NUM_FREEMEM_REG],
+    pub memory_count: usize,
+    /// The memory reservation block, the nodes of `/reserved-memory` and the initrd
+    pub reserved: [p_region_t; MAX_NUM_FDT_RESV_REG],
+    pub reserved_count: usize,
+    /// The kernel command line of `/chosen`
+    pub bootargs: Option<&'static str>,
+    pub interrupt_controller: Option<FdtDevice>,
+    /// The UART of `stdout-path` in `/chosen`, or the first UART the default serial drives
+    pub uart: Option<FdtDevice>,
+}
+
+impl FdtInfo {
+    const fn new() -> Self {
+        Self {
+            memory: [p_region_t { start: 0, end: 0 }; MAX_NUM_FREEMEM_REG],
+            memory_count: 0,
+            reserved: [p_region_t { start: 0, end: 0 }; MAX_NUM_FDT_RESV_REG],
+            reserved_count: 0,
+            bootargs: None,
+            interrupt_controller: None,
+            uart: None,
+        }
+    }
+
+    /// Add a memory region, clipped to the physical memory the kernel window maps.
+    fn add_memory(&mut self, region: p_region_t) {
+        let region = p_region_t {
+            start: region.start.max(PADDR_BASE),
+            end: region.end.min(PADDR_TOP),
+        };
+        if region.start >= region.end {
+            return;
+        }
+        // the memory past MAX_NUM_FREEMEM_REG regions is not used
+        if self.memory_count == MAX_NUM_FREEMEM_REG {
+            return;
+        }
+        // keep the regions in order
+        let mut i = self.memory_count;
+        while i > 0 && self.memory[i - 1].start > region.start {
+            self.memory[i] = self.memory[i - 1];
+            i -= 1;
+        }
+        self.memory[i] = region;
+        self.memory_count += 1;
+    }
+
+    fn add_reserved(&mut self, region: p_region_t) -> bool {
+        if region.start >= region.end {
+            return true;
+        }
+        if self.reserved_count == MAX_NUM_FDT_RESV_REG {
+            return false;
+        }
+        self.reserved[self.reserved_count] = region;
+        self.reserved_count += 1;
+        true
+    }
+
+    /// Take the reserved regions out of the memory, and trim the memory to whole pages.
+    fn carve_reserved(&mut self) -> bool {
+        for r in 0..self.reserved_count {
+            let reserved = p_region_t {
+                start: ROUND_DOWN!(self.reserved[r].start, seL4_PageBits),
+                end: ROUND_UP!(self.reserved[r].end, seL4_PageBits),
+            };
+            let mut i = 0;
+            while i < self.memory_count {
+                let memory = self.memory[i];
+                if reserved.end <= memory.start || reserved.start >= memory.end {
+                    i += 1;
+                } else if reserved.start > memory.start && reserved.end < memory.end {
+                    // split the region around the reserved one
+                    if self.memory_count == MAX_NUM_FREEMEM_REG {
+                        return false;
+                    }
+                    self.memory.copy_within(i + 1..self.memory_count, i + 2);
+                    self.memory[i].end = reserved.start;
+                    self.memory[i + 1] = p_region_t {
+                        start: reserved.end,
+                        end: memory.end,
+                    };
+                    self.memory_count += 1;
+                    i += 2;
+                } else if reserved.start <= memory.start && reserved.end >= memory.end {
+                    self.memory.copy_within(i + 1..self.memory_count, i);
+                    self.memory_count -= 1;
+                } else {
+                    if reserved.start <= memory.start {
+                        self.memory[i].start = reserved.end;
+                    } else {
+                        self.memory[i].end = reserved.start;
+                    }
+                    i += 1;
+                }
+            }
+        }
+        let mut count = 0;
+        for i in 0..self.memory_count {
+            let region = p_region_t {
+                // ROUND_UP does not take a region starting at 0
+                start: ROUND_DOWN!(
+                    self.memory[i].start + BIT!(seL4_PageBits) - 1,
+                    seL4_PageBits
+                ),
+                end: ROUND_DOWN!(self.memory[i].end, seL4_PageBits),
+            };
+            if region.start < region.end {
+                self.memory[count] = region;
+                count += 1;
+            }
+        }
+        self.memory_count = count;
+        true
+    }
+}
+
+static mut ksFdtInfo: FdtInfo = FdtInfo::new();
+
+/// Get what the kernel learned from the device tree at boot.
+pub fn get_fdt_info() -> &'static FdtInfo {
+    unsafe { &*core::ptr::addr_of!(ksFdtInfo) }
+}
+
+fn parse_fdt(fdt: &Fdt<'static>) -> Option<FdtInfo> {
+    let mut info = FdtInfo::new();
+    let mut ok = true;
+    for region in fdt.reservations() {
+        ok &= info.add_reserved(region);
+    }
+    let mut stdout_path = None;
+    fdt.for_each_node(|node| {
+        if node.depth == 1 && node.base_name() == "memory"
+            || node.str_property("device_type") == Some("memory")
+        {
+            node.reg().for_each(|region| info.add_memory(region));
+        } else if node.parent == "reserved-memory" && node.depth == 2 {
+            node.reg()
+                .for_each(|region| ok &= info.add_reserved(region));
+        } else if node.depth == 1 && node.name == "chosen" {
+            info.bootargs = node.str_property("bootargs");
+            stdout_path = node.str_property("stdout-path");
+            if let (Some(start), Some(end)) = (
+                node.u64_property("linux,initrd-start"),
+                node.u64_property("linux,initrd-end"),
+            ) {
+                ok &= info.add_reserved(p_region_t {
+                    start: start as usize,
+                    end: end as usize,
+                });
+            }
+        } else if node.property("interrupt-controller").is_some() {
+            // the interrupt controllers of the harts have no registers and are skipped
+            if info.interrupt_controller.is_none() {
+                info.interrupt_controller = FdtDevice::new(node);
+            }
+        } else if info.uart.is_none()
+            && driver_collect::SERIAL_COMPATIBLES
+                .iter()
+                .any(|c| node.is_compatible(c))
+        {
+            info.uart = FdtDevice::new(node);
+        }
+    })?;
+    if !ok {
+        return None;
+    }
+
+    // `stdout-path` is a path or an alias, with the options of the UART after a ':'
+    if let Some(path) = stdout_path.and_then(|path| path.split(':').next()) {
+        let path = if path.starts_with('/') {
+            Some(path)
+        } else {
+            fdt.find_node("/aliases")
+                .and_then(|aliases| aliases.str_property(path))
+        };
+        if let Some(node) = path.and_then(|path| fdt.find_node(path)) {
+            info.uart = FdtDevice::new(&node)
+                .filter(FdtDevice::is_default_serial)
+                .or(info.uart);
+        }
+    }
+
+    if !info.carve_reserved() {
+        return None;
+    }
+    Some(info)
+}
+
+/// Read the device tree at `dtb_phys_addr`, `None` if there is none or it can not be parsed.
+///
+/// The kernel window has to be active. The memory found replaces the regions of the platform
+/// config for `rust_init_freemem`. Nothing is printed here, the console may not be mapped yet,
+/// `log_fdt_info` prints what was found once it is.
+pub fn init_fdt(dtb_phys_addr: usize, dtb_size: usize) -> Option<&'static FdtInfo> {
+    if dtb_size == 0 || dtb_phys_addr.checked_add(dtb_size)? >= PADDR_TOP {
+        return None;
+    }
+    let data =
+        unsafe { core::slice::from_raw_parts(paddr_to_pptr(dtb_phys_addr) as *const u8, dtb_size) };
+    let info = parse_fdt(&Fdt::new(data)?)?;
+    unsafe {
+        ksFdtInfo = info;
+        if ksFdtInfo.memory_count > 0 {
+            avail_p_regs_addr = ksFdtInfo.memory.as_ptr() as usize;
+            avail_p_regs_size = ksFdtInfo.memory_count;
+        }
+    }
+    Some(get_fdt_info())
+}
+
+/// Print what `init_fdt` found, once the console is mapped.
+pub fn log_fdt_info(info: Option<&FdtInfo>) {
+    let Some(info) = info else {
+        warn!("FDT: no device tree could be parsed, the platform config is used");
+        return;
+    };
+    if let Some(bootargs) = info.bootargs {
+        debug!("FDT: bootargs \"{}\"", bootargs);
+    }
+    for region in &info.memory[..info.memory_count] {
+        debug!("FDT: memory [{:#x}..{:#x}]", region.start, region.end);
+    }
+}
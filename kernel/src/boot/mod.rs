@@ -1,3 +1,4 @@
+mod fdt;
 mod interface;
 mod mm;
 mod root_server;
@@ -6,7 +7,6 @@ mod utils;
 
 use core::mem::size_of;
 
-// use crate::ffi::tcbDebugAppend;
 use crate::{BIT, ROUND_UP};
 use log::debug;
 use sel4_common::arch::config::PADDR_TOP;
@@ -19,6 +19,9 @@ use crate::structures::{
     ndks_boot_t, p_region_t, region_t, seL4_BootInfo, seL4_BootInfoHeader, seL4_SlotRegion,
 };
 
+#[cfg(any(target_arch = "aarch64", feature = "PLAT_QEMU_RISCV_VIRT"))]
+pub use fdt::FdtDevice;
+pub use fdt::{init_fdt, log_fdt_info, FdtInfo};
 #[cfg(target_arch = "aarch64")]
 pub use mm::reserve_region;
 pub use mm::{avail_p_regs_addr, avail_p_regs_size, res_reg, rust_init_freemem};
@@ -30,10 +33,7 @@ pub use root_server::root_server_init;
 pub use untyped::create_untypeds;
 
 #[cfg(feature = "ENABLE_SMP")]
-pub use utils::{provide_cap, write_slot};
-
-#[cfg(feature = "ENABLE_SMP")]
-use crate::ffi::{clh_lock_acquire, clh_lock_init};
+use crate::smp::clh_lock_acquire;
 
 #[cfg(feature = "ENABLE_SMP")]
 use core::arch::asm;
@@ -142,52 +142,56 @@ pub fn bi_finalise(dtb_size: usize, dtb_phys_addr: usize, extra_bi_size: usize)
 }
 
 pub fn init_core_state(scheduler_action: *mut tcb_t) {
-    // unsafe {
-    // #[cfg(feature = "ENABLE_SMP")]
-    // if scheduler_action as usize != 0 && scheduler_action as usize != 1 {
-    //     tcbDebugAppend(scheduler_action);
-    // }
-    // let idle_thread = {
-    //     #[cfg(not(feature = "ENABLE_SMP"))]
-    //     {
-    //         ksIdleThread as *mut tcb_t
-    //     }
-    //     #[cfg(feature = "ENABLE_SMP")]
-    //     {
-    //         ksSMP[cpu_id()].ksIdleThread as *mut tcb_t
-    //     }
-    // };
-    // tcbDebugAppend(idle_thread);
-    // }
+    if scheduler_action as usize != SchedulerAction_ResumeCurrentThread
+        && scheduler_action as usize != SchedulerAction_ChooseNewThread
+    {
+        unsafe { (*scheduler_action).DebugAppend() };
+    }
+    get_idle_thread().DebugAppend();
 
     set_current_scheduler_action(scheduler_action as usize);
     set_current_thread(get_idle_thread());
+    #[cfg(feature = "KERNEL_MCS")]
+    unsafe {
+        ksCurSC = get_idle_thread().tcbSchedContext;
+        ksConsumed = 0;
+        ksReprogram = true;
+        ksReleaseHead = 0;
+        ksCurTime = crate::arch::getCurrentTime();
+    }
 }
 
 #[cfg(feature = "ENABLE_SMP")]
-pub fn try_init_kernel_secondary_core(hartid: usize, core_id: usize) -> bool {
+pub fn try_init_kernel_secondary_core(_hartid: usize, _core_id: usize) -> bool {
     use core::ops::AddAssign;
     while node_boot_lock.lock().eq(&0) {}
     // debug!("start try_init_kernel_secondary_core");
-    init_cpu();
+    crate::arch::init_cpu();
     debug!("init cpu compl");
-    unsafe { clh_lock_acquire(cpu_id(), false) }
+    clh_lock_acquire(cpu_id(), false);
     ksNumCPUs.lock().add_assign(1);
     init_core_state(SchedulerAction_ResumeCurrentThread as *mut tcb_t);
     debug!("init_core_state compl");
 
+    // the code the boot core wrote is seen by the instruction fetch of this core
     unsafe {
+        #[cfg(target_arch = "riscv64")]
         asm!("fence.i");
+        #[cfg(target_arch = "aarch64")]
+        asm!("isb");
     }
     true
 }
 
 #[cfg(feature = "ENABLE_SMP")]
-fn release_secondary_cores() {
+pub fn release_secondary_cores() {
     use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
     *node_boot_lock.lock() = 1;
     unsafe {
+        #[cfg(target_arch = "riscv64")]
         asm!("fence rw, rw");
+        #[cfg(target_arch = "aarch64")]
+        asm!("dmb ish");
     }
     while ksNumCPUs.lock().ne(&CONFIG_MAX_NUM_NODES) {}
 }
@@ -1,7 +1,7 @@
 use super::calculate_extra_bi_size_bits;
 use super::utils::{arch_get_n_paging, provide_cap, write_slot};
 use super::{ndks_boot, utils::is_reg_empty};
-use crate::interrupt::{setIRQState, IRQState};
+use crate::interrupt::{ksSerialIRQ, setIRQState, IRQState, IS_IRQ_VALID};
 use crate::structures::{
     create_frames_of_region_ret_t, region_t, rootserver_mem_t, seL4_BootInfo, seL4_SlotRegion,
     v_region_t,
@@ -18,6 +18,10 @@ use sel4_common::sel4_config::{
     tcbCTable, tcbVTable, wordBits, CONFIG_MAX_NUM_NODES, CONFIG_NUM_DOMAINS, CONFIG_TIME_SLICE,
     IT_ASID, PAGE_BITS, TCB_OFFSET,
 };
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_common::sel4_config::{
+    seL4_MinSchedContextBits, CONFIG_BOOT_THREAD_TIME_SLICE, MIN_REFILLS,
+};
 use sel4_common::structures::{exception_t, seL4_IPCBuffer};
 use sel4_common::utils::convert_to_mut_type_ref;
 use sel4_cspace::interface::*;
@@ -41,6 +45,8 @@ pub static mut rootserver: rootserver_mem_t = rootserver_mem_t {
     boot_info: 0,
     extra_bi: 0,
     tcb: 0,
+    #[cfg(feature = "KERNEL_MCS")]
+    sc: 0,
     paging: region_t {
         start: (0),
         end: (0),
@@ -105,6 +111,11 @@ pub fn root_server_init(
         return None;
     }
 
+    #[cfg(feature = "KERNEL_MCS")]
+    if !init_sched_control(&root_cnode_cap) {
+        return None;
+    }
+
     let initial = unsafe {
         create_initial_thread(
             &root_cnode_cap,
@@ -167,9 +178,25 @@ unsafe fn create_initial_thread(
     tcb.tcbMCP = seL4_MaxPrio;
     tcb.tcbPriority = seL4_MaxPrio;
     set_thread_state(tcb, ThreadState::ThreadStateRunning);
+    #[cfg(not(feature = "KERNEL_MCS"))]
     tcb.setup_reply_master();
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        let sc = convert_to_mut_type_ref::<sched_context_t>(rootserver.sc);
+        sc.refill_new(
+            MIN_REFILLS,
+            us_to_ticks(CONFIG_BOOT_THREAD_TIME_SLICE * 1000),
+            0,
+        );
+        sc.scTcb = tcb.get_ptr();
+        tcb.tcbSchedContext = sc.get_ptr();
+        write_slot(
+            cnode.get_offset_slot(seL4_CapInitThreadSC) as *mut cte_t,
+            cap_t::new_sched_context_cap(seL4_MinSchedContextBits, rootserver.sc),
+        );
+    }
     ksCurDomain = ksDomSchedule[ksDomScheduleIdx].domain;
-    ksDomainTime = ksDomSchedule[ksDomScheduleIdx].length;
+    ksDomainTime = cur_domain_length();
     tcb.domain = ksCurDomain;
     // log::error!("tcb.domain:{:#x}", &tcb.domain as *const usize as usize);
     #[cfg(feature = "ENABLE_SMP")]
@@ -310,6 +337,10 @@ fn calculate_rootserver_size(it_v_reg: v_region_t, extra_bi_size_bits: usize) ->
         0
     };
     size += BIT!(seL4_VSpaceBits);
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        size += BIT!(seL4_MinSchedContextBits);
+    }
     return size + arch_get_n_paging(it_v_reg) * BIT!(seL4_PageTableBits);
 }
 
@@ -373,6 +404,10 @@ unsafe fn create_rootserver_objects(start: usize, it_v_reg: v_region_t, extra_bi
     rootserver.paging.start = alloc_rootserver_obj(seL4_PageTableBits, n);
     rootserver.paging.end = rootserver.paging.start + n * BIT!(seL4_PageTableBits);
     rootserver.tcb = alloc_rootserver_obj(seL4_TCBBits, 1);
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        rootserver.sc = alloc_rootserver_obj(seL4_MinSchedContextBits, 1);
+    }
 
     assert_eq!(rootserver_mem.start, rootserver_mem.end);
 }
@@ -392,6 +427,24 @@ fn create_domain_cap(root_cnode_cap: &cap_t) {
     }
 }
 
+/// Provide a sched control cap for each core and record them in the boot info.
+#[cfg(feature = "KERNEL_MCS")]
+fn init_sched_control(root_cnode_cap: &cap_t) -> bool {
+    let start = unsafe { ndks_boot.slot_pos_cur };
+    for core in 0..CONFIG_MAX_NUM_NODES {
+        if !provide_cap(root_cnode_cap, cap_t::new_sched_control_cap(core)) {
+            return false;
+        }
+    }
+    unsafe {
+        (*ndks_boot.bi_frame).schedcontrol = seL4_SlotRegion {
+            start,
+            end: ndks_boot.slot_pos_cur,
+        };
+    }
+    true
+}
+
 fn init_irqs(root_cnode_cap: &cap_t) {
     for i in 0..maxIRQ + 1 {
         if i != irqInvalid {
@@ -399,11 +452,25 @@ fn init_irqs(root_cnode_cap: &cap_t) {
         }
     }
     setIRQState(IRQState::IRQTimer, KERNEL_TIMER_IRQ);
+    // the kernel takes the input of its console
+    let serial_irq = unsafe { ksSerialIRQ };
+    if IS_IRQ_VALID(serial_irq) {
+        setIRQState(IRQState::IRQReserved, serial_irq);
+        sel4_common::console::set_rx_interrupt(true);
+    }
     #[cfg(feature = "ENABLE_SMP")]
     {
         setIRQState(IRQState::IRQIPI, INTERRUPT_IPI_0);
         setIRQState(IRQState::IRQIPI, INTERRUPT_IPI_1);
     }
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    {
+        use crate::arch::arm_gic::{INTERRUPT_VGIC_MAINTENANCE, INTERRUPT_VTIMER_EVENT};
+        setIRQState(IRQState::IRQReserved, INTERRUPT_VGIC_MAINTENANCE);
+        setIRQState(IRQState::IRQReserved, INTERRUPT_VTIMER_EVENT);
+        // the virtual timer is unmasked while a guest runs
+        crate::interrupt::mask_interrupt(true, INTERRUPT_VTIMER_EVENT);
+    }
     unsafe {
         let ptr = root_cnode_cap.get_cap_ptr() as *mut cte_t;
         write_slot(ptr.add(seL4_CapIRQControl), cap_t::new_irq_control_cap());
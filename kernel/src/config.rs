@@ -18,7 +18,14 @@ pub const APP_BASE_ADDRESS: usize = 0x84000000;
 pub const APP_SIZE_LIMIT: usize = 0x20000;
 pub const PT_OFFSET_BITS: usize = 12;
 pub const KDEV_BASE: usize = 0xFFFFFFFFC0000000;
+/// The kernel log buffer takes the last megapage of the kernel device window
+#[cfg(target_arch = "riscv64")]
 pub const KS_LOG_PPTR: usize = 0xFFFFFFFFFFE00000;
+/// The last large page is the page table of the kernel devices, the log buffer is right below it
+#[cfg(all(target_arch = "aarch64", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
+pub const KS_LOG_PPTR: usize = 0xFFFFFFFFFFC00000;
+#[cfg(all(target_arch = "aarch64", feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub const KS_LOG_PPTR: usize = 0x000000FFFFC00000;
 pub const RISCVPageBits: usize = 12;
 pub const RISCVMegaPageBits: usize = 21;
 pub const RISCVGigaPageBits: usize = 30;
@@ -56,7 +63,12 @@ pub const seL4_CapInitThreadIPCBuffer: usize = 10;
 pub const seL4_CapDomain: usize = 11;
 pub const seL4_CapSMMUSIDControl: usize = 12;
 pub const seL4_CapSMMUCBControl: usize = 13;
+#[cfg(not(feature = "KERNEL_MCS"))]
 pub const seL4_NumInitialCaps: usize = 14;
+#[cfg(feature = "KERNEL_MCS")]
+pub const seL4_CapInitThreadSC: usize = 14;
+#[cfg(feature = "KERNEL_MCS")]
+pub const seL4_NumInitialCaps: usize = 15;
 
 pub const SIP_SSIP: usize = 1;
 pub const SIP_MSIP: usize = 3;
@@ -82,9 +94,14 @@ pub const RISCVLoadAccessFault: usize = 5;
 pub const RISCVAddressMisaligned: usize = 6;
 pub const RISCVStoreAccessFault: usize = 7;
 pub const RISCVEnvCall: usize = 8;
+pub const RISCVVirtualSupervisorEnvCall: usize = 10;
 pub const RISCVInstructionPageFault: usize = 12;
 pub const RISCVLoadPageFault: usize = 13;
 pub const RISCVStorePageFault: usize = 15;
+pub const RISCVInstructionGuestPageFault: usize = 20;
+pub const RISCVLoadGuestPageFault: usize = 21;
+pub const RISCVVirtualInstruction: usize = 22;
+pub const RISCVStoreGuestPageFault: usize = 23;
 pub const RISCVSupervisorTimer: usize = 9223372036854775813;
 
 pub const thread_control_update_priority: usize = 0x1;
@@ -98,22 +115,39 @@ pub const seL4_UserTop: usize = 0x00007fffffffffff;
 pub const USER_TOP: usize = seL4_UserTop;
 
 //IRQConstants
-#[cfg(feature = "ENABLE_SMP")]
-pub const PLIC_IRQ_OFFSET: usize = 0;
-pub const PLIC_MAX_IRQ: usize = 0;
+/// The number of interrupt sources of the PLIC, the spike machine has no PLIC
+#[cfg(all(target_arch = "riscv64", not(feature = "PLAT_QEMU_RISCV_VIRT")))]
+pub const PLIC_MAX_NUM_INT: usize = 0;
+#[cfg(all(target_arch = "riscv64", feature = "PLAT_QEMU_RISCV_VIRT"))]
+pub const PLIC_MAX_NUM_INT: usize = 128;
+/// The PLIC is the first kernel device, see `kernel_device_frames`
+#[cfg(target_arch = "riscv64")]
+pub const PLIC_PPTR: usize = 0xFFFFFFFFC0000000;
+
+/// The PLIC sources keep their numbers, the kernel interrupts come after them
+#[cfg(target_arch = "riscv64")]
+pub const PLIC_MAX_IRQ: usize = PLIC_MAX_NUM_INT;
 
-#[cfg(feature = "ENABLE_SMP")]
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "riscv64"))]
+pub const INTERRUPT_IPI_0: usize = PLIC_MAX_IRQ + 1;
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "riscv64"))]
+pub const INTERRUPT_IPI_1: usize = PLIC_MAX_IRQ + 2;
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "riscv64"))]
+pub const KERNEL_TIMER_IRQ: usize = PLIC_MAX_IRQ + 3;
+
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "aarch64"))]
 pub const INTERRUPT_IPI_0: usize = 1;
-#[cfg(feature = "ENABLE_SMP")]
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "aarch64"))]
 pub const INTERRUPT_IPI_1: usize = 2;
-#[cfg(feature = "ENABLE_SMP")]
-pub const KERNEL_TIMER_IRQ: usize = 3;
 
 #[cfg(all(not(feature = "ENABLE_SMP"), target_arch = "riscv64"))]
-pub const KERNEL_TIMER_IRQ: usize = 1;
+pub const KERNEL_TIMER_IRQ: usize = PLIC_MAX_IRQ + 1;
 
-#[cfg(all(not(feature = "ENABLE_SMP"), target_arch = "aarch64"))]
+#[cfg(all(target_arch = "aarch64", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
 pub const KERNEL_TIMER_IRQ: usize = 27;
+/// The EL2 physical timer, the virtual timer belongs to the guests
+#[cfg(all(target_arch = "aarch64", feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub const KERNEL_TIMER_IRQ: usize = 26;
 
 #[cfg(target_arch = "riscv64")]
 pub const maxIRQ: usize = KERNEL_TIMER_IRQ;
@@ -129,9 +163,22 @@ pub const CONFIG_MAX_NUM_BOOTINFO_UNTYPED_CAPS: usize = 230;
 
 pub const seL4_MaxPrio: usize = 255;
 
+#[cfg(target_arch = "riscv64")]
 pub const TIMER_CLOCK_HZ: usize = 10000000;
+#[cfg(target_arch = "aarch64")]
+pub const TIMER_CLOCK_HZ: usize = 62500000;
 pub const MS_IN_S: usize = 1000;
+#[cfg(feature = "KERNEL_MCS")]
+pub const US_IN_S: usize = 1000000;
+/// The precision of the kernel timer in microseconds
+#[cfg(feature = "KERNEL_MCS")]
+pub const TIMER_PRECISION: usize = 1;
+/// The ticks taken to program the kernel timer
+#[cfg(feature = "KERNEL_MCS")]
+pub const TIMER_OVERHEAD_TICKS: usize = 0;
 pub const RESET_CYCLES: usize = (TIMER_CLOCK_HZ / MS_IN_S) * 2;
+#[cfg(target_arch = "aarch64")]
+pub const CONFIG_TIMER_TICK_MS: usize = 10;
 
 pub const seL4_MinPrio: usize = 0;
 
@@ -1,15 +1,18 @@
 use crate::config::CONFIG_MAX_NUM_WORK_UNITS_PER_PREEMPTION;
-// use crate::ffi::tcbDebugRemove;
 use crate::interrupt::{deletingIRQHandler, isIRQPending, setIRQState, IRQState};
 use crate::kernel::boot::current_lookup_fault;
 use crate::syscall::safe_unbind_notification;
 use sel4_common::sel4_config::{tcbCNodeEntries, tcbCTable, tcbVTable};
 use sel4_common::structures::exception_t;
 use sel4_common::utils::convert_to_mut_type_ref;
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_common::utils::convert_to_option_mut_type_ref;
 use sel4_cspace::compatibility::{ZombieType_ZombieTCB, Zombie_new};
 use sel4_cspace::interface::{cap_t, finaliseCap_ret, CapTag};
 use sel4_ipc::{endpoint_t, notification_t, Transfer};
 use sel4_task::{get_currenct_thread, ksWorkUnitsCompleted, tcb_t};
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::{reply_t, sched_context_t, ThreadState};
 #[cfg(target_arch = "riscv64")]
 use sel4_vspace::find_vspace_for_asid;
 #[cfg(target_arch = "aarch64")]
@@ -61,6 +64,12 @@ pub fn Arch_finaliseCap(cap: &cap_t, final_: bool) -> finaliseCap_ret {
                 deleteASIDPool(cap.get_asid_base(), cap.get_asid_pool() as *mut asid_pool_t);
             }
         }
+        #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+        CapTag::CapVCPUCap => {
+            if final_ {
+                crate::arch::vcpu::vcpu_finalise(convert_to_mut_type_ref(cap.get_vcpu_ptr()));
+            }
+        }
         _ => {}
     }
     fc_ret.remainder = cap_t::new_null_cap();
@@ -125,6 +134,12 @@ pub fn Arch_finaliseCap(cap: &cap_t, final_: bool) -> finaliseCap_ret {
             }
         }
         CapTag::CapASIDControlCap => {}
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        CapTag::CapVCPUCap => {
+            if final_ {
+                crate::arch::vcpu::vcpu_finalise(convert_to_mut_type_ref(cap.get_vcpu_ptr()));
+            }
+        }
         _ => unimplemented!("finaliseCap: {:?}", cap.get_cap_type()),
     }
     fc_ret.remainder = cap_t::new_null_cap();
@@ -165,7 +180,41 @@ pub fn finaliseCap(cap: &cap_t, _final: bool, _exposed: bool) -> finaliseCap_ret
             fc_ret.cleanupInfo = cap_t::new_null_cap();
             return fc_ret;
         }
-        CapTag::CapReplyCap | CapTag::CapNullCap | CapTag::CapDomainCap => {
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapReplyCap => {
+            if _final {
+                let reply = convert_to_mut_type_ref::<reply_t>(cap.get_reply_ptr());
+                if let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(reply.replyTCB) {
+                    match tcb.get_state() {
+                        ThreadState::ThreadStateBlockedOnReply => reply.unlink(tcb),
+                        ThreadState::ThreadStateBlockedOnReceive => tcb.cancel_ipc(),
+                        _ => panic!("invalid state of the thread bound to a reply object"),
+                    }
+                }
+            }
+            fc_ret.remainder = cap_t::new_null_cap();
+            fc_ret.cleanupInfo = cap_t::new_null_cap();
+            return fc_ret;
+        }
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapSchedContextCap => {
+            if _final {
+                let sc = convert_to_mut_type_ref::<sched_context_t>(cap.get_sc_ptr());
+                sc.unbind_tcb();
+                // the scheduling context is no longer valid
+                sc.scRefillMax = 0;
+            }
+            fc_ret.remainder = cap_t::new_null_cap();
+            fc_ret.cleanupInfo = cap_t::new_null_cap();
+            return fc_ret;
+        }
+        #[cfg(not(feature = "KERNEL_MCS"))]
+        CapTag::CapReplyCap => {
+            fc_ret.remainder = cap_t::new_null_cap();
+            fc_ret.cleanupInfo = cap_t::new_null_cap();
+            return fc_ret;
+        }
+        CapTag::CapNullCap | CapTag::CapDomainCap | CapTag::CapSchedControlCap => {
             fc_ret.remainder = cap_t::new_null_cap();
             fc_ret.cleanupInfo = cap_t::new_null_cap();
             return fc_ret;
@@ -197,17 +246,23 @@ pub fn finaliseCap(cap: &cap_t, _final: bool, _exposed: bool) -> finaliseCap_ret
             if _final {
                 let tcb = convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr());
                 #[cfg(feature = "ENABLE_SMP")]
-                unsafe {
-                    crate::ffi::remoteTCBStall(tcb)
-                };
+                crate::smp::remote_tcb_stall(tcb);
                 let cte_ptr = tcb.get_cspace_mut_ref(tcbCTable);
                 safe_unbind_notification(tcb);
                 tcb.cancel_ipc();
                 tcb.suspend();
-                // #[cfg(feature="DEBUG_BUILD")]
-                // unsafe {
-                //     tcbDebugRemove(tcb as *mut tcb_t);
-                // }
+                #[cfg(feature = "KERNEL_MCS")]
+                if tcb.tcbSchedContext != 0 {
+                    tcb.get_sched_context().unbind_tcb();
+                }
+                tcb.DebugRemove();
+                #[cfg(any(target_arch = "aarch64", feature = "HAVE_FPU"))]
+                crate::arch::fpu_thread_delete(tcb);
+                #[cfg(any(
+                    feature = "ARM_HYPERVISOR_SUPPORT",
+                    feature = "RISCV_HYPERVISOR_SUPPORT"
+                ))]
+                crate::arch::vcpu::dissociate_vcpu_tcb(tcb);
                 fc_ret.remainder =
                     Zombie_new(tcbCNodeEntries, ZombieType_ZombieTCB, cte_ptr.get_ptr());
                 fc_ret.cleanupInfo = cap_t::new_null_cap();
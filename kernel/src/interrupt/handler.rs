@@ -1,16 +1,35 @@
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::arm_gic::{INTERRUPT_VGIC_MAINTENANCE, INTERRUPT_VTIMER_EVENT};
+#[cfg(not(feature = "KERNEL_MCS"))]
 use crate::arch::resetTimer;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::vcpu::{handle_vgic_maintenance, handle_vppi_event};
 use crate::config::{irqInvalid, maxIRQ};
 use crate::interrupt::*;
+use crate::kernel::monitor::{monitor_check, monitor_filter};
 use core::intrinsics::unlikely;
 use log::debug;
 use sel4_common::structures::exception_t;
 use sel4_cspace::interface::CapTag;
 use sel4_ipc::notification_t;
-use sel4_task::{activateThread, schedule, timerTick};
+#[cfg(not(feature = "KERNEL_MCS"))]
+use sel4_task::timerTick;
+use sel4_task::{activateThread, schedule};
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::{check_budget, ksReprogram, update_timestamp};
 
 #[no_mangle]
 pub fn handleInterruptEntry() -> exception_t {
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        update_timestamp();
+        check_budget();
+    }
     let irq = getActiveIRQ();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    crate::kernel::benchmark::benchmark_track_entry(
+        crate::kernel::benchmark::kernel_entry_t::new_interrupt_entry(irq),
+    );
 
     if irq != irqInvalid {
         handleInterrupt(irq);
@@ -28,15 +47,18 @@ pub fn handleInterrupt(irq: usize) {
             "Received IRQ {}, which is above the platforms maxIRQ of {}\n",
             irq, maxIRQ
         );
-        mask_interrupt(true, irq);
         ackInterrupt(irq);
+        mask_interrupt(true, irq);
         return;
     }
     match get_irq_state(irq) {
         IRQState::IRQInactive => {
             debug!("IRQInactive");
-            mask_interrupt(true, irq);
             debug!("Received disabled IRQ: {}\n", irq);
+            // the PLIC ignores the completion of a disabled interrupt, so ack before masking
+            ackInterrupt(irq);
+            mask_interrupt(true, irq);
+            return;
         }
         IRQState::IRQSignal => {
             debug!("IRQSignal");
@@ -48,18 +70,56 @@ pub fn handleInterrupt(irq: usize) {
                 let nf = convert_to_mut_type_ref::<notification_t>(handler_cap.get_nf_ptr());
                 nf.send_signal(handler_cap.get_nf_badge());
             }
+            // the line stays masked until IRQHandler_Ack, the PLIC holds the claim instead
+            #[cfg(target_arch = "aarch64")]
+            mask_interrupt(true, irq);
         }
         IRQState::IRQTimer => {
-            timerTick();
-            resetTimer();
+            // the console output left by a full UART
+            sel4_common::console::poll();
+            if !IS_IRQ_VALID(unsafe { ksSerialIRQ }) {
+                // the console input of a UART without an interrupt
+                sel4_common::console::poll_rx(monitor_filter);
+                monitor_check();
+            }
+            #[cfg(not(feature = "KERNEL_MCS"))]
+            {
+                timerTick();
+                resetTimer();
+            }
+            #[cfg(feature = "KERNEL_MCS")]
+            {
+                crate::arch::ackDeadlineIRQ();
+                unsafe {
+                    ksReprogram = true;
+                }
+            }
         }
         #[cfg(feature = "ENABLE_SMP")]
         IRQState::IRQIPI => {
-            unsafe { crate::ffi::handleIPI(irq, true) };
-        }
-        IRQState::IRQReserved => {
-            debug!("Received unhandled reserved IRQ: {}\n", irq);
+            crate::smp::handle_ipi(irq, true);
         }
+        IRQState::IRQReserved => handle_reserved_irq(irq),
     }
     ackInterrupt(irq);
 }
+
+/// Handle the interrupts the kernel keeps for itself
+fn handle_reserved_irq(irq: usize) {
+    if irq == unsafe { ksSerialIRQ } {
+        sel4_common::console::handle_rx_irq(monitor_filter);
+        monitor_check();
+        return;
+    }
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    if irq == INTERRUPT_VGIC_MAINTENANCE {
+        handle_vgic_maintenance();
+        return;
+    }
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    if irq == INTERRUPT_VTIMER_EVENT {
+        handle_vppi_event(irq);
+        return;
+    }
+    debug!("Received unhandled reserved IRQ: {}\n", irq);
+}
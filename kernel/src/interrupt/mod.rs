@@ -13,23 +13,29 @@ use sel4_vspace::pptr_t;
 
 use crate::config::*;
 
+#[cfg(target_arch = "aarch64")]
+use crate::arch::arm_gic::{Gic, GicDriver};
 #[cfg(target_arch = "riscv64")]
-use crate::arch::read_sip;
+use crate::arch::{plic_complete_claim, plic_get_claim, plic_mask_irq, read_sip};
 
-#[cfg(feature = "ENABLE_SMP")]
-use crate::ffi::{ipi_clear_irq, ipi_get_irq};
+#[cfg(all(feature = "ENABLE_SMP", target_arch = "riscv64"))]
+use crate::smp::{ipi_clear_irq, ipi_get_irq};
 
 #[no_mangle]
 pub static mut intStateIRQTable: [usize; maxIRQ + 1] = [0; maxIRQ + 1];
 
 pub static mut intStateIRQNode: pptr_t = 0;
 
+/// The interrupt of the UART of the kernel console, found in the device tree
+pub static mut ksSerialIRQ: usize = irqInvalid;
+
 #[no_mangle]
 // #[link_section = ".boot.bss"]
 pub static mut active_irq: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
 
 #[cfg(feature = "ENABLE_SMP")]
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[allow(dead_code)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum IRQState {
     IRQInactive = 0,
     IRQSignal = 1,
@@ -115,17 +121,11 @@ pub fn mask_interrupt(disable: bool, irq: usize) {
         } else {
             set_sie_mask(BIT!(SIE_STIE));
         }
+    } else if irq != irqInvalid && irq <= PLIC_MAX_IRQ {
+        plic_mask_irq(disable, irq);
     }
     #[cfg(target_arch = "aarch64")]
-    {
-        if irq == KERNEL_TIMER_IRQ {
-            if disable {
-                log::info!("disable interrupt: {}", irq);
-            } else {
-                crate::arch::arm_gic::gic_v2::irq_enable(irq);
-            }
-        }
-    }
+    Gic::mask_irq(disable, irq);
 }
 
 #[cfg(target_arch = "riscv64")]
@@ -140,29 +140,32 @@ pub fn isIRQPending() -> bool {
 
 #[cfg(target_arch = "aarch64")]
 pub fn isIRQPending() -> bool {
-    false
+    Gic::is_irq_pending()
 }
 
 #[no_mangle]
 pub fn ackInterrupt(irq: usize) {
+    // the EOI of a GICv2 SGI takes the id of the sending core as well, like the acknowledge gave it
+    #[cfg(target_arch = "aarch64")]
+    {
+        let raw_irq = global_ops!(active_irq[cpu_id()]);
+        assert!(raw_irq & Gic::IRQ_MASK == irq);
+        Gic::ack_irq(raw_irq);
+    }
     unsafe {
         active_irq[cpu_id()] = irqInvalid;
     }
     if irq == KERNEL_TIMER_IRQ {
-        #[cfg(target_arch = "aarch64")]
-        {
-            crate::arch::arm_gic::gic_v2::gic_v2::ack_irq(irq);
-            global_ops!(active_irq[cpu_id()] = 0);
-        }
         return;
     }
-    #[cfg(feature = "ENABLE_SMP")]
-    {
-        if irq == INTERRUPT_IPI_0 || irq == INTERRUPT_IPI_1 {
-            unsafe {
-                ipi_clear_irq(irq);
-            }
-        }
+    // the claim of an interrupt given to the user is completed by IRQHandler_Ack
+    #[cfg(target_arch = "riscv64")]
+    if irq != irqInvalid && irq <= PLIC_MAX_IRQ && get_irq_state(irq) != IRQState::IRQSignal {
+        plic_complete_claim(irq);
+    }
+    #[cfg(all(feature = "ENABLE_SMP", target_arch = "riscv64"))]
+    if irq == INTERRUPT_IPI_0 || irq == INTERRUPT_IPI_1 {
+        ipi_clear_irq(irq);
     }
 }
 
@@ -187,12 +190,12 @@ pub fn getActiveIRQ() -> usize {
     let sip = read_sip();
     #[cfg(feature = "ENABLE_SMP")]
     {
-        use sel4_common::sbi::clear_ipi;
+        use sel4_common::arch::clear_ipi;
         if (sip & BIT!(SIP_SEIP)) != 0 {
-            irq = 0;
+            irq = plic_get_claim();
         } else if (sip & BIT!(SIP_SSIP)) != 0 {
             clear_ipi();
-            irq = unsafe { ipi_get_irq() };
+            irq = ipi_get_irq();
             // debug!("irq: {}", irq);
         } else if (sip & BIT!(SIP_STIP)) != 0 {
             irq = KERNEL_TIMER_IRQ;
@@ -202,7 +205,7 @@ pub fn getActiveIRQ() -> usize {
     }
     #[cfg(not(feature = "ENABLE_SMP"))]
     if (sip & BIT!(SIP_SEIP)) != 0 {
-        irq = 0;
+        irq = plic_get_claim();
     } else if (sip & BIT!(SIP_STIP)) != 0 {
         irq = KERNEL_TIMER_IRQ;
     } else {
@@ -230,13 +233,13 @@ pub fn getActiveIRQ() -> usize {
             irq = irqInvalid;
         }
     */
-    use crate::arch::arm_gic::gic_v2::{consts::IRQ_MASK, gic_v2::gic_int_ack};
-
-    if !IS_IRQ_VALID(global_ops!(active_irq[cpu_id()])) {
-        global_ops!(active_irq[cpu_id()] = gic_int_ack());
+    // active_irq keeps the raw interrupt id for the EOI
+    if !IS_IRQ_VALID(global_ops!(active_irq[cpu_id()] & Gic::IRQ_MASK)) {
+        global_ops!(active_irq[cpu_id()] = Gic::get_active_irq());
     }
-    let irq = match global_ops!(IS_IRQ_VALID(active_irq[cpu_id()])) {
-        true => global_ops!(active_irq[cpu_id()] & IRQ_MASK as usize),
+    let irq = global_ops!(active_irq[cpu_id()] & Gic::IRQ_MASK);
+    let irq = match IS_IRQ_VALID(irq) {
+        true => irq,
         false => irqInvalid,
     };
     log::debug!("active irq: {}", irq);
@@ -0,0 +1,211 @@
+//! Kernel entry tracking, the benchmark log buffer of seL4.
+//!
+//! Every kernel entry is recorded, with the time spent in the kernel, to the large page frame
+//! the user hands in with `SysBenchmarkSetLogBuffer`. The frame is mapped at `KS_LOG_PPTR` so
+//! the exit path can write the log without looking up the frame again.
+use core::intrinsics::{likely, unlikely};
+use core::mem::size_of;
+
+use log::debug;
+use sel4_common::arch::ArchReg::Cap;
+use sel4_common::fault::seL4_Fault_t;
+use sel4_common::message_info::seL4_MessageInfo_t;
+use sel4_common::sel4_config::{
+    seL4_IllegalOperation, seL4_LargePageBits, seL4_NoError, CONFIG_MAX_NUM_NODES,
+};
+use sel4_common::structures::exception_t;
+use sel4_common::utils::{cpu_id, pageBitsForSize};
+use sel4_common::{plus_define_bitfield, BIT};
+use sel4_cspace::interface::CapTag;
+use sel4_task::{get_currenct_thread, tcb_t};
+use sel4_vspace::{map_kernel_log_frame, pptr_to_paddr};
+
+use crate::config::KS_LOG_PPTR;
+use crate::kernel::boot::current_fault;
+use crate::object::lookupCapAndSlot;
+use crate::syscall::{SysCall, SysNBRecv};
+
+/// The reason of a kernel entry, the values are the `path` tags of seL4
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum KernelEntryPath {
+    Interrupt = 0,
+    UnknownSyscall = 1,
+    UserLevelFault = 2,
+    VMFault = 4,
+    Syscall = 5,
+}
+
+/// The entries other than the syscalls give the core they were taken on before their word, with
+/// more than one core
+#[cfg(feature = "ENABLE_SMP")]
+const ENTRY_CORE_OFFSET: usize = 3;
+#[cfg(feature = "ENABLE_SMP")]
+const ENTRY_WORD_OFFSET: usize = 6;
+#[cfg(not(feature = "ENABLE_SMP"))]
+const ENTRY_WORD_OFFSET: usize = 3;
+/// The entry is packed in 32 bits, as libsel4 reads it
+const ENTRY_WORD_BITS: usize = 32 - ENTRY_WORD_OFFSET;
+
+plus_define_bitfield! {
+    kernel_entry_t, 1, 0, 0, 3 => {
+        new_interrupt_entry, KernelEntryPath::Interrupt as usize => {
+            irq, interrupt_entry_get_irq, interrupt_entry_set_irq, 0, ENTRY_WORD_OFFSET, ENTRY_WORD_BITS, 0, false
+        },
+        new_unknown_syscall_entry, KernelEntryPath::UnknownSyscall as usize => {
+            word, unknown_syscall_entry_get_word, unknown_syscall_entry_set_word, 0, ENTRY_WORD_OFFSET, ENTRY_WORD_BITS, 0, false
+        },
+        new_user_level_fault_entry, KernelEntryPath::UserLevelFault as usize => {
+            number, user_level_fault_entry_get_number, user_level_fault_entry_set_number, 0, ENTRY_WORD_OFFSET, ENTRY_WORD_BITS, 0, false
+        },
+        new_vm_fault_entry, KernelEntryPath::VMFault as usize => {
+            fault_type, vm_fault_entry_get_fault_type, vm_fault_entry_set_fault_type, 0, ENTRY_WORD_OFFSET, ENTRY_WORD_BITS, 0, false
+        },
+        new_syscall_entry, KernelEntryPath::Syscall as usize => {
+            syscall_no, syscall_entry_get_syscall_no, syscall_entry_set_syscall_no, 0, 3, 4, 0, false,
+            cap_type, syscall_entry_get_cap_type, syscall_entry_set_cap_type, 0, 7, 5, 0, false,
+            is_fastpath, syscall_entry_get_is_fastpath, syscall_entry_set_is_fastpath, 0, 12, 1, 0, false,
+            invocation_tag, syscall_entry_get_invocation_tag, syscall_entry_set_invocation_tag, 0, 13, 19, 0, false
+        }
+    }
+}
+
+/// One record of the log buffer, the `benchmark_track_kernel_entry_t` of libsel4
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct benchmark_track_kernel_entry_t {
+    /// The cycle counter when the kernel was entered
+    pub start_time: u64,
+    /// The cycles spent in the kernel
+    pub duration: u32,
+    /// The word of the `kernel_entry_t`
+    pub entry: u32,
+}
+
+const _: () = assert!(size_of::<benchmark_track_kernel_entry_t>() == 16);
+
+/// The number of records that fit in the log buffer
+pub const MAX_LOG_SIZE: usize =
+    BIT!(seL4_LargePageBits) / size_of::<benchmark_track_kernel_entry_t>();
+
+/// Physical address of the log buffer, 0 until the user sets one
+static mut ksUserLogBuffer: usize = 0;
+/// The next free record of the log buffer
+static mut ksLogIndex: usize = 0;
+/// The number of records when the log was last finalized
+static mut ksLogIndexFinalized: usize = 0;
+/// The last kernel entry of each core
+static mut ksKernelEntry: [kernel_entry_t; CONFIG_MAX_NUM_NODES] =
+    [kernel_entry_t { words: [0] }; CONFIG_MAX_NUM_NODES];
+
+/// Record why the current core entered the kernel.
+#[inline]
+pub fn benchmark_track_entry(entry: kernel_entry_t) {
+    #[cfg(feature = "ENABLE_SMP")]
+    let entry = if entry.get_type() == KernelEntryPath::Syscall as usize {
+        entry
+    } else {
+        kernel_entry_t {
+            words: [entry.words[0] | (cpu_id() << ENTRY_CORE_OFFSET)],
+        }
+    };
+    unsafe {
+        ksKernelEntry[cpu_id()] = entry;
+    }
+}
+
+/// Record a syscall with the type of the invoked cap and the label of the message.
+pub fn benchmark_track_syscall(cptr: usize, msg_info: usize, syscall: isize, is_fastpath: bool) {
+    if syscall < SysNBRecv || syscall > SysCall {
+        benchmark_track_entry(kernel_entry_t::new_unknown_syscall_entry(
+            syscall.unsigned_abs(),
+        ));
+        return;
+    }
+    let lu_ret = lookupCapAndSlot(get_currenct_thread(), cptr);
+    benchmark_track_entry(kernel_entry_t::new_syscall_entry(
+        syscall.unsigned_abs(),
+        lu_ret.cap.get_cap_type() as usize,
+        is_fastpath as usize,
+        seL4_MessageInfo_t::from_word(msg_info).get_usize_label(),
+    ));
+}
+
+/// The fastpath gave up, the syscall is handled by the slowpath after all.
+#[inline]
+pub fn benchmark_track_slowpath() {
+    let entry = unsafe { &mut ksKernelEntry[cpu_id()] };
+    if entry.get_type() == KernelEntryPath::Syscall as usize {
+        entry.syscall_entry_set_is_fastpath(0);
+    }
+}
+
+/// Write the kernel entry of the current core to the log buffer, nothing is written once the
+/// buffer is full.
+pub(super) fn benchmark_log_write(enter: usize, exit: usize) {
+    // The IPIs are handled without the big kernel lock, the log may only be written with it
+    #[cfg(feature = "ENABLE_SMP")]
+    if !crate::smp::clh_is_self_in_queue() {
+        return;
+    }
+    unsafe {
+        if likely(ksUserLogBuffer != 0) && likely(ksLogIndex < MAX_LOG_SIZE) {
+            let log = KS_LOG_PPTR as *mut benchmark_track_kernel_entry_t;
+            log.add(ksLogIndex)
+                .write_volatile(benchmark_track_kernel_entry_t {
+                    start_time: enter as u64,
+                    duration: exit.wrapping_sub(enter) as u32,
+                    entry: ksKernelEntry[cpu_id()].words[0] as u32,
+                });
+            ksLogIndex += 1;
+        }
+    }
+}
+
+/// Handle `SysBenchmarkSetLogBuffer`, the large page frame cap in the cap register becomes the
+/// log buffer.
+pub fn benchmark_set_log_buffer(thread: &mut tcb_t) -> exception_t {
+    let cptr = thread.tcbArch.get_register(Cap);
+    let lu_ret = lookupCapAndSlot(thread, cptr);
+    let cap = &lu_ret.cap;
+    if unlikely(
+        lu_ret.status != exception_t::EXCEPTION_NONE
+            || cap.get_cap_type() != CapTag::CapFrameCap
+            || pageBitsForSize(cap.get_frame_size()) != seL4_LargePageBits,
+    ) {
+        debug!(
+            "SysBenchmarkSetLogBuffer: invalid log buffer cap {:#x}",
+            cptr
+        );
+        unsafe {
+            current_fault = seL4_Fault_t::new_cap_fault(cptr, 0);
+        }
+        thread.tcbArch.set_register(Cap, seL4_IllegalOperation);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let paddr = pptr_to_paddr(cap.get_frame_base_ptr());
+    unsafe {
+        ksUserLogBuffer = paddr;
+    }
+    map_kernel_log_frame(paddr, KS_LOG_PPTR);
+    thread.tcbArch.set_register(Cap, seL4_NoError);
+    exception_t::EXCEPTION_NONE
+}
+
+/// Start the log over from the first record, false if no log buffer is set.
+pub(super) fn benchmark_log_reset() -> bool {
+    unsafe {
+        if ksUserLogBuffer == 0 {
+            return false;
+        }
+        ksLogIndex = 0;
+    }
+    true
+}
+
+/// Stop the log and get the number of records in it.
+pub(super) fn benchmark_log_finalize() -> usize {
+    unsafe {
+        ksLogIndexFinalized = ksLogIndex;
+        ksLogIndexFinalized
+    }
+}
@@ -0,0 +1,114 @@
+//! The benchmarks of the kernel, enabled by `ENABLE_BENCHMARKS`.
+//!
+//! Every kernel entry is timed with the cycle counter. `BENCHMARK_TRACK_KERNEL_ENTRIES` writes
+//! the entries to the log buffer of the user, `BENCHMARK_TRACK_UTILISATION` charges the cycles
+//! to the threads and the cores.
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+mod log_buffer;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+mod utilisation;
+
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+pub use log_buffer::*;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub use utilisation::*;
+
+use sel4_common::arch::ArchReg::Cap;
+use sel4_common::sel4_config::{seL4_NoError, CONFIG_MAX_NUM_NODES};
+use sel4_common::structures::exception_t;
+use sel4_common::utils::cpu_id;
+use sel4_task::tcb_t;
+
+use crate::arch::read_cycle_counter;
+use crate::syscall::{SysBenchmarkFinalizeLog, SysBenchmarkResetLog};
+
+/// The cycle counter of each core when it entered the kernel
+static mut ksEnter: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+/// Stamp the kernel entry of the current core.
+#[inline]
+pub fn benchmark_track_start() {
+    unsafe {
+        ksEnter[cpu_id()] = read_cycle_counter();
+    }
+}
+
+/// The cycle counter of the current core when it last entered the kernel.
+#[cfg(any(
+    feature = "BENCHMARK_TRACK_KERNEL_ENTRIES",
+    feature = "BENCHMARK_TRACK_UTILISATION"
+))]
+#[inline]
+pub fn get_kernel_entry_time() -> usize {
+    unsafe { ksEnter[cpu_id()] }
+}
+
+/// Account the kernel entry of the current core, called right before returning to the user.
+pub fn benchmark_track_exit() {
+    #[cfg(any(
+        feature = "BENCHMARK_TRACK_KERNEL_ENTRIES",
+        feature = "BENCHMARK_TRACK_UTILISATION"
+    ))]
+    let (enter, exit) = (get_kernel_entry_time(), read_cycle_counter());
+    #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+    sel4_task::benchmark_utilisation_kentry_stamp(enter as u64, exit as u64);
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_log_write(enter, exit);
+}
+
+/// Handle the benchmark syscalls, `None` if `w` is not one of them.
+pub fn handle_benchmark_syscall(w: isize, thread: &mut tcb_t) -> Option<exception_t> {
+    let ret = match w {
+        SysBenchmarkResetLog => benchmark_reset_log(thread),
+        SysBenchmarkFinalizeLog => benchmark_finalize_log(thread),
+        #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+        crate::syscall::SysBenchmarkSetLogBuffer => benchmark_set_log_buffer(thread),
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        crate::syscall::SysBenchmarkGetThreadUtilisation => {
+            benchmark_get_thread_utilisation(thread)
+        }
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        crate::syscall::SysBenchmarkResetThreadUtilisation => {
+            benchmark_reset_thread_utilisation(thread)
+        }
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        crate::syscall::SysBenchmarkDumpAllThreadsUtilisation => {
+            benchmark_dump_all_threads_utilisation();
+            exception_t::EXCEPTION_NONE
+        }
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        crate::syscall::SysBenchmarkResetAllThreadsUtilisation => {
+            benchmark_reset_all_threads_utilisation();
+            exception_t::EXCEPTION_NONE
+        }
+        _ => return None,
+    };
+    Some(ret)
+}
+
+/// Handle `SysBenchmarkResetLog`, the log and the utilisation accounting start over.
+fn benchmark_reset_log(thread: &mut tcb_t) -> exception_t {
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    if !benchmark_log_reset() {
+        log::debug!("SysBenchmarkResetLog: no log buffer set");
+        thread
+            .tcbArch
+            .set_register(Cap, sel4_common::sel4_config::seL4_IllegalOperation);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+    sel4_task::benchmark_utilisation_start(get_kernel_entry_time() as u64);
+    thread.tcbArch.set_register(Cap, seL4_NoError);
+    exception_t::EXCEPTION_NONE
+}
+
+/// Handle `SysBenchmarkFinalizeLog`, the number of log records is returned in the cap register.
+fn benchmark_finalize_log(thread: &mut tcb_t) -> exception_t {
+    #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+    sel4_task::benchmark_utilisation_finalise(get_kernel_entry_time() as u64);
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    thread.tcbArch.set_register(Cap, benchmark_log_finalize());
+    #[cfg(not(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES"))]
+    thread.tcbArch.set_register(Cap, 0);
+    exception_t::EXCEPTION_NONE
+}
@@ -0,0 +1,206 @@
+//! The syscalls of the CPU utilisation accounting, the counters themselves are kept by
+//! `sel4_task` as the threads are switched there.
+use core::mem::size_of;
+
+use log::debug;
+use sel4_common::arch::ArchReg::Cap;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::structures::exception_t;
+use sel4_common::utils::{convert_to_mut_type_ref, cpu_id};
+use sel4_common::{print, println};
+use sel4_cspace::interface::CapTag;
+use sel4_task::{
+    benchmark_track_reset_utilisation, get_core_utilisation, get_debug_tcbs_head, tcb_t,
+};
+
+use super::get_kernel_entry_time;
+use crate::compatibility::lookupIPCBuffer;
+use crate::object::lookupCapAndSlot;
+
+/// The indexes of the counters in the IPC buffer, as `benchmark_track_util_ipc_index` of libsel4
+pub const BENCHMARK_TCB_UTILISATION: usize = 0;
+pub const BENCHMARK_TCB_NUMBER_SCHEDULES: usize = 1;
+pub const BENCHMARK_TCB_KERNEL_UTILISATION: usize = 2;
+pub const BENCHMARK_TCB_NUMBER_KERNEL_ENTRIES: usize = 3;
+pub const BENCHMARK_IDLE_LOCALCPU_UTILISATION: usize = 4;
+pub const BENCHMARK_IDLE_TCBCPU_UTILISATION: usize = 5;
+pub const BENCHMARK_IDLE_NUMBER_SCHEDULES: usize = 6;
+pub const BENCHMARK_IDLE_KERNEL_UTILISATION: usize = 7;
+pub const BENCHMARK_IDLE_NUMBER_KERNEL_ENTRIES: usize = 8;
+pub const BENCHMARK_TOTAL_UTILISATION: usize = 9;
+pub const BENCHMARK_TOTAL_NUMBER_SCHEDULES: usize = 10;
+pub const BENCHMARK_TOTAL_KERNEL_UTILISATION: usize = 11;
+pub const BENCHMARK_TOTAL_NUMBER_KERNEL_ENTRIES: usize = 12;
+
+#[no_mangle]
+pub fn getKernelEntryTime() -> u64 {
+    get_kernel_entry_time() as u64
+}
+
+/// Get the idle thread of the given core.
+fn get_core_idle_thread(_cpu: usize) -> &'static mut tcb_t {
+    #[cfg(feature = "ENABLE_SMP")]
+    {
+        convert_to_mut_type_ref::<tcb_t>(unsafe { sel4_task::ksSMP[_cpu].ksIdleThread })
+    }
+    #[cfg(not(feature = "ENABLE_SMP"))]
+    {
+        sel4_task::get_idle_thread()
+    }
+}
+
+/// Look up the TCB cap in the cap register of `thread`.
+fn lookup_tcb_cap(thread: &mut tcb_t, syscall: &str) -> Option<&'static mut tcb_t> {
+    let cptr = thread.tcbArch.get_register(Cap);
+    let lu_ret = lookupCapAndSlot(thread, cptr);
+    if lu_ret.status != exception_t::EXCEPTION_NONE
+        || lu_ret.cap.get_cap_type() != CapTag::CapThreadCap
+    {
+        debug!("{}: cap is not a TCB", syscall);
+        return None;
+    }
+    Some(convert_to_mut_type_ref::<tcb_t>(lu_ret.cap.get_tcb_ptr()))
+}
+
+/// Handle `SysBenchmarkGetThreadUtilisation`, the counters of the TCB in the cap register, of
+/// its idle thread and of its core are written to the IPC buffer.
+pub fn benchmark_get_thread_utilisation(thread: &mut tcb_t) -> exception_t {
+    let Some(tcb) = lookup_tcb_cap(thread, "SysBenchmarkGetThreadUtilisation") else {
+        return exception_t::EXCEPTION_NONE;
+    };
+    let buffer = lookupIPCBuffer(true, thread);
+    if buffer == 0 {
+        debug!("SysBenchmarkGetThreadUtilisation: no IPC buffer");
+        return exception_t::EXCEPTION_NONE;
+    }
+    // the counters start at the first message register, right after the tag
+    let counters = (buffer + size_of::<usize>()) as *mut u64;
+    let local_idle = get_core_idle_thread(cpu_id()).benchmark;
+    let tcb_idle = get_core_idle_thread(tcb.get_cpu()).benchmark;
+    let core = get_core_utilisation(tcb.get_cpu());
+    let end_time = if core.enabled {
+        get_kernel_entry_time() as u64
+    } else {
+        core.end_time
+    };
+    let values = [
+        (BENCHMARK_TCB_UTILISATION, tcb.benchmark.utilisation),
+        (
+            BENCHMARK_TCB_NUMBER_SCHEDULES,
+            tcb.benchmark.number_schedules,
+        ),
+        (
+            BENCHMARK_TCB_KERNEL_UTILISATION,
+            tcb.benchmark.kernel_utilisation,
+        ),
+        (
+            BENCHMARK_TCB_NUMBER_KERNEL_ENTRIES,
+            tcb.benchmark.number_kernel_entries,
+        ),
+        (BENCHMARK_IDLE_LOCALCPU_UTILISATION, local_idle.utilisation),
+        (BENCHMARK_IDLE_TCBCPU_UTILISATION, tcb_idle.utilisation),
+        (BENCHMARK_IDLE_NUMBER_SCHEDULES, tcb_idle.number_schedules),
+        (
+            BENCHMARK_IDLE_KERNEL_UTILISATION,
+            tcb_idle.kernel_utilisation,
+        ),
+        (
+            BENCHMARK_IDLE_NUMBER_KERNEL_ENTRIES,
+            tcb_idle.number_kernel_entries,
+        ),
+        (
+            BENCHMARK_TOTAL_UTILISATION,
+            end_time.wrapping_sub(core.start_time),
+        ),
+        (BENCHMARK_TOTAL_NUMBER_SCHEDULES, core.number_schedules),
+        (BENCHMARK_TOTAL_KERNEL_UTILISATION, core.kernel_time),
+        (
+            BENCHMARK_TOTAL_NUMBER_KERNEL_ENTRIES,
+            core.number_kernel_entries,
+        ),
+    ];
+    for (index, value) in values {
+        unsafe {
+            counters.add(index).write(value);
+        }
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+/// Handle `SysBenchmarkResetThreadUtilisation` for the TCB in the cap register.
+pub fn benchmark_reset_thread_utilisation(thread: &mut tcb_t) -> exception_t {
+    if let Some(tcb) = lookup_tcb_cap(thread, "SysBenchmarkResetThreadUtilisation") {
+        benchmark_track_reset_utilisation(tcb);
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+/// Call `f` on every TCB in the debug TCB lists of all the cores.
+fn for_each_debug_tcb(mut f: impl FnMut(&mut tcb_t)) {
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        let mut curr = get_debug_tcbs_head(cpu);
+        while curr != 0 {
+            let tcb = convert_to_mut_type_ref::<tcb_t>(curr);
+            f(tcb);
+            curr = tcb.tcbDebugNext;
+        }
+    }
+}
+
+/// Handle `SysBenchmarkDumpAllThreadsUtilisation`, the counters of the cores and of every thread
+/// are printed as JSON.
+pub fn benchmark_dump_all_threads_utilisation() {
+    println!("{{");
+    println!("  \"BENCHMARK_TOTAL_UTILISATION\": [");
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        let core = get_core_utilisation(cpu);
+        let end_time = if core.enabled {
+            get_kernel_entry_time() as u64
+        } else {
+            core.end_time
+        };
+        print!(
+            "    {{ \"core\": {}, \"total\": {}, \"kernel\": {}, \"entries\": {}, \"schedules\": {} }}",
+            cpu,
+            end_time.wrapping_sub(core.start_time),
+            core.kernel_time,
+            core.number_kernel_entries,
+            core.number_schedules
+        );
+        println!(
+            "{}",
+            if cpu + 1 < CONFIG_MAX_NUM_NODES {
+                ","
+            } else {
+                ""
+            }
+        );
+    }
+    println!("  ],");
+    println!("  \"BENCHMARK_THREAD_UTILISATION\": [");
+    let mut first = true;
+    for_each_debug_tcb(|tcb| {
+        if !first {
+            println!(",");
+        }
+        first = false;
+        print!(
+            "    {{ \"name\": \"{}\", \"tcb\": {:#x}, \"core\": {}, \"utilisation\": {}, \"schedules\": {}, \"kernel\": {}, \"entries\": {} }}",
+            tcb.get_name(),
+            tcb.get_ptr(),
+            tcb.get_cpu(),
+            tcb.benchmark.utilisation,
+            tcb.benchmark.number_schedules,
+            tcb.benchmark.kernel_utilisation,
+            tcb.benchmark.number_kernel_entries
+        );
+    });
+    println!("");
+    println!("  ]");
+    println!("}}");
+}
+
+/// Handle `SysBenchmarkResetAllThreadsUtilisation`, the counters of every thread are cleared.
+pub fn benchmark_reset_all_threads_utilisation() {
+    for_each_debug_tcb(benchmark_track_reset_utilisation);
+}
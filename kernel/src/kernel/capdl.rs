@@ -0,0 +1,332 @@
+//! capDL-style snapshot of the current thread, used by SysDebugSnapshot.
+//!
+//! The CSpace is walked breadth first from the thread's root CNode. Mapped frames and page
+//! tables are taken from the caps found in that walk whose mapped ASID matches the thread's
+//! VSpace root, so mappings made through caps outside of the CSpace are not shown.
+
+use core::mem::size_of;
+
+use sel4_common::arch::{vm_rights_t, ArchReg};
+use sel4_common::println;
+use sel4_common::sel4_config::{tcbCTable, tcbVTable};
+use sel4_common::utils::{convert_to_mut_type_ref, pageBitsForSize};
+use sel4_common::vm_rights::vm_rights_from_word;
+use sel4_common::BIT;
+use sel4_cspace::interface::{cap_t, cte_t, CapTag};
+use sel4_task::{get_currenct_thread, tcb_t};
+
+/// The max number of CNodes visited by one snapshot
+const MAX_SNAPSHOT_CNODES: usize = 64;
+
+#[cfg(target_arch = "riscv64")]
+const SNAPSHOT_ARCH: &str = "riscv64";
+#[cfg(target_arch = "aarch64")]
+const SNAPSHOT_ARCH: &str = "aarch64";
+
+/// Print the CSpace and VSpace of the current thread in a capDL-like text format.
+pub fn debug_capdl_snapshot() {
+    let thread = get_currenct_thread();
+    let mut cnodes = [(0usize, 0usize); MAX_SNAPSHOT_CNODES];
+    let mut cnode_count = 0;
+    let root = &thread.get_cspace(tcbCTable).cap;
+    if root.get_cap_type() == CapTag::CapCNodeCap {
+        cnodes[0] = (root.get_cnode_ptr(), root.get_cnode_radix());
+        cnode_count = 1;
+    }
+
+    println!("arch {}", SNAPSHOT_ARCH);
+    println!("");
+    println!("objects {{");
+    print_tcb_object(thread);
+    // The object list is the walk order, so the cnodes are discovered while printing their caps
+    let mut truncated = false;
+    let mut i = 0;
+    while i < cnode_count {
+        let (cnode_ptr, radix) = cnodes[i];
+        for index in 0..BIT!(radix) {
+            let cap = &cnode_slot(cnode_ptr, index).cap;
+            if cap.get_cap_type() != CapTag::CapCNodeCap {
+                continue;
+            }
+            let ptr = cap.get_cnode_ptr();
+            if cnodes[..cnode_count].iter().any(|&(p, _)| p == ptr) {
+                continue;
+            }
+            if cnode_count == MAX_SNAPSHOT_CNODES {
+                truncated = true;
+                continue;
+            }
+            cnodes[cnode_count] = (ptr, cap.get_cnode_radix());
+            cnode_count += 1;
+        }
+        println!("cnode_{:#x} = cnode ({} bits)", cnode_ptr, radix);
+        i += 1;
+    }
+    println!("}}");
+    if truncated {
+        println!(
+            "/* more than {} cnodes, the walk is truncated */",
+            MAX_SNAPSHOT_CNODES
+        );
+    }
+
+    println!("");
+    println!("caps {{");
+    print_tcb_caps(thread);
+    for &(cnode_ptr, radix) in cnodes[..cnode_count].iter() {
+        println!("cnode_{:#x} {{", cnode_ptr);
+        for index in 0..BIT!(radix) {
+            let slot = cnode_slot(cnode_ptr, index);
+            if slot.cap.get_cap_type() == CapTag::CapNullCap {
+                continue;
+            }
+            print_slot(index, slot);
+        }
+        println!("}}");
+    }
+    println!("}}");
+
+    println!("");
+    print_vspace(thread, &cnodes[..cnode_count]);
+}
+
+#[inline]
+/// Get the `index` th slot of a CNode
+fn cnode_slot(cnode_ptr: usize, index: usize) -> &'static mut cte_t {
+    convert_to_mut_type_ref::<cte_t>(cnode_ptr + index * size_of::<cte_t>())
+}
+
+/// Print the object line of the TCB
+fn print_tcb_object(thread: &tcb_t) {
+    println!(
+        "tcb_{:#x} = tcb (name: \"{}\", ip: {:#x}, sp: {:#x}, ipc_buffer_addr: {:#x}, prio: {}, max_prio: {}, dom: {})",
+        thread.get_ptr(),
+        thread.get_name(),
+        thread.tcbArch.get_register(ArchReg::FaultIP),
+        thread.tcbArch.get_register(ArchReg::SP),
+        thread.tcbIPCBuffer,
+        thread.tcbPriority,
+        thread.tcbMCP,
+        thread.domain
+    );
+}
+
+/// Print the caps held in the TCB CNode entries
+fn print_tcb_caps(thread: &mut tcb_t) {
+    const TCB_SLOT_NAMES: [&str; 5] = ["cspace", "vspace", "reply", "caller", "ipc_buffer_slot"];
+    println!("tcb_{:#x} {{", thread.get_ptr());
+    for (index, name) in TCB_SLOT_NAMES.iter().enumerate() {
+        let cap = &thread.get_cspace(index).cap;
+        if cap.get_cap_type() == CapTag::CapNullCap {
+            continue;
+        }
+        println!("{}: {}", name, CapDisplay(cap));
+    }
+    println!("}}");
+}
+
+/// Print one slot of a CNode, with its MDB parent if it has one
+fn print_slot(index: usize, slot: &cte_t) {
+    match find_mdb_parent(slot) {
+        Some(parent) => println!(
+            "{:#x}: {} /* slot {:#x}, parent slot {:#x} */",
+            index,
+            CapDisplay(&slot.cap),
+            slot.get_ptr(),
+            parent
+        ),
+        None => println!(
+            "{:#x}: {} /* slot {:#x} */",
+            index,
+            CapDisplay(&slot.cap),
+            slot.get_ptr()
+        ),
+    }
+}
+
+/// Find the closest preceding slot in the MDB that is the parent of `slot`
+fn find_mdb_parent(slot: &cte_t) -> Option<usize> {
+    let mut prev = slot.cteMDBNode.get_prev();
+    while prev != 0 {
+        let prev_slot = convert_to_mut_type_ref::<cte_t>(prev);
+        if prev_slot.is_mdb_parent_of(slot) {
+            return Some(prev);
+        }
+        prev = prev_slot.cteMDBNode.get_prev();
+    }
+    None
+}
+
+/// Print the page tables and frames mapped in the VSpace of the thread
+fn print_vspace(thread: &mut tcb_t, cnodes: &[(usize, usize)]) {
+    let root = &thread.get_cspace(tcbVTable).cap;
+    let (root_ptr, asid) = match vspace_root(root) {
+        Some(root) => root,
+        None => {
+            println!("/* no valid vspace root */");
+            return;
+        }
+    };
+    println!("vspace_{:#x} (asid: {:#x}) {{", root_ptr, asid);
+    for &(cnode_ptr, radix) in cnodes.iter() {
+        for index in 0..BIT!(radix) {
+            let cap = &cnode_slot(cnode_ptr, index).cap;
+            match cap.get_cap_type() {
+                CapTag::CapFrameCap
+                    if cap.get_frame_mapped_asid() == asid
+                        && cap.get_frame_mapped_address() != 0 =>
+                {
+                    println!("{:#x}: {}", cap.get_frame_mapped_address(), CapDisplay(cap));
+                }
+                CapTag::CapPageTableCap
+                    if cap.get_pt_is_mapped() != 0 && cap.get_pt_mapped_asid() == asid =>
+                {
+                    println!("{:#x}: {}", cap.get_pt_mapped_address(), CapDisplay(cap));
+                }
+                _ => {}
+            }
+        }
+    }
+    println!("}}");
+}
+
+#[cfg(target_arch = "riscv64")]
+/// Get the VSpace root and its ASID from the vtable cap
+fn vspace_root(cap: &cap_t) -> Option<(usize, usize)> {
+    if cap.get_cap_type() == CapTag::CapPageTableCap && cap.get_pt_is_mapped() != 0 {
+        Some((cap.get_pt_base_ptr(), cap.get_pt_mapped_asid()))
+    } else {
+        None
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+/// Get the VSpace root and its ASID from the vtable cap
+fn vspace_root(cap: &cap_t) -> Option<(usize, usize)> {
+    if cap.get_cap_type() == CapTag::CapVspaceCap && cap.get_vs_is_mapped() != 0 {
+        Some((cap.get_vs_base_ptr(), cap.get_vs_mapped_asid()))
+    } else {
+        None
+    }
+}
+
+/// Get the capDL rights string of a frame
+fn frame_rights(cap: &cap_t) -> &'static str {
+    match vm_rights_from_word(cap.get_frame_vm_rights()) {
+        vm_rights_t::VMReadWrite => "RW",
+        vm_rights_t::VMReadOnly => "R",
+        vm_rights_t::VMKernelOnly => "",
+    }
+}
+
+/// Formats a cap as `<object> (<attributes>)`
+struct CapDisplay<'a>(&'a cap_t);
+
+impl core::fmt::Display for CapDisplay<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let cap = self.0;
+        match cap.get_cap_type() {
+            CapTag::CapNullCap => write!(f, "null"),
+            CapTag::CapUntypedCap => write!(
+                f,
+                "ut_{:#x} ({} bits{})",
+                cap.get_untyped_ptr(),
+                cap.get_untyped_block_size(),
+                if cap.get_untyped_is_device() != 0 {
+                    ", device"
+                } else {
+                    ""
+                }
+            ),
+            CapTag::CapEndpointCap => write!(
+                f,
+                "ep_{:#x} ({}{}{}{}, badge: {:#x})",
+                cap.get_ep_ptr(),
+                if cap.get_ep_can_receive() != 0 {
+                    "R"
+                } else {
+                    ""
+                },
+                if cap.get_ep_can_send() != 0 { "W" } else { "" },
+                if cap.get_ep_can_grant() != 0 { "G" } else { "" },
+                if cap.get_ep_can_grant_reply() != 0 {
+                    "P"
+                } else {
+                    ""
+                },
+                cap.get_ep_badge()
+            ),
+            CapTag::CapNotificationCap => write!(
+                f,
+                "notification_{:#x} ({}{}, badge: {:#x})",
+                cap.get_nf_ptr(),
+                if cap.get_nf_can_receive() != 0 {
+                    "R"
+                } else {
+                    ""
+                },
+                if cap.get_nf_can_send() != 0 { "W" } else { "" },
+                cap.get_nf_badge()
+            ),
+            CapTag::CapReplyCap => write!(
+                f,
+                "reply_{:#x} ({}{})",
+                cap.get_reply_tcb_ptr(),
+                if cap.get_reply_master() != 0 {
+                    "master"
+                } else {
+                    "caller"
+                },
+                if cap.get_reply_can_grant() != 0 {
+                    ", G"
+                } else {
+                    ""
+                }
+            ),
+            CapTag::CapCNodeCap => write!(
+                f,
+                "cnode_{:#x} (guard: {:#x}, guard_size: {})",
+                cap.get_cnode_ptr(),
+                cap.get_cnode_guard(),
+                cap.get_cnode_guard_size()
+            ),
+            CapTag::CapThreadCap => write!(f, "tcb_{:#x}", cap.get_tcb_ptr()),
+            CapTag::CapIrqControlCap => write!(f, "irq_control"),
+            CapTag::CapIrqHandlerCap => write!(f, "irq_{}", cap.get_irq_handler()),
+            CapTag::CapZombieCap => write!(f, "zombie_{:#x}", cap.get_zombie_ptr()),
+            CapTag::CapDomainCap => write!(f, "domain"),
+            CapTag::CapSchedContextCap => write!(
+                f,
+                "sc_{:#x} ({} bits)",
+                cap.get_sc_ptr(),
+                cap.get_sc_size_bits()
+            ),
+            CapTag::CapSchedControlCap => {
+                write!(f, "sched_control (core: {})", cap.get_sched_control_core())
+            }
+            CapTag::CapFrameCap => write!(
+                f,
+                "frame_{:#x} ({} bits, {}{})",
+                cap.get_frame_base_ptr(),
+                pageBitsForSize(cap.get_frame_size()),
+                frame_rights(cap),
+                if cap.get_frame_is_device() != 0 {
+                    ", device"
+                } else {
+                    ""
+                }
+            ),
+            CapTag::CapPageTableCap => write!(f, "pt_{:#x}", cap.get_pt_base_ptr()),
+            #[cfg(target_arch = "aarch64")]
+            CapTag::CapVspaceCap => write!(f, "vspace_{:#x}", cap.get_vs_base_ptr()),
+            CapTag::CapASIDControlCap => write!(f, "asid_control"),
+            CapTag::CapASIDPoolCap => write!(
+                f,
+                "asid_pool_{:#x} (asid_high: {:#x})",
+                cap.get_asid_pool(),
+                cap.get_asid_base()
+            ),
+            CapTag::CapVCPUCap => write!(f, "vcpu_{:#x}", cap.get_vcpu_ptr()),
+        }
+    }
+}
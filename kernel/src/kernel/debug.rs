@@ -0,0 +1,77 @@
+//! Kernel debug facilities, used by the debug syscalls.
+
+use sel4_common::arch::ArchReg;
+use sel4_common::println;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::convert_to_option_mut_type_ref;
+use sel4_task::{get_debug_tcbs_head, tcb_t, ThreadState};
+
+/// Print one row per TCB in the debug TCB lists of all cores.
+pub fn debug_dump_scheduler() {
+    println!("Dumping all tcbs!");
+    println!(
+        "{:<18} | {:<31} | {:<16} | {:>4} | {:>4} | {:>3} | {:>4} | {:<18} | {:<18} | {:<18}",
+        "Address", "Name", "State", "Prio", "MCP", "Dom", "Core", "Blocking Object", "IP", "SP"
+    );
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        let mut tcb_ptr = get_debug_tcbs_head(cpu);
+        while let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(tcb_ptr) {
+            debug_print_tcb(tcb);
+            tcb_ptr = tcb.tcbDebugNext;
+        }
+    }
+}
+
+/// Find the TCB at `addr` in the debug TCB lists, so a wrong address given by a debugger does not
+/// take the kernel down.
+pub(crate) fn find_debug_tcb(addr: usize) -> Option<&'static mut tcb_t> {
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        let mut tcb_ptr = get_debug_tcbs_head(cpu);
+        while let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(tcb_ptr) {
+            if tcb_ptr == addr {
+                return Some(tcb);
+            }
+            tcb_ptr = tcb.tcbDebugNext;
+        }
+    }
+    None
+}
+
+/// Print one row of the scheduler dump for the given TCB.
+fn debug_print_tcb(tcb: &tcb_t) {
+    let state = tcb.get_state();
+    let blocking_object = match state {
+        ThreadState::ThreadStateBlockedOnReceive
+        | ThreadState::ThreadStateBlockedOnSend
+        | ThreadState::ThreadStateBlockedOnNotification => tcb.tcbState.get_blocking_object(),
+        _ => 0,
+    };
+    println!(
+        "{:<#18x} | {:<31} | {:<16} | {:>4} | {:>4} | {:>3} | {:>4} | {:<#18x} | {:<#18x} | {:<#18x}",
+        tcb.get_ptr(),
+        tcb.get_name(),
+        thread_state_name(&state),
+        tcb.tcbPriority,
+        tcb.tcbMCP,
+        tcb.domain,
+        tcb.get_cpu(),
+        blocking_object,
+        tcb.tcbArch.get_register(ArchReg::FaultIP),
+        tcb.tcbArch.get_register(ArchReg::SP)
+    );
+}
+
+/// Get the printable name of a thread state.
+pub(crate) fn thread_state_name(state: &ThreadState) -> &'static str {
+    match state {
+        ThreadState::ThreadStateInactive => "inactive",
+        ThreadState::ThreadStateRunning => "running",
+        ThreadState::ThreadStateRestart => "restart",
+        ThreadState::ThreadStateBlockedOnReceive => "blocked on recv",
+        ThreadState::ThreadStateBlockedOnSend => "blocked on send",
+        ThreadState::ThreadStateBlockedOnReply => "blocked on reply",
+        ThreadState::ThreadStateBlockedOnNotification => "blocked on ntfn",
+        ThreadState::ThreadStateIdleThreadState => "idle",
+        ThreadState::ThreadStateExited => "exited",
+    }
+}
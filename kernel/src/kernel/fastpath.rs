@@ -1,10 +1,15 @@
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+use crate::kernel::benchmark::benchmark_track_syscall;
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+use crate::kernel::benchmark::{benchmark_track_exit, benchmark_track_start};
 use crate::MASK;
 use crate::{
+    arch::restore_user_context,
     config::seL4_MsgLengthBits,
-    syscall::{slowpath, SysCall, SysReplyRecv},
+    syscall::{slowpath, SysCall, SysReplyRecv, SysSend},
 };
 use core::intrinsics::{likely, unlikely};
-use sel4_common::arch::msgRegister;
+use sel4_common::arch::{msgRegister, ArchReg};
 use sel4_common::{
     fault::*,
     message_info::*,
@@ -77,10 +82,20 @@ pub fn endpoint_ptr_mset_epQueue_tail_state(ptr: *mut endpoint_t, tail: usize, s
 pub fn switchToThread_fp(thread: *mut tcb_t, vroot: *mut PTE, stored_hw_asid: PTE) {
     let asid = stored_hw_asid.0;
     unsafe {
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        benchmark_utilisation_switch(&mut *thread);
         #[cfg(target_arch = "riscv64")]
         setVSpaceRoot(pptr_to_paddr(vroot as usize), asid);
-        #[cfg(target_arch = "aarch64")]
+        #[cfg(all(target_arch = "aarch64", not(feature = "ARM_HYPERVISOR_SUPPORT")))]
         setCurrentUserVSpaceRoot(ttbr_new(asid, pptr_to_paddr(vroot as usize)));
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        match get_hw_asid(asid, vroot as usize) {
+            Some(vmid) => setCurrentUserVSpaceRoot(ttbr_new(vmid, pptr_to_paddr(vroot as usize))),
+            None => setCurrentUserVSpaceRoot(ttbr_new(
+                0,
+                kpptr_to_paddr(get_arm_global_user_vspace_base()),
+            )),
+        }
         // panic!("switchToThread_fp");
         // ksCurThread = thread as usize;
         set_current_thread(&*thread);
@@ -135,21 +150,22 @@ pub fn fastpath_copy_mrs(length: usize, src: &mut tcb_t, dest: &mut tcb_t) {
 #[cfg(target_arch = "aarch64")]
 pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t) {
     use core::arch::asm;
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_exit();
     unsafe {
         (*cur_thread).tcbArch.load_thread_local();
+        crate::arch::lazy_fpu_restore(&*cur_thread);
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        crate::arch::vcpu_switch((*cur_thread).tcbArch.tcbVCPU);
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        crate::arch::restore_breakpoint_state(&mut *cur_thread);
         asm!(
             "mov     sp, {}                     \n",
             /* Restore thread's SPSR, LR, and SP */
             "ldp     x21, x22, [sp, #31 * 8]  \n",
             "ldr     x23, [sp, #33 * 8]     \n",
             "msr     sp_el0, x21                \n",
-            // #ifdef CONFIG_ARM_HYPERVISOR_SUPPORT
-            // 		"msr     elr_el2, x22               \n"
-            // 		"msr     spsr_el2, x23              \n"
-            // #else
-            "msr     elr_el1, x22               \n",
-            "msr     spsr_el1, x23              \n",
-            // #endif
+            crate::msr_return_state!(),
 
             /* Restore remaining registers */
             "ldp     x2,  x3,  [sp, #16 * 1]    \n",
@@ -178,12 +194,18 @@ pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t)
 #[no_mangle]
 #[cfg(target_arch = "riscv64")]
 pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t) {
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_exit();
     #[cfg(feature = "ENABLE_SMP")]
     {}
     extern "C" {
         pub fn __fastpath_restore(badge: usize, msgInfo: usize, cur_thread_reg: usize);
     }
     unsafe {
+        #[cfg(feature = "HAVE_FPU")]
+        crate::arch::lazy_fpu_restore(&mut *cur_thread);
+        #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+        crate::arch::vcpu_switch((*cur_thread).tcbArch.tcbVCPU);
         __fastpath_restore(_badge, _msgInfo, (*cur_thread).tcbArch.raw_ptr());
     }
     panic!("unreachable")
@@ -192,6 +214,14 @@ pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t)
 #[inline]
 #[no_mangle]
 pub fn fastpath_call(cptr: usize, msgInfo: usize) {
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_syscall(cptr, msgInfo, SysCall, true);
+    // the fastpath works on the reply caps of the TCBs, which the MCS kernel replaces with
+    // reply objects
+    #[cfg(feature = "KERNEL_MCS")]
+    slowpath(SysCall as usize);
     let current = get_currenct_thread();
     let mut info = seL4_MessageInfo_t::from_word(msgInfo);
     let length = info.get_length();
@@ -218,10 +248,13 @@ pub fn fastpath_call(cptr: usize, msgInfo: usize) {
         slowpath(SysCall as usize);
     }
 
-    let dom = 0;
+    let dom = get_fastpath_domain();
     if unlikely(dest.tcbPriority < current.tcbPriority && !isHighestPrio(dom, dest.tcbPriority)) {
         slowpath(SysCall as usize);
     }
+    if unlikely(CONFIG_NUM_DOMAINS > 1 && dest.domain != get_current_domain()) {
+        slowpath(SysCall as usize);
+    }
     if unlikely((ep_cap.get_ep_can_grant() == 0) && (ep_cap.get_ep_can_grant_reply() == 0)) {
         slowpath(SysCall as usize);
     }
@@ -268,6 +301,12 @@ pub fn fastpath_call(cptr: usize, msgInfo: usize) {
 #[inline]
 #[no_mangle]
 pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
+    #[cfg(feature = "ENABLE_BENCHMARKS")]
+    benchmark_track_start();
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_syscall(cptr, msgInfo, SysReplyRecv, true);
+    #[cfg(feature = "KERNEL_MCS")]
+    slowpath(SysReplyRecv as usize);
     // debug!("enter fastpath_reply_recv");
     let current = get_currenct_thread();
     let mut info = seL4_MessageInfo_t::from_word(msgInfo);
@@ -315,10 +354,13 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
         slowpath(SysReplyRecv as usize);
     }
 
-    let dom = 0;
+    let dom = get_fastpath_domain();
     if unlikely(!isHighestPrio(dom, caller.tcbPriority)) {
         slowpath(SysReplyRecv as usize);
     }
+    if unlikely(CONFIG_NUM_DOMAINS > 1 && caller.domain != get_current_domain()) {
+        slowpath(SysReplyRecv as usize);
+    }
     thread_state_ptr_mset_blockingObject_tsType(
         &mut current.tcbState,
         ep.get_ptr(),
@@ -359,3 +401,79 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
     fastpath_restore(0, msg_info1, get_currenct_thread() as *mut tcb_t);
     // }
 }
+
+/// The fastpath of `seL4_Signal`, a `Send` on a notification cap.
+///
+/// The thread waiting on the notification, or the bound thread blocked on receive, is woken up
+/// here directly and switched to if it has a higher priority in the same domain. The kernel entry
+/// stamped by `c_handle_syscall` is marked as a fastpath one once the checks have passed.
+#[inline]
+#[no_mangle]
+pub fn fastpath_signal(cptr: usize, _msgInfo: usize) {
+    #[cfg(feature = "KERNEL_MCS")]
+    slowpath(SysSend as usize);
+    let current = get_currenct_thread();
+    if unlikely(current.tcbFault.get_fault_type() != FaultType::NullFault) {
+        slowpath(SysSend as usize);
+    }
+    let ntfn_cap = lookup_fp(&current.get_cspace(tcbCTable).cap, cptr);
+    if unlikely(
+        ntfn_cap.get_cap_type() != CapTag::CapNotificationCap || ntfn_cap.get_nf_can_send() == 0,
+    ) {
+        slowpath(SysSend as usize);
+    }
+    let ntfn = convert_to_mut_type_ref::<notification_t>(ntfn_cap.get_nf_ptr());
+    let badge = ntfn_cap.get_nf_badge();
+    let dest = match ntfn.get_state() {
+        NtfnState::Active => None,
+        NtfnState::Idle => convert_to_option_mut_type_ref::<tcb_t>(ntfn.get_bound_tcb())
+            .filter(|tcb| tcb.get_state() == ThreadState::ThreadStateBlockedOnReceive),
+        NtfnState::Waiting => convert_to_option_mut_type_ref::<tcb_t>(ntfn.get_queue_head()),
+    };
+    let Some(dest) = dest else {
+        #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+        benchmark_track_syscall(cptr, _msgInfo, SysSend, true);
+        // nobody to wake up, the badge is kept in the notification
+        ntfn.send_signal(badge);
+        return restore_user_context();
+    };
+
+    #[cfg(feature = "ENABLE_SMP")]
+    if unlikely(current.tcbAffinity != dest.tcbAffinity) {
+        slowpath(SysSend as usize);
+    }
+    let same_domain = CONFIG_NUM_DOMAINS == 1 || dest.domain == get_current_domain();
+    let switch = same_domain && dest.tcbPriority > current.tcbPriority;
+    let new_vtable = dest.get_cspace(tcbVTable).cap;
+    if unlikely(switch && !isValidVTableRoot_fp(&new_vtable)) {
+        slowpath(SysSend as usize);
+    }
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    benchmark_track_syscall(cptr, _msgInfo, SysSend, true);
+
+    if ntfn.get_state() == NtfnState::Waiting {
+        let mut queue = ntfn.get_queue();
+        queue.ep_dequeue(dest);
+        ntfn.set_queue(&queue);
+        if queue.empty() {
+            ntfn.set_state(NtfnState::Idle as usize);
+        }
+    } else {
+        // the bound thread leaves the endpoint it is blocked on
+        dest.cancel_ipc();
+    }
+    dest.tcbArch.set_register(ArchReg::Badge, badge);
+    dest.tcbState.words[0] = ThreadState::ThreadStateRunning as usize;
+
+    if switch {
+        current.sched_enqueue();
+        let cap_pd = new_vtable.get_pt_base_ptr() as *mut PTE;
+        let stored_hw_asid: PTE = PTE(new_vtable.get_pt_mapped_asid());
+        switchToThread_fp(dest, cap_pd, stored_hw_asid);
+    } else if same_domain && dest.tcbPriority == current.tcbPriority {
+        dest.sched_append();
+    } else {
+        dest.sched_enqueue();
+    }
+    restore_user_context();
+}
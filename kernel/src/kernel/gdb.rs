@@ -0,0 +1,633 @@
+//! A GDB remote serial protocol stub, to debug a user thread over the kernel console.
+//!
+//! A thread is attached by the `gdb` command of the [monitor](super::monitor), then GDB is
+//! connected to the serial, e.g. `target remote /dev/pts/N` for the pty of QEMU. The faults of
+//! the thread, its breakpoints and steps included, stop it in the stub instead of being sent to
+//! its fault handler, and Ctrl-C from GDB stops it as well. The stub runs in the kernel with the
+//! interrupts disabled until GDB resumes the thread.
+//!
+//! The console output of the kernel goes over the same serial, GDB ignores it between packets.
+
+use super::debug::find_debug_tcb;
+use crate::arch::gdb::{
+    breakpoint_instruction, is_stray_fault, lookup_user_addr, read_register, register_size,
+    set_single_step, sync_icache, user_exception_signal, write_register, GDB_PC_REGISTER,
+    GDB_REG_NUM,
+};
+use crate::config::USER_TOP;
+use crate::kernel::boot::current_fault;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use driver_collect::{default_serial, SerialDriver};
+use sel4_common::arch::{frameRegNum, frameRegisters, gpRegNum, gpRegisters, ArchReg};
+use sel4_common::console;
+use sel4_common::fault::{seL4_Fault_t, FaultType};
+use sel4_task::tcb_t;
+use spin::Mutex;
+
+pub const GDB_SIGNAL_INT: u8 = 2;
+pub const GDB_SIGNAL_ILL: u8 = 4;
+pub const GDB_SIGNAL_TRAP: u8 = 5;
+pub const GDB_SIGNAL_BUS: u8 = 10;
+pub const GDB_SIGNAL_SEGV: u8 = 11;
+pub const GDB_SIGNAL_SYS: u8 = 12;
+
+/// The size of the packet data the stub takes and sends, in bytes
+const PACKET_SIZE: usize = 1024;
+/// The number of software breakpoints
+const MAX_BREAKPOINTS: usize = 16;
+/// The thread id of the attached thread, there is only one
+const THREAD_ID: usize = 1;
+
+/// The attached thread, 0 for none
+static GDB_THREAD: AtomicUsize = AtomicUsize::new(0);
+/// Whether the attached thread is being stepped
+static GDB_STEPPING: AtomicBool = AtomicBool::new(false);
+/// Whether Ctrl-C has been received and the stub is yet to stop the thread
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static GDB: Mutex<GdbStub> = Mutex::new(GdbStub::new());
+
+/// How the thread goes on when the stub is left
+#[derive(PartialEq, Eq)]
+enum Resume {
+    Continue,
+    /// Continue with the fault sent to the fault handler, for the `C` packet
+    Deliver,
+    Detach,
+}
+
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    addr: usize,
+    /// The size of the instruction, 0 for a free breakpoint
+    kind: usize,
+    saved: [u8; 4],
+}
+
+impl Breakpoint {
+    const fn new() -> Self {
+        Self {
+            addr: 0,
+            kind: 0,
+            saved: [0; 4],
+        }
+    }
+
+    /// Put the saved instruction back and free the breakpoint.
+    fn restore(&mut self, thread: &mut tcb_t) {
+        for (i, &byte) in self.saved[..self.kind].iter().enumerate() {
+            write_byte(thread, self.addr + i, byte);
+        }
+        self.kind = 0;
+    }
+}
+
+/// The packet data to send
+struct Reply {
+    buf: [u8; PACKET_SIZE],
+    len: usize,
+}
+
+impl Reply {
+    const fn new() -> Self {
+        Self {
+            buf: [0; PACKET_SIZE],
+            len: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push(&mut self, c: u8) {
+        if self.len < PACKET_SIZE {
+            self.buf[self.len] = c;
+            self.len += 1;
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for &c in s.as_bytes() {
+            self.push(c);
+        }
+    }
+
+    fn push_hex_byte(&mut self, b: u8) {
+        self.push(HEX_DIGITS[(b >> 4) as usize]);
+        self.push(HEX_DIGITS[(b & 0xf) as usize]);
+    }
+
+    /// Push a number in big-endian hex, without leading zeros
+    fn push_hex(&mut self, value: usize) {
+        let digits = (usize::BITS - value.leading_zeros()).div_ceil(4).max(1);
+        for i in (0..digits).rev() {
+            self.push(HEX_DIGITS[(value >> (i * 4)) & 0xf]);
+        }
+    }
+
+    /// Push a register value in the target byte order, little-endian
+    fn push_register(&mut self, value: usize, size: usize) {
+        for i in 0..size {
+            self.push_hex_byte((value >> (i * 8)) as u8);
+        }
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+struct GdbStub {
+    packet: [u8; PACKET_SIZE],
+    session: Session,
+}
+
+impl GdbStub {
+    const fn new() -> Self {
+        Self {
+            packet: [0; PACKET_SIZE],
+            session: Session {
+                breakpoints: [Breakpoint::new(); MAX_BREAKPOINTS],
+                reply: Reply::new(),
+            },
+        }
+    }
+
+    /// Serve GDB until it resumes the thread. The stop reply is sent first if `announce`, GDB
+    /// waits for it after resuming the thread.
+    fn run(&mut self, thread: &mut tcb_t, signal: u8, announce: bool) -> Resume {
+        let session = &mut self.session;
+        console::flush();
+        if announce {
+            session.stop_reply(signal);
+            send_packet(&session.reply);
+        }
+        loop {
+            let len = recv_packet(&mut self.packet);
+            session.reply.clear();
+            match session.handle_packet(thread, signal, &self.packet[..len]) {
+                None => send_packet(&session.reply),
+                Some(Resume::Detach) => {
+                    session.remove_breakpoints(thread);
+                    // D is answered, k is not
+                    if session.reply.len != 0 {
+                        send_packet(&session.reply);
+                    }
+                    return Resume::Detach;
+                }
+                // the continue and the step are answered by the next stop
+                Some(resume) => return resume,
+            }
+        }
+    }
+}
+
+/// The state of the stub which lasts between the packets
+struct Session {
+    breakpoints: [Breakpoint; MAX_BREAKPOINTS],
+    reply: Reply,
+}
+
+impl Session {
+    fn stop_reply(&mut self, signal: u8) {
+        self.reply.clear();
+        self.reply.push(b'T');
+        self.reply.push_hex_byte(signal);
+        self.reply.push_str("thread:");
+        self.reply.push_hex(THREAD_ID);
+        self.reply.push(b';');
+    }
+
+    fn handle_packet(&mut self, thread: &mut tcb_t, signal: u8, packet: &[u8]) -> Option<Resume> {
+        let (&cmd, args) = packet.split_first()?;
+        match cmd {
+            b'?' => self.stop_reply(signal),
+            b'g' => {
+                for n in 0..GDB_REG_NUM {
+                    self.reply
+                        .push_register(read_register(thread, n), register_size(n));
+                }
+            }
+            b'G' => {
+                let mut data = args;
+                for n in 0..GDB_REG_NUM {
+                    let size = register_size(n);
+                    if data.len() < size * 2 {
+                        break;
+                    }
+                    let (value, rest) = data.split_at(size * 2);
+                    let Some(value) = parse_register(value) else {
+                        self.reply.push_str("E01");
+                        return None;
+                    };
+                    write_register(thread, n, value);
+                    data = rest;
+                }
+                self.reply.push_str("OK");
+            }
+            b'p' => match parse_hex(args) {
+                Some(n) if register_size(n) != 0 => self
+                    .reply
+                    .push_register(read_register(thread, n), register_size(n)),
+                _ => self.reply.push_str("E01"),
+            },
+            b'P' => match split(args, b'=') {
+                Some((n, value)) => match (parse_hex(n), parse_register(value)) {
+                    (Some(n), Some(value)) if register_size(n) != 0 => {
+                        write_register(thread, n, value);
+                        self.reply.push_str("OK");
+                    }
+                    _ => self.reply.push_str("E01"),
+                },
+                None => self.reply.push_str("E01"),
+            },
+            b'm' => self.read_memory(thread, args),
+            b'M' => self.write_memory(thread, args),
+            b'Z' | b'z' => self.breakpoint(thread, cmd == b'Z', args),
+            b'c' | b'C' | b's' | b'S' => {
+                // C and S take a signal before the address
+                let addr = match cmd {
+                    b'c' | b's' => args,
+                    _ => split(args, b';').map_or(&[][..], |(_, addr)| addr),
+                };
+                if let Some(addr) = parse_hex(addr) {
+                    write_register(thread, GDB_PC_REGISTER, addr);
+                }
+                if cmd == b's' || cmd == b'S' {
+                    if !set_single_step(thread, true) {
+                        self.reply.push_str("E01");
+                        return None;
+                    }
+                    GDB_STEPPING.store(true, Ordering::Relaxed);
+                }
+                return Some(if cmd == b'C' {
+                    Resume::Deliver
+                } else {
+                    Resume::Continue
+                });
+            }
+            b'D' => {
+                self.reply.push_str("OK");
+                return Some(Resume::Detach);
+            }
+            // the thread cannot be killed, it is let go instead
+            b'k' => return Some(Resume::Detach),
+            b'H' => self.reply.push_str("OK"),
+            b'T' => match parse_hex(args) {
+                Some(THREAD_ID) => self.reply.push_str("OK"),
+                _ => self.reply.push_str("E01"),
+            },
+            b'q' => self.query(args),
+            _ => {}
+        }
+        None
+    }
+
+    fn query(&mut self, args: &[u8]) {
+        let name = split(args, b':').map_or(args, |(name, _)| name);
+        match name {
+            b"Supported" => {
+                self.reply.push_str("PacketSize=");
+                self.reply.push_hex(PACKET_SIZE);
+            }
+            b"Attached" => self.reply.push_str("1"),
+            b"C" => {
+                self.reply.push_str("QC");
+                self.reply.push_hex(THREAD_ID);
+            }
+            b"fThreadInfo" => {
+                self.reply.push(b'm');
+                self.reply.push_hex(THREAD_ID);
+            }
+            b"sThreadInfo" => self.reply.push(b'l'),
+            _ => {}
+        }
+    }
+
+    /// The `m addr,length` packet, the bytes up to the first unmapped one are sent.
+    fn read_memory(&mut self, thread: &mut tcb_t, args: &[u8]) {
+        let Some((addr, len)) = parse_addr_len(args) else {
+            self.reply.push_str("E01");
+            return;
+        };
+        for addr in addr..addr.saturating_add(len.min(PACKET_SIZE / 2)) {
+            match user_addr(thread, addr) {
+                Some(kaddr) => self.reply.push_hex_byte(unsafe { *(kaddr as *const u8) }),
+                None => break,
+            }
+        }
+        if self.reply.len == 0 {
+            self.reply.push_str("E14");
+        }
+    }
+
+    /// The `M addr,length:XX...` packet
+    fn write_memory(&mut self, thread: &mut tcb_t, args: &[u8]) {
+        let Some((addr_len, data)) = split(args, b':') else {
+            self.reply.push_str("E01");
+            return;
+        };
+        let Some((addr, len)) = parse_addr_len(addr_len) else {
+            self.reply.push_str("E01");
+            return;
+        };
+        let Some(hex_len) = len.checked_mul(2) else {
+            self.reply.push_str("E01");
+            return;
+        };
+        if data.len() < hex_len || addr >= USER_TOP {
+            self.reply.push_str("E01");
+            return;
+        }
+        for (i, byte) in data.chunks(2).take(len).enumerate() {
+            let Some(byte) = parse_hex(byte) else {
+                self.reply.push_str("E01");
+                return;
+            };
+            if !write_byte(thread, addr + i, byte as u8) {
+                self.reply.push_str("E14");
+                return;
+            }
+        }
+        self.reply.push_str("OK");
+    }
+
+    /// The `Z0,addr,kind` and `z0,addr,kind` packets, the other breakpoint types are not
+    /// supported.
+    fn breakpoint(&mut self, thread: &mut tcb_t, insert: bool, args: &[u8]) {
+        let Some((b"0", args)) = split(args, b',') else {
+            return;
+        };
+        let Some((addr, kind)) = parse_addr_len(args) else {
+            self.reply.push_str("E01");
+            return;
+        };
+        if addr >= USER_TOP {
+            self.reply.push_str("E01");
+            return;
+        }
+        let Some(instruction) = breakpoint_instruction(kind) else {
+            self.reply.push_str("E01");
+            return;
+        };
+        let ok = if insert {
+            self.insert_breakpoint(thread, addr, instruction)
+        } else {
+            self.remove_breakpoint(thread, addr)
+        };
+        self.reply.push_str(if ok { "OK" } else { "E01" });
+    }
+
+    fn insert_breakpoint(&mut self, thread: &mut tcb_t, addr: usize, instruction: &[u8]) -> bool {
+        if self
+            .breakpoints
+            .iter()
+            .any(|bp| bp.kind != 0 && bp.addr == addr)
+        {
+            return true;
+        }
+        let Some(bp) = self.breakpoints.iter_mut().find(|bp| bp.kind == 0) else {
+            return false;
+        };
+        let mut saved = [0; 4];
+        for (i, byte) in saved.iter_mut().take(instruction.len()).enumerate() {
+            match user_addr(thread, addr + i) {
+                Some(kaddr) => *byte = unsafe { *(kaddr as *const u8) },
+                None => return false,
+            }
+        }
+        for (i, &byte) in instruction.iter().enumerate() {
+            write_byte(thread, addr + i, byte);
+        }
+        *bp = Breakpoint {
+            addr,
+            kind: instruction.len(),
+            saved,
+        };
+        true
+    }
+
+    fn remove_breakpoint(&mut self, thread: &mut tcb_t, addr: usize) -> bool {
+        let Some(bp) = self
+            .breakpoints
+            .iter_mut()
+            .find(|bp| bp.kind != 0 && bp.addr == addr)
+        else {
+            return false;
+        };
+        bp.restore(thread);
+        true
+    }
+
+    fn remove_breakpoints(&mut self, thread: &mut tcb_t) {
+        for bp in self.breakpoints.iter_mut().filter(|bp| bp.kind != 0) {
+            bp.restore(thread);
+        }
+    }
+}
+
+/// Translate the address of the thread to the kernel address of its byte, the kernel mappings in
+/// the VSpace are left out.
+fn user_addr(thread: &mut tcb_t, addr: usize) -> Option<usize> {
+    if addr >= USER_TOP {
+        return None;
+    }
+    lookup_user_addr(thread, addr)
+}
+
+/// Write a byte of the thread memory through the kernel window, so the breakpoints can go in the
+/// read-only code.
+fn write_byte(thread: &mut tcb_t, addr: usize, byte: u8) -> bool {
+    match user_addr(thread, addr) {
+        Some(kaddr) => {
+            unsafe { *(kaddr as *mut u8) = byte };
+            sync_icache(kaddr);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Get the register of the saved context at `index`, through the frame and gp registers which
+/// cover all the integer registers.
+pub fn context_reg(index: usize) -> ArchReg {
+    if let Some(i) = (0..frameRegNum).find(|&i| frameRegisters[i] == index) {
+        return ArchReg::Frame(i);
+    }
+    let i = (0..gpRegNum).find(|&i| gpRegisters[i] == index).unwrap();
+    ArchReg::GP(i)
+}
+
+fn recv_byte() -> u8 {
+    let serial = default_serial();
+    loop {
+        if let Some(c) = serial.getchar() {
+            return c;
+        }
+    }
+}
+
+/// Receive a packet into `buf`, acknowledge it and return the length of its data.
+fn recv_packet(buf: &mut [u8; PACKET_SIZE]) -> usize {
+    let serial = default_serial();
+    'packet: loop {
+        // the acks, Ctrl-C and the console noise before the packet are dropped
+        while recv_byte() != b'$' {}
+        let mut len = 0;
+        let mut checksum: u8 = 0;
+        loop {
+            let c = recv_byte();
+            match c {
+                b'#' => break,
+                b'$' => continue 'packet,
+                _ if len == PACKET_SIZE => {
+                    serial.putchar(b'-');
+                    continue 'packet;
+                }
+                _ => {
+                    buf[len] = c;
+                    len += 1;
+                    checksum = checksum.wrapping_add(c);
+                }
+            }
+        }
+        let sent = [recv_byte(), recv_byte()];
+        if parse_hex(&sent) == Some(checksum as usize) {
+            serial.putchar(b'+');
+            return len;
+        }
+        serial.putchar(b'-');
+    }
+}
+
+/// Send a packet and wait until GDB acknowledges it.
+fn send_packet(reply: &Reply) {
+    let serial = default_serial();
+    let data = &reply.buf[..reply.len];
+    let checksum = data.iter().fold(0u8, |sum, &c| sum.wrapping_add(c));
+    loop {
+        serial.putchar(b'$');
+        for &c in data {
+            serial.putchar(c);
+        }
+        serial.putchar(b'#');
+        serial.putchar(HEX_DIGITS[(checksum >> 4) as usize]);
+        serial.putchar(HEX_DIGITS[(checksum & 0xf) as usize]);
+        loop {
+            match recv_byte() {
+                b'+' => return,
+                b'-' => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn split(s: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = s.iter().position(|&c| c == sep)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+fn parse_hex(s: &[u8]) -> Option<usize> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    s.iter().try_fold(0, |value, &c| {
+        Some((value << 4) | (c as char).to_digit(16)? as usize)
+    })
+}
+
+/// Parse a register value of the target byte order, little-endian.
+fn parse_register(s: &[u8]) -> Option<usize> {
+    if s.len() > 16 {
+        return None;
+    }
+    s.chunks(2).enumerate().try_fold(0, |value, (i, byte)| {
+        Some(value | parse_hex(byte)? << (i * 8))
+    })
+}
+
+fn parse_addr_len(s: &[u8]) -> Option<(usize, usize)> {
+    let (addr, len) = split(s, b',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+fn fault_signal(fault: &seL4_Fault_t) -> u8 {
+    match fault.get_fault_type() {
+        FaultType::VMFault => GDB_SIGNAL_SEGV,
+        FaultType::UnknownSyscall => GDB_SIGNAL_SYS,
+        FaultType::UserException => user_exception_signal(fault.user_exeception_get_number()),
+        _ => GDB_SIGNAL_TRAP,
+    }
+}
+
+fn detach(thread: &mut tcb_t) {
+    if GDB_STEPPING.swap(false, Ordering::Relaxed) {
+        set_single_step(thread, false);
+    }
+    GDB_THREAD.store(0, Ordering::Relaxed);
+}
+
+/// Attach the stub to the thread and wait for GDB, called by the monitor.
+pub fn gdb_attach(thread: &mut tcb_t) {
+    let attached = GDB_THREAD.load(Ordering::Relaxed);
+    if attached != 0 && attached != thread.get_ptr() {
+        if let Some(old) = find_debug_tcb(attached) {
+            GDB.lock().session.remove_breakpoints(old);
+            detach(old);
+        }
+    }
+    GDB_THREAD.store(thread.get_ptr(), Ordering::Relaxed);
+    if GDB.lock().run(thread, GDB_SIGNAL_TRAP, false) == Resume::Detach {
+        detach(thread);
+    }
+}
+
+/// Whether the thread is stepped on its way back to the user mode.
+#[cfg(target_arch = "aarch64")]
+pub fn gdb_is_stepping(thread: &tcb_t) -> bool {
+    GDB_STEPPING.load(Ordering::Relaxed) && GDB_THREAD.load(Ordering::Relaxed) == thread.get_ptr()
+}
+
+/// Stop the attached thread in the stub on its fault, instead of sending the fault to the fault
+/// handler. Returns false for the faults the fault handler should get.
+pub fn gdb_handle_fault(thread: &mut tcb_t) -> bool {
+    let fault = unsafe { current_fault };
+    if GDB_THREAD.load(Ordering::Relaxed) != thread.get_ptr() {
+        return is_stray_fault(&fault);
+    }
+    if GDB_STEPPING.swap(false, Ordering::Relaxed) {
+        set_single_step(thread, false);
+    }
+    match GDB.lock().run(thread, fault_signal(&fault), true) {
+        Resume::Continue => true,
+        Resume::Deliver => false,
+        Resume::Detach => {
+            detach(thread);
+            true
+        }
+    }
+}
+
+/// Look for Ctrl-C of GDB in the console input, for [console::handle_rx_irq].
+pub fn gdb_filter(c: u8) -> bool {
+    if c == 0x03 && GDB_THREAD.load(Ordering::Relaxed) != 0 {
+        STOP_REQUESTED.store(true, Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+
+/// Stop the attached thread in the stub if Ctrl-C has been received since the last call.
+pub fn gdb_check() {
+    if !STOP_REQUESTED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    // the thread may have been deleted since it was attached
+    let Some(thread) = find_debug_tcb(GDB_THREAD.load(Ordering::Relaxed)) else {
+        GDB_THREAD.store(0, Ordering::Relaxed);
+        return;
+    };
+    if GDB.lock().run(thread, GDB_SIGNAL_INT, true) == Resume::Detach {
+        detach(thread);
+    }
+}
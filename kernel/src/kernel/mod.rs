@@ -1,5 +1,12 @@
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub mod benchmark;
 pub mod boot;
+pub mod capdl;
+pub mod debug;
 pub mod fastpath;
 pub mod fault;
+#[cfg(feature = "ENABLE_GDB")]
+pub mod gdb;
+pub mod monitor;
 #[cfg(target_arch = "riscv64")]
 core::arch::global_asm!(include_str!("fastpath_restore.S"));
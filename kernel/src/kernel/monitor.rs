@@ -0,0 +1,214 @@
+//! A small interactive monitor on the kernel console, to look at the kernel state of a hung
+//! system.
+//!
+//! Typing Ctrl-X Ctrl-X m on the console breaks into the monitor. It then owns the UART and runs
+//! with the interrupts disabled until `resume`; the other cores keep running.
+
+use super::debug::{debug_dump_scheduler, find_debug_tcb, thread_state_name};
+#[cfg(feature = "ENABLE_GDB")]
+use super::gdb::{gdb_attach, gdb_check, gdb_filter};
+use crate::config::maxIRQ;
+use crate::interrupt::{get_irq_state, IRQState};
+use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use driver_collect::{default_serial, SerialDriver};
+use sel4_common::arch::{frameRegNum, gpRegNum, ArchReg};
+use sel4_common::console;
+use sel4_common::sel4_config::tcbCTable;
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_common::{print, println};
+use sel4_cspace::interface::{cte_t, CapTag};
+use sel4_task::tcb_t;
+
+/// The key sequence which breaks into the monitor
+const MAGIC: &[u8] = b"\x18\x18m";
+/// The size of the command line, in bytes
+const LINE_SIZE: usize = 64;
+
+/// How much of [MAGIC] has been typed
+static MAGIC_MATCHED: AtomicUsize = AtomicUsize::new(0);
+/// Whether [MAGIC] has been typed and the monitor is yet to run
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Look for [MAGIC] in the console input, for [console::handle_rx_irq].
+///
+/// Returns false for the characters of the sequence, so the threads reading the console do not
+/// see them. The Ctrl-X of a sequence which is broken off are dropped too.
+pub fn monitor_filter(c: u8) -> bool {
+    #[cfg(feature = "ENABLE_GDB")]
+    if !gdb_filter(c) {
+        return false;
+    }
+    let matched = MAGIC_MATCHED.load(Ordering::Relaxed);
+    if c == MAGIC[matched] {
+        if matched + 1 == MAGIC.len() {
+            MAGIC_MATCHED.store(0, Ordering::Relaxed);
+            REQUESTED.store(true, Ordering::Relaxed);
+        } else {
+            MAGIC_MATCHED.store(matched + 1, Ordering::Relaxed);
+        }
+        return false;
+    }
+    MAGIC_MATCHED.store(if c == MAGIC[0] { 1 } else { 0 }, Ordering::Relaxed);
+    c != MAGIC[0]
+}
+
+/// Run the monitor if [MAGIC] has been typed since the last call.
+pub fn monitor_check() {
+    #[cfg(feature = "ENABLE_GDB")]
+    gdb_check();
+    if REQUESTED.swap(false, Ordering::Relaxed) {
+        monitor_run();
+    }
+}
+
+/// Read and run commands until `resume`.
+fn monitor_run() {
+    println!("");
+    println!("Entering the kernel monitor, type help for the commands");
+    let mut line = [0u8; LINE_SIZE];
+    loop {
+        print!("monitor> ");
+        let len = read_line(&mut line);
+        let line = core::str::from_utf8(&line[..len]).unwrap_or("");
+        let mut args = line.split_whitespace();
+        match (args.next(), args.next()) {
+            (None, _) => {}
+            (Some("help"), _) => print_help(),
+            (Some("threads"), _) => debug_dump_scheduler(),
+            (Some("regs"), Some(arg)) => match parse_tcb(arg) {
+                Some(tcb) => dump_registers(tcb),
+                None => println!("{} is not a TCB", arg),
+            },
+            (Some("cnode"), Some(arg)) => match parse_tcb(arg) {
+                Some(tcb) => walk_cnode(tcb),
+                None => println!("{} is not a TCB", arg),
+            },
+            (Some("irqs"), _) => dump_irqs(),
+            // the thread is resumed when GDB lets it go
+            #[cfg(feature = "ENABLE_GDB")]
+            (Some("gdb"), Some(arg)) => match parse_tcb(arg) {
+                Some(tcb) => {
+                    println!("Waiting for GDB on the serial");
+                    console::flush();
+                    gdb_attach(tcb);
+                    break;
+                }
+                None => println!("{} is not a TCB", arg),
+            },
+            (Some("resume" | "c"), _) => break,
+            (Some(cmd), _) => println!("Unknown command {}, type help for the commands", cmd),
+        }
+        console::flush();
+    }
+    println!("Leaving the kernel monitor");
+    console::flush();
+}
+
+/// Read a line from the UART into `line`, echoing it, and return its length.
+fn read_line(line: &mut [u8; LINE_SIZE]) -> usize {
+    let serial = default_serial();
+    let mut len = 0;
+    loop {
+        console::flush();
+        let c = match serial.getchar() {
+            Some(c) => c,
+            None => continue,
+        };
+        match c {
+            b'\r' | b'\n' => {
+                println!("");
+                return len;
+            }
+            // backspace and delete
+            0x08 | 0x7f => {
+                if len > 0 {
+                    len -= 1;
+                    print!("\x08 \x08");
+                }
+            }
+            0x20..=0x7e if len < LINE_SIZE => {
+                line[len] = c;
+                len += 1;
+                print!("{}", c as char);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn print_help() {
+    println!("help          print this help");
+    println!("threads       list the threads");
+    println!("regs <tcb>    dump the registers of a thread");
+    println!("cnode <tcb>   list the caps in the root CNode of a thread");
+    println!("irqs          list the IRQs in use");
+    #[cfg(feature = "ENABLE_GDB")]
+    println!("gdb <tcb>     debug a thread with GDB over the serial");
+    println!("resume, c     leave the monitor");
+}
+
+/// Find the TCB at the hex address `arg`.
+fn parse_tcb(arg: &str) -> Option<&'static mut tcb_t> {
+    find_debug_tcb(usize::from_str_radix(arg.trim_start_matches("0x"), 16).ok()?)
+}
+
+fn dump_registers(tcb: &tcb_t) {
+    println!(
+        "{:#x} \"{}\" {}",
+        tcb.get_ptr(),
+        tcb.get_name(),
+        thread_state_name(&tcb.get_state())
+    );
+    println!(
+        "ip {:#x} sp {:#x}",
+        tcb.tcbArch.get_register(ArchReg::FaultIP),
+        tcb.tcbArch.get_register(ArchReg::SP)
+    );
+    for i in 0..frameRegNum {
+        println!(
+            "frame[{:>2}] {:#018x}",
+            i,
+            tcb.tcbArch.get_register(ArchReg::Frame(i))
+        );
+    }
+    for i in 0..gpRegNum {
+        println!(
+            "gp[{:>2}]    {:#018x}",
+            i,
+            tcb.tcbArch.get_register(ArchReg::GP(i))
+        );
+    }
+}
+
+fn walk_cnode(tcb: &mut tcb_t) {
+    let cap = &tcb.get_cspace(tcbCTable).cap;
+    if cap.get_cap_type() != CapTag::CapCNodeCap {
+        println!("The thread has no CNode");
+        return;
+    }
+    let radix = cap.get_cnode_radix();
+    let cnode = cap.get_cnode_ptr();
+    println!(
+        "CNode {:#x}, radix {}, guard size {}",
+        cnode,
+        radix,
+        cap.get_cnode_guard_size()
+    );
+    for i in 0..(1usize << radix) {
+        let slot = convert_to_mut_type_ref::<cte_t>(cnode + i * size_of::<cte_t>());
+        let cap_type = slot.cap.get_cap_type();
+        if cap_type != CapTag::CapNullCap {
+            println!("{:>6} {:?}", i, cap_type);
+        }
+    }
+}
+
+fn dump_irqs() {
+    for irq in 0..=maxIRQ {
+        let state = get_irq_state(irq);
+        if state != IRQState::IRQInactive {
+            println!("{:>4} {:?}", irq, state);
+        }
+    }
+}
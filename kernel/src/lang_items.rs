@@ -6,6 +6,7 @@ use sel4_common::arch::shutdown;
 /// Print `[ERROR 0] rel4_kernel: PANICED` if panic is detected
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    sel4_common::console::set_panicking();
     if let Some(location) = info.location() {
         error!(
             "Panicked at {}:{} {}",
@@ -17,5 +18,6 @@ fn panic(info: &PanicInfo) -> ! {
         error!("[kernel] Panicked: {}", info.message().unwrap());
     }
     error!("rel4_kernel: PANICED");
+    log::logger().flush();
     shutdown()
 }
@@ -24,29 +24,34 @@ mod interrupt;
 mod kernel;
 mod lang_items;
 mod object;
+#[cfg(feature = "ENABLE_SMP")]
+mod smp;
 mod structures;
 mod syscall;
 mod utils;
 
 mod compatibility;
-mod ffi;
 mod interfaces_impl;
 
 pub use sel4_common::{plus_define_bitfield, BIT, IS_ALIGNED, MASK, ROUND_DOWN, ROUND_UP};
 
 #[no_mangle]
 pub extern "C" fn halt() {
+    // park the other cores, the call returns once all of them are parked
+    #[cfg(feature = "ENABLE_SMP")]
+    smp::do_remote_mask_op(
+        smp::IpiRemoteCall::Halt,
+        MASK!(sel4_common::sel4_config::CONFIG_MAX_NUM_NODES),
+    );
     shutdown()
 }
 
 #[no_mangle]
-pub extern "C" fn strnlen(str: *const u8, _max_len: usize) -> usize {
+pub extern "C" fn strnlen(str: *const u8, max_len: usize) -> usize {
     unsafe {
-        let mut c = str;
         let mut ans = 0;
-        while (*c) != 0 {
+        while ans < max_len && *str.add(ans) != 0 {
             ans += 1;
-            c = c.add(1);
         }
         ans
     }
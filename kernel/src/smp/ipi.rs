@@ -0,0 +1,287 @@
+//! Inter-processor interrupts.
+//!
+//! `INTERRUPT_IPI_1` asks the target cores to reschedule and isn't waited for. `INTERRUPT_IPI_0`
+//! carries a remote call: the sender, which holds the big kernel lock, publishes an
+//! [`IpiRemoteCall`], flags the targets in the lock and waits on a barrier until all of them have
+//! run it. Cores waiting for the lock poll their flag, so they can't deadlock against the sender.
+
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+use sel4_common::BIT;
+use sel4_task::{
+    activateThread, get_currenct_thread, rescheduleRequired, set_ks_scheduler_action,
+    set_thread_state, switch_to_idle_thread, SchedulerAction_ResumeCurrentThread, ThreadState,
+};
+
+use super::lock::{
+    clh_is_ipi_pending, clh_is_self_in_queue, clh_set_ipi_pending, clh_wait_for_predecessor,
+};
+use crate::arch::restore_user_context;
+use crate::config::{INTERRUPT_IPI_0, INTERRUPT_IPI_1};
+
+/// The requests a core can make to other cores with `INTERRUPT_IPI_0`
+#[derive(Clone, Copy)]
+pub enum IpiRemoteCall {
+    /// Switch the core to its idle thread so that its current thread can be modified
+    Stall,
+    /// Park the core for good with its interrupts masked, before the machine is powered off
+    Halt,
+    /// Invalidate the TLB entry of a virtual address, tagged with its ASID in the top bits
+    #[cfg(target_arch = "aarch64")]
+    InvalidateTranslationSingle(usize),
+    /// Invalidate the TLB entries of an ASID
+    #[cfg(target_arch = "aarch64")]
+    InvalidateTranslationASID(usize),
+    /// Run a function with the given argument
+    #[cfg(any(
+        target_arch = "aarch64",
+        feature = "HAVE_FPU",
+        feature = "RISCV_HYPERVISOR_SUPPORT"
+    ))]
+    FunctionCall(fn(usize), usize),
+}
+
+/// A sense reversing barrier for the sender and the targets of a remote call
+struct IpiBarrier {
+    count: AtomicUsize,
+    sense: AtomicBool,
+}
+
+static ipi_sync_barrier: IpiBarrier = IpiBarrier {
+    count: AtomicUsize::new(0),
+    sense: AtomicBool::new(false),
+};
+
+/// The remote call in flight, only written by the holder of the big kernel lock
+static mut remote_call: IpiRemoteCall = IpiRemoteCall::Stall;
+
+/// The number of targets of the remote call in flight
+static total_core_barrier: AtomicUsize = AtomicUsize::new(0);
+
+/// The IPIs raised on each core and not handled yet, bit n standing for `INTERRUPT_IPI_0 + n`
+#[cfg(target_arch = "riscv64")]
+static ipi_pending_irqs: [AtomicUsize; CONFIG_MAX_NUM_NODES] = {
+    const NONE: AtomicUsize = AtomicUsize::new(0);
+    [NONE; CONFIG_MAX_NUM_NODES]
+};
+
+/// Wait until `cores` other cores reached the barrier.
+fn ipi_wait(cores: usize) {
+    let local_sense = ipi_sync_barrier.sense.load(Ordering::Acquire);
+    if ipi_sync_barrier.count.fetch_add(1, Ordering::AcqRel) == cores {
+        // the last one to arrive lets everybody go
+        ipi_sync_barrier.count.store(0, Ordering::Relaxed);
+        ipi_sync_barrier
+            .sense
+            .store(!local_sense, Ordering::Release);
+    }
+    while ipi_sync_barrier.sense.load(Ordering::Acquire) == local_sense {
+        spin_loop();
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+fn ipi_send_mask(irq: usize, mask: usize) {
+    use sel4_common::smp::cpu_index_to_id;
+    let mut hart_mask = 0;
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        if mask & BIT!(cpu) != 0 {
+            ipi_pending_irqs[cpu].fetch_or(BIT!(irq - INTERRUPT_IPI_0), Ordering::Release);
+            hart_mask |= BIT!(cpu_index_to_id(cpu));
+        }
+    }
+    sel4_common::arch::send_ipi(hart_mask);
+}
+
+#[cfg(target_arch = "aarch64")]
+fn ipi_send_mask(irq: usize, mask: usize) {
+    use crate::arch::arm_gic::{Gic, GicDriver};
+    Gic::send_sgi(irq, mask);
+}
+
+/// Get the IPI to handle after the software interrupt of the current core was taken.
+/// The remote call goes first as its sender is spinning on it.
+#[cfg(target_arch = "riscv64")]
+pub fn ipi_get_irq() -> usize {
+    let pending = ipi_pending_irqs[cpu_id()].load(Ordering::Acquire);
+    if pending & BIT!(0) != 0 {
+        INTERRUPT_IPI_0
+    } else if pending & BIT!(INTERRUPT_IPI_1 - INTERRUPT_IPI_0) != 0 {
+        INTERRUPT_IPI_1
+    } else {
+        crate::config::irqInvalid
+    }
+}
+
+/// Mark the IPI of the current core as handled, and take the software interrupt again if
+/// another IPI was raised meanwhile.
+#[cfg(target_arch = "riscv64")]
+pub fn ipi_clear_irq(irq: usize) {
+    use crate::config::SIP_SSIP;
+    let bit = BIT!(irq - INTERRUPT_IPI_0);
+    let pending = ipi_pending_irqs[cpu_id()].fetch_and(!bit, Ordering::AcqRel);
+    if pending & !bit != 0 {
+        unsafe {
+            core::arch::asm!("csrs sip, {}", in(reg) BIT!(SIP_SSIP));
+        }
+    }
+}
+
+/// Handle the IPI `irq`. `irq_path` tells if the current core entered the kernel through an
+/// interrupt, in which case it may not hold the big kernel lock.
+pub fn handle_ipi(irq: usize, irq_path: bool) {
+    match irq {
+        INTERRUPT_IPI_0 => handle_remote_call(irq_path),
+        INTERRUPT_IPI_1 => {
+            rescheduleRequired();
+            #[cfg(target_arch = "riscv64")]
+            unsafe {
+                core::arch::asm!("fence.i");
+            }
+        }
+        _ => panic!("Received unknown IPI: {}", irq),
+    }
+}
+
+fn handle_remote_call(irq_path: bool) {
+    let cpu = cpu_id();
+    // the call may have been run already while this core was waiting for the lock
+    if !clh_is_ipi_pending(cpu) {
+        return;
+    }
+    match unsafe { remote_call } {
+        IpiRemoteCall::Stall => ipi_stall_core_callback(irq_path),
+        IpiRemoteCall::Halt => ipi_halt_core_callback(),
+        #[cfg(target_arch = "aarch64")]
+        IpiRemoteCall::InvalidateTranslationSingle(mva_plus_asid) => {
+            sel4_vspace::invalidate_local_tlb_va_asid(mva_plus_asid)
+        }
+        #[cfg(target_arch = "aarch64")]
+        IpiRemoteCall::InvalidateTranslationASID(asid) => {
+            sel4_vspace::invalidate_local_tlb_asid(asid)
+        }
+        #[cfg(any(
+            target_arch = "aarch64",
+            feature = "HAVE_FPU",
+            feature = "RISCV_HYPERVISOR_SUPPORT"
+        ))]
+        IpiRemoteCall::FunctionCall(func, arg) => func(arg),
+    }
+    clh_set_ipi_pending(cpu, false);
+    ipi_wait(total_core_barrier.load(Ordering::Acquire));
+}
+
+/// Move the current thread of the core out of the way of the sender of a stall.
+fn ipi_stall_core_callback(irq_path: bool) {
+    let cpu = cpu_id();
+    let thread = get_currenct_thread();
+    if clh_is_self_in_queue() && !irq_path {
+        // The core is waiting for the lock to handle a syscall or an exception of its current
+        // thread. Restart the thread, the syscall will be made again after the stall.
+        if thread.get_state() == ThreadState::ThreadStateRunning {
+            set_thread_state(thread, ThreadState::ThreadStateRestart);
+        }
+        if thread.is_runnable() {
+            thread.sched_enqueue();
+        }
+        switch_to_idle_thread();
+        set_ks_scheduler_action(SchedulerAction_ResumeCurrentThread);
+
+        // let the sender go on while this core keeps waiting for the lock
+        clh_set_ipi_pending(cpu, false);
+        ipi_wait(total_core_barrier.load(Ordering::Acquire));
+        clh_wait_for_predecessor(cpu, irq_path);
+
+        // the stalled thread is gone, so leave the kernel from the idle thread
+        activateThread();
+        restore_user_context();
+    } else {
+        // Either the core entered through an interrupt and doesn't hold the lock, or it will
+        // go back to acquiring the lock after the call. Interrupts are asynchronous, so the
+        // current thread can simply be replaced.
+        if thread.is_runnable() {
+            thread.sched_enqueue();
+        }
+        switch_to_idle_thread();
+        set_ks_scheduler_action(SchedulerAction_ResumeCurrentThread);
+    }
+}
+
+/// Let the sender of the halt know that the core is parked, and never leave.
+fn ipi_halt_core_callback() -> ! {
+    unsafe {
+        #[cfg(target_arch = "riscv64")]
+        core::arch::asm!("csrc sstatus, {}", in(reg) sel4_common::arch::SSTATUS_SIE);
+        #[cfg(target_arch = "aarch64")]
+        core::arch::asm!("msr daifset, #0xf");
+    }
+    clh_set_ipi_pending(cpu_id(), false);
+    ipi_wait(total_core_barrier.load(Ordering::Acquire));
+    loop {
+        // a pending interrupt wakes the core up even masked
+        unsafe { core::arch::asm!("wfi") };
+    }
+}
+
+fn init_ipi_args(call: IpiRemoteCall, mask: usize) {
+    unsafe {
+        remote_call = call;
+    }
+    total_core_barrier.store(mask.count_ones() as usize, Ordering::Release);
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        if mask & BIT!(cpu) != 0 {
+            clh_set_ipi_pending(cpu, true);
+        }
+    }
+}
+
+/// Run `call` on every other core in `mask` and wait until all of them are done.
+pub fn do_remote_mask_op(call: IpiRemoteCall, mask: usize) {
+    let mask = mask & !BIT!(cpu_id());
+    if mask != 0 {
+        init_ipi_args(call, mask);
+        ipi_send_mask(INTERRUPT_IPI_0, mask);
+        ipi_wait(mask.count_ones() as usize);
+    }
+}
+
+/// Run `call` on `cpu` and wait until it is done.
+#[inline]
+pub fn do_remote_op(call: IpiRemoteCall, cpu: usize) {
+    do_remote_mask_op(call, BIT!(cpu));
+}
+
+/// Ask every other core in `mask` to reschedule, the cores with threads queued by this core
+#[no_mangle]
+pub fn doMaskReschedule(mask: usize) {
+    let mask = mask & !BIT!(cpu_id());
+    if mask != 0 {
+        ipi_send_mask(INTERRUPT_IPI_1, mask);
+    }
+}
+
+/// Invalidate the TLB entries of `asid` on every core
+#[cfg(target_arch = "aarch64")]
+#[no_mangle]
+pub fn invalidateTranslationASID(asid: usize) {
+    use sel4_common::MASK;
+    sel4_vspace::invalidate_local_tlb_asid(asid);
+    do_remote_mask_op(
+        IpiRemoteCall::InvalidateTranslationASID(asid),
+        MASK!(CONFIG_MAX_NUM_NODES),
+    );
+}
+
+/// Invalidate the TLB entry of a virtual address tagged with its ASID on every core
+#[cfg(target_arch = "aarch64")]
+#[no_mangle]
+pub fn invalidateTranslationSingle(mva_plus_asid: usize) {
+    use sel4_common::MASK;
+    sel4_vspace::invalidate_local_tlb_va_asid(mva_plus_asid);
+    do_remote_mask_op(
+        IpiRemoteCall::InvalidateTranslationSingle(mva_plus_asid),
+        MASK!(CONFIG_MAX_NUM_NODES),
+    );
+}
@@ -0,0 +1,125 @@
+//! The big kernel lock, a CLH queue lock.
+//!
+//! A core taking the lock swaps its node into the tail of the queue and spins on the node of its
+//! predecessor. On release the core hands its node over to its successor and keeps the node of
+//! its predecessor, so `CONFIG_MAX_NUM_NODES + 1` nodes are enough and are recycled forever.
+//! Nodes are referred to by their index in the lock.
+
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+
+use super::ipi::handle_ipi;
+use crate::config::INTERRUPT_IPI_0;
+
+/// A queue node, padded to a cache line so that the spinning cores don't share lines
+#[repr(align(64))]
+struct ClhNode {
+    /// Set while the core owning the node holds the lock or waits for it
+    pending: AtomicBool,
+}
+
+/// The per-core state of the lock
+#[repr(align(64))]
+struct ClhOwner {
+    /// The node enqueued by the core on its next acquire
+    node: AtomicUsize,
+    /// The node of the predecessor of the core in the queue
+    next: AtomicUsize,
+    /// Set by the sender of a remote call until the core has handled it
+    ipi: AtomicBool,
+}
+
+struct ClhLock {
+    nodes: [ClhNode; CONFIG_MAX_NUM_NODES + 1],
+    owners: [ClhOwner; CONFIG_MAX_NUM_NODES],
+    /// The tail of the queue
+    head: AtomicUsize,
+}
+
+const CLH_NODE_INIT: ClhNode = ClhNode {
+    pending: AtomicBool::new(false),
+};
+
+const CLH_OWNER_INIT: ClhOwner = ClhOwner {
+    node: AtomicUsize::new(0),
+    next: AtomicUsize::new(0),
+    ipi: AtomicBool::new(false),
+};
+
+static big_kernel_lock: ClhLock = ClhLock {
+    nodes: [CLH_NODE_INIT; CONFIG_MAX_NUM_NODES + 1],
+    owners: [CLH_OWNER_INIT; CONFIG_MAX_NUM_NODES],
+    head: AtomicUsize::new(CONFIG_MAX_NUM_NODES),
+};
+
+/// Give every core its own node and queue the spare node as released.
+pub fn clh_lock_init() {
+    for (cpu, owner) in big_kernel_lock.owners.iter().enumerate() {
+        owner.node.store(cpu, Ordering::Relaxed);
+        owner.ipi.store(false, Ordering::Relaxed);
+    }
+    big_kernel_lock.nodes[CONFIG_MAX_NUM_NODES]
+        .pending
+        .store(false, Ordering::Relaxed);
+    big_kernel_lock
+        .head
+        .store(CONFIG_MAX_NUM_NODES, Ordering::Release);
+}
+
+/// Take the lock on behalf of `cpu`, handling the remote calls sent to it while waiting.
+pub fn clh_lock_acquire(cpu: usize, irq_path: bool) {
+    let owner = &big_kernel_lock.owners[cpu];
+    let node = owner.node.load(Ordering::Relaxed);
+    big_kernel_lock.nodes[node]
+        .pending
+        .store(true, Ordering::Relaxed);
+    let prev = big_kernel_lock.head.swap(node, Ordering::AcqRel);
+    owner.next.store(prev, Ordering::Relaxed);
+    clh_wait_for_predecessor(cpu, irq_path);
+}
+
+/// Spin until the predecessor of `cpu` in the queue releases the lock.
+pub(super) fn clh_wait_for_predecessor(cpu: usize, irq_path: bool) {
+    let prev = big_kernel_lock.owners[cpu].next.load(Ordering::Relaxed);
+    while big_kernel_lock.nodes[prev].pending.load(Ordering::Acquire) {
+        // The core holding the lock may be waiting for us to run its remote call
+        if clh_is_ipi_pending(cpu) {
+            handle_ipi(INTERRUPT_IPI_0, irq_path);
+        }
+        spin_loop();
+    }
+}
+
+/// Release the lock held by `cpu`.
+pub fn clh_lock_release(cpu: usize) {
+    let owner = &big_kernel_lock.owners[cpu];
+    let node = owner.node.load(Ordering::Relaxed);
+    big_kernel_lock.nodes[node]
+        .pending
+        .store(false, Ordering::Release);
+    owner
+        .node
+        .store(owner.next.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Check if the current core holds the lock or waits for it.
+pub fn clh_is_self_in_queue() -> bool {
+    let node = big_kernel_lock.owners[cpu_id()]
+        .node
+        .load(Ordering::Relaxed);
+    big_kernel_lock.nodes[node].pending.load(Ordering::Relaxed)
+}
+
+#[inline]
+pub(super) fn clh_is_ipi_pending(cpu: usize) -> bool {
+    big_kernel_lock.owners[cpu].ipi.load(Ordering::Acquire)
+}
+
+#[inline]
+pub(super) fn clh_set_ipi_pending(cpu: usize, pending: bool) {
+    big_kernel_lock.owners[cpu]
+        .ipi
+        .store(pending, Ordering::Release);
+}
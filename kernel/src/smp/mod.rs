@@ -0,0 +1,31 @@
+//! Multicore support: the big kernel lock serialising the cores in the kernel, the IPIs the cores
+//! use to ask each other for work, and the migration of threads between cores.
+
+mod ipi;
+mod lock;
+
+pub use ipi::{do_remote_mask_op, do_remote_op, handle_ipi, IpiRemoteCall};
+#[cfg(target_arch = "riscv64")]
+pub use ipi::{ipi_clear_irq, ipi_get_irq};
+pub use lock::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_init, clh_lock_release};
+
+use sel4_common::utils::cpu_id;
+use sel4_task::{ksSMP, tcb_t};
+
+/// Stall the core of `tcb` if `tcb` is running there, so that it can be modified safely.
+pub fn remote_tcb_stall(tcb: &tcb_t) {
+    let cpu = tcb.tcbAffinity;
+    if cpu != cpu_id() && unsafe { ksSMP[cpu].ksCurThread } == tcb.get_ptr() {
+        do_remote_op(IpiRemoteCall::Stall, cpu);
+    }
+}
+
+/// Move `tcb` to `new_core`. The thread must not be queued nor running.
+pub fn migrate_tcb(tcb: &mut tcb_t, new_core: usize) {
+    tcb.DebugRemove();
+    // the FPU registers of the thread may only be live on its old core
+    #[cfg(any(target_arch = "aarch64", feature = "HAVE_FPU"))]
+    crate::arch::fpu_thread_migrate(tcb);
+    tcb.tcbAffinity = new_core;
+    tcb.DebugAppend();
+}
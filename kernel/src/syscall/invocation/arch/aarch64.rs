@@ -1,3 +1,7 @@
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::vcpu::{vcpu_init, vcpu_t};
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use sel4_common::utils::convert_to_mut_type_ref;
 use sel4_common::{
     arch::{vm_rights_t, ObjectType},
     sel4_config::{asidInvalid, ARM_Huge_Page, ARM_Large_Page, ARM_Small_Page},
@@ -40,6 +44,11 @@ pub fn arch_create_object(
         ObjectType::seL4_ARM_PageTableObject => {
             cap_t::new_page_table_cap(asidInvalid, region_base, 0, 0)
         }
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        ObjectType::seL4_ARM_VCPUObject => {
+            vcpu_init(convert_to_mut_type_ref::<vcpu_t>(region_base));
+            cap_t::new_vcpu_cap(region_base)
+        }
         _ => {
             unimplemented!(
                 "create object: {:?} region: {:#x} - {:#x}",
@@ -1,3 +1,7 @@
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use crate::arch::vcpu::{vcpu_init, vcpu_t};
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use sel4_common::utils::convert_to_mut_type_ref;
 use sel4_common::{
     arch::{vm_rights_t, ObjectType},
     sel4_config::asidInvalid,
@@ -24,6 +28,11 @@ pub fn arch_create_object(
                 0,
             )
         }
+        #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+        ObjectType::VCPUObject => {
+            vcpu_init(convert_to_mut_type_ref::<vcpu_t>(region_base));
+            cap_t::new_vcpu_cap(region_base)
+        }
         _ => {
             unimplemented!(
                 "create object: {:?} region: {:#x} - {:#x}",
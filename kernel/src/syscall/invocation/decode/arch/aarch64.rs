@@ -35,6 +35,11 @@ use sel4_vspace::{
     vptr_t, PTE,
 };
 
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::arch::{
+    arm_gic::{Gic, VGicDriver, INTERRUPT_VTIMER_EVENT},
+    vcpu::*,
+};
 use crate::syscall::invocation::invoke_mmu_op::{
     invoke_page_get_address, invoke_page_map, invoke_page_table_unmap, invoke_page_unmap,
 };
@@ -43,6 +48,10 @@ use crate::{
     interrupt::is_irq_active,
     syscall::{invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp},
 };
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use sel4_common::arch::seL4_VCPUReg_Num;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use sel4_task::tcb_t;
 
 pub fn decode_mmu_invocation(
     label: MessageLabel,
@@ -57,6 +66,8 @@ pub fn decode_mmu_invocation(
         CapTag::CapFrameCap => decode_frame_invocation(label, length, slot, call, buffer),
         CapTag::CapASIDControlCap => decode_asid_control(label, length, buffer),
         CapTag::CapASIDPoolCap => decode_asid_pool(label, slot),
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        CapTag::CapVCPUCap => decode_vcpu_invocation(label, length, slot, call, buffer),
         _ => {
             panic!("Invalid arch cap type");
         }
@@ -412,7 +423,7 @@ fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
     get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
     vspace_cap.set_vs_mapped_asid(asid);
     vspace_cap.set_vs_is_mapped(1);
-    let asid_map = asid_map_t::new_vspace(vspace_cap.get_vs_base_ptr());
+    let asid_map = asid_map_t::new_vspace(vspace_cap.get_vs_base_ptr(), 0, 0);
     pool[asid & MASK!(asidLowBits)] = asid_map;
     exception_t::EXCEPTION_NONE
 }
@@ -489,7 +500,7 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     }
     let pt_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-	frame_slot.cap.set_frame_mapped_asid(asid);
+    frame_slot.cap.set_frame_mapped_asid(asid);
     frame_slot.cap.set_frame_mapped_address(vaddr);
     return invoke_page_map(
         asid,
@@ -1096,3 +1107,165 @@ pub fn arch_decode_irq_control_invocation(
         return exception_t::EXCEPTION_SYSCALL_ERROR;
     }
 }
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_invocation(
+    label: MessageLabel,
+    length: usize,
+    cte: &mut cte_t,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    let vcpu = convert_to_mut_type_ref::<vcpu_t>(cte.cap.get_vcpu_ptr());
+    match label {
+        MessageLabel::ARMVCPUSetTCB => decode_vcpu_set_tcb(vcpu),
+        MessageLabel::ARMVCPUReadReg => decode_vcpu_read_reg(vcpu, length, call, buffer),
+        MessageLabel::ARMVCPUWriteReg => decode_vcpu_write_reg(vcpu, length, buffer),
+        MessageLabel::ARMVCPUInjectIRQ => decode_vcpu_inject_irq(vcpu, length, buffer),
+        MessageLabel::ARMVCPUAckVPPI => decode_vcpu_ack_vppi(vcpu, length, buffer),
+        _ => {
+            debug!("VCPU: Illegal operation.");
+            global_ops!(current_syscall_error._type = seL4_IllegalOperation);
+            exception_t::EXCEPTION_SYSCALL_ERROR
+        }
+    }
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_set_tcb(vcpu: &mut vcpu_t) -> exception_t {
+    if unlikely(get_extra_cap_by_index(0).is_none()) {
+        debug!("VCPU SetTCB: Truncated message.");
+        global_ops!(current_syscall_error._type = seL4_TruncatedMessage);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb_cap = get_extra_cap_by_index(0).unwrap().cap;
+    if unlikely(tcb_cap.get_cap_type() != CapTag::CapThreadCap) {
+        debug!("VCPU SetTCB: TCB cap is not a TCB cap.");
+        global_ops!(current_syscall_error._type = seL4_IllegalOperation);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_set_tcb(
+        vcpu,
+        convert_to_mut_type_ref::<tcb_t>(tcb_cap.get_tcb_ptr()),
+    )
+}
+
+/// Check the register number of `ARMVCPUReadReg` and `ARMVCPUWriteReg`
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn check_vcpu_reg(reg: usize) -> exception_t {
+    if unlikely(reg >= seL4_VCPUReg_Num) {
+        debug!("VCPUReadReg/WriteReg with invalid field {}.", reg);
+        global_ops!(current_syscall_error._type = seL4_InvalidArgument);
+        global_ops!(current_syscall_error.invalidArgumentNumber = 1);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_read_reg(
+    vcpu: &mut vcpu_t,
+    length: usize,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if unlikely(length < 1) {
+        debug!("VCPUReadReg: Truncated message.");
+        global_ops!(current_syscall_error._type = seL4_TruncatedMessage);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let reg = get_syscall_arg(0, buffer);
+    let status = check_vcpu_reg(reg);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_read_reg(vcpu, reg, call)
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_write_reg(vcpu: &mut vcpu_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+    if unlikely(length < 2) {
+        debug!("VCPUWriteReg: Truncated message.");
+        global_ops!(current_syscall_error._type = seL4_TruncatedMessage);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let reg = get_syscall_arg(0, buffer);
+    let value = get_syscall_arg(1, buffer);
+    let status = check_vcpu_reg(reg);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_write_reg(vcpu, reg, value)
+}
+
+/// Fail the invocation with a range error on the argument
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn vcpu_range_error(argument: usize, max: usize) -> exception_t {
+    global_ops!(current_syscall_error._type = seL4_RangeError);
+    global_ops!(current_syscall_error.rangeErrorMin = 0);
+    global_ops!(current_syscall_error.rangeErrorMax = max);
+    global_ops!(current_syscall_error.invalidArgumentNumber = argument);
+    exception_t::EXCEPTION_SYSCALL_ERROR
+}
+
+/// The first word packs the virtual IRQ, its priority, its group and the list register to use
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_inject_irq(
+    vcpu: &mut vcpu_t,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if unlikely(length < 1) {
+        debug!("VCPUInjectIRQ: Truncated message.");
+        global_ops!(current_syscall_error._type = seL4_TruncatedMessage);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let mr0 = get_syscall_arg(0, buffer);
+    let vid = mr0 & 0xffff;
+    let priority = (mr0 >> 16) & 0xff;
+    let group = (mr0 >> 24) & 0xff;
+    let index = (mr0 >> 32) & 0xff;
+
+    if unlikely(vid >= BIT!(10)) {
+        return vcpu_range_error(1, BIT!(10) - 1);
+    }
+    if unlikely(priority >= 32) {
+        return vcpu_range_error(2, 31);
+    }
+    if unlikely(group >= 2) {
+        return vcpu_range_error(3, 1);
+    }
+    if unlikely(index >= num_list_regs()) {
+        return vcpu_range_error(4, num_list_regs().saturating_sub(1));
+    }
+    // the guest hasn't finished with the interrupt in the list register yet
+    if unlikely(Gic::virq_is_active(vcpu_get_lr(vcpu, index))) {
+        global_ops!(current_syscall_error._type = seL4_DeleteFirst);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let virq = Gic::virq_pending_new(group, priority, vid);
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_inject_irq(vcpu, index, virq)
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_ack_vppi(vcpu: &mut vcpu_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+    if unlikely(length < 1) {
+        debug!("VCPUAckVPPI: Truncated message.");
+        global_ops!(current_syscall_error._type = seL4_TruncatedMessage);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let irq = get_syscall_arg(0, buffer);
+    // the virtual timer is the only PPI handed to the guests
+    if unlikely(irq != INTERRUPT_VTIMER_EVENT) {
+        debug!("VCPUAckVPPI: Invalid VPPI {}.", irq);
+        global_ops!(current_syscall_error._type = seL4_InvalidArgument);
+        global_ops!(current_syscall_error.invalidArgumentNumber = 0);
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_ack_vppi(vcpu, irq)
+}
@@ -37,8 +37,17 @@ use crate::{
 
 use sel4_common::sel4_config::seL4_RangeError;
 
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use crate::arch::vcpu::*;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use sel4_common::arch::{
+    seL4_VCPUReg_Num, seL4_VCPU_VSExternalIRQ, seL4_VCPU_VSSoftwareIRQ, seL4_VCPU_VSTimerIRQ,
+};
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use sel4_task::tcb_t;
+
 use crate::{
-    config::{irqInvalid, maxIRQ},
+    config::{irqInvalid, PLIC_MAX_IRQ},
     interrupt::is_irq_active,
     syscall::{invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp},
 };
@@ -55,6 +64,8 @@ pub fn decode_mmu_invocation(
         CapTag::CapFrameCap => decode_frame_invocation(label, length, slot, call, buffer),
         CapTag::CapASIDControlCap => decode_asid_control(label, length, buffer),
         CapTag::CapASIDPoolCap => decode_asid_pool(label, slot),
+        #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+        CapTag::CapVCPUCap => decode_vcpu_invocation(label, length, slot, call, buffer),
         _ => {
             panic!("Invalid arch cap type");
         }
@@ -457,13 +468,14 @@ fn get_vspace(lvl1pt_cap: &cap_t) -> Option<(&mut PTE, usize)> {
 }
 
 pub(crate) fn check_irq(irq: usize) -> exception_t {
-    if irq > maxIRQ || irq == irqInvalid {
+    // only the PLIC sources can be given to the user
+    if irq > PLIC_MAX_IRQ || irq == irqInvalid {
         unsafe {
             current_syscall_error._type = seL4_RangeError;
             current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = maxIRQ;
+            current_syscall_error.rangeErrorMax = PLIC_MAX_IRQ;
             debug!(
-                "Rejecting request for IRQ {}. IRQ is out of range [1..maxIRQ].",
+                "Rejecting request for IRQ {}. IRQ is out of range [1..PLIC_MAX_IRQ].",
                 irq
             );
             return exception_t::EXCEPTION_SYSCALL_ERROR;
@@ -519,3 +531,139 @@ pub fn arch_decode_irq_control_invocation(
         return exception_t::EXCEPTION_SYSCALL_ERROR;
     }
 }
+
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_invocation(
+    label: MessageLabel,
+    length: usize,
+    cte: &mut cte_t,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    let vcpu = convert_to_mut_type_ref::<vcpu_t>(cte.cap.get_vcpu_ptr());
+    match label {
+        MessageLabel::RISCVVCPUSetTCB => decode_vcpu_set_tcb(vcpu),
+        MessageLabel::RISCVVCPUReadReg => decode_vcpu_read_reg(vcpu, length, call, buffer),
+        MessageLabel::RISCVVCPUWriteReg => decode_vcpu_write_reg(vcpu, length, buffer),
+        MessageLabel::RISCVVCPUInjectIRQ => decode_vcpu_inject_irq(vcpu, length, buffer),
+        _ => {
+            debug!("VCPU: Illegal operation.");
+            unsafe {
+                current_syscall_error._type = seL4_IllegalOperation;
+            }
+            exception_t::EXCEPTION_SYSCALL_ERROR
+        }
+    }
+}
+
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_set_tcb(vcpu: &mut vcpu_t) -> exception_t {
+    if unlikely(get_extra_cap_by_index(0).is_none()) {
+        debug!("VCPU SetTCB: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb_cap = get_extra_cap_by_index(0).unwrap().cap;
+    if unlikely(tcb_cap.get_cap_type() != CapTag::CapThreadCap) {
+        debug!("VCPU SetTCB: TCB cap is not a TCB cap.");
+        unsafe {
+            current_syscall_error._type = seL4_IllegalOperation;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_set_tcb(
+        vcpu,
+        convert_to_mut_type_ref::<tcb_t>(tcb_cap.get_tcb_ptr()),
+    )
+}
+
+/// Check the register number of `RISCVVCPUReadReg` and `RISCVVCPUWriteReg`
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn check_vcpu_reg(reg: usize) -> exception_t {
+    if unlikely(reg >= seL4_VCPUReg_Num) {
+        debug!("VCPUReadReg/WriteReg with invalid field {}.", reg);
+        unsafe {
+            current_syscall_error._type = seL4_InvalidArgument;
+            current_syscall_error.invalidArgumentNumber = 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_read_reg(
+    vcpu: &mut vcpu_t,
+    length: usize,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if unlikely(length < 1) {
+        debug!("VCPUReadReg: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let reg = get_syscall_arg(0, buffer);
+    let status = check_vcpu_reg(reg);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_read_reg(vcpu, reg, call)
+}
+
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_write_reg(vcpu: &mut vcpu_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+    if unlikely(length < 2) {
+        debug!("VCPUWriteReg: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let reg = get_syscall_arg(0, buffer);
+    let value = get_syscall_arg(1, buffer);
+    let status = check_vcpu_reg(reg);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_write_reg(vcpu, reg, value)
+}
+
+/// The first word is the virtual interrupt of hvip and the second one its level
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+fn decode_vcpu_inject_irq(
+    vcpu: &mut vcpu_t,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if unlikely(length < 2) {
+        debug!("VCPUInjectIRQ: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let irq = get_syscall_arg(0, buffer);
+    let level = get_syscall_arg(1, buffer);
+    if unlikely(
+        irq != seL4_VCPU_VSSoftwareIRQ
+            && irq != seL4_VCPU_VSTimerIRQ
+            && irq != seL4_VCPU_VSExternalIRQ,
+    ) {
+        debug!("VCPUInjectIRQ: Invalid virtual IRQ {}.", irq);
+        unsafe {
+            current_syscall_error._type = seL4_InvalidArgument;
+            current_syscall_error.invalidArgumentNumber = 0;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_inject_irq(vcpu, irq, level != 0)
+}
@@ -22,7 +22,11 @@ pub fn decode_cnode_invocation(
     cap: &cap_t,
     buffer: &seL4_IPCBuffer,
 ) -> exception_t {
-    if invLabel < MessageLabel::CNodeRevoke || invLabel > MessageLabel::CNodeSaveCaller {
+    #[cfg(not(feature = "KERNEL_MCS"))]
+    let last_label = MessageLabel::CNodeSaveCaller;
+    #[cfg(feature = "KERNEL_MCS")]
+    let last_label = MessageLabel::CNodeRotate;
+    if invLabel < MessageLabel::CNodeRevoke || invLabel > last_label {
         debug!("CNodeCap: Illegal Operation attempted.");
         unsafe {
             current_syscall_error._type = seL4_IllegalOperation;
@@ -55,6 +59,7 @@ pub fn decode_cnode_invocation(
         }
         MessageLabel::CNodeRevoke => invoke_cnode_revoke(dest_slot),
         MessageLabel::CNodeDelete => invoke_cnode_delete(dest_slot),
+        #[cfg(not(feature = "KERNEL_MCS"))]
         MessageLabel::CNodeSaveCaller => invoke_cnode_save_caller(dest_slot),
         MessageLabel::CNodeCancelBadgedSends => invoke_cnode_cancel_badged_sends(dest_slot),
         MessageLabel::CNodeRotate => decode_cnode_rotate(dest_slot, length, buffer),
@@ -34,8 +34,11 @@ pub fn decode_domain_invocation(
         return exception_t::EXCEPTION_SYSCALL_ERROR;
     }
     let domain = get_syscall_arg(0, buffer);
-    if domain >= 1 {
-        debug!("Domain Configure: invalid domain ({} >= 1).", domain);
+    if domain >= CONFIG_NUM_DOMAINS {
+        debug!(
+            "Domain Configure: invalid domain ({} >= {}).",
+            domain, CONFIG_NUM_DOMAINS
+        );
         unsafe {
             current_syscall_error._type = seL4_InvalidArgument;
             current_syscall_error.invalidArgumentNumber = 0;
@@ -9,7 +9,9 @@ use sel4_cspace::interface::{cte_t, CapTag};
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
 use super::arch::{arch_decode_irq_control_invocation, check_irq};
-use crate::syscall::invocation::invoke_irq::{invoke_clear_irq_handler, invoke_set_irq_handler};
+use crate::syscall::invocation::invoke_irq::{
+    invoke_clear_irq_handler, invoke_irq_handler_ack, invoke_set_irq_handler,
+};
 use crate::{
     interrupt::is_irq_active,
     kernel::boot::{current_syscall_error, get_extra_cap_by_index},
@@ -73,6 +75,7 @@ pub fn decode_irq_handler_invocation(label: MessageLabel, irq: usize) -> excepti
     return match label {
         MessageLabel::IRQAckIRQ => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_irq_handler_ack(irq);
             exception_t::EXCEPTION_NONE
         }
 
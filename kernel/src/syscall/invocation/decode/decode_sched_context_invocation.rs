@@ -0,0 +1,197 @@
+use log::debug;
+use sel4_common::{
+    arch::MessageLabel,
+    sel4_config::*,
+    structures::{exception_t, seL4_IPCBuffer},
+    utils::convert_to_mut_type_ref,
+};
+use sel4_cspace::interface::{cap_t, CapTag};
+use sel4_task::{
+    get_currenct_thread, refill_absolute_max, sched_context_t, set_thread_state, tcb_t,
+    us_to_ticks, ThreadState,
+};
+
+use crate::{
+    kernel::boot::{current_syscall_error, get_extra_cap_by_index},
+    syscall::{
+        get_syscall_arg,
+        invocation::invoke_sched_context::{
+            invoke_sched_context_bind, invoke_sched_context_consumed, invoke_sched_context_unbind,
+            invoke_sched_control_configure_flags,
+        },
+    },
+};
+
+pub fn decode_sched_context_invocation(
+    invLabel: MessageLabel,
+    cap: &cap_t,
+    call: bool,
+) -> exception_t {
+    let sc = convert_to_mut_type_ref::<sched_context_t>(cap.get_sc_ptr());
+    match invLabel {
+        MessageLabel::SchedContextConsumed => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sched_context_consumed(sc, call)
+        }
+        MessageLabel::SchedContextBind => decode_sched_context_bind(sc),
+        MessageLabel::SchedContextUnbindObject => decode_sched_context_unbind_object(sc),
+        MessageLabel::SchedContextUnbind => {
+            if sc.scTcb == get_currenct_thread().get_ptr() {
+                debug!("SchedContext Unbind: cannot unbind the scheduling context of the current thread.");
+                unsafe {
+                    current_syscall_error._type = seL4_IllegalOperation;
+                }
+                return exception_t::EXCEPTION_SYSCALL_ERROR;
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sched_context_unbind(sc)
+        }
+        _ => {
+            // the scheduling contexts are not donated, so there is nothing to yield to
+            debug!("SchedContext: Illegal operation invLabel :{:?}", invLabel);
+            unsafe {
+                current_syscall_error._type = seL4_IllegalOperation;
+            }
+            exception_t::EXCEPTION_SYSCALL_ERROR
+        }
+    }
+}
+
+fn decode_sched_context_bind(sc: &mut sched_context_t) -> exception_t {
+    if get_extra_cap_by_index(0).is_none() {
+        debug!("SchedContext Bind: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb_cap = get_extra_cap_by_index(0).unwrap().cap;
+    if tcb_cap.get_cap_type() != CapTag::CapThreadCap {
+        debug!("SchedContext Bind: thread cap required.");
+        unsafe {
+            current_syscall_error._type = seL4_InvalidCapability;
+            current_syscall_error.invalidCapNumber = 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb = convert_to_mut_type_ref::<tcb_t>(tcb_cap.get_tcb_ptr());
+    if sc.scTcb != 0 || tcb.tcbSchedContext != 0 {
+        debug!("SchedContext Bind: scheduling context or TCB already bound.");
+        unsafe {
+            current_syscall_error._type = seL4_IllegalOperation;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_sched_context_bind(sc, tcb)
+}
+
+fn decode_sched_context_unbind_object(sc: &mut sched_context_t) -> exception_t {
+    if get_extra_cap_by_index(0).is_none() {
+        debug!("SchedContext UnbindObject: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb_cap = get_extra_cap_by_index(0).unwrap().cap;
+    if tcb_cap.get_cap_type() != CapTag::CapThreadCap {
+        debug!("SchedContext UnbindObject: thread cap required.");
+        unsafe {
+            current_syscall_error._type = seL4_InvalidCapability;
+            current_syscall_error.invalidCapNumber = 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let tcb = convert_to_mut_type_ref::<tcb_t>(tcb_cap.get_tcb_ptr());
+    if sc.scTcb != tcb.get_ptr() {
+        debug!("SchedContext UnbindObject: object not bound.");
+        unsafe {
+            current_syscall_error._type = seL4_IllegalOperation;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    if tcb.is_current() {
+        debug!("SchedContext UnbindObject: cannot unbind the scheduling context of the current thread.");
+        unsafe {
+            current_syscall_error._type = seL4_IllegalOperation;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_sched_context_unbind(sc)
+}
+
+pub fn decode_sched_control_invocation(
+    invLabel: MessageLabel,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if invLabel != MessageLabel::SchedControlConfigureFlags {
+        debug!("SchedControl: Illegal operation invLabel :{:?}", invLabel);
+        unsafe {
+            current_syscall_error._type = seL4_IllegalOperation;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    if length < 5 || get_extra_cap_by_index(0).is_none() {
+        debug!("SchedControl ConfigureFlags: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let budget_us = get_syscall_arg(0, buffer);
+    let period_us = get_syscall_arg(1, buffer);
+    let extra_refills = get_syscall_arg(2, buffer);
+    let badge = get_syscall_arg(3, buffer);
+    let flags = get_syscall_arg(4, buffer);
+
+    let target_cap = get_extra_cap_by_index(0).unwrap().cap;
+    if target_cap.get_cap_type() != CapTag::CapSchedContextCap {
+        debug!("SchedControl ConfigureFlags: target cap not a scheduling context cap.");
+        unsafe {
+            current_syscall_error._type = seL4_InvalidCapability;
+            current_syscall_error.invalidCapNumber = 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    if budget_us < MIN_BUDGET_US || budget_us > MAX_PERIOD_US {
+        debug!("SchedControl ConfigureFlags: budget out of range.");
+        unsafe {
+            current_syscall_error._type = seL4_RangeError;
+            current_syscall_error.rangeErrorMin = MIN_BUDGET_US;
+            current_syscall_error.rangeErrorMax = MAX_PERIOD_US;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    if period_us < budget_us || period_us > MAX_PERIOD_US {
+        debug!("SchedControl ConfigureFlags: period out of range.");
+        unsafe {
+            current_syscall_error._type = seL4_RangeError;
+            current_syscall_error.rangeErrorMin = budget_us;
+            current_syscall_error.rangeErrorMax = MAX_PERIOD_US;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let refill_max = refill_absolute_max(target_cap.get_sc_size_bits());
+    if extra_refills + MIN_REFILLS > refill_max {
+        debug!("SchedControl ConfigureFlags: too many extra refills.");
+        unsafe {
+            current_syscall_error._type = seL4_RangeError;
+            current_syscall_error.rangeErrorMin = 0;
+            current_syscall_error.rangeErrorMax = refill_max - MIN_REFILLS;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_sched_control_configure_flags(
+        convert_to_mut_type_ref::<sched_context_t>(target_cap.get_sc_ptr()),
+        us_to_ticks(budget_us),
+        us_to_ticks(period_us),
+        extra_refills + MIN_REFILLS,
+        badge,
+        flags,
+    )
+}
@@ -5,7 +5,14 @@
 
 use log::debug;
 use sel4_common::arch::MessageLabel;
-use sel4_common::arch::{frameRegNum, gpRegNum};
+use sel4_common::arch::{fpRegNum, frameRegNum, gpRegNum};
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+use sel4_common::arch::{
+    seL4_BreakOnRead, seL4_BreakOnReadWrite, seL4_DataBreakpoint, seL4_FirstWatchpoint,
+    seL4_InstructionBreakpoint, seL4_NumHWBreakpoints, Breakpoint,
+};
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+use sel4_common::sel4_config::seL4_InvalidArgument;
 use sel4_common::sel4_config::{
     seL4_IllegalOperation, seL4_InvalidCapability, seL4_RangeError, seL4_TruncatedMessage,
     tcbCTable, tcbVTable,
@@ -15,8 +22,12 @@ use sel4_common::utils::convert_to_mut_type_ref;
 use sel4_common::BIT;
 use sel4_cspace::interface::{cap_t, cte_t, CapTag};
 use sel4_ipc::notification_t;
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::sched_context_t;
 use sel4_task::{get_currenct_thread, set_thread_state, tcb_t, ThreadState};
 
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+use crate::config::USER_TOP;
 use crate::{
     kernel::boot::{current_syscall_error, get_extra_cap_by_index},
     syscall::utils::{check_ipc_buffer_vaild, check_prio, get_syscall_arg},
@@ -28,7 +39,7 @@ use crate::syscall::is_valid_vtable_root;
 use super::super::invoke_tcb::*;
 
 #[cfg(feature = "ENABLE_SMP")]
-use crate::ffi::remoteTCBStall;
+use crate::smp::remote_tcb_stall;
 
 pub const CopyRegisters_suspendSource: usize = 0;
 pub const CopyRegisters_resumeTarget: usize = 1;
@@ -46,9 +57,7 @@ pub fn decode_tcb_invocation(
     call: bool,
     buffer: &seL4_IPCBuffer,
 ) -> exception_t {
-    unsafe {
-        remoteTCBStall(convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()));
-    }
+    remote_tcb_stall(convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()));
     match invLabel {
         MessageLabel::TCBReadRegisters => decode_read_registers(cap, length, call, buffer),
         MessageLabel::TCBWriteRegisters => decode_write_registers(cap, length, buffer),
@@ -70,6 +79,16 @@ pub fn decode_tcb_invocation(
         MessageLabel::TCBBindNotification => decode_bind_notification(cap),
         MessageLabel::TCBUnbindNotification => decode_unbind_notification(cap),
         MessageLabel::TCBSetAffinity => decode_set_affinity(cap, length, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBSetBreakpoint => decode_set_breakpoint(cap, length, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBGetBreakpoint => decode_get_breakpoint(cap, length, call, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBUnsetBreakpoint => decode_unset_breakpoint(cap, length, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBConfigureSingleStepping => {
+            decode_configure_single_stepping(cap, length, call, buffer)
+        }
         MessageLabel::TCBSetTLSBase => decode_set_tls_base(cap, length, buffer),
         _ => unsafe {
             debug!("TCB: Illegal operation invLabel :{:?}", invLabel);
@@ -109,6 +128,16 @@ pub fn decode_tcb_invocation(
         MessageLabel::TCBSetSpace => decode_set_space(cap, length, slot, buffer),
         MessageLabel::TCBBindNotification => decode_bind_notification(cap),
         MessageLabel::TCBUnbindNotification => decode_unbind_notification(cap),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBSetBreakpoint => decode_set_breakpoint(cap, length, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBGetBreakpoint => decode_get_breakpoint(cap, length, call, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBUnsetBreakpoint => decode_unset_breakpoint(cap, length, buffer),
+        #[cfg(feature = "HARDWARE_DEBUG_API")]
+        MessageLabel::TCBConfigureSingleStepping => {
+            decode_configure_single_stepping(cap, length, call, buffer)
+        }
         MessageLabel::TCBSetTLSBase => decode_set_tls_base(cap, length, buffer),
         _ => unsafe {
             debug!("TCB: Illegal operation invLabel :{:?}", invLabel);
@@ -133,7 +162,7 @@ fn decode_read_registers(
     }
     let flags = get_syscall_arg(0, buffer);
     let n = get_syscall_arg(1, buffer);
-    if n < 1 || n > frameRegNum + gpRegNum {
+    if n < 1 || n > frameRegNum + gpRegNum + fpRegNum {
         debug!(
             "TCB ReadRegisters: Attempted to read an invalid number of registers:{}",
             n
@@ -141,7 +170,7 @@ fn decode_read_registers(
         unsafe {
             current_syscall_error._type = seL4_RangeError;
             current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = frameRegNum + gpRegNum;
+            current_syscall_error.rangeErrorMax = frameRegNum + gpRegNum + fpRegNum;
             return exception_t::EXCEPTION_SYSCALL_ERROR;
         }
     }
@@ -394,7 +423,15 @@ fn decode_set_mc_priority(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer) -
 }
 
 fn decode_set_sched_params(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
-    if length < 2 || get_extra_cap_by_index(0).is_some() {
+    if length < 2 || get_extra_cap_by_index(0).is_none() {
+        debug!("TCB SetSchedParams: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    #[cfg(feature = "KERNEL_MCS")]
+    if get_extra_cap_by_index(1).is_none() {
         debug!("TCB SetSchedParams: Truncated message.");
         unsafe {
             current_syscall_error._type = seL4_TruncatedMessage;
@@ -431,9 +468,54 @@ fn decode_set_sched_params(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer)
         return status;
     }
 
-    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let target = convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr());
+    #[cfg(feature = "KERNEL_MCS")]
+    let sc = {
+        let sc_cap = get_extra_cap_by_index(1).unwrap().cap;
+        match sc_cap.get_cap_type() {
+            CapTag::CapSchedContextCap => {
+                let sc = convert_to_mut_type_ref::<sched_context_t>(sc_cap.get_sc_ptr());
+                if target.tcbSchedContext != 0 && target.tcbSchedContext != sc.get_ptr() {
+                    debug!("TCB SetSchedParams: TCB already has a scheduling context.");
+                    unsafe {
+                        current_syscall_error._type = seL4_IllegalOperation;
+                    }
+                    return exception_t::EXCEPTION_SYSCALL_ERROR;
+                }
+                if sc.scTcb != 0 && sc.scTcb != target.get_ptr() {
+                    debug!("TCB SetSchedParams: scheduling context already bound.");
+                    unsafe {
+                        current_syscall_error._type = seL4_IllegalOperation;
+                    }
+                    return exception_t::EXCEPTION_SYSCALL_ERROR;
+                }
+                Some(sc)
+            }
+            CapTag::CapNullCap => {
+                if target.is_current() {
+                    debug!("TCB SetSchedParams: cannot unbind the scheduling context of the current thread.");
+                    unsafe {
+                        current_syscall_error._type = seL4_IllegalOperation;
+                    }
+                    return exception_t::EXCEPTION_SYSCALL_ERROR;
+                }
+                None
+            }
+            _ => {
+                debug!("TCB SetSchedParams: scheduling context cap invalid.");
+                unsafe {
+                    current_syscall_error._type = seL4_InvalidCapability;
+                    current_syscall_error.invalidCapNumber = 2;
+                }
+                return exception_t::EXCEPTION_SYSCALL_ERROR;
+            }
+        }
+    };
+
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_set_mcp(target, new_mcp);
+    #[cfg(feature = "KERNEL_MCS")]
+    invoke_tcb_set_sched_context(target, sc);
     invoke_tcb_set_priority(target, new_prio)
 }
 
@@ -655,6 +737,158 @@ fn decode_set_tls_base(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer) -> e
     invoke_tcb_set_tls_base(convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()), base)
 }
 
+/// Check the breakpoint number of the debug invocations.
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+fn check_breakpoint_number(bp_num: usize) -> exception_t {
+    if bp_num >= seL4_NumHWBreakpoints {
+        debug!("TCB Breakpoint: Invalid breakpoint number {}.", bp_num);
+        unsafe {
+            current_syscall_error._type = seL4_RangeError;
+            current_syscall_error.rangeErrorMin = 0;
+            current_syscall_error.rangeErrorMax = seL4_NumHWBreakpoints - 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    exception_t::EXCEPTION_NONE
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+fn decode_set_breakpoint(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+    if length < 5 {
+        debug!("TCB SetBreakpoint: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let bp_num = get_syscall_arg(0, buffer);
+    let bp = Breakpoint {
+        vaddr: get_syscall_arg(1, buffer),
+        type_: get_syscall_arg(2, buffer),
+        size: get_syscall_arg(3, buffer),
+        rw: get_syscall_arg(4, buffer),
+        is_enabled: true,
+    };
+    let status = check_breakpoint_number(bp_num);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    // the instruction breakpoints and the data breakpoints are separate registers
+    let bp_type = if bp_num < seL4_FirstWatchpoint {
+        seL4_InstructionBreakpoint
+    } else {
+        seL4_DataBreakpoint
+    };
+    let invalid_argument = if bp.type_ != bp_type {
+        Some(2)
+    } else if bp.vaddr >= USER_TOP {
+        Some(1)
+    } else if bp.type_ == seL4_InstructionBreakpoint {
+        if bp.vaddr % 4 != 0 {
+            Some(1)
+        } else if bp.size != 0 {
+            Some(3)
+        } else if bp.rw != seL4_BreakOnRead {
+            Some(4)
+        } else {
+            None
+        }
+    } else if !matches!(bp.size, 1 | 2 | 4 | 8) || bp.vaddr % bp.size != 0 {
+        Some(3)
+    } else if bp.rw > seL4_BreakOnReadWrite {
+        Some(4)
+    } else {
+        None
+    };
+    if let Some(arg) = invalid_argument {
+        debug!("TCB SetBreakpoint: Invalid argument {}.", arg);
+        unsafe {
+            current_syscall_error._type = seL4_InvalidArgument;
+            current_syscall_error.invalidArgumentNumber = arg;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_set_breakpoint(
+        convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()),
+        bp_num,
+        &bp,
+    )
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+fn decode_get_breakpoint(
+    cap: &cap_t,
+    length: usize,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if length < 1 {
+        debug!("TCB GetBreakpoint: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let bp_num = get_syscall_arg(0, buffer);
+    let status = check_breakpoint_number(bp_num);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_get_breakpoint(
+        convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()),
+        bp_num,
+        call,
+    )
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+fn decode_unset_breakpoint(cap: &cap_t, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+    if length < 1 {
+        debug!("TCB UnsetBreakpoint: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let bp_num = get_syscall_arg(0, buffer);
+    let status = check_breakpoint_number(bp_num);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_unset_breakpoint(convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()), bp_num)
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+fn decode_configure_single_stepping(
+    cap: &cap_t,
+    length: usize,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+) -> exception_t {
+    if length < 2 {
+        debug!("TCB ConfigureSingleStepping: Truncated message.");
+        unsafe {
+            current_syscall_error._type = seL4_TruncatedMessage;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
+    let bp_num = get_syscall_arg(0, buffer);
+    let n_instructions = get_syscall_arg(1, buffer);
+    let status = check_breakpoint_number(bp_num);
+    if status != exception_t::EXCEPTION_NONE {
+        return status;
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_configure_single_stepping(
+        convert_to_mut_type_ref::<tcb_t>(cap.get_tcb_ptr()),
+        n_instructions,
+        call,
+    )
+}
+
 #[inline]
 fn decode_set_space_args(
     root_data: usize,
@@ -166,6 +166,16 @@ fn check_object_type(new_type: ObjectType, user_obj_size: usize) -> exception_t
         }
         return exception_t::EXCEPTION_SYSCALL_ERROR;
     }
+
+    #[cfg(feature = "KERNEL_MCS")]
+    if new_type == ObjectType::SchedContextObject && user_obj_size < seL4_MinSchedContextBits {
+        debug!("Untyped Retype: Requested a scheduling context too small.");
+        unsafe {
+            current_syscall_error._type = seL4_InvalidArgument;
+            current_syscall_error.invalidArgumentNumber = 1;
+        }
+        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    }
     return exception_t::EXCEPTION_NONE;
 }
 
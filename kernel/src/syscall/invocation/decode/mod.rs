@@ -1,6 +1,8 @@
 mod decode_cnode_invocation;
 mod decode_domain_invocation;
 pub mod decode_irq_invocation;
+#[cfg(feature = "KERNEL_MCS")]
+mod decode_sched_context_invocation;
 
 pub mod arch;
 mod decode_tcb_invocation;
@@ -17,11 +19,19 @@ use sel4_common::{
 };
 use sel4_cspace::interface::{cap_t, cte_t, CapTag};
 use sel4_ipc::{endpoint_t, notification_t, Transfer};
-use sel4_task::{get_currenct_thread, set_thread_state, tcb_t, ThreadState};
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::reply_t;
+#[cfg(not(feature = "KERNEL_MCS"))]
+use sel4_task::tcb_t;
+use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
 use crate::kernel::boot::current_syscall_error;
 use crate::syscall::invocation::decode::decode_irq_invocation::decode_irq_handler_invocation;
 
+#[cfg(feature = "KERNEL_MCS")]
+use self::decode_sched_context_invocation::{
+    decode_sched_context_invocation, decode_sched_control_invocation,
+};
 use self::{
     arch::decode_mmu_invocation, decode_cnode_invocation::decode_cnode_invocation,
     decode_domain_invocation::decode_domain_invocation,
@@ -96,6 +106,7 @@ pub fn decode_invocation(
             exception_t::EXCEPTION_NONE
         }
 
+        #[cfg(not(feature = "KERNEL_MCS"))]
         CapTag::CapReplyCap => {
             if unlikely(cap.get_reply_master() != 0) {
                 debug!("Attempted to invoke an invalid reply cap {}.", cap_index);
@@ -113,8 +124,21 @@ pub fn decode_invocation(
             );
             exception_t::EXCEPTION_NONE
         }
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapReplyCap => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            get_currenct_thread().do_reply(
+                convert_to_mut_type_ref::<reply_t>(cap.get_reply_ptr()),
+                cap.get_reply_can_grant() != 0,
+            );
+            exception_t::EXCEPTION_NONE
+        }
         CapTag::CapThreadCap => decode_tcb_invocation(label, length, cap, slot, call, buffer),
         CapTag::CapDomainCap => decode_domain_invocation(label, length, buffer),
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapSchedContextCap => decode_sched_context_invocation(label, cap, call),
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapSchedControlCap => decode_sched_control_invocation(label, length, buffer),
         CapTag::CapCNodeCap => decode_cnode_invocation(label, length, cap, buffer),
         CapTag::CapUntypedCap => decode_untyed_invocation(label, length, slot, cap, buffer),
         CapTag::CapIrqControlCap => decode_irq_control_invocation(label, length, slot, buffer),
@@ -1,8 +1,8 @@
 use log::debug;
+#[cfg(not(feature = "KERNEL_MCS"))]
+use sel4_common::sel4_config::{seL4_DeleteFirst, tcbCaller};
 use sel4_common::{
-    cap_rights::seL4_CapRights_t,
-    sel4_config::{seL4_DeleteFirst, seL4_IllegalOperation, tcbCaller},
-    structures::exception_t,
+    cap_rights::seL4_CapRights_t, sel4_config::seL4_IllegalOperation, structures::exception_t,
     utils::convert_to_mut_type_ref,
 };
 use sel4_cspace::interface::{cap_t, cte_insert, cte_move, cte_swap, cte_t, CapTag};
@@ -82,6 +82,7 @@ pub fn invoke_cnode_mutate(
     exception_t::EXCEPTION_NONE
 }
 
+#[cfg(not(feature = "KERNEL_MCS"))]
 #[inline]
 pub fn invoke_cnode_save_caller(dest_slot: &mut cte_t) -> exception_t {
     if dest_slot.cap.get_cap_type() != CapTag::CapNullCap {
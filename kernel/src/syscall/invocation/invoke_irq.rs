@@ -1,6 +1,8 @@
 use sel4_common::structures::exception_t;
 use sel4_cspace::interface::{cap_t, cte_insert, cte_t};
 
+#[cfg(target_arch = "aarch64")]
+use crate::interrupt::mask_interrupt;
 use crate::interrupt::{get_irq_handler_slot, set_irq_state, IRQState};
 
 pub fn invoke_irq_control(
@@ -24,3 +26,14 @@ pub fn invoke_set_irq_handler(irq: usize, cap: &cap_t, slot: &mut cte_t) {
 pub fn invoke_clear_irq_handler(irq: usize) {
     get_irq_handler_slot(irq).delete_one();
 }
+
+/// Let the interrupt be raised again. The PLIC doesn't raise a claimed interrupt until the claim
+/// is completed, and the GIC line is masked when the interrupt is delivered, both are left to the
+/// user so that the device is serviced first.
+#[inline]
+pub fn invoke_irq_handler_ack(irq: usize) {
+    #[cfg(target_arch = "riscv64")]
+    crate::arch::plic_complete_claim(irq);
+    #[cfg(target_arch = "aarch64")]
+    mask_interrupt(false, irq);
+}
@@ -0,0 +1,83 @@
+use sel4_common::arch::ArchReg;
+use sel4_common::{
+    message_info::seL4_MessageInfo_t,
+    sel4_config::{seL4_SchedContext_Sporadic, MIN_REFILLS},
+    structures::exception_t,
+    utils::convert_to_option_mut_type_ref,
+};
+use sel4_task::{
+    commit_time, get_currenct_thread, ksCurSC, possible_switch_to, release_remove,
+    rescheduleRequired, sched_context_t, set_thread_state, tcb_t, ticks_t, ticks_to_us,
+    ThreadState,
+};
+
+pub fn invoke_sched_context_bind(sc: &mut sched_context_t, tcb: &mut tcb_t) -> exception_t {
+    sc.bind_tcb(tcb);
+    exception_t::EXCEPTION_NONE
+}
+
+#[inline]
+pub fn invoke_sched_context_unbind(sc: &mut sched_context_t) -> exception_t {
+    sc.unbind_tcb();
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_sched_context_consumed(sc: &mut sched_context_t, call: bool) -> exception_t {
+    let thread = get_currenct_thread();
+    let consumed = ticks_to_us(sc.scConsumed);
+    sc.scConsumed = 0;
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = thread.set_mr(0, consumed);
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo_t::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+    exception_t::EXCEPTION_NONE
+}
+
+pub fn invoke_sched_control_configure_flags(
+    target: &mut sched_context_t,
+    budget: ticks_t,
+    period: ticks_t,
+    max_refills: usize,
+    badge: usize,
+    flags: usize,
+) -> exception_t {
+    target.scBadge = badge;
+    target.scSporadic = flags & seL4_SchedContext_Sporadic != 0;
+
+    if let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(target.scTcb) {
+        if target.is_active() {
+            // take the thread off the queues while its refills change
+            release_remove(tcb);
+            tcb.sched_dequeue();
+            if target.get_ptr() == unsafe { ksCurSC } {
+                commit_time();
+            }
+        }
+    }
+
+    if budget == period {
+        // a full budget is a round robin scheduling context
+        target.refill_new(MIN_REFILLS, budget, 0);
+    } else if target.is_active()
+        && convert_to_option_mut_type_ref::<tcb_t>(target.scTcb)
+            .is_some_and(|tcb| tcb.is_runnable())
+    {
+        target.refill_update(period, budget, max_refills);
+    } else {
+        target.refill_new(max_refills, budget, period);
+    }
+
+    if let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(target.scTcb) {
+        if tcb.is_current() {
+            rescheduleRequired();
+        } else if tcb.is_schedulable() {
+            possible_switch_to(tcb);
+        }
+    }
+    exception_t::EXCEPTION_NONE
+}
@@ -6,6 +6,8 @@ use sel4_common::{
 };
 use sel4_cspace::interface::{cap_t, cte_insert, cte_t, same_object_as};
 use sel4_ipc::{notification_t, Transfer};
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::sched_context_t;
 use sel4_task::{get_currenct_thread, rescheduleRequired, set_thread_state, tcb_t, ThreadState};
 
 use crate::syscall::{do_bind_notification, safe_unbind_notification, utils::get_syscall_arg};
@@ -52,15 +54,26 @@ pub fn invoke_tcb_read_registers(
             i += 1;
         }
 
-        if let Some(ipc_buffer) = op_ipc_buffer {
+        if let Some(ipc_buffer) = op_ipc_buffer.as_deref_mut() {
             while i < gpRegNum && i + frameRegNum < n {
                 ipc_buffer.msg[i + frameRegNum] = src.tcbArch.get_register(ArchReg::GP(i));
                 i += 1;
             }
         }
+        #[allow(unused_mut)]
+        let mut k = 0;
+        // The FP registers never fit in the message registers
+        #[cfg(all(target_arch = "riscv64", feature = "HAVE_FPU"))]
+        if let Some(ipc_buffer) = op_ipc_buffer {
+            crate::arch::fpu_sync(src);
+            while k < fpRegNum && k + frameRegNum + gpRegNum < n {
+                ipc_buffer.msg[k + frameRegNum + gpRegNum] = src.tcbArch.get_fp_register(k);
+                k += 1;
+            }
+        }
         thread.tcbArch.set_register(
             ArchReg::MsgInfo,
-            seL4_MessageInfo_t::new(0, 0, 0, i + j).to_word(),
+            seL4_MessageInfo_t::new(0, 0, 0, i + j + k).to_word(),
         );
     }
     set_thread_state(thread, ThreadState::ThreadStateRunning);
@@ -74,8 +87,8 @@ pub fn invoke_tcb_write_registers(
     _arch: usize,
     buffer: &seL4_IPCBuffer,
 ) -> exception_t {
-    if n > frameRegNum + gpRegNum {
-        n = frameRegNum + gpRegNum;
+    if n > frameRegNum + gpRegNum + fpRegNum {
+        n = frameRegNum + gpRegNum + fpRegNum;
     }
 
     let mut i = 0;
@@ -90,6 +103,18 @@ pub fn invoke_tcb_write_registers(
             .set_register(ArchReg::GP(i), get_syscall_arg(i + frameRegNum + 2, buffer));
         i += 1;
     }
+    #[cfg(all(target_arch = "riscv64", feature = "HAVE_FPU"))]
+    if frameRegNum + gpRegNum < n {
+        // Keep the registers that are not written, and load the new ones on the next FP access
+        crate::arch::fpu_sync(dest);
+        crate::arch::fpu_release(dest);
+        i = 0;
+        while i < fpRegNum && i + frameRegNum + gpRegNum < n {
+            dest.tcbArch
+                .set_fp_register(i, get_syscall_arg(i + frameRegNum + gpRegNum + 2, buffer));
+            i += 1;
+        }
+    }
 
     dest.tcbArch
         .set_register(ArchReg::NextIP, dest.tcbArch.get_register(ArchReg::FaultIP));
@@ -158,6 +183,16 @@ pub fn invoke_tcb_set_mcp(target: &mut tcb_t, mcp: usize) -> exception_t {
     exception_t::EXCEPTION_NONE
 }
 
+/// Bind the scheduling context to the TCB, or unbind the one of the TCB for `None`.
+#[cfg(feature = "KERNEL_MCS")]
+pub fn invoke_tcb_set_sched_context(target: &mut tcb_t, sc: Option<&mut sched_context_t>) {
+    match sc {
+        Some(sc) if sc.get_ptr() != target.tcbSchedContext => sc.bind_tcb(target),
+        None if target.tcbSchedContext != 0 => target.get_sched_context().unbind_tcb(),
+        _ => {}
+    }
+}
+
 #[inline]
 pub fn invoke_tcb_set_priority(target: &mut tcb_t, prio: usize) -> exception_t {
     target.set_priority(prio);
@@ -245,13 +280,74 @@ pub fn invoke_tcb_set_tls_base(thread: &mut tcb_t, base: usize) -> exception_t {
     exception_t::EXCEPTION_NONE
 }
 
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub fn invoke_tcb_set_breakpoint(
+    thread: &mut tcb_t,
+    bp_num: usize,
+    bp: &Breakpoint,
+) -> exception_t {
+    thread.tcbArch.breakpoints.set(bp_num, bp);
+    crate::arch::breakpoint_state_changed(thread);
+    exception_t::EXCEPTION_NONE
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub fn invoke_tcb_get_breakpoint(thread: &mut tcb_t, bp_num: usize, call: bool) -> exception_t {
+    let current = get_currenct_thread();
+    if call {
+        let bp = thread.tcbArch.breakpoints.get(bp_num);
+        current.tcbArch.set_register(ArchReg::Badge, 0);
+        current.set_mr(0, bp.vaddr);
+        current.set_mr(1, bp.type_);
+        current.set_mr(2, bp.size);
+        current.set_mr(3, bp.rw);
+        let length = current.set_mr(4, bp.is_enabled as usize);
+        current.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo_t::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(current, ThreadState::ThreadStateRunning);
+    exception_t::EXCEPTION_NONE
+}
+
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub fn invoke_tcb_unset_breakpoint(thread: &mut tcb_t, bp_num: usize) -> exception_t {
+    thread.tcbArch.breakpoints.unset(bp_num);
+    crate::arch::breakpoint_state_changed(thread);
+    exception_t::EXCEPTION_NONE
+}
+
+/// The software step of aarch64 does not need a breakpoint, the reply tells that none was
+/// consumed.
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub fn invoke_tcb_configure_single_stepping(
+    thread: &mut tcb_t,
+    n_instructions: usize,
+    call: bool,
+) -> exception_t {
+    thread
+        .tcbArch
+        .breakpoints
+        .configure_single_stepping(n_instructions);
+    let current = get_currenct_thread();
+    if call {
+        current.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = current.set_mr(0, 0);
+        current.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo_t::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(current, ThreadState::ThreadStateRunning);
+    exception_t::EXCEPTION_NONE
+}
+
 #[cfg(feature = "ENABLE_SMP")]
 #[inline]
 pub fn invoke_tcb_set_affinity(thread: &mut tcb_t, affinitiy: usize) -> exception_t {
     thread.sched_dequeue();
-    unsafe {
-        crate::ffi::migrateTCB(thread, affinitiy);
-    }
+    crate::smp::migrate_tcb(thread, affinitiy);
     // debug!("tcb migrate: {}", thread.tcbAffinity);
     if thread.is_runnable() {
         thread.sched_append();
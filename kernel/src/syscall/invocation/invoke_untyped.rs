@@ -1,4 +1,3 @@
-// use crate::ffi::tcbDebugAppend;
 use super::arch::arch_create_object;
 use crate::syscall::{
     FREE_INDEX_TO_OFFSET, GET_FREE_INDEX, GET_OFFSET_FREE_PTR, OFFSET_TO_FREE_IDNEX,
@@ -77,16 +76,17 @@ fn create_object(
             tcb.init();
             tcb.tcbTimeSlice = CONFIG_TIME_SLICE;
             tcb.domain = get_current_domain();
-            // #[cfg(feature="DEBUG_BUILD")]
-            // unsafe {
-            //     tcbDebugAppend(tcb as *mut tcb_t);
-            // }
+            tcb.DebugAppend();
             return cap_t::new_thread_cap(tcb.get_ptr());
         }
         ObjectType::CapTableObject => cap_t::new_cnode_cap(user_size, 0, 0, region_base),
         ObjectType::NotificationObject => cap_t::new_notification_cap(0, 1, 1, region_base),
         ObjectType::EndpointObject => cap_t::new_endpoint_cap(0, 1, 1, 1, 1, region_base),
         ObjectType::UnytpedObject => cap_t::new_untyped_cap(0, device_mem, user_size, region_base),
+        #[cfg(feature = "KERNEL_MCS")]
+        ObjectType::SchedContextObject => cap_t::new_sched_context_cap(user_size, region_base),
+        #[cfg(feature = "KERNEL_MCS")]
+        ObjectType::ReplyObject => cap_t::new_reply_cap(1, 0, region_base),
         _ => arch_create_object(obj_type, region_base, user_size, device_mem),
     }
 }
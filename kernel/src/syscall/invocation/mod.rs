@@ -3,6 +3,8 @@ pub mod decode;
 mod invoke_cnode;
 pub mod invoke_irq;
 mod invoke_mmu_op;
+#[cfg(feature = "KERNEL_MCS")]
+mod invoke_sched_context;
 mod invoke_tcb;
 mod invoke_untyped;
 
@@ -7,6 +7,7 @@ use core::intrinsics::unlikely;
 use sel4_common::arch::ArchReg;
 use sel4_common::fault::{lookup_fault_t, seL4_Fault_t, FaultType};
 // use sel4_common::ffi_call;
+#[cfg(not(feature = "KERNEL_MCS"))]
 use sel4_common::sel4_config::tcbCaller;
 
 pub const SysCall: isize = -1;
@@ -24,7 +25,24 @@ pub const SysDebugHalt: isize = -11;
 pub const SysDebugCapIdentify: isize = -12;
 pub const SysDebugSnapshot: isize = -13;
 pub const SysDebugNameThread: isize = -14;
+// -15 is SysBenchmarkFlushCaches of seL4, which is not supported
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub const SysBenchmarkResetLog: isize = -16;
+#[cfg(feature = "ENABLE_BENCHMARKS")]
+pub const SysBenchmarkFinalizeLog: isize = -17;
+#[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+pub const SysBenchmarkSetLogBuffer: isize = -18;
+// -19 is SysBenchmarkNullSyscall of seL4, which is not supported
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub const SysBenchmarkGetThreadUtilisation: isize = -20;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub const SysBenchmarkResetThreadUtilisation: isize = -21;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub const SysBenchmarkDumpAllThreadsUtilisation: isize = -22;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub const SysBenchmarkResetAllThreadsUtilisation: isize = -23;
 pub const SysGetClock: isize = -30;
+pub const SysDebugGetChar: isize = -31;
 use sel4_common::structures::exception_t;
 use sel4_common::utils::{convert_to_mut_type_ref, ptr_to_mut};
 use sel4_cspace::interface::CapTag;
@@ -33,6 +51,8 @@ use sel4_task::{
     activateThread, get_currenct_thread, rescheduleRequired, schedule, set_thread_state, tcb_t,
     ThreadState,
 };
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::{check_budget_restart, reply_t, update_timestamp};
 pub use utils::*;
 
 use crate::arch::restore_user_context;
@@ -44,6 +64,8 @@ use self::invocation::handleInvocation;
 
 #[no_mangle]
 pub fn slowpath(syscall: usize) {
+    #[cfg(feature = "BENCHMARK_TRACK_KERNEL_ENTRIES")]
+    crate::kernel::benchmark::benchmark_track_slowpath();
     if (syscall as isize) < -8 || (syscall as isize) > -1 {
         // using ffi_call! macro to call c function
         handleUnknownSyscall(syscall as isize);
@@ -60,6 +82,16 @@ pub fn handleSyscall(_syscall: usize) -> exception_t {
     // if hart_id() == 0 {
     //     debug!("handle syscall: {}", syscall);
     // }
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        update_timestamp();
+        // the syscall restarts once the current thread gets a new budget
+        if unlikely(!check_budget_restart()) {
+            schedule();
+            activateThread();
+            return exception_t::EXCEPTION_NONE;
+        }
+    }
     match syscall {
         SysSend => {
             let ret = handleInvocation(false, true);
@@ -110,6 +142,12 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
     let origin_lookup_fault = unsafe { current_lookup_fault };
     let lu_ret = thread.lookup_slot(thread.tcbFaultHandler);
     if lu_ret.status != exception_t::EXCEPTION_NONE {
+        log::debug!(
+            "Fault from thread {:#x} \"{}\": fault handler {:#x} lookup failed",
+            thread.get_ptr(),
+            thread.get_name(),
+            thread.tcbFaultHandler
+        );
         unsafe {
             current_fault = seL4_Fault_t::new_cap_fault(thread.tcbFaultHandler, 0);
         }
@@ -132,6 +170,12 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
             true,
         );
     } else {
+        log::debug!(
+            "Fault from thread {:#x} \"{}\": fault handler {:#x} is not a valid endpoint",
+            thread.get_ptr(),
+            thread.get_name(),
+            thread.tcbFaultHandler
+        );
         unsafe {
             current_fault = seL4_Fault_t::new_cap_fault(thread.tcbFaultHandler, 0);
             current_lookup_fault = lookup_fault_t::new_missing_cap(0);
@@ -143,11 +187,21 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
 
 #[inline]
 pub fn handle_fault(thread: &mut tcb_t) {
+    #[cfg(feature = "ENABLE_GDB")]
+    if crate::kernel::gdb::gdb_handle_fault(thread) {
+        return;
+    }
     if send_fault_ipc(thread) != exception_t::EXCEPTION_NONE {
+        log::debug!(
+            "Thread {:#x} \"{}\" has no valid fault handler, suspending",
+            thread.get_ptr(),
+            thread.get_name()
+        );
         set_thread_state(thread, ThreadState::ThreadStateInactive);
     }
 }
 
+#[cfg(not(feature = "KERNEL_MCS"))]
 fn handle_reply() {
     let current_thread = get_currenct_thread();
     let caller_slot = current_thread.get_cspace_mut_ref(tcbCaller);
@@ -161,6 +215,61 @@ fn handle_reply() {
     }
 }
 
+#[cfg(feature = "KERNEL_MCS")]
+fn handle_reply() {
+    let current_thread = get_currenct_thread();
+    let reply_cptr = current_thread.tcbArch.get_register(ArchReg::Reply);
+    let lu_ret = current_thread.lookup_slot(reply_cptr);
+    if lu_ret.status != exception_t::EXCEPTION_NONE {
+        return;
+    }
+    let reply_cap = unsafe { (*lu_ret.slot).cap };
+    if reply_cap.get_cap_type() == CapTag::CapReplyCap {
+        current_thread.do_reply(
+            convert_to_mut_type_ref::<reply_t>(reply_cap.get_reply_ptr()),
+            reply_cap.get_reply_can_grant() != 0,
+        );
+    }
+}
+
+/// Look up the reply object given to a receive syscall, `None` stands for no reply object and
+/// `Err` for a fault already raised on the current thread.
+#[cfg(feature = "KERNEL_MCS")]
+fn lookup_reply(current_thread: &mut tcb_t) -> Result<Option<&'static mut reply_t>, ()> {
+    let reply_cptr = current_thread.tcbArch.get_register(ArchReg::Reply);
+    if reply_cptr == 0 {
+        return Ok(None);
+    }
+    let lu_ret = current_thread.lookup_slot(reply_cptr);
+    if lu_ret.status != exception_t::EXCEPTION_NONE {
+        unsafe {
+            current_fault = seL4_Fault_t::new_cap_fault(reply_cptr, 1);
+        }
+        handle_fault(current_thread);
+        return Err(());
+    }
+    let reply_cap = unsafe { (*lu_ret.slot).cap };
+    match reply_cap.get_cap_type() {
+        CapTag::CapNullCap => Ok(None),
+        CapTag::CapReplyCap => {
+            let reply = convert_to_mut_type_ref::<reply_t>(reply_cap.get_reply_ptr());
+            // a reply object serves one receive at a time
+            if reply.replyTCB != 0 && reply.replyTCB != current_thread.get_ptr() {
+                convert_to_mut_type_ref::<tcb_t>(reply.replyTCB).cancel_ipc();
+            }
+            Ok(Some(reply))
+        }
+        _ => {
+            unsafe {
+                current_lookup_fault = lookup_fault_t::new_missing_cap(0);
+                current_fault = seL4_Fault_t::new_cap_fault(reply_cptr, 1);
+            }
+            handle_fault(current_thread);
+            Err(())
+        }
+    }
+}
+
 fn handle_recv(block: bool) {
     let current_thread = get_currenct_thread();
     let ep_cptr = current_thread.tcbArch.get_register(ArchReg::Cap);
@@ -181,7 +290,16 @@ fn handle_recv(block: bool) {
                 }
                 return handle_fault(current_thread);
             }
+            #[cfg(not(feature = "KERNEL_MCS"))]
             current_thread.delete_caller_cap();
+            #[cfg(feature = "KERNEL_MCS")]
+            {
+                let reply_ptr = match lookup_reply(current_thread) {
+                    Ok(reply) => reply.map_or(0, |reply| reply.get_ptr()),
+                    Err(()) => return,
+                };
+                current_thread.tcbState.set_reply_object(reply_ptr);
+            }
             convert_to_mut_type_ref::<endpoint_t>(ipc_cap.get_ep_ptr()).receive_ipc(
                 current_thread,
                 block,
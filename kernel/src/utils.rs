@@ -66,7 +66,10 @@ pub fn clear_memory(ptr: *mut u8, bits: usize) {
 pub fn setVTable(addr: usize) {
     dsb();
     unsafe {
+        #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
         core::arch::asm!("MSR vbar_el1, {0}", in(reg) addr);
+        #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+        core::arch::asm!("MSR vbar_el2, {0}", in(reg) addr);
     }
     isb();
 }
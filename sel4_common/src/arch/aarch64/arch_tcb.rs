@@ -1,5 +1,7 @@
 use core::arch::asm;
 
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+use super::BreakpointState;
 use super::{CONTEXT_REG_NUM, ELR_EL1, SPSR_EL1, TLS_BASE, TPIDRRO_EL0, TPIDR_EL0};
 
 /// Get value from the system register
@@ -16,6 +18,18 @@ macro_rules! mrs {
     };
 }
 
+/// SPSR_EL1.SS
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+const SPSR_SS: usize = 1 << 21;
+/// SPSR.M, the exception level and stack pointer the eret returns to
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const SPSR_MODE_MASK: usize = 0x1f;
+/// SPSR.M of the idle thread, EL1h or EL2h for the kernel in EL2
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+const SPSR_MODE_IDLE: usize = 5;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const SPSR_MODE_IDLE: usize = 9;
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct FPUState {
@@ -23,12 +37,83 @@ pub struct FPUState {
     fpsr: u32,
     fpcr: u32,
 }
+
+impl FPUState {
+    /// Save the SIMD and floating-point registers, the FPU must be enabled at EL1
+    #[inline]
+    pub fn save(&mut self) {
+        unsafe {
+            asm!(
+                ".arch_extension fp",
+                "stp q0,  q1,  [{0}, #16 * 0]",
+                "stp q2,  q3,  [{0}, #16 * 2]",
+                "stp q4,  q5,  [{0}, #16 * 4]",
+                "stp q6,  q7,  [{0}, #16 * 6]",
+                "stp q8,  q9,  [{0}, #16 * 8]",
+                "stp q10, q11, [{0}, #16 * 10]",
+                "stp q12, q13, [{0}, #16 * 12]",
+                "stp q14, q15, [{0}, #16 * 14]",
+                "stp q16, q17, [{0}, #16 * 16]",
+                "stp q18, q19, [{0}, #16 * 18]",
+                "stp q20, q21, [{0}, #16 * 20]",
+                "stp q22, q23, [{0}, #16 * 22]",
+                "stp q24, q25, [{0}, #16 * 24]",
+                "stp q26, q27, [{0}, #16 * 26]",
+                "stp q28, q29, [{0}, #16 * 28]",
+                "stp q30, q31, [{0}, #16 * 30]",
+                "mrs {1}, fpsr",
+                "str {1:w}, [{0}, #16 * 32]",
+                "mrs {1}, fpcr",
+                "str {1:w}, [{0}, #16 * 32 + 4]",
+                in(reg) self as *mut FPUState,
+                out(reg) _,
+            );
+        }
+    }
+
+    /// Load the SIMD and floating-point registers, the FPU must be enabled at EL1
+    #[inline]
+    pub fn load(&self) {
+        unsafe {
+            asm!(
+                ".arch_extension fp",
+                "ldp q0,  q1,  [{0}, #16 * 0]",
+                "ldp q2,  q3,  [{0}, #16 * 2]",
+                "ldp q4,  q5,  [{0}, #16 * 4]",
+                "ldp q6,  q7,  [{0}, #16 * 6]",
+                "ldp q8,  q9,  [{0}, #16 * 8]",
+                "ldp q10, q11, [{0}, #16 * 10]",
+                "ldp q12, q13, [{0}, #16 * 12]",
+                "ldp q14, q15, [{0}, #16 * 14]",
+                "ldp q16, q17, [{0}, #16 * 16]",
+                "ldp q18, q19, [{0}, #16 * 18]",
+                "ldp q20, q21, [{0}, #16 * 20]",
+                "ldp q22, q23, [{0}, #16 * 22]",
+                "ldp q24, q25, [{0}, #16 * 24]",
+                "ldp q26, q27, [{0}, #16 * 26]",
+                "ldp q28, q29, [{0}, #16 * 28]",
+                "ldp q30, q31, [{0}, #16 * 30]",
+                "ldr {1:w}, [{0}, #16 * 32]",
+                "msr fpsr, {1}",
+                "ldr {1:w}, [{0}, #16 * 32 + 4]",
+                "msr fpcr, {1}",
+                in(reg) self as *const FPUState,
+                out(reg) _,
+            );
+        }
+    }
+}
 /// This is `arch_tcb_t` in the sel4_c_impl.
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct ArchTCB {
     pub(in crate::arch) registers: [usize; CONTEXT_REG_NUM],
     pub(in crate::arch) fpu: FPUState,
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    pub breakpoints: BreakpointState,
+    /// The VCPU bound to the thread, 0 for a native thread
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    pub tcbVCPU: usize,
 }
 
 /// Implements the Default for the `ArchTCB`
@@ -43,14 +128,25 @@ impl Default for ArchTCB {
                 fpsr: 0,
                 fpcr: 0,
             },
+            #[cfg(feature = "HARDWARE_DEBUG_API")]
+            breakpoints: BreakpointState::default(),
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            tcbVCPU: 0,
         }
     }
 }
 impl ArchTCB {
-    /// Config the registers fot the idle thread.
-    pub fn config_idle_thread(&mut self, idle_thread: usize) {
+    /// Config the registers fot the idle thread, it runs on the kernel stack of its core.
+    pub fn config_idle_thread(&mut self, idle_thread: usize, _core: usize) {
         self.registers[ELR_EL1] = idle_thread;
-        self.registers[SPSR_EL1] = (1 << 6) | 5 | (1 << 8);
+        self.registers[SPSR_EL1] = (1 << 6) | SPSR_MODE_IDLE | (1 << 8);
+    }
+
+    /// Put the thread back to EL0 once it has no VCPU to run its EL1 code
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    #[inline]
+    pub fn sanitise_spsr(&mut self) {
+        self.registers[SPSR_EL1] &= !SPSR_MODE_MASK;
     }
 
     /// Save TLS(Thread local Storage) registers
@@ -59,6 +155,30 @@ impl ArchTCB {
         self.registers[TPIDR_EL0] = mrs!("tpidr_el0");
         self.registers[TPIDRRO_EL0] = mrs!("tpidrro_el0");
     }
+    /// Save the FPU registers of the thread
+    #[inline]
+    pub fn save_fpu_state(&mut self) {
+        self.fpu.save();
+    }
+
+    /// Load the FPU registers of the thread
+    #[inline]
+    pub fn load_fpu_state(&self) {
+        self.fpu.load();
+    }
+
+    /// Set SPSR_EL1.SS of the thread, with the software step enabled it executes one
+    /// instruction after the eret and takes the step exception.
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    #[inline]
+    pub fn set_software_step(&mut self, enable: bool) {
+        if enable {
+            self.registers[SPSR_EL1] |= SPSR_SS;
+        } else {
+            self.registers[SPSR_EL1] &= !SPSR_SS;
+        }
+    }
+
     #[inline]
     pub fn load_thread_local(&mut self) {
         unsafe {
@@ -0,0 +1,198 @@
+//! The hardware breakpoints, watchpoints and single-stepping of a thread.
+//!
+//! The breakpoint numbers below [seL4_FirstWatchpoint] are the instruction breakpoints, kept in
+//! the DBGBVR/DBGBCR registers, the ones from it are the data breakpoints, kept in the
+//! DBGWVR/DBGWCR registers. All of them only match at EL0.
+
+use core::arch::asm;
+
+/// Set value to the system register
+macro_rules! msr {
+    ($reg: literal, $value: expr) => {
+        unsafe {
+            asm!(concat!("msr ", $reg, ", {0}"), in(reg) $value);
+        }
+    };
+}
+
+pub const seL4_DataBreakpoint: usize = 0;
+pub const seL4_InstructionBreakpoint: usize = 1;
+pub const seL4_SingleStep: usize = 2;
+pub const seL4_SoftwareBreakRequest: usize = 3;
+
+pub const seL4_BreakOnRead: usize = 0;
+pub const seL4_BreakOnWrite: usize = 1;
+pub const seL4_BreakOnReadWrite: usize = 2;
+
+pub const seL4_NumExclusiveBreakpoints: usize = 6;
+pub const seL4_NumExclusiveWatchpoints: usize = 4;
+pub const seL4_FirstBreakpoint: usize = 0;
+pub const seL4_FirstWatchpoint: usize = seL4_NumExclusiveBreakpoints;
+pub const seL4_NumHWBreakpoints: usize =
+    seL4_NumExclusiveBreakpoints + seL4_NumExclusiveWatchpoints;
+
+/// DBGBCR.E and DBGWCR.E
+const DBGCR_ENABLE: usize = 1 << 0;
+/// DBGBCR.PMC and DBGWCR.PAC, match at EL0 only
+const DBGCR_EL0: usize = 0b10 << 1;
+const DBGWCR_LSC_SHIFT: usize = 3;
+const DBGWCR_LSC_MASK: usize = 0b11;
+const DBGCR_BAS_SHIFT: usize = 5;
+const DBGWCR_BAS_MASK: usize = 0xff;
+/// DBGBCR.BAS, match the whole A64 instruction
+const DBGBCR_BAS_A64: usize = 0b1111 << DBGCR_BAS_SHIFT;
+/// The bytes covered by one watchpoint, from its doubleword aligned DBGWVR
+const WATCHPOINT_SPAN: usize = 8;
+
+/// A breakpoint as it is set and read by the TCB invocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub vaddr: usize,
+    pub type_: usize,
+    /// The bytes watched from `vaddr` by a data breakpoint, 0 for an instruction breakpoint
+    pub size: usize,
+    pub rw: usize,
+    pub is_enabled: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct BreakpointState {
+    bvr: [usize; seL4_NumExclusiveBreakpoints],
+    bcr: [usize; seL4_NumExclusiveBreakpoints],
+    wvr: [usize; seL4_NumExclusiveWatchpoints],
+    wcr: [usize; seL4_NumExclusiveWatchpoints],
+    /// The instructions left to execute before the single-step fault, 0 when not stepping
+    n_instructions: usize,
+}
+
+impl BreakpointState {
+    /// Set the breakpoint `bp_num`, the arguments must have been checked by the caller: the
+    /// type matches the number, the size of a data breakpoint is 1, 2, 4 or 8 and `vaddr` is
+    /// aligned to it.
+    pub fn set(&mut self, bp_num: usize, bp: &Breakpoint) {
+        if bp_num < seL4_FirstWatchpoint {
+            self.bvr[bp_num] = bp.vaddr;
+            self.bcr[bp_num] = DBGCR_ENABLE | DBGCR_EL0 | DBGBCR_BAS_A64;
+        } else {
+            let i = bp_num - seL4_FirstWatchpoint;
+            let lsc = match bp.rw {
+                seL4_BreakOnRead => 0b01,
+                seL4_BreakOnWrite => 0b10,
+                _ => 0b11,
+            };
+            let bas = ((1 << bp.size) - 1) << (bp.vaddr % WATCHPOINT_SPAN);
+            self.wvr[i] = bp.vaddr & !(WATCHPOINT_SPAN - 1);
+            self.wcr[i] =
+                DBGCR_ENABLE | DBGCR_EL0 | (lsc << DBGWCR_LSC_SHIFT) | (bas << DBGCR_BAS_SHIFT);
+        }
+    }
+
+    /// Get the breakpoint `bp_num`, which must be below [seL4_NumHWBreakpoints].
+    pub fn get(&self, bp_num: usize) -> Breakpoint {
+        if bp_num < seL4_FirstWatchpoint {
+            return Breakpoint {
+                vaddr: self.bvr[bp_num],
+                type_: seL4_InstructionBreakpoint,
+                size: 0,
+                rw: seL4_BreakOnRead,
+                is_enabled: self.bcr[bp_num] & DBGCR_ENABLE != 0,
+            };
+        }
+        let i = bp_num - seL4_FirstWatchpoint;
+        let bas = (self.wcr[i] >> DBGCR_BAS_SHIFT) & DBGWCR_BAS_MASK;
+        let rw = match (self.wcr[i] >> DBGWCR_LSC_SHIFT) & DBGWCR_LSC_MASK {
+            0b01 => seL4_BreakOnRead,
+            0b10 => seL4_BreakOnWrite,
+            _ => seL4_BreakOnReadWrite,
+        };
+        let offset = if bas == 0 {
+            0
+        } else {
+            bas.trailing_zeros() as usize
+        };
+        Breakpoint {
+            vaddr: self.wvr[i] + offset,
+            type_: seL4_DataBreakpoint,
+            size: bas.count_ones() as usize,
+            rw,
+            is_enabled: self.wcr[i] & DBGCR_ENABLE != 0,
+        }
+    }
+
+    /// Clear the breakpoint `bp_num`, which must be below [seL4_NumHWBreakpoints].
+    pub fn unset(&mut self, bp_num: usize) {
+        if bp_num < seL4_FirstWatchpoint {
+            self.bvr[bp_num] = 0;
+            self.bcr[bp_num] = 0;
+        } else {
+            self.wvr[bp_num - seL4_FirstWatchpoint] = 0;
+            self.wcr[bp_num - seL4_FirstWatchpoint] = 0;
+        }
+    }
+
+    /// Whether any breakpoint of the thread is enabled.
+    pub fn is_used(&self) -> bool {
+        self.bcr
+            .iter()
+            .chain(self.wcr.iter())
+            .any(|cr| cr & DBGCR_ENABLE != 0)
+    }
+
+    /// Find the enabled instruction breakpoint at `vaddr`.
+    pub fn find_breakpoint(&self, vaddr: usize) -> Option<usize> {
+        (0..seL4_NumExclusiveBreakpoints)
+            .find(|&i| self.bcr[i] & DBGCR_ENABLE != 0 && self.bvr[i] == vaddr)
+    }
+
+    /// Find the enabled data breakpoint watching the doubleword of `vaddr`, the address of a
+    /// watchpoint exception may be anywhere in the access which hit it.
+    pub fn find_watchpoint(&self, vaddr: usize) -> Option<usize> {
+        (0..seL4_NumExclusiveWatchpoints)
+            .find(|&i| {
+                self.wcr[i] & DBGCR_ENABLE != 0 && self.wvr[i] == vaddr & !(WATCHPOINT_SPAN - 1)
+            })
+            .map(|i| i + seL4_FirstWatchpoint)
+    }
+
+    /// Step the thread by `n_instructions` before the single-step fault, 0 stops stepping.
+    pub fn configure_single_stepping(&mut self, n_instructions: usize) {
+        self.n_instructions = n_instructions;
+    }
+
+    /// Whether the thread is being stepped by the debug API.
+    pub fn is_single_stepping(&self) -> bool {
+        self.n_instructions != 0
+    }
+
+    /// Count a step exception of the thread, returns true when it has executed the last
+    /// instruction to step, which ends the stepping.
+    pub fn single_step_done(&mut self) -> bool {
+        self.n_instructions = self.n_instructions.saturating_sub(1);
+        self.n_instructions == 0
+    }
+
+    /// Load the breakpoints into the debug registers of the current core.
+    pub fn load(&self) {
+        msr!("dbgbvr0_el1", self.bvr[0]);
+        msr!("dbgbcr0_el1", self.bcr[0]);
+        msr!("dbgbvr1_el1", self.bvr[1]);
+        msr!("dbgbcr1_el1", self.bcr[1]);
+        msr!("dbgbvr2_el1", self.bvr[2]);
+        msr!("dbgbcr2_el1", self.bcr[2]);
+        msr!("dbgbvr3_el1", self.bvr[3]);
+        msr!("dbgbcr3_el1", self.bcr[3]);
+        msr!("dbgbvr4_el1", self.bvr[4]);
+        msr!("dbgbcr4_el1", self.bcr[4]);
+        msr!("dbgbvr5_el1", self.bvr[5]);
+        msr!("dbgbcr5_el1", self.bcr[5]);
+        msr!("dbgwvr0_el1", self.wvr[0]);
+        msr!("dbgwcr0_el1", self.wcr[0]);
+        msr!("dbgwvr1_el1", self.wvr[1]);
+        msr!("dbgwcr1_el1", self.wcr[1]);
+        msr!("dbgwvr2_el1", self.wvr[2]);
+        msr!("dbgwcr2_el1", self.wcr[2]);
+        msr!("dbgwvr3_el1", self.wvr[3]);
+        msr!("dbgwcr3_el1", self.wcr[3]);
+    }
+}
@@ -1,11 +1,18 @@
 // boot 相关的常数
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 pub const PPTR_TOP: usize = 0xffffffffc0000000;
+/// At EL2 there is only the TTBR0_EL2, the kernel window sits in the lower half
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const PPTR_TOP: usize = 0x000000ffc0000000;
 pub const physBase: usize = 0x4000_0000;
 pub const KERNEL_ELF_PADDR_BASE: usize = physBase;
 // pub const KERNEL_ELF_BASE: usize = PPTR_TOP + (KERNEL_ELF_PADDR_BASE & MASK!(30));
 pub const KERNEL_ELF_BASE: usize = PPTR_BASE_OFFSET + KERNEL_ELF_PADDR_BASE;
 pub const KERNEL_ELF_BASE_OFFSET: usize = KERNEL_ELF_BASE - KERNEL_ELF_PADDR_BASE;
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 pub const PPTR_BASE: usize = 0xffffff8000000000;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const PPTR_BASE: usize = 0x0000008000000000;
 pub const PADDR_BASE: usize = 0x0;
 pub const PPTR_BASE_OFFSET: usize = PPTR_BASE - PADDR_BASE;
 pub const PADDR_TOP: usize = PPTR_TOP - PPTR_BASE_OFFSET;
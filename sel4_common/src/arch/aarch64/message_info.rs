@@ -11,6 +11,8 @@ pub enum MessageLabel {
     TCBSetPriority,
     TCBSetMCPriority,
     TCBSetSchedParams,
+    #[cfg(feature = "KERNEL_MCS")]
+    TCBSetTimeoutEndpoint,
     TCBSetIPCBuffer,
     TCBSetSpace,
     TCBSuspend,
@@ -19,6 +21,14 @@ pub enum MessageLabel {
     TCBUnbindNotification,
     #[cfg(feature = "ENABLE_SMP")]
     TCBSetAffinity,
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    TCBSetBreakpoint,
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    TCBGetBreakpoint,
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    TCBUnsetBreakpoint,
+    #[cfg(feature = "HARDWARE_DEBUG_API")]
+    TCBConfigureSingleStepping,
     TCBSetTLSBase,
     CNodeRevoke,
     CNodeDelete,
@@ -28,12 +38,25 @@ pub enum MessageLabel {
     CNodeMove,
     CNodeMutate,
     CNodeRotate,
+    #[cfg(not(feature = "KERNEL_MCS"))]
     CNodeSaveCaller,
     IRQIssueIRQHandler,
     IRQAckIRQ,
     IRQSetIRQHandler,
     IRQClearIRQHandler,
     DomainSetSet,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedControlConfigureFlags,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextBind,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextUnbind,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextUnbindObject,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextConsumed,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextYieldTo,
     ARMVSpaceClean_Data,
     ARMVSpaceInvalidate_Data,
     ARMVSpaceCleanInvalidate_Data,
@@ -50,6 +73,16 @@ pub enum MessageLabel {
     ARMPageGetAddress,
     ARMASIDControlMakePool,
     ARMASIDPoolAssign,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    ARMVCPUSetTCB,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    ARMVCPUInjectIRQ,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    ARMVCPUReadReg,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    ARMVCPUWriteReg,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    ARMVCPUAckVPPI,
     ARMIRQIssueIRQHandlerTrigger,
     nArchInvocationLabels,
 }
@@ -1,14 +1,22 @@
 #![allow(unused)]
 mod arch_tcb;
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+mod breakpoint;
 pub mod config;
 mod message_info;
 mod object;
 mod registers;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+mod vcpu;
 mod vm_rights;
 pub use arch_tcb::ArchTCB;
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+pub use breakpoint::*;
 pub use message_info::*;
 pub use object::*;
 pub use registers::*;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub use vcpu::*;
 pub use vm_rights::*;
 
 pub const PSCI_0_2_FN_BASE: u32 = 0x84000000;
@@ -1,3 +1,6 @@
+use crate::object::seL4_NonArchObjectTypeCount;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::sel4_config::seL4_VCPUBits;
 use crate::sel4_config::{
     seL4_PGDBits, seL4_PUDBits, seL4_PageDirBits, seL4_PageTableBits, seL4_VSpaceBits,
     ARMHugePageBits, ARMLargePageBits, ARMSmallPageBits, ARM_Huge_Page, ARM_Large_Page,
@@ -12,11 +15,17 @@ pub enum ObjectType {
     EndpointObject = 2,
     NotificationObject = 3,
     CapTableObject = 4,
-    seL4_ARM_HugePageObject = 5,
-    seL4_ARM_VSpaceObject = 6,
-    seL4_ARM_SmallPageObject = 7,
-    seL4_ARM_LargePageObject = 8,
-    seL4_ARM_PageTableObject = 9,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextObject = 5,
+    #[cfg(feature = "KERNEL_MCS")]
+    ReplyObject = 6,
+    seL4_ARM_HugePageObject = seL4_NonArchObjectTypeCount as isize,
+    seL4_ARM_VSpaceObject,
+    seL4_ARM_SmallPageObject,
+    seL4_ARM_LargePageObject,
+    seL4_ARM_PageTableObject,
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    seL4_ARM_VCPUObject,
 }
 
 impl ObjectType {
@@ -27,6 +36,8 @@ impl ObjectType {
             Self::seL4_ARM_HugePageObject => ARMHugePageBits,
             Self::seL4_ARM_PageTableObject => seL4_PageTableBits,
             Self::seL4_ARM_VSpaceObject => seL4_VSpaceBits,
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            Self::seL4_ARM_VCPUObject => seL4_VCPUBits,
             _ => panic!("unsupported object type:{}", *self as usize),
         }
     }
@@ -52,13 +63,15 @@ impl ObjectType {
     ///
     /// true if the object type is an architecture-specific type, false otherwise.
     pub fn is_arch_type(self) -> bool {
-        matches!(
-            self,
+        match self {
             Self::seL4_ARM_HugePageObject
-                | Self::seL4_ARM_SmallPageObject
-                | Self::seL4_ARM_LargePageObject
-                | Self::seL4_ARM_PageTableObject
-                | Self::seL4_ARM_VSpaceObject
-        )
+            | Self::seL4_ARM_SmallPageObject
+            | Self::seL4_ARM_LargePageObject
+            | Self::seL4_ARM_PageTableObject
+            | Self::seL4_ARM_VSpaceObject => true,
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            Self::seL4_ARM_VCPUObject => true,
+            _ => false,
+        }
     }
 }
@@ -69,6 +69,8 @@ pub const fault_messages: [[usize; MAX_MSG_SIZE]; 2] = [
 ];
 pub const frameRegNum: usize = 17;
 pub const gpRegNum: usize = 19;
+/// The FP/SIMD registers are not accessible through TCBReadRegisters/TCBWriteRegisters
+pub const fpRegNum: usize = 0;
 pub const frameRegisters: [usize; frameRegNum] =
     [34, 31, 33, 0, 1, 2, 3, 4, 5, 6, 7, 8, 16, 17, 18, 29, 30];
 pub const gpRegisters: [usize; gpRegNum] = [
@@ -83,8 +85,10 @@ impl ArchReg {
             ArchReg::Cap => 0,
             ArchReg::Badge => 0,
             ArchReg::MsgInfo => 1,
+            ArchReg::Reply => 6,
             ArchReg::FaultIP => 34,
             ArchReg::NextIP => 32,
+            ArchReg::SP => 31,
             ArchReg::Msg(i) => msgRegister[*i],
             ArchReg::Frame(i) => frameRegisters[*i],
             ArchReg::GP(i) => gpRegisters[*i],
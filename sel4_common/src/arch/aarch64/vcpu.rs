@@ -0,0 +1,29 @@
+//! The registers of a VCPU, as `ARMVCPUReadReg` and `ARMVCPUWriteReg` number them.
+//!
+//! These are the EL1 system registers of the guest, which the kernel swaps in and out of the
+//! hardware when it switches between the VCPUs.
+
+pub const seL4_VCPUReg_SCTLR: usize = 0;
+pub const seL4_VCPUReg_TTBR0: usize = 1;
+pub const seL4_VCPUReg_TTBR1: usize = 2;
+pub const seL4_VCPUReg_TCR: usize = 3;
+pub const seL4_VCPUReg_MAIR: usize = 4;
+pub const seL4_VCPUReg_AMAIR: usize = 5;
+pub const seL4_VCPUReg_CIDR: usize = 6;
+pub const seL4_VCPUReg_ACTLR: usize = 7;
+pub const seL4_VCPUReg_CPACR: usize = 8;
+pub const seL4_VCPUReg_AFSR0: usize = 9;
+pub const seL4_VCPUReg_AFSR1: usize = 10;
+pub const seL4_VCPUReg_ESR: usize = 11;
+pub const seL4_VCPUReg_FAR: usize = 12;
+pub const seL4_VCPUReg_ISR: usize = 13;
+pub const seL4_VCPUReg_VBAR: usize = 14;
+pub const seL4_VCPUReg_TPIDR_EL1: usize = 15;
+pub const seL4_VCPUReg_SP_EL1: usize = 16;
+pub const seL4_VCPUReg_ELR_EL1: usize = 17;
+pub const seL4_VCPUReg_SPSR_EL1: usize = 18;
+pub const seL4_VCPUReg_CNTV_CTL: usize = 19;
+pub const seL4_VCPUReg_CNTV_CVAL: usize = 20;
+pub const seL4_VCPUReg_CNTVOFF: usize = 21;
+pub const seL4_VCPUReg_CNTKCTL_EL1: usize = 22;
+pub const seL4_VCPUReg_Num: usize = 23;
@@ -0,0 +1,25 @@
+use super::{NextIP, CONTEXT_REG_NUM, STATUS};
+
+/// This is `arch_tcb_t` in the sel4_c_impl, without any state living in the host CPU.
+#[repr(C)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArchTCB {
+    pub(in crate::arch) registers: [usize; CONTEXT_REG_NUM],
+}
+
+impl Default for ArchTCB {
+    fn default() -> Self {
+        Self {
+            registers: [0; CONTEXT_REG_NUM],
+        }
+    }
+}
+
+impl ArchTCB {
+    /// Config the registers fot the idle thread.
+    pub fn config_idle_thread(&mut self, idle_thread: usize, _core: usize) {
+        self.registers[NextIP] = idle_thread;
+        // the idle thread runs in the kernel
+        self.registers[STATUS] = 1;
+    }
+}
@@ -0,0 +1,12 @@
+//! The kernel window of the host is the identity, so that the kernel pointers and the
+//! physical addresses of the host objects are their addresses in the test process.
+
+pub const PPTR_TOP: usize = 1 << 47;
+pub const physBase: usize = 0;
+pub const KERNEL_ELF_PADDR_BASE: usize = physBase;
+pub const KERNEL_ELF_BASE: usize = PPTR_BASE_OFFSET + KERNEL_ELF_PADDR_BASE;
+pub const KERNEL_ELF_BASE_OFFSET: usize = KERNEL_ELF_BASE - KERNEL_ELF_PADDR_BASE;
+pub const PPTR_BASE: usize = 0;
+pub const PADDR_BASE: usize = 0x0;
+pub const PPTR_BASE_OFFSET: usize = PPTR_BASE - PADDR_BASE;
+pub const PADDR_TOP: usize = PPTR_TOP - PPTR_BASE_OFFSET;
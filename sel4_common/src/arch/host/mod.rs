@@ -0,0 +1,89 @@
+//! The backend of the host the unit tests run on, e.g. x86_64 Linux.
+//!
+//! The objects, caps and message labels are the ones of aarch64, as their 48-bit pointer fields
+//! hold the addresses of the host. The machine is simulated: the registers only live in the
+//! [`ArchTCB`], the timer is a counter moved by the tests, and the SBI calls are recorded.
+//!
+//! Run the suites with `cargo host-test` at the root of the workspace, an alias of
+//! `.cargo/config.toml` for the host target, which the bare-metal default target would hide.
+#![allow(unused)]
+
+extern crate std;
+
+mod arch_tcb;
+pub mod config;
+#[path = "../aarch64/message_info.rs"]
+mod message_info;
+#[path = "../aarch64/object.rs"]
+mod object;
+mod registers;
+#[path = "../aarch64/vm_rights.rs"]
+mod vm_rights;
+pub use arch_tcb::ArchTCB;
+pub use message_info::*;
+pub use object::*;
+pub use registers::*;
+pub use vm_rights::*;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Held by the test cases touching the kernel globals, which the harness runs in parallel
+static HOST_KERNEL_STATE: Mutex<()> = Mutex::new(());
+
+/// Take the kernel globals for the current test case, a failed case doesn't keep them.
+pub fn host_lock_kernel_state() -> MutexGuard<'static, ()> {
+    HOST_KERNEL_STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The current time of the simulated timer in ticks
+static HOST_TIME: AtomicUsize = AtomicUsize::new(0);
+/// The time the simulated timer interrupt is raised at
+static HOST_DEADLINE: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// The harts the software interrupt was sent to and not cleared yet
+static HOST_IPI_MASK: AtomicUsize = AtomicUsize::new(0);
+
+pub fn get_time() -> usize {
+    HOST_TIME.load(Ordering::Relaxed)
+}
+
+pub fn set_timer(timer: usize) {
+    HOST_DEADLINE.store(timer, Ordering::Relaxed);
+}
+
+/// Move the simulated timer forward by `ticks`
+pub fn host_advance_time(ticks: usize) {
+    HOST_TIME.fetch_add(ticks, Ordering::Relaxed);
+}
+
+/// Get the time the simulated timer interrupt was last set at
+pub fn host_get_deadline() -> usize {
+    HOST_DEADLINE.load(Ordering::Relaxed)
+}
+
+/// Check if the simulated timer interrupt is pending
+pub fn host_timer_pending() -> bool {
+    get_time() >= host_get_deadline()
+}
+
+pub fn clear_ipi() {
+    HOST_IPI_MASK.store(0, Ordering::Relaxed);
+}
+
+/// Raise a software interrupt on every hart in `hart_mask`
+pub fn send_ipi(hart_mask: usize) {
+    HOST_IPI_MASK.fetch_or(hart_mask, Ordering::Relaxed);
+}
+
+/// Get the harts with a software interrupt pending
+pub fn host_get_ipi_mask() -> usize {
+    HOST_IPI_MASK.load(Ordering::Relaxed)
+}
+
+pub fn shutdown() -> ! {
+    panic!("shutdown");
+}
+
+pub fn sys_write(_fd: usize, _buffer: &[u8]) {}
+
+pub fn remote_sfence_vma(_hart_mask: usize, _start: usize, _size: usize) {}
@@ -0,0 +1,75 @@
+//! The register file of the host backend, shaped like the one of aarch64 so that the fault
+//! and message layouts shared with aarch64 hold.
+
+use crate::arch::ArchReg;
+
+// R0 - R30 are the general purpose registers
+pub const capRegister: usize = 0;
+pub(super) const badgeRegister: usize = 0;
+pub(super) const msgInfoRegister: usize = 1;
+pub(super) const replyRegister: usize = 6;
+pub(super) const SP: usize = 31;
+pub(super) const NextIP: usize = 32;
+pub(super) const STATUS: usize = 33;
+pub(super) const FaultIP: usize = 34;
+pub(super) const TLS_BASE: usize = 35;
+/// A second thread ID register, read only for the user
+pub(super) const TLS_BASE_RO: usize = 36;
+pub const CONTEXT_REG_NUM: usize = 37;
+pub const n_exceptionMessage: usize = 3;
+pub const n_syscallMessage: usize = 12;
+pub const msgRegisterNum: usize = 4;
+pub const msgRegister: [usize; msgRegisterNum] = [2, 3, 4, 5];
+pub const MAX_MSG_SIZE: usize = n_syscallMessage;
+pub const fault_messages: [[usize; MAX_MSG_SIZE]; 2] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, FaultIP, SP, NextIP, STATUS],
+    [FaultIP, SP, STATUS, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+pub const frameRegNum: usize = 17;
+pub const gpRegNum: usize = 19;
+/// The host has no FP registers
+pub const fpRegNum: usize = 0;
+pub const frameRegisters: [usize; frameRegNum] = [
+    FaultIP, SP, STATUS, 0, 1, 2, 3, 4, 5, 6, 7, 8, 16, 17, 18, 29, 30,
+];
+pub const gpRegisters: [usize; gpRegNum] = [
+    9,
+    10,
+    11,
+    12,
+    13,
+    14,
+    15,
+    19,
+    20,
+    21,
+    22,
+    23,
+    24,
+    25,
+    26,
+    27,
+    28,
+    TLS_BASE,
+    TLS_BASE_RO,
+];
+
+impl ArchReg {
+    /// Convert Enum to register index.
+    pub const fn to_index(&self) -> usize {
+        match self {
+            ArchReg::TlsBase => TLS_BASE,
+            ArchReg::Cap => capRegister,
+            ArchReg::Badge => badgeRegister,
+            ArchReg::MsgInfo => msgInfoRegister,
+            ArchReg::Reply => replyRegister,
+            ArchReg::FaultIP => FaultIP,
+            ArchReg::NextIP => NextIP,
+            ArchReg::SP => SP,
+            ArchReg::Msg(i) => msgRegister[*i],
+            ArchReg::Frame(i) => frameRegisters[*i],
+            ArchReg::GP(i) => gpRegisters[*i],
+            ArchReg::FaultMessage(id, index) => fault_messages[*id][*index],
+        }
+    }
+}
@@ -5,11 +5,16 @@ use core::ops::Range;
 #[cfg(target_arch = "riscv64")]
 pub use riscv64::*;
 
-#[cfg(any(target_arch = "aarch64", test))]
+#[cfg(target_arch = "aarch64")]
 mod aarch64;
-#[cfg(any(target_arch = "aarch64", test))]
+#[cfg(target_arch = "aarch64")]
 pub use aarch64::*;
 
+#[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+mod host;
+#[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+pub use host::*;
+
 /// ArchTCB Common part
 impl ArchTCB {
     /// Set the register of the TCB
@@ -56,8 +61,12 @@ pub enum ArchReg {
     Cap,
     Badge,
     MsgInfo,
+    /// Reply object cap of the receive syscalls under MCS
+    Reply,
     FaultIP,
     NextIP,
+    /// User stack pointer
+    SP,
     /// Message Registers Msg(offset)
     Msg(usize),
     /// Frame Registers Frame(Offset)
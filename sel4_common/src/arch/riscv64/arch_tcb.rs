@@ -1,32 +1,222 @@
 use crate::ffi::kernel_stack_alloc;
 // use crate::idle_thread;
 use super::{fault_messages, msgRegister, NextIP};
+#[cfg(feature = "HAVE_FPU")]
+use super::{fpRegNum, SSTATUS_FS};
 use super::{sp, CONTEXT_REG_NUM, SSTATUS, SSTATUS_SPIE, SSTATUS_SPP};
 use crate::sel4_config::CONFIG_KERNEL_STACK_BITS;
 use crate::BIT;
+#[cfg(feature = "HAVE_FPU")]
+use core::arch::asm;
+
+#[cfg(feature = "HAVE_FPU")]
+#[repr(C)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct FPUState {
+    regs: [u64; 32],
+    fcsr: u32,
+}
+
+#[cfg(feature = "HAVE_FPU")]
+impl FPUState {
+    /// Save the FP registers, sstatus.FS must not be Off
+    #[inline]
+    pub fn save(&mut self) {
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +d",
+                "fsd f0,  0*8({0})",
+                "fsd f1,  1*8({0})",
+                "fsd f2,  2*8({0})",
+                "fsd f3,  3*8({0})",
+                "fsd f4,  4*8({0})",
+                "fsd f5,  5*8({0})",
+                "fsd f6,  6*8({0})",
+                "fsd f7,  7*8({0})",
+                "fsd f8,  8*8({0})",
+                "fsd f9,  9*8({0})",
+                "fsd f10, 10*8({0})",
+                "fsd f11, 11*8({0})",
+                "fsd f12, 12*8({0})",
+                "fsd f13, 13*8({0})",
+                "fsd f14, 14*8({0})",
+                "fsd f15, 15*8({0})",
+                "fsd f16, 16*8({0})",
+                "fsd f17, 17*8({0})",
+                "fsd f18, 18*8({0})",
+                "fsd f19, 19*8({0})",
+                "fsd f20, 20*8({0})",
+                "fsd f21, 21*8({0})",
+                "fsd f22, 22*8({0})",
+                "fsd f23, 23*8({0})",
+                "fsd f24, 24*8({0})",
+                "fsd f25, 25*8({0})",
+                "fsd f26, 26*8({0})",
+                "fsd f27, 27*8({0})",
+                "fsd f28, 28*8({0})",
+                "fsd f29, 29*8({0})",
+                "fsd f30, 30*8({0})",
+                "fsd f31, 31*8({0})",
+                "frcsr {1}",
+                "sw {1}, 32*8({0})",
+                ".option pop",
+                in(reg) self as *mut FPUState,
+                out(reg) _,
+            );
+        }
+    }
+
+    /// Load the FP registers, sstatus.FS must not be Off
+    #[inline]
+    pub fn load(&self) {
+        unsafe {
+            asm!(
+                ".option push",
+                ".option arch, +d",
+                "fld f0,  0*8({0})",
+                "fld f1,  1*8({0})",
+                "fld f2,  2*8({0})",
+                "fld f3,  3*8({0})",
+                "fld f4,  4*8({0})",
+                "fld f5,  5*8({0})",
+                "fld f6,  6*8({0})",
+                "fld f7,  7*8({0})",
+                "fld f8,  8*8({0})",
+                "fld f9,  9*8({0})",
+                "fld f10, 10*8({0})",
+                "fld f11, 11*8({0})",
+                "fld f12, 12*8({0})",
+                "fld f13, 13*8({0})",
+                "fld f14, 14*8({0})",
+                "fld f15, 15*8({0})",
+                "fld f16, 16*8({0})",
+                "fld f17, 17*8({0})",
+                "fld f18, 18*8({0})",
+                "fld f19, 19*8({0})",
+                "fld f20, 20*8({0})",
+                "fld f21, 21*8({0})",
+                "fld f22, 22*8({0})",
+                "fld f23, 23*8({0})",
+                "fld f24, 24*8({0})",
+                "fld f25, 25*8({0})",
+                "fld f26, 26*8({0})",
+                "fld f27, 27*8({0})",
+                "fld f28, 28*8({0})",
+                "fld f29, 29*8({0})",
+                "fld f30, 30*8({0})",
+                "fld f31, 31*8({0})",
+                "lwu {1}, 32*8({0})",
+                "fscsr {1}",
+                ".option pop",
+                in(reg) self as *const FPUState,
+                out(reg) _,
+            );
+        }
+    }
+}
 
 /// This is `arch_tcb_t` in the sel4_c_impl.
 #[repr(C)]
 #[derive(Debug, PartialEq, Clone)]
 pub struct ArchTCB {
     pub(in crate::arch) registers: [usize; CONTEXT_REG_NUM],
+    #[cfg(feature = "HAVE_FPU")]
+    pub(in crate::arch) fpu: FPUState,
+    /// The VCPU bound to the thread, 0 for a native thread
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    pub tcbVCPU: usize,
 }
 
 impl Default for ArchTCB {
     fn default() -> Self {
         let mut registers = [0; CONTEXT_REG_NUM];
+        // sstatus.FS starts Off, the first FP access traps to give the FPU to the thread
         registers[SSTATUS] = 0x00040020;
-        Self { registers }
+        Self {
+            registers,
+            #[cfg(feature = "HAVE_FPU")]
+            fpu: FPUState {
+                regs: [0; 32],
+                fcsr: 0,
+            },
+            #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+            tcbVCPU: 0,
+        }
     }
 }
 
 impl ArchTCB {
-    /// Config the registers fot the idle thread.
-    pub fn config_idle_thread(&mut self, idle_thread: usize) {
+    /// Config the registers fot the idle thread of the core, it runs on the kernel stack of the core.
+    pub fn config_idle_thread(&mut self, idle_thread: usize, core: usize) {
         self.registers[NextIP] = idle_thread;
         self.registers[SSTATUS] = SSTATUS_SPP | SSTATUS_SPIE;
-        self.registers[sp] =
-            unsafe { &kernel_stack_alloc.data[0][BIT!(CONFIG_KERNEL_STACK_BITS) - 1] as *const u8 }
-                as usize;
+        self.registers[sp] = unsafe {
+            &kernel_stack_alloc.data[core][BIT!(CONFIG_KERNEL_STACK_BITS) - 1] as *const u8
+        } as usize;
+    }
+
+    /// Make the thread go back to VS-mode, where the guest kernel on its VCPU starts
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    #[inline]
+    pub fn set_guest_mode(&mut self) {
+        self.registers[SSTATUS] |= SSTATUS_SPP;
+    }
+
+    /// Put the thread back to U-mode once it has no VCPU to run its VS-mode code
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    #[inline]
+    pub fn sanitise_sstatus(&mut self) {
+        self.registers[SSTATUS] &= !SSTATUS_SPP;
+    }
+
+    /// Get the sstatus.FS field saved on the last kernel entry
+    #[cfg(feature = "HAVE_FPU")]
+    #[inline]
+    pub fn get_fs(&self) -> usize {
+        self.registers[SSTATUS] & SSTATUS_FS
+    }
+
+    /// Set the sstatus.FS field restored on the next kernel exit
+    #[cfg(feature = "HAVE_FPU")]
+    #[inline]
+    pub fn set_fs(&mut self, fs: usize) {
+        self.registers[SSTATUS] = (self.registers[SSTATUS] & !SSTATUS_FS) | (fs & SSTATUS_FS);
+    }
+
+    /// Save the FPU registers of the thread
+    #[cfg(feature = "HAVE_FPU")]
+    #[inline]
+    pub fn save_fpu_state(&mut self) {
+        self.fpu.save();
+    }
+
+    /// Load the FPU registers of the thread
+    #[cfg(feature = "HAVE_FPU")]
+    #[inline]
+    pub fn load_fpu_state(&self) {
+        self.fpu.load();
+    }
+
+    /// Get the saved value of f0-f31 (`index` 0 to 31) or fcsr (`index` 32)
+    #[cfg(feature = "HAVE_FPU")]
+    pub fn get_fp_register(&self, index: usize) -> usize {
+        assert!(index < fpRegNum);
+        if index < self.fpu.regs.len() {
+            self.fpu.regs[index] as usize
+        } else {
+            self.fpu.fcsr as usize
+        }
+    }
+
+    /// Set the saved value of f0-f31 (`index` 0 to 31) or fcsr (`index` 32)
+    #[cfg(feature = "HAVE_FPU")]
+    pub fn set_fp_register(&mut self, index: usize, value: usize) {
+        assert!(index < fpRegNum);
+        if index < self.fpu.regs.len() {
+            self.fpu.regs[index] = value as u64;
+        } else {
+            self.fpu.fcsr = value as u32;
+        }
     }
 }
@@ -11,6 +11,8 @@ pub enum MessageLabel {
     TCBSetPriority,
     TCBSetMCPriority,
     TCBSetSchedParams,
+    #[cfg(feature = "KERNEL_MCS")]
+    TCBSetTimeoutEndpoint,
     TCBSetIPCBuffer,
     TCBSetSpace,
     TCBSuspend,
@@ -28,12 +30,25 @@ pub enum MessageLabel {
     CNodeMove,
     CNodeMutate,
     CNodeRotate,
+    #[cfg(not(feature = "KERNEL_MCS"))]
     CNodeSaveCaller,
     IRQIssueIRQHandler,
     IRQAckIRQ,
     IRQSetIRQHandler,
     IRQClearIRQHandler,
     DomainSetSet,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedControlConfigureFlags,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextBind,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextUnbind,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextUnbindObject,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextConsumed,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextYieldTo,
     RISCVPageTableMap,
     RISCVPageTableUnmap,
     RISCVPageMap,
@@ -41,6 +56,14 @@ pub enum MessageLabel {
     RISCVPageGetAddress,
     RISCVASIDControlMakePool,
     RISCVASIDPoolAssign,
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    RISCVVCPUSetTCB,
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    RISCVVCPUInjectIRQ,
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    RISCVVCPUReadReg,
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    RISCVVCPUWriteReg,
     RISCVIRQIssueIRQHandlerTrigger,
     nArchInvocationLabels,
 }
@@ -6,12 +6,16 @@ pub mod config;
 mod message_info;
 mod object;
 mod registers;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+mod vcpu;
 mod vm_rights;
 pub use arch_tcb::ArchTCB;
 pub use message_info::*;
 pub use object::*;
 pub use registers::*;
 use riscv::register::time;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+pub use vcpu::*;
 pub use vm_rights::*;
 
 const SBI_SET_TIMER: usize = 0;
@@ -19,10 +23,17 @@ const SBI_CONSOLE_PUTCHAR: usize = 1;
 const SBI_CONSOLE_GETCHAR: usize = 2;
 
 const SBI_CLEAR_IPI: usize = 3;
+const SBI_SEND_IPI: usize = 4;
 const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_SHUTDOWN: usize = 8;
 const SYSCALL_WRITE: usize = 64;
 
+/// The remote fences of the SBI v0.2 calls, the legacy ones have no G-stage fence
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+const SBI_EXT_RFENCE: usize = 0x52464E43;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+const SBI_EXT_RFENCE_REMOTE_HFENCE_GVMA: usize = 2;
+
 #[no_mangle]
 pub fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     let mut ret;
@@ -45,6 +56,12 @@ pub fn set_timer(timer: usize) {
 pub fn clear_ipi() {
     sbi_call(SBI_CLEAR_IPI, 0, 0, 0);
 }
+
+/// Raise a supervisor software interrupt on every hart in `hart_mask`
+pub fn send_ipi(hart_mask: usize) {
+    let virt_addr_hart_mask = (&hart_mask) as *const usize as usize;
+    sbi_call(SBI_SEND_IPI, virt_addr_hart_mask, 0, 0);
+}
 pub fn shutdown() -> ! {
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
@@ -59,6 +76,22 @@ pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
     sbi_call(SBI_REMOTE_SFENCE_VMA, virt_addr_hart_mask, 0, 0);
 }
 
+/// Flush the G-stage translations of all the VMIDs on every hart in `hart_mask`
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+pub fn remote_hfence_gvma(hart_mask: usize) {
+    unsafe {
+        core::arch::asm!(
+        "ecall",
+        inlateout("x10") hart_mask => _,
+        inlateout("x11") 0usize => _,
+        in("x12") 0usize,
+        in("x13") 0usize,
+        in("x16") SBI_EXT_RFENCE_REMOTE_HFENCE_GVMA,
+        in("x17") SBI_EXT_RFENCE,
+        );
+    }
+}
+
 pub fn get_time() -> usize {
     time::read()
 }
@@ -1,3 +1,6 @@
+use crate::object::seL4_NonArchObjectTypeCount;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+use crate::sel4_config::seL4_VCPUBits;
 use crate::sel4_config::{
     seL4_HugePageBits, seL4_LargePageBits, seL4_PageBits, RISCV_4K_Page, RISCV_Giga_Page,
     RISCV_Mega_Page,
@@ -11,11 +14,17 @@ pub enum ObjectType {
     EndpointObject = 2,
     NotificationObject = 3,
     CapTableObject = 4,
+    #[cfg(feature = "KERNEL_MCS")]
+    SchedContextObject = 5,
+    #[cfg(feature = "KERNEL_MCS")]
+    ReplyObject = 6,
     // RISCV relevant object
-    GigaPageObject = 5,
-    NormalPageObject = 6,
-    MegaPageObject = 7,
-    PageTableObject = 8,
+    GigaPageObject = seL4_NonArchObjectTypeCount as isize,
+    NormalPageObject,
+    MegaPageObject,
+    PageTableObject,
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    VCPUObject,
 }
 
 impl ObjectType {
@@ -25,6 +34,8 @@ impl ObjectType {
             ObjectType::NormalPageObject => seL4_PageBits,
             ObjectType::MegaPageObject => seL4_LargePageBits,
             ObjectType::PageTableObject => seL4_PageBits,
+            #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+            ObjectType::VCPUObject => seL4_VCPUBits,
             _ => panic!("unsupported cap type:{}", (*self) as usize),
         }
     }
@@ -51,9 +62,11 @@ impl ObjectType {
     ///
     /// true if the object type is an architecture-specific type, false otherwise.
     pub fn is_arch_type(self) -> bool {
-        matches!(
-            self,
-            Self::GigaPageObject | Self::NormalPageObject | Self::MegaPageObject
-        )
+        match self {
+            Self::GigaPageObject | Self::NormalPageObject | Self::MegaPageObject => true,
+            #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+            Self::VCPUObject => true,
+            _ => false,
+        }
     }
 }
@@ -49,8 +49,15 @@ pub(super) const CONTEXT_REG_NUM: usize = 35;
 pub const msgRegisterNum: usize = 4;
 pub const msgRegister: [usize; msgRegisterNum] = [11, 12, 13, 14];
 
+pub const SSTATUS_SIE: usize = 0x00000002;
 pub const SSTATUS_SPIE: usize = 0x00000020;
 pub const SSTATUS_SPP: usize = 0x00000100;
+/// The FS field of sstatus, the state of the FP registers
+pub const SSTATUS_FS: usize = 0x00006000;
+pub const SSTATUS_FS_OFF: usize = 0x00000000;
+pub const SSTATUS_FS_INITIAL: usize = 0x00002000;
+pub const SSTATUS_FS_CLEAN: usize = 0x00004000;
+pub const SSTATUS_FS_DIRTY: usize = 0x00006000;
 
 pub const n_syscallMessage: usize = 10;
 pub const n_exceptionMessage: usize = 2;
@@ -62,6 +69,11 @@ pub const fault_messages: [[usize; MAX_MSG_SIZE]; 2] = [
 
 pub const frameRegNum: usize = 16;
 pub const gpRegNum: usize = 16;
+/// f0-f31 and fcsr, accessed after the frame and gp registers by TCBReadRegisters/TCBWriteRegisters
+#[cfg(feature = "HAVE_FPU")]
+pub const fpRegNum: usize = 33;
+#[cfg(not(feature = "HAVE_FPU"))]
+pub const fpRegNum: usize = 0;
 
 pub const frameRegisters: [usize; frameRegNum] =
     [33, 0, 1, 2, 7, 8, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26];
@@ -76,8 +88,10 @@ impl ArchReg {
             ArchReg::Cap => 9,
             ArchReg::Badge => 9,
             ArchReg::MsgInfo => 10,
+            ArchReg::Reply => 15,
             ArchReg::FaultIP => 33,
             ArchReg::NextIP => 34,
+            ArchReg::SP => 1,
             ArchReg::Msg(i) => msgRegister[*i],
             ArchReg::Frame(i) => frameRegisters[*i],
             ArchReg::GP(i) => gpRegisters[*i],
@@ -0,0 +1,23 @@
+//! The registers of a VCPU, as `RISCVVCPUReadReg` and `RISCVVCPUWriteReg` number them.
+//!
+//! These are the VS-mode CSRs of the guest with the hypervisor CSRs that belong to it, which the
+//! kernel swaps in and out of the hardware when it switches between the VCPUs.
+
+pub const seL4_VCPUReg_VSSTATUS: usize = 0;
+pub const seL4_VCPUReg_VSIE: usize = 1;
+pub const seL4_VCPUReg_VSTVEC: usize = 2;
+pub const seL4_VCPUReg_VSSCRATCH: usize = 3;
+pub const seL4_VCPUReg_VSEPC: usize = 4;
+pub const seL4_VCPUReg_VSCAUSE: usize = 5;
+pub const seL4_VCPUReg_VSTVAL: usize = 6;
+pub const seL4_VCPUReg_VSATP: usize = 7;
+/// The virtual interrupts pending for the guest, `RISCVVCPUInjectIRQ` sets them one at a time
+pub const seL4_VCPUReg_HVIP: usize = 8;
+/// The offset from the time of the core to the time the guest reads
+pub const seL4_VCPUReg_HTIMEDELTA: usize = 9;
+pub const seL4_VCPUReg_Num: usize = 10;
+
+/// The virtual interrupts of hvip: the software, timer and external interrupts of VS-mode
+pub const seL4_VCPU_VSSoftwareIRQ: usize = 2;
+pub const seL4_VCPU_VSTimerIRQ: usize = 6;
+pub const seL4_VCPU_VSExternalIRQ: usize = 10;
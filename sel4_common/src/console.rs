@@ -1,16 +1,200 @@
-//! SBI console driver, for text output
+//! The console of the kernel, for text output
+//!
+//! The output goes through a ring buffer, so a full UART FIFO does not keep the printing core,
+//! and the locks it holds, spinning. The ring is drained as the UART takes the bytes, by the next
+//! print or by [poll]. The input is taken by the UART interrupt into another ring.
+//!
+//! After [set_panicking] the output doesn't wait for a lock the panicking core may hold, and goes
+//! straight to the UART when the ring is busy.
 
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use driver_collect::default_serial;
 use driver_collect::SerialDriver;
+use spin::{Mutex, MutexGuard};
+
+/// The size of the transmit ring, in bytes
+const TX_RING_SIZE: usize = 4096;
+/// The size of the receive ring, in bytes
+const RX_RING_SIZE: usize = 256;
+
+static TX_RING: Mutex<RingBuffer<TX_RING_SIZE>> = Mutex::new(RingBuffer::new());
+static RX_RING: Mutex<RingBuffer<RX_RING_SIZE>> = Mutex::new(RingBuffer::new());
+/// Whether the kernel is panicking
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// A FIFO of bytes
+pub(crate) struct RingBuffer<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Put `c` at the tail, false if the ring is full.
+    pub fn push(&mut self, c: u8) -> bool {
+        if self.len == N {
+            return false;
+        }
+        self.buf[(self.head + self.len) % N] = c;
+        self.len += 1;
+        true
+    }
+
+    /// Take the byte at the head.
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let c = self.buf[self.head];
+        self.consume(1);
+        Some(c)
+    }
+
+    /// The bytes from the head which are contiguous in the buffer.
+    pub fn front(&self) -> &[u8] {
+        let end = (self.head + self.len).min(N);
+        &self.buf[self.head..end]
+    }
+
+    /// Drop `n` bytes from the head.
+    pub fn consume(&mut self, n: usize) {
+        let n = n.min(self.len);
+        self.head = (self.head + n) % N;
+        self.len -= n;
+    }
+}
+
+/// Give the UART as many bytes of `ring` as it takes without waiting.
+fn drain<const N: usize>(ring: &mut RingBuffer<N>) {
+    let serial = default_serial();
+    while !ring.is_empty() {
+        let written = serial.write(ring.front());
+        if written == 0 {
+            break;
+        }
+        ring.consume(written);
+    }
+}
+
+/// Lock the transmit ring, or give up if it is taken while panicking.
+fn lock_tx() -> Option<MutexGuard<'static, RingBuffer<TX_RING_SIZE>>> {
+    if is_panicking() {
+        TX_RING.try_lock()
+    } else {
+        Some(TX_RING.lock())
+    }
+}
+
+/// Put `bytes` to the UART, waiting for it to take each one.
+fn write_direct(bytes: &[u8]) {
+    let serial = default_serial();
+    for &c in bytes {
+        serial.putchar(c);
+    }
+}
+
+/// Stop waiting for the locks of the console, called first by the panic handler.
+pub fn set_panicking() {
+    PANICKING.store(true, Ordering::Relaxed);
+}
+
+/// Whether [set_panicking] was called.
+pub fn is_panicking() -> bool {
+    PANICKING.load(Ordering::Relaxed)
+}
+
+/// Give the UART what it takes of the pending output, unless another core is at it.
+pub fn poll() {
+    if let Some(mut ring) = TX_RING.try_lock() {
+        drain(&mut ring);
+    }
+}
+
+/// Wait until the UART has taken all the pending output.
+///
+/// The lock is dropped between the attempts, so the other cores can print meanwhile.
+pub fn flush() {
+    while let Some(mut ring) = lock_tx() {
+        drain(&mut ring);
+        if ring.is_empty() {
+            return;
+        }
+        drop(ring);
+        core::hint::spin_loop();
+    }
+}
+
+/// Enable or disable the interrupt of the UART for the received characters.
+pub fn set_rx_interrupt(enable: bool) {
+    default_serial().set_rx_interrupt(enable);
+}
+
+/// Take the characters the UART has received, called on its interrupt.
+///
+/// Every character is passed to `filter` first, which keeps it out of the ring by returning
+/// false.
+pub fn handle_rx_irq(filter: impl FnMut(u8) -> bool) {
+    poll_rx(filter);
+    default_serial().ack_interrupts();
+}
+
+/// Take the characters the UART has received, for a UART without a usable interrupt.
+pub fn poll_rx(mut filter: impl FnMut(u8) -> bool) {
+    let serial = default_serial();
+    let mut ring = RX_RING.lock();
+    while !serial.rx_fifo_empty() {
+        match serial.getchar() {
+            // the characters past the size of the ring are dropped
+            Some(c) if filter(c) => {
+                ring.push(c);
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+}
+
+/// Get a received character, from the ring or else from the UART itself.
+pub fn getchar() -> Option<u8> {
+    RX_RING.lock().pop().or_else(|| default_serial().getchar())
+}
 
 struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            default_serial().putchar(c as _);
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            let Some(mut ring) = lock_tx() else {
+                write_direct(bytes);
+                break;
+            };
+            while let Some((&c, rest)) = bytes.split_first() {
+                if !ring.push(c) {
+                    break;
+                }
+                bytes = rest;
+            }
+            if !bytes.is_empty() {
+                // only a full ring waits for the UART, and not with the lock held
+                drain(&mut ring);
+                drop(ring);
+                core::hint::spin_loop();
+            }
         }
         Ok(())
     }
@@ -18,6 +202,7 @@ impl Write for Stdout {
 
 pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
+    poll();
 }
 
 /// print string macro
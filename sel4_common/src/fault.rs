@@ -1,6 +1,8 @@
 //! This module defines fault types and related constants for the seL4 microkernel.
 //! It provides bitfield definitions for different fault types, such as NullFault, CapFault,
-//! UnknownSyscall, UserException, and VMFault.
+//! UnknownSyscall, UserException, DebugException and VMFault, along with the VGICMaintenance,
+//! VCPUFault and VPPIEvent faults raised by guests under the hypervisor support. The RISC-V
+//! guests only raise VCPUFault.
 //!
 //! The `FaultType` enum represents the different fault types, and the `seL4_Fault_t` struct
 //! provides methods to get the fault type.
@@ -38,6 +40,11 @@ plus_define_bitfield! {
             address, vm_fault_get_address, vm_fault_set_address, 1, 0, 64, 0, false,
             fsr, vm_fault_get_fsr, vm_fault_set_fsr, 0, 27, 5, 0, false,
             instruction_fault, vm_fault_get_instruction_fault, vm_fault_set_instruction_fault, 0, 19, 1, 0, false
+        },
+        new_vcpu_fault, seL4_Fault_VCPUFault => {
+            address, vcpu_fault_get_address, vcpu_fault_set_address, 1, 0, 64, 0, false,
+            inst, vcpu_fault_get_inst, vcpu_fault_set_inst, 0, 32, 32, 0, false,
+            cause, vcpu_fault_get_cause, vcpu_fault_set_cause, 0, 4, 8, 0, false
         }
     }
 }
@@ -45,7 +52,7 @@ plus_define_bitfield! {
 // TODO: Improve seL4_fault_T type
 // TIPS: This sel4_fault was defined in bitfield file(2words).
 //       sel4_c_impl/include/arch/arm/arch/64/mode/object/structures.bf: VMFault
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 plus_define_bitfield! {
     seL4_Fault_t, 2, 0, 0, 4 => {
         new_null_fault, seL4_Fault_NullFault => {},
@@ -64,6 +71,21 @@ plus_define_bitfield! {
             address, vm_fault_get_address, vm_fault_set_address, 1, 0, 64, 0, false,
             fsr, vm_fault_get_fsr, vm_fault_set_fsr, 0, 32, 32, 0, false,
             instruction_fault, vm_fault_get_instruction_fault, vm_fault_set_instruction_fault, 0, 31, 1, 0, false
+        },
+        new_debug_exception, seL4_Fault_DebugException => {
+            breakpoint_address, debug_exception_get_breakpoint_address, debug_exception_set_breakpoint_address, 1, 0, 64, 0, false,
+            breakpoint_number, debug_exception_get_breakpoint_number, debug_exception_set_breakpoint_number, 0, 16, 8, 0, false,
+            exception_reason, debug_exception_get_exception_reason, debug_exception_set_exception_reason, 0, 8, 3, 0, false
+        },
+        new_vgic_maintenance, seL4_Fault_VGICMaintenance => {
+            idx, vgic_maintenance_get_idx, vgic_maintenance_set_idx, 0, 58, 6, 0, false,
+            idx_valid, vgic_maintenance_get_idx_valid, vgic_maintenance_set_idx_valid, 0, 57, 1, 0, false
+        },
+        new_vcpu_fault, seL4_Fault_VCPUFault => {
+            hsr, vcpu_fault_get_hsr, vcpu_fault_set_hsr, 0, 32, 32, 0, false
+        },
+        new_vppi_event, seL4_Fault_VPPIEvent => {
+            irq, vppi_event_get_irq, vppi_event_set_irq, 1, 0, 64, 0, false
         }
     }
 }
@@ -74,7 +96,11 @@ pub enum FaultType {
     CapFault = 1,
     UnknownSyscall = 2,
     UserException = 3,
+    DebugException = 4,
     VMFault = 5,
+    VGICMaintenance = 6,
+    VCPUFault = 7,
+    VPPIEvent = 8,
 }
 
 impl seL4_Fault_t {
@@ -88,6 +114,10 @@ pub const seL4_Fault_CapFault: usize = FaultType::CapFault as usize;
 pub const seL4_Fault_UnknownSyscall: usize = FaultType::UnknownSyscall as usize;
 pub const seL4_Fault_UserException: usize = FaultType::UserException as usize;
 pub const seL4_Fault_VMFault: usize = FaultType::VMFault as usize;
+pub const seL4_Fault_DebugException: usize = FaultType::DebugException as usize;
+pub const seL4_Fault_VGICMaintenance: usize = FaultType::VGICMaintenance as usize;
+pub const seL4_Fault_VCPUFault: usize = FaultType::VCPUFault as usize;
+pub const seL4_Fault_VPPIEvent: usize = FaultType::VPPIEvent as usize;
 
 //seL4_VMFault_Msg
 pub const seL4_VMFault_IP: usize = 0;
@@ -96,6 +126,37 @@ pub const seL4_VMFault_PrefetchFault: usize = 2;
 pub const seL4_VMFault_FSR: usize = 3;
 pub const seL4_VMFault_Length: usize = 4;
 
+//seL4_DebugException_Msg
+pub const seL4_DebugException_FaultIP: usize = 0;
+pub const seL4_DebugException_ExceptionReason: usize = 1;
+pub const seL4_DebugException_TriggerAddress: usize = 2;
+pub const seL4_DebugException_BreakpointNumber: usize = 3;
+pub const seL4_DebugException_Length: usize = 4;
+
+//seL4_VGICMaintenance_Msg
+pub const seL4_VGICMaintenance_IDX: usize = 0;
+pub const seL4_VGICMaintenance_Length: usize = 1;
+
+//seL4_VCPUFault_Msg
+#[cfg(not(target_arch = "riscv64"))]
+pub const seL4_VCPUFault_HSR: usize = 0;
+#[cfg(not(target_arch = "riscv64"))]
+pub const seL4_VCPUFault_Length: usize = 1;
+#[cfg(target_arch = "riscv64")]
+pub const seL4_VCPUFault_Cause: usize = 0;
+/// The guest physical address of a guest-page fault, stval for the other causes
+#[cfg(target_arch = "riscv64")]
+pub const seL4_VCPUFault_Addr: usize = 1;
+/// htinst, the trapped instruction as the hardware transformed it, 0 if it gives none
+#[cfg(target_arch = "riscv64")]
+pub const seL4_VCPUFault_Inst: usize = 2;
+#[cfg(target_arch = "riscv64")]
+pub const seL4_VCPUFault_Length: usize = 3;
+
+//seL4_VPPIEvent_Msg
+pub const seL4_VPPIEvent_IRQ: usize = 0;
+pub const seL4_VPPIEvent_Length: usize = 1;
+
 pub const seL4_CapFault_IP: usize = 0;
 pub const seL4_CapFault_Addr: usize = 1;
 pub const seL4_CapFault_InRecvPhase: usize = 2;
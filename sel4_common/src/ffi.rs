@@ -9,7 +9,7 @@ pub static mut kernel_stack_alloc: kernel_stack_alloc_data = kernel_stack_alloc_
     data: [[0_u8; BIT!(CONFIG_KERNEL_STACK_BITS)]; CONFIG_MAX_NUM_NODES],
 };
 #[cfg(feature = "ENABLE_SMP")]
-/// This function is used to map the core.
 extern "C" {
+    /// This function is used to map the core.
     pub fn coreMap();
 }
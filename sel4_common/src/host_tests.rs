@@ -0,0 +1,45 @@
+//! Console ring buffer and utils tests run by `cargo host-test` on the host backend.
+use crate::console::RingBuffer;
+use crate::utils::ticks_to_cycles;
+
+#[test]
+fn ring_buffer_is_fifo() {
+    let mut ring = RingBuffer::<4>::new();
+    assert!(ring.is_empty());
+    assert!(ring.push(1) && ring.push(2) && ring.push(3) && ring.push(4));
+    assert!(!ring.push(5));
+    assert_eq!(ring.pop(), Some(1));
+    assert_eq!(ring.pop(), Some(2));
+    assert!(ring.push(5));
+    assert_eq!(ring.pop(), Some(3));
+    assert_eq!(ring.pop(), Some(4));
+    assert_eq!(ring.pop(), Some(5));
+    assert_eq!(ring.pop(), None);
+}
+
+#[test]
+fn ring_buffer_front_stops_at_the_wrap() {
+    let mut ring = RingBuffer::<4>::new();
+    for c in 0..4 {
+        ring.push(c);
+    }
+    ring.consume(3);
+    ring.push(4);
+    ring.push(5);
+    assert_eq!(ring.front(), &[3]);
+    ring.consume(1);
+    assert_eq!(ring.front(), &[4, 5]);
+    ring.consume(10);
+    assert!(ring.is_empty());
+    assert_eq!(ring.front(), &[] as &[u8]);
+}
+
+#[test]
+fn ticks_to_cycles_test() {
+    assert_eq!(ticks_to_cycles(62_500_000, 10), 625_000);
+    assert_eq!(ticks_to_cycles(10_000_000, 2), 20_000);
+    assert_eq!(ticks_to_cycles(19_200_000, 1), 19_200);
+    // frequencies that are not a multiple of 1kHz keep their precision
+    assert_eq!(ticks_to_cycles(32_768, 10), 327);
+    assert_eq!(ticks_to_cycles(62_500_000, 0), 0);
+}
@@ -7,11 +7,21 @@
 #![allow(non_upper_case_globals)]
 #![allow(clippy::enum_clike_unportable_variant)]
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+#[cfg(all(feature = "HARDWARE_DEBUG_API", not(target_arch = "aarch64")))]
+compile_error!("HARDWARE_DEBUG_API is only implemented on aarch64");
+#[cfg(all(feature = "ARM_HYPERVISOR_SUPPORT", not(target_arch = "aarch64")))]
+compile_error!("ARM_HYPERVISOR_SUPPORT is only implemented on aarch64");
+#[cfg(all(feature = "RISCV_HYPERVISOR_SUPPORT", not(target_arch = "riscv64")))]
+compile_error!("RISCV_HYPERVISOR_SUPPORT is only implemented on riscv64");
+
 pub mod arch;
 pub mod cap_rights;
 pub mod console;
 pub mod fault;
 pub mod ffi;
+#[cfg(all(test, not(any(target_arch = "riscv64", target_arch = "aarch64"))))]
+mod host_tests;
 pub mod logging;
 pub mod message_info;
 pub mod object;
@@ -24,9 +34,9 @@ pub mod vm_rights;
 
 /// The ffi_call macro, It declares the function and call it
 ///
-/// Usage:
+/// Usage, not compiled as a doctest because the called functions are only defined by the kernel:
 ///
-/// ```rust
+/// ```text
 /// ffi_call!(map_kernel_devices);
 ///
 /// // call with arguments
@@ -11,9 +11,18 @@ use log::{self, Level, LevelFilter, Log, Metadata, Record};
 use super::utils::cpu_id;
 use crate::println;
 
-use spin::Mutex;
+use spin::{Mutex, MutexGuard};
 
 static LOG_MUTEX: Mutex<()> = Mutex::new(());
+
+/// Take the log lock, unless it is taken while panicking: the holder may be the panicking core.
+fn lock_log() -> Option<MutexGuard<'static, ()>> {
+    if crate::console::is_panicking() {
+        LOG_MUTEX.try_lock()
+    } else {
+        Some(LOG_MUTEX.lock())
+    }
+}
 struct SimpleLogger;
 
 impl Log for SimpleLogger {
@@ -21,7 +30,7 @@ impl Log for SimpleLogger {
         true
     }
     fn log(&self, record: &Record) {
-        let _lock = LOG_MUTEX.lock();
+        let _lock = lock_log();
         if !self.enabled(record.metadata()) {
             return;
         }
@@ -41,7 +50,8 @@ impl Log for SimpleLogger {
         );
     }
     fn flush(&self) {
-        let _lock = LOG_MUTEX.lock();
+        let _lock = lock_log();
+        crate::console::flush();
     }
 }
 
@@ -7,14 +7,19 @@
 //! Creating a new `seL4_MessageInfo_t` from a word:
 //!
 //! ```
-//! let word = 0x12345678;
+//! use sel4_common::message_info::seL4_MessageInfo_t;
+//!
+//! let word = (1 << 12) | 2;
 //! let message_info = seL4_MessageInfo_t::from_word(word);
 //! ```
 //!
-//! Getting the label of the message:
+//! Getting the label and the length of the message:
 //!
 //! ```
-//! let label = message_info.get_label();
+//! # use sel4_common::message_info::seL4_MessageInfo_t;
+//! # let message_info = seL4_MessageInfo_t::from_word((1 << 12) | 2);
+//! assert_eq!(message_info.get_usize_label(), 1);
+//! assert_eq!(message_info.get_length(), 2);
 //! ```
 
 use super::sel4_config::seL4_MsgMaxLength;
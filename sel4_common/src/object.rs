@@ -7,12 +7,20 @@ use crate::arch::ObjectType;
 
 use super::sel4_config::*;
 
-#[cfg(target_arch = "riscv64")]
+#[cfg(all(target_arch = "riscv64", not(feature = "RISCV_HYPERVISOR_SUPPORT")))]
 pub const seL4_ObjectTypeCount: usize = ObjectType::PageTableObject as usize + 1;
+#[cfg(all(target_arch = "riscv64", feature = "RISCV_HYPERVISOR_SUPPORT"))]
+pub const seL4_ObjectTypeCount: usize = ObjectType::VCPUObject as usize + 1;
 // FIXED: Need to add 1 to cover all possible object types
-#[cfg(any(target_arch = "aarch64", test))]
+#[cfg(all(not(target_arch = "riscv64"), not(feature = "ARM_HYPERVISOR_SUPPORT")))]
 pub const seL4_ObjectTypeCount: usize = ObjectType::seL4_ARM_PageTableObject as usize + 1;
-pub const seL4_NonArchObjectTypeCount: usize = ObjectType::CapTableObject as usize + 1;
+#[cfg(all(not(target_arch = "riscv64"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub const seL4_ObjectTypeCount: usize = ObjectType::seL4_ARM_VCPUObject as usize + 1;
+#[cfg(not(feature = "KERNEL_MCS"))]
+pub const seL4_NonArchObjectTypeCount: usize = 5;
+/// The scheduling context and reply objects come before the arch objects
+#[cfg(feature = "KERNEL_MCS")]
+pub const seL4_NonArchObjectTypeCount: usize = 7;
 
 impl ObjectType {
     /// Returns the size of the object based on its type.
@@ -34,6 +42,10 @@ impl ObjectType {
             ObjectType::EndpointObject => seL4_EndpointBits,
             ObjectType::NotificationObject => seL4_NotificationBits,
             ObjectType::CapTableObject => seL4_SlotBits + user_object_size,
+            #[cfg(feature = "KERNEL_MCS")]
+            ObjectType::SchedContextObject => user_object_size,
+            #[cfg(feature = "KERNEL_MCS")]
+            ObjectType::ReplyObject => seL4_ReplyBits,
             _ => panic!("unsupported cap type:{}", (*self) as usize),
         }
     }
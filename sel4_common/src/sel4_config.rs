@@ -6,7 +6,10 @@ pub const wordBits: usize = BIT!(wordRadix);
 pub const seL4_EndpointBits: usize = 4;
 pub const seL4_NotificationBits: usize = 4;
 pub const seL4_SlotBits: usize = 5;
+#[cfg(not(feature = "KERNEL_MCS"))]
 pub const seL4_ReplyBits: usize = 4;
+#[cfg(feature = "KERNEL_MCS")]
+pub const seL4_ReplyBits: usize = 5;
 pub const seL4_MinUntypedBits: usize = 4;
 pub const seL4_MaxUntypedBits: usize = 38;
 
@@ -47,6 +50,12 @@ pub const seL4_LargePageBits: usize = 21;
 
 pub const seL4_PML4Bits: usize = 12;
 pub const seL4_VSpaceBits: usize = seL4_PML4Bits;
+/// A VCPU object takes a whole page
+#[cfg(any(
+    feature = "ARM_HYPERVISOR_SUPPORT",
+    feature = "RISCV_HYPERVISOR_SUPPORT"
+))]
+pub const seL4_VCPUBits: usize = 12;
 
 pub const PT_INDEX_OFFSET: usize = seL4_PageBits;
 pub const PD_INDEX_OFFSET: usize = PT_INDEX_OFFSET + PT_INDEX_BITS;
@@ -67,17 +76,56 @@ pub const IT_ASID: usize = 1;
 pub const L1_CACHE_LINE_SIZE_BITS: usize = 6;
 
 // scheduler relevant
+/// The domain schedule, a comma separated list of `domain:length` entries with the length in
+/// timer ticks, or in milliseconds for MCS which has no periodic tick, e.g.
+/// `DOMAIN_SCHEDULE="0:60,1:30" cargo build --features ENABLE_DOMAINS`.
+pub const DOMAIN_SCHEDULE: &str = match option_env!("DOMAIN_SCHEDULE") {
+    Some(schedule) => schedule,
+    None => "0:60",
+};
+#[cfg(not(feature = "ENABLE_DOMAINS"))]
 pub const CONFIG_NUM_DOMAINS: usize = 1;
+/// The domains up to the highest one of the schedule
+#[cfg(feature = "ENABLE_DOMAINS")]
+pub const CONFIG_NUM_DOMAINS: usize = dom_schedule_num_domains(DOMAIN_SCHEDULE);
 pub const CONFIG_NUM_PRIORITIES: usize = 256;
 pub const L2_BITMAP_SIZE: usize = (CONFIG_NUM_PRIORITIES + wordBits - 1) / wordBits;
 pub const NUM_READY_QUEUES: usize = CONFIG_NUM_DOMAINS * CONFIG_NUM_PRIORITIES;
 pub const CONFIG_TIME_SLICE: usize = 5;
 
+// MCS relevant
+/// Budget of the root thread in milliseconds
+pub const CONFIG_BOOT_THREAD_TIME_SLICE: usize = 5;
+/// Worst case execution time of the kernel in microseconds
+pub const CONFIG_KERNEL_WCET_US: usize = 10;
+/// The smallest budget of a scheduling context, enough to enter and leave the kernel
+pub const MIN_BUDGET_US: usize = 2 * CONFIG_KERNEL_WCET_US;
+/// One hour in microseconds, keeps the refill arithmetic far from overflowing
+pub const MAX_PERIOD_US: usize = 60 * 60 * 1000 * 1000;
+pub const seL4_MinSchedContextBits: usize = 8;
+pub const MIN_REFILLS: usize = 2;
+pub const seL4_SchedContext_NoFlag: usize = 0;
+pub const seL4_SchedContext_Sporadic: usize = 1;
+
 // TCB relevant
-#[cfg(target_arch = "riscv64")]
-pub const seL4_TCBBits: usize = 10;
-#[cfg(any(target_arch = "aarch64", test))]
-pub const seL4_TCBBits: usize = 11;
+#[cfg(all(target_arch = "riscv64", not(feature = "HAVE_FPU")))]
+const TCB_ARCH_BITS: usize = 10;
+/// The FP registers don't fit in a 1KiB TCB
+#[cfg(not(any(
+    all(target_arch = "riscv64", not(feature = "HAVE_FPU")),
+    all(target_arch = "aarch64", feature = "HARDWARE_DEBUG_API")
+)))]
+const TCB_ARCH_BITS: usize = 11;
+/// Nor do the breakpoint registers fit in a 1KiB TCB next to them
+#[cfg(all(target_arch = "aarch64", feature = "HARDWARE_DEBUG_API"))]
+const TCB_ARCH_BITS: usize = 12;
+/// The thread name of a debug build and the utilisation counters don't fit together next to the
+/// registers, the TCB is doubled for them
+pub const seL4_TCBBits: usize = TCB_ARCH_BITS
+    + cfg!(all(
+        feature = "DEBUG_BUILD",
+        feature = "BENCHMARK_TRACK_UTILISATION"
+    )) as usize;
 pub const TCB_SIZE_BITS: usize = seL4_TCBBits - 1;
 pub const TCB_OFFSET: usize = BIT!(TCB_SIZE_BITS);
 pub const tcbCTable: usize = 0;
@@ -123,3 +171,28 @@ pub const CONFIG_KERNEL_STACK_BITS: usize = 12;
 
 pub const ID_AA64PFR0_EL1_FP: u32 = 16;
 pub const ID_AA64PFR0_EL1_ASIMD: u32 = 20;
+
+/// Get one more than the highest domain of the domain schedule, which is checked when parsed.
+#[cfg(feature = "ENABLE_DOMAINS")]
+const fn dom_schedule_num_domains(schedule: &str) -> usize {
+    let bytes = schedule.as_bytes();
+    let mut num_domains = 1;
+    let mut value = 0;
+    let mut in_domain = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b':' {
+            if in_domain && value >= num_domains {
+                num_domains = value + 1;
+            }
+            in_domain = false;
+        } else if bytes[i] == b',' {
+            in_domain = true;
+            value = 0;
+        } else if in_domain && bytes[i].is_ascii_digit() {
+            value = value * 10 + (bytes[i] - b'0') as usize;
+        }
+        i += 1;
+    }
+    num_domains
+}
@@ -1,5 +1,9 @@
-use super::sel4_config::{CONFIG_KERNEL_STACK_BITS, CONFIG_MAX_NUM_NODES};
-use crate::deps::{coreMap, kernel_stack_alloc};
+#[cfg(target_arch = "riscv64")]
+use super::sel4_config::CONFIG_KERNEL_STACK_BITS;
+use super::sel4_config::CONFIG_MAX_NUM_NODES;
+use crate::ffi::coreMap;
+#[cfg(target_arch = "riscv64")]
+use crate::ffi::kernel_stack_alloc;
 use crate::BIT;
 use core::arch::asm;
 
@@ -11,16 +15,14 @@ fn get_core_map_ref() -> &'static [usize; CONFIG_MAX_NUM_NODES] {
 #[inline]
 pub fn cpu_index_to_id(index: usize) -> usize {
     assert!(index < CONFIG_MAX_NUM_NODES);
-    unsafe { get_core_map_ref()[index] }
+    get_core_map_ref()[index]
 }
 
 #[inline]
 pub fn hart_id_to_core_id(hart_id: usize) -> usize {
-    unsafe {
-        match get_core_map_ref().iter().position(|&x| x == hart_id) {
-            Some(core_id) => core_id,
-            _ => 0,
-        }
+    match get_core_map_ref().iter().position(|&x| x == hart_id) {
+        Some(core_id) => core_id,
+        _ => 0,
     }
 }
 
@@ -33,7 +35,7 @@ pub fn get_currenct_cpu_index() -> usize {
         "csrr {}, sscratch",
         out(reg) cur_sp,
         );
-        cur_sp -= unsafe { &kernel_stack_alloc.data[0][0] as *const u8 } as usize + 8;
+        cur_sp -= &kernel_stack_alloc.data[0][0] as *const u8 as usize + 8;
         cur_sp >> CONFIG_KERNEL_STACK_BITS
     }
     #[cfg(target_arch = "aarch64")]
@@ -37,7 +37,7 @@ macro_rules! plus_define_bitfield {
                         if $sign_ext && (ret & (1usize << 38)) != 0 {
                             ret |= 0xffffff8000000000;
                         }
-                        #[cfg(target_arch = "aarch64")]
+                        #[cfg(not(target_arch = "riscv64"))]
                         if $sign_ext && (ret & (1usize << 47)) != 0 {
                             ret |= 0xffff800000000000;
                         }
@@ -211,3 +211,14 @@ pub fn pageBitsForSize(page_size: usize) -> usize {
         _ => panic!("Invalid page size!"),
     }
 }
+
+/// Convert a tick period to the cycles of a timer.
+/// # Arguments
+/// * `clock_hz` - The frequency of the timer.
+/// * `tick_ms` - The tick period in milliseconds.
+/// # Returns
+/// The timer cycles of one tick.
+pub const fn ticks_to_cycles(clock_hz: usize, tick_ms: usize) -> usize {
+    // multiply first, a frequency that is not a multiple of 1kHz keeps its precision
+    clock_hz * tick_ms / 1000
+}
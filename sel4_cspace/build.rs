@@ -0,0 +1,13 @@
+//! Link the bare-metal test binaries with the linker script of their target. The script is given
+//! by its full path, rustc runs from the workspace root.
+use std::env;
+
+fn main() {
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    if os == "none" && (arch == "riscv64" || arch == "aarch64") {
+        let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rustc-link-arg=-T{}/linker-{}.ld", dir, arch);
+        println!("cargo:rerun-if-changed=linker-{}.ld", arch);
+    }
+}
@@ -19,11 +19,14 @@ pub enum CapTag {
     CapIrqHandlerCap = 16,
     CapZombieCap = 18,
     CapDomainCap = 20,
+    CapSchedContextCap = 22,
+    CapSchedControlCap = 24,
     CapFrameCap = 1,
     CapPageTableCap = 3,
     CapVspaceCap = 9,
     CapASIDControlCap = 11,
     CapASIDPoolCap = 13,
+    CapVCPUCap = 15,
 }
 
 plus_define_bitfield! {
@@ -72,6 +75,13 @@ plus_define_bitfield! {
             capZombieType, get_zombie_type, set_zombie_type, 0, 0, 7, 0, false
         },
         new_domain_cap, CapTag::CapDomainCap as usize => {},
+        new_sched_context_cap, CapTag::CapSchedContextCap as usize => {
+            capSCSizeBits, get_sc_size_bits, set_sc_size_bits, 1, 0, 6, 0, false,
+            capSCPtr, get_sc_ptr, set_sc_ptr, 0, 0, 48, 0, true
+        },
+        new_sched_control_cap, CapTag::CapSchedControlCap as usize => {
+            core, get_sched_control_core, set_sched_control_core, 1, 0, 64, 0, false
+        },
         new_frame_cap, CapTag::CapFrameCap as usize => {
             capFIsDevice, get_frame_is_device,set_frame_is_device, 0, 6, 1, 0, false,
             capFVMRights,get_frame_vm_rights, set_frame_vm_rights, 0, 7, 2, 0, false,
@@ -114,6 +124,9 @@ plus_define_bitfield! {
             capASIDBase, get_asid_base, set_asid_base, 0, 43, 16, 0, false,
             // FIXED: asid_pool need to shift left 11 bits.
             capASIDPool, get_asid_pool, set_asid_pool, 0, 0, 37, 11, true
+        },
+        new_vcpu_cap, CapTag::CapVCPUCap as usize => {
+            capVCPUPtr, get_vcpu_ptr, set_vcpu_ptr, 0, 0, 48, 0, true
         }
     }
 }
@@ -127,6 +140,9 @@ impl cap_t {
             CapTag::CapCNodeCap => self.get_cnode_ptr(),
             CapTag::CapThreadCap => self.get_tcb_ptr(),
             CapTag::CapZombieCap => self.get_zombie_ptr(),
+            CapTag::CapSchedContextCap => self.get_sc_ptr(),
+            #[cfg(feature = "KERNEL_MCS")]
+            CapTag::CapReplyCap => self.get_reply_tcb_ptr(),
             CapTag::CapFrameCap => self.get_frame_base_ptr(),
             CapTag::CapPageTableCap => self.get_pt_base_ptr(),
             CapTag::CapVspaceCap => self.get_vs_base_ptr(),
@@ -135,6 +151,7 @@ impl cap_t {
             // CapTag::CapPageGlobalDirectoryCap => self.get_pgd_base_ptr(),
             CapTag::CapASIDControlCap => 0,
             CapTag::CapASIDPoolCap => self.get_asid_pool(),
+            CapTag::CapVCPUCap => self.get_vcpu_ptr(),
             _ => 0,
         }
     }
@@ -212,7 +229,7 @@ impl cte_t {
                 newCap.set_frame_mapped_asid(0);
                 ret.cap = newCap;
             }
-            CapTag::CapASIDControlCap | CapTag::CapASIDPoolCap => {
+            CapTag::CapASIDControlCap | CapTag::CapASIDPoolCap | CapTag::CapVCPUCap => {
                 ret.cap = cap.clone();
             }
             _ => {
@@ -264,6 +281,11 @@ pub fn arch_same_region_as(cap1: &cap_t, cap2: &cap_t) -> bool {
                 return cap1.get_asid_pool() == cap2.get_asid_pool();
             }
         }
+        CapTag::CapVCPUCap => {
+            if cap2.get_cap_type() == CapTag::CapVCPUCap {
+                return cap1.get_vcpu_ptr() == cap2.get_vcpu_ptr();
+            }
+        }
         _ => panic!("unknown cap"),
     }
     false
@@ -4,8 +4,9 @@ mod riscv64;
 #[cfg(target_arch = "riscv64")]
 pub use riscv64::*;
 
-#[cfg(target_arch = "aarch64")]
+// The host backend of the unit tests shares the caps of aarch64
+#[cfg(not(target_arch = "riscv64"))]
 mod aarch64;
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 pub use aarch64::*;
@@ -5,7 +5,7 @@
 //!
 //! 记录在阅读代码段过程中用到的`cap`的特定字段含义：
 //!
-//! ```
+//! ```text
 //! untyped_cap:
 //!  - capFreeIndex：从capPtr到可用的块的偏移，单位是2^seL4_MinUntypedBits大小的块数。如果seL4_MinUntypedBits是4，那么2^seL4_MinUntypedBits就是16字节。如果一个64字节的内存块已经分配了前32字节，则CapFreeIndex会存储2，因为已经使用了2个16字节的块。
 //!  - capBlockSize：当前untyped块中剩余空间大小
@@ -142,6 +142,13 @@ impl cap_t {
     }
 
     pub fn get_cap_size_bits(&self) -> usize {
+        #[cfg(any(
+            feature = "ARM_HYPERVISOR_SUPPORT",
+            feature = "RISCV_HYPERVISOR_SUPPORT"
+        ))]
+        if self.get_cap_type() == CapTag::CapVCPUCap {
+            return seL4_VCPUBits;
+        }
         match self.get_cap_type() {
             CapTag::CapUntypedCap => self.get_untyped_block_size(),
             CapTag::CapEndpointCap => seL4_EndpointBits,
@@ -149,11 +156,23 @@ impl cap_t {
             CapTag::CapCNodeCap => self.get_cnode_radix() + seL4_SlotBits,
             CapTag::CapPageTableCap => PT_SIZE_BITS,
             CapTag::CapReplyCap => seL4_ReplyBits,
+            CapTag::CapSchedContextCap => self.get_sc_size_bits(),
             _ => 0,
         }
     }
 
     pub fn get_cap_is_physical(&self) -> bool {
+        #[cfg(feature = "KERNEL_MCS")]
+        if self.get_cap_type() == CapTag::CapReplyCap {
+            return true;
+        }
+        #[cfg(any(
+            feature = "ARM_HYPERVISOR_SUPPORT",
+            feature = "RISCV_HYPERVISOR_SUPPORT"
+        ))]
+        if self.get_cap_type() == CapTag::CapVCPUCap {
+            return true;
+        }
         matches!(
             self.get_cap_type(),
             CapTag::CapUntypedCap
@@ -165,9 +184,17 @@ impl cap_t {
                 | CapTag::CapPageTableCap
                 | CapTag::CapZombieCap
                 | CapTag::CapThreadCap
+                | CapTag::CapSchedContextCap
         )
     }
 
+    /// The reply object of a reply cap, kept where the non-MCS reply cap keeps its TCB
+    #[cfg(feature = "KERNEL_MCS")]
+    #[inline]
+    pub fn get_reply_ptr(&self) -> usize {
+        self.get_reply_tcb_ptr()
+    }
+
     pub fn isArchCap(&self) -> bool {
         self.get_cap_type() as usize % 2 != 0
     }
@@ -199,7 +226,21 @@ pub fn same_region_as(cap1: &cap_t, cap2: &cap_t) -> bool {
             }
             false
         }
-        CapTag::CapASIDControlCap | CapTag::CapDomainCap => {
+        #[cfg(feature = "KERNEL_MCS")]
+        CapTag::CapReplyCap => {
+            if cap2.get_cap_type() == CapTag::CapReplyCap {
+                return cap1.get_reply_ptr() == cap2.get_reply_ptr();
+            }
+            false
+        }
+        CapTag::CapSchedContextCap => {
+            if cap2.get_cap_type() == CapTag::CapSchedContextCap {
+                return (cap1.get_sc_ptr() == cap2.get_sc_ptr())
+                    && (cap1.get_sc_size_bits() == cap2.get_sc_size_bits());
+            }
+            false
+        }
+        CapTag::CapASIDControlCap | CapTag::CapDomainCap | CapTag::CapSchedControlCap => {
             if cap2.get_cap_type() == cap1.get_cap_type() {
                 return true;
             }
@@ -62,6 +62,7 @@ impl cte_t {
                     ret.cap = cap.clone();
                 }
             }
+            #[cfg(not(feature = "KERNEL_MCS"))]
             CapTag::CapReplyCap => {
                 ret.cap = cap_t::new_null_cap();
             }
@@ -85,7 +86,7 @@ impl cte_t {
         exception_t::EXCEPTION_NONE
     }
     /// 判断当前`cte`是否为`next`节点的父节点（除了父节点，还有兄弟节点的关系可能）
-    fn is_mdb_parent_of(&self, next: &Self) -> bool {
+    pub fn is_mdb_parent_of(&self, next: &Self) -> bool {
         if self.cteMDBNode.get_revocable() == 0 {
             return false;
         }
@@ -297,14 +298,14 @@ impl cte_t {
         }
     }
 
-    #[cfg(target_arch = "aarch64")]
+    #[cfg(not(target_arch = "riscv64"))]
     #[inline]
     fn get_volatile_value(&self) -> usize {
         unsafe {
             let raw_value = ptr::read_volatile((self.get_ptr() + 24) as *const usize);
             let mut value = ((raw_value >> 2) & MASK!(46)) << 2;
-            if (value & (1usize << 46)) != 0 {
-                value |= 0xffffff8000000000;
+            if (value & (1usize << 47)) != 0 {
+                value |= 0xffff800000000000;
             }
             value
         }
@@ -0,0 +1,16 @@
+    .section .text.entry
+    .globl _start
+_start:
+    ldr x0, =boot_stack_top
+    mov sp, x0
+    bl call_test_main
+1:
+    wfe
+    b 1b
+
+    .section .bss.stack
+    .globl boot_stack_lower_bound
+boot_stack_lower_bound:
+    .space 4096 * 16
+    .globl boot_stack_top
+boot_stack_top:
@@ -0,0 +1,155 @@
+//! MDB and revoke tests run by `cargo host-test` on the host backend.
+//!
+//! The kernel side of the revoke is the one of [crate::test_deps], whose state
+//! is global, so the cases that revoke hold the kernel state lock.
+extern crate std;
+
+use std::boxed::Box;
+use std::vec::Vec;
+
+use crate::arch::{cap_t, CapTag};
+use crate::cte::{cte_insert, cte_move, cte_swap, cte_t, insert_new_cap};
+use crate::mdb::mdb_node_t;
+use crate::test_deps::{finalised_caps, reset, set_irq_pending, WORK_UNITS_PER_PREEMPTION};
+use sel4_common::arch::host_lock_kernel_state;
+use sel4_common::structures::exception_t;
+
+fn null_slot() -> cte_t {
+    cte_t {
+        cap: cap_t::new_null_cap(),
+        cteMDBNode: mdb_node_t::new(0, 0, 0, 0),
+    }
+}
+
+/// Slots handed to the MDB must not move, so they live on the heap
+fn new_slots(n: usize) -> Box<[cte_t]> {
+    (0..n)
+        .map(|_| null_slot())
+        .collect::<Vec<_>>()
+        .into_boxed_slice()
+}
+
+fn ptr(slot: &cte_t) -> usize {
+    slot as *const cte_t as usize
+}
+
+#[test]
+fn cte_insert_links_the_new_slot_after_the_source() {
+    let mut slots = new_slots(3);
+    let [a, b, c] = &mut *slots else {
+        unreachable!()
+    };
+    cte_insert(&cap_t::new_asid_control_cap(), a, b);
+    cte_insert(&cap_t::new_domain_cap(), b, c);
+    assert_eq!(b.cap.get_cap_type(), CapTag::CapASIDControlCap);
+    assert_eq!(c.cap.get_cap_type(), CapTag::CapDomainCap);
+    assert_eq!(a.cteMDBNode.get_next(), ptr(b));
+    assert_eq!(b.cteMDBNode.get_next(), ptr(c));
+    assert_eq!(b.cteMDBNode.get_prev(), ptr(a));
+    assert_eq!(c.cteMDBNode.get_prev(), ptr(b));
+}
+
+#[test]
+fn cte_move_takes_over_the_mdb_links() {
+    let mut slots = new_slots(4);
+    let [a, b, c, d] = &mut *slots else {
+        unreachable!()
+    };
+    cte_insert(&cap_t::new_asid_control_cap(), a, b);
+    cte_insert(&cap_t::new_domain_cap(), b, c);
+    cte_move(&cap_t::new_irq_control_cap(), b, d);
+    assert_eq!(b.cap.get_cap_type(), CapTag::CapNullCap);
+    assert_eq!(b.cteMDBNode.get_next(), 0);
+    assert_eq!(b.cteMDBNode.get_prev(), 0);
+    assert_eq!(d.cap.get_cap_type(), CapTag::CapIrqControlCap);
+    assert_eq!(d.cteMDBNode.get_prev(), ptr(a));
+    assert_eq!(d.cteMDBNode.get_next(), ptr(c));
+    assert_eq!(a.cteMDBNode.get_next(), ptr(d));
+    assert_eq!(c.cteMDBNode.get_prev(), ptr(d));
+}
+
+#[test]
+fn cte_swap_exchanges_caps_and_links() {
+    let mut slots = new_slots(4);
+    let [a, b, c, d] = &mut *slots else {
+        unreachable!()
+    };
+    let cap1 = cap_t::new_asid_control_cap();
+    let cap2 = cap_t::new_domain_cap();
+    cte_insert(&cap1, a, b);
+    cte_insert(&cap2, c, d);
+    cte_swap(&cap1, b, &cap2, d);
+    assert_eq!(b.cap.get_cap_type(), CapTag::CapDomainCap);
+    assert_eq!(d.cap.get_cap_type(), CapTag::CapASIDControlCap);
+    assert_eq!(a.cteMDBNode.get_next(), ptr(d));
+    assert_eq!(d.cteMDBNode.get_prev(), ptr(a));
+    assert_eq!(c.cteMDBNode.get_next(), ptr(b));
+    assert_eq!(b.cteMDBNode.get_prev(), ptr(c));
+}
+
+#[test]
+fn derived_caps_are_children_of_their_parent() {
+    let mut slots = new_slots(3);
+    let [parent, child, other] = &mut *slots else {
+        unreachable!()
+    };
+    let ep_cap = cap_t::new_endpoint_cap(0, 1, 1, 1, 1, 0x8400_0000);
+    parent.cap = ep_cap;
+    parent.cteMDBNode = mdb_node_t::new(0, 1, 1, 0);
+    insert_new_cap(parent, child, &ep_cap);
+    assert!(parent.is_mdb_parent_of(child));
+    assert_eq!(
+        parent.ensure_no_children(),
+        exception_t::EXCEPTION_SYSCALL_ERROR
+    );
+
+    other.cap = cap_t::new_endpoint_cap(0, 1, 1, 1, 1, 0x8500_0000);
+    assert!(!parent.is_mdb_parent_of(other));
+}
+
+#[test]
+fn revoke_deletes_every_child() {
+    let _ks = host_lock_kernel_state();
+    reset();
+    let mut parent = Box::new(null_slot());
+    let mut children = new_slots(16);
+    let ep_cap = cap_t::new_endpoint_cap(0, 1, 1, 1, 1, 0x8400_0000);
+    parent.cap = ep_cap;
+    parent.cteMDBNode = mdb_node_t::new(0, 1, 1, 0);
+    for child in children.iter_mut() {
+        insert_new_cap(&mut parent, child, &ep_cap);
+    }
+    assert_eq!(parent.revoke(), exception_t::EXCEPTION_NONE);
+    assert_eq!(parent.cteMDBNode.get_next(), 0);
+    assert_eq!(parent.cap.get_cap_type(), CapTag::CapEndpointCap);
+    assert!(children
+        .iter()
+        .all(|child| child.cap.get_cap_type() == CapTag::CapNullCap));
+}
+
+#[test]
+fn revoke_stops_at_a_pending_interrupt_and_restarts() {
+    let _ks = host_lock_kernel_state();
+    const REVOKE_CHILDREN: usize = 1024;
+    let mut parent = Box::new(null_slot());
+    let mut children = new_slots(REVOKE_CHILDREN);
+    let ep_cap = cap_t::new_endpoint_cap(0, 1, 1, 1, 1, 0x8400_0000);
+    parent.cap = ep_cap;
+    parent.cteMDBNode = mdb_node_t::new(0, 1, 1, 0);
+    for child in children.iter_mut() {
+        insert_new_cap(&mut parent, child, &ep_cap);
+    }
+
+    reset();
+    set_irq_pending(true);
+    assert_eq!(parent.revoke(), exception_t::EXCEPTION_PREEMTED);
+    assert!(finalised_caps() <= WORK_UNITS_PER_PREEMPTION);
+    assert_ne!(parent.cteMDBNode.get_next(), 0);
+
+    set_irq_pending(false);
+    assert_eq!(parent.revoke(), exception_t::EXCEPTION_NONE);
+    assert_eq!(parent.cteMDBNode.get_next(), 0);
+    assert!(children
+        .iter()
+        .all(|child| child.cap.get_cap_type() == CapTag::CapNullCap));
+}
@@ -1,15 +1,24 @@
 #![feature(core_intrinsics)]
 #![no_std]
-#![no_main]
+#![cfg_attr(any(target_arch = "riscv64", target_arch = "aarch64"), no_main)]
 #![feature(asm_const)]
 #![allow(internal_features)]
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
 #![allow(clippy::clone_on_copy)]
-#![feature(custom_test_frameworks)]
-#![test_runner(crate::tests::test_runner)]
-#![reexport_test_harness_main = "test_main"]
+#![cfg_attr(
+    any(target_arch = "riscv64", target_arch = "aarch64"),
+    feature(custom_test_frameworks)
+)]
+#![cfg_attr(
+    any(target_arch = "riscv64", target_arch = "aarch64"),
+    test_runner(crate::tests::test_runner)
+)]
+#![cfg_attr(
+    any(target_arch = "riscv64", target_arch = "aarch64"),
+    reexport_test_harness_main = "test_main"
+)]
 
 mod cap;
 mod cte;
@@ -26,16 +35,33 @@ pub mod compatibility;
 
 pub mod arch;
 
-#[cfg(test)]
+#[cfg(any(test, not(any(target_arch = "riscv64", target_arch = "aarch64"))))]
+pub mod test_deps;
+
+#[cfg(all(test, not(any(target_arch = "riscv64", target_arch = "aarch64"))))]
+mod host_tests;
+
+#[cfg(all(test, any(target_arch = "riscv64", target_arch = "aarch64")))]
 mod tests {
     use arch::{cap_t, CapTag};
     use cap::same_object_as;
     use core::arch::global_asm;
     use cte::{cte_insert, cte_move, cte_swap, cte_t, insert_new_cap, resolve_address_bits};
     use mdb::mdb_node_t;
+    #[cfg(target_arch = "riscv64")]
     use riscv::register::{stvec, utvec::TrapMode};
-    use sel4_common::{arch::shutdown, println, utils::convert_to_mut_type_ref};
+    use sel4_common::{
+        arch::shutdown, println, structures::exception_t, utils::convert_to_mut_type_ref,
+    };
+    #[cfg(target_arch = "riscv64")]
+    use test_deps::set_irq_pending;
+    #[cfg(target_arch = "aarch64")]
+    use test_deps::{clear_timer_irq, raise_timer_irq, read_counter};
+    use test_deps::{finalised_caps, reset, WORK_UNITS_PER_PREEMPTION};
+    #[cfg(target_arch = "riscv64")]
     global_asm!(include_str!("entry.asm"));
+    #[cfg(target_arch = "aarch64")]
+    global_asm!(include_str!("entry-aarch64.asm"));
 
     use super::*;
 
@@ -260,6 +286,77 @@ mod tests {
         println!("Test slot_get_ptr_happy_case_test passed");
     }
 
+    /// The number of caps derived from the revoked cap in `revoke_preemption_test`
+    const REVOKE_CHILDREN: usize = 1024;
+
+    static mut revoke_parent: cte_t = cte_t {
+        cap: cap_t { words: [0; 2] },
+        cteMDBNode: mdb_node_t { words: [0; 2] },
+    };
+    static mut revoke_children: [cte_t; REVOKE_CHILDREN] = [cte_t {
+        cap: cap_t { words: [0; 2] },
+        cteMDBNode: mdb_node_t { words: [0; 2] },
+    }; REVOKE_CHILDREN];
+
+    #[test_case]
+    pub fn revoke_preemption_test() {
+        println!("-----------------------------------");
+        println!("Entering revoke_preemption_test case");
+        let ep_cap = cap_t::new_endpoint_cap(0, 1, 1, 1, 1, 0x8400_0000);
+        unsafe {
+            revoke_parent.cap = ep_cap;
+            revoke_parent.cteMDBNode = mdb_node_t::new(0, 1, 1, 0);
+            for child in revoke_children.iter_mut() {
+                insert_new_cap(&mut *core::ptr::addr_of_mut!(revoke_parent), child, &ep_cap);
+            }
+
+            // A large revoke with an interrupt already pending must stop at the first preemption
+            // point that checks for interrupts. On riscv the interrupt controller is the fake one
+            // of `test_deps`, on aarch64 the timer interrupt is pending at the GIC.
+            reset();
+            #[cfg(target_arch = "riscv64")]
+            set_irq_pending(true);
+            #[cfg(target_arch = "aarch64")]
+            raise_timer_irq();
+            #[cfg(target_arch = "aarch64")]
+            let start = read_counter();
+            let status = revoke_parent.revoke();
+            #[cfg(target_arch = "aarch64")]
+            let preempted_time = read_counter() - start;
+            println!(
+                "Revoke preempted after {} of {} caps",
+                finalised_caps(),
+                REVOKE_CHILDREN
+            );
+            assert_eq!(status, exception_t::EXCEPTION_PREEMTED);
+            assert!(finalised_caps() <= WORK_UNITS_PER_PREEMPTION);
+
+            // The restarted revoke goes on from where it stopped and finishes
+            #[cfg(target_arch = "riscv64")]
+            set_irq_pending(false);
+            #[cfg(target_arch = "aarch64")]
+            clear_timer_irq();
+            #[cfg(target_arch = "aarch64")]
+            let start = read_counter();
+            assert_eq!(revoke_parent.revoke(), exception_t::EXCEPTION_NONE);
+            #[cfg(target_arch = "aarch64")]
+            {
+                let finish_time = read_counter() - start;
+                println!(
+                    "Revoke took {} counter ticks until preempted, {} to finish",
+                    preempted_time, finish_time
+                );
+                // the preempted revoke stopped early, before the bulk of the children
+                assert!(preempted_time <= finish_time);
+            }
+            assert_eq!(revoke_parent.cteMDBNode.get_next(), 0);
+            assert!(revoke_children
+                .iter()
+                .all(|child| child.cap.get_cap_type() == CapTag::CapNullCap));
+        }
+        println!("Test revoke_preemption_test passed");
+    }
+
     #[test_case]
     pub fn shutdown_test() {
         println!("All Test Cases passed, shutdown");
@@ -320,6 +417,7 @@ mod tests {
         }
     }
 
+    #[cfg(target_arch = "riscv64")]
     #[no_mangle]
     pub fn call_test_main() {
         extern "C" {
@@ -330,6 +428,16 @@ mod tests {
         }
         crate::test_main();
     }
+
+    #[cfg(target_arch = "aarch64")]
+    #[no_mangle]
+    pub fn call_test_main() {
+        // the PL011 of QEMU `virt`, at its physical address as the MMU is off
+        driver_collect::set_serial(0x0900_0000, 0, 4);
+        crate::test_main();
+    }
+
+    #[cfg(target_arch = "riscv64")]
     #[no_mangle]
     pub fn c_handle_syscall() {
         unsafe {
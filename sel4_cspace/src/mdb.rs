@@ -18,7 +18,7 @@ plus_define_bitfield! {
     }
 }
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 plus_define_bitfield! {
     mdb_node_t, 2, 0, 0, 0 => {
         new, 0 => {
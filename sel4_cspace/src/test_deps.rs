@@ -0,0 +1,154 @@
+//! The kernel side of [crate::deps] for the unit tests, shared by the tests run on the target and
+//! the ones run on the host backend.
+//!
+//! No object is really finalised, the finalised caps are only counted. On aarch64
+//! `preemptionPoint` reads the pending interrupts of the GICv2 of QEMU `virt` as the kernel does,
+//! the tests raise the virtual timer interrupt with [raise_timer_irq]. Elsewhere the interrupt
+//! controller is faked by a flag the tests raise with [set_irq_pending]. The host harness runs the
+//! cases in parallel, so the host tests using this state hold
+//! `sel4_common::arch::host_lock_kernel_state`.
+
+#[cfg(target_arch = "aarch64")]
+use core::arch::asm;
+#[cfg(target_arch = "aarch64")]
+use core::ptr::{read_volatile, write_volatile};
+#[cfg(not(target_arch = "aarch64"))]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::arch::cap_t;
+use crate::structures::finaliseCap_ret;
+use sel4_common::structures::exception_t;
+
+/// Same as `CONFIG_MAX_NUM_WORK_UNITS_PER_PREEMPTION` of the kernel
+pub const WORK_UNITS_PER_PREEMPTION: usize = 100;
+
+/// Set while the fake interrupt controller has an interrupt pending
+#[cfg(not(target_arch = "aarch64"))]
+static IRQ_PENDING: AtomicBool = AtomicBool::new(false);
+static WORK_UNITS_COMPLETED: AtomicUsize = AtomicUsize::new(0);
+/// The number of caps finalised since the last [reset]
+static FINALISED_CAPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Raise or clear the interrupt of the fake interrupt controller.
+#[cfg(not(target_arch = "aarch64"))]
+pub fn set_irq_pending(pending: bool) {
+    IRQ_PENDING.store(pending, Ordering::Relaxed);
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn is_irq_pending() -> bool {
+    IRQ_PENDING.load(Ordering::Relaxed)
+}
+
+/// The GICv2 distributor and CPU interface of QEMU `virt`, the MMU is off in the tests
+#[cfg(target_arch = "aarch64")]
+const GICD_BASE: usize = 0x0800_0000;
+#[cfg(target_arch = "aarch64")]
+const GICC_BASE: usize = 0x0801_0000;
+#[cfg(target_arch = "aarch64")]
+const GICD_CTLR: usize = 0x000;
+#[cfg(target_arch = "aarch64")]
+const GICD_ISENABLER: usize = 0x100;
+#[cfg(target_arch = "aarch64")]
+const GICD_ICENABLER: usize = 0x180;
+#[cfg(target_arch = "aarch64")]
+const GICC_CTLR: usize = 0x000;
+#[cfg(target_arch = "aarch64")]
+const GICC_PMR: usize = 0x004;
+#[cfg(target_arch = "aarch64")]
+const GICC_HPPIR: usize = 0x018;
+/// The PPI of the EL1 virtual timer
+#[cfg(target_arch = "aarch64")]
+const VIRTUAL_TIMER_IRQ: usize = 27;
+
+#[cfg(target_arch = "aarch64")]
+fn gic_write(addr: usize, value: u32) {
+    unsafe { write_volatile(addr as *mut u32, value) }
+}
+
+/// Same as `is_irq_pending` of the GICv2 driver of the kernel, the ids from 1020 on are special
+#[cfg(target_arch = "aarch64")]
+fn is_irq_pending() -> bool {
+    let hppir = unsafe { read_volatile((GICC_BASE + GICC_HPPIR) as *const u32) };
+    (hppir & 0x3ff) < 1020
+}
+
+/// Make the virtual timer interrupt pending at the GIC. The interrupts stay masked at the core,
+/// so it is only seen by reading the GIC.
+#[cfg(target_arch = "aarch64")]
+pub fn raise_timer_irq() {
+    gic_write(GICD_BASE + GICD_CTLR, 1);
+    gic_write(GICD_BASE + GICD_ISENABLER, 1 << VIRTUAL_TIMER_IRQ);
+    gic_write(GICC_BASE + GICC_PMR, 0xf0);
+    gic_write(GICC_BASE + GICC_CTLR, 1);
+    unsafe {
+        // the timer fires as soon as it is enabled
+        asm!("msr cntv_tval_el0, {}", in(reg) 0usize);
+        asm!("msr cntv_ctl_el0, {}", in(reg) 1usize);
+        asm!("isb");
+    }
+    while !is_irq_pending() {
+        core::hint::spin_loop();
+    }
+}
+
+/// Stop the virtual timer and disable its interrupt.
+#[cfg(target_arch = "aarch64")]
+pub fn clear_timer_irq() {
+    unsafe {
+        asm!("msr cntv_ctl_el0, {}", in(reg) 0usize);
+        asm!("isb");
+    }
+    gic_write(GICD_BASE + GICD_ICENABLER, 1 << VIRTUAL_TIMER_IRQ);
+}
+
+/// Read the virtual counter, to time the cases.
+#[cfg(target_arch = "aarch64")]
+pub fn read_counter() -> u64 {
+    let counter: u64;
+    unsafe {
+        asm!("isb", "mrs {}, cntvct_el0", out(reg) counter);
+    }
+    counter
+}
+
+/// Get the number of caps finalised since the last [reset].
+pub fn finalised_caps() -> usize {
+    FINALISED_CAPS.load(Ordering::Relaxed)
+}
+
+/// Clear the pending interrupt, the completed work units and the finalised caps.
+pub fn reset() {
+    #[cfg(not(target_arch = "aarch64"))]
+    IRQ_PENDING.store(false, Ordering::Relaxed);
+    #[cfg(target_arch = "aarch64")]
+    clear_timer_irq();
+    WORK_UNITS_COMPLETED.store(0, Ordering::Relaxed);
+    FINALISED_CAPS.store(0, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub fn finaliseCap(_cap: &cap_t, _final: bool, _exposed: bool) -> finaliseCap_ret {
+    FINALISED_CAPS.fetch_add(1, Ordering::Relaxed);
+    finaliseCap_ret {
+        remainder: cap_t::new_null_cap(),
+        cleanupInfo: cap_t::new_null_cap(),
+    }
+}
+
+#[no_mangle]
+pub fn post_cap_deletion(_cap: &cap_t) {}
+
+/// Same as the kernel, with the interrupt controller of the tests
+#[no_mangle]
+pub fn preemptionPoint() -> exception_t {
+    let completed = WORK_UNITS_COMPLETED.fetch_add(1, Ordering::Relaxed) + 1;
+    if completed >= WORK_UNITS_PER_PREEMPTION {
+        WORK_UNITS_COMPLETED.store(0, Ordering::Relaxed);
+        if is_irq_pending() {
+            return exception_t::EXCEPTION_PREEMTED;
+        }
+    }
+    exception_t::EXCEPTION_NONE
+}
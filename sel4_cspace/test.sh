@@ -1,11 +1,27 @@
 #!/bin/bash
 
-echo "ARGS1 $1"
+# usage: test.sh <riscv64|aarch64> <test binary>
+ARCH=$1
 
-rust-objcopy --binary-architecture=riscv64 $1 --strip-all -O binary $1.bin
+echo "ARGS1 $2"
 
-qemu-system-riscv64 \
-    -machine virt \
-    -kernel $1.bin \
-    -nographic -smp 1 \
-    -D qemu.log -d in_asm,int,pcall,cpu_reset,guest_errors
+case $ARCH in
+riscv64)
+    rust-objcopy --binary-architecture=riscv64 $2 --strip-all -O binary $2.bin
+
+    qemu-system-riscv64 \
+        -machine virt \
+        -kernel $2.bin \
+        -nographic -smp 1 \
+        -D qemu.log -d in_asm,int,pcall,cpu_reset,guest_errors
+    ;;
+aarch64)
+    # the ELF is loaded at its own address, the tests run at EL1 with the MMU off
+    qemu-system-aarch64 \
+        -machine virt,gic-version=2 \
+        -cpu cortex-a57 \
+        -kernel $2 \
+        -nographic -smp 1 \
+        -D qemu.log -d int,cpu_reset,guest_errors
+    ;;
+esac
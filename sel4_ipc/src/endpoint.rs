@@ -2,6 +2,8 @@ use crate::transfer::Transfer;
 use sel4_common::arch::ArchReg;
 use sel4_common::plus_define_bitfield;
 use sel4_common::utils::{convert_to_mut_type_ref, convert_to_option_mut_type_ref};
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::reply_t;
 use sel4_task::{
     possible_switch_to, rescheduleRequired, schedule_tcb, set_thread_state, tcb_queue_t, tcb_t,
     ThreadState,
@@ -32,7 +34,7 @@ plus_define_bitfield! {
     }
 }
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 // The structure of an endpoint, which is used to send and receive IPC
 plus_define_bitfield! {
     endpoint_t, 2, 0, 0, 0 => {
@@ -190,16 +192,27 @@ impl endpoint_t {
                 if queue.empty() {
                     self.set_state(EPState::Idle as usize);
                 }
+                #[cfg(feature = "KERNEL_MCS")]
+                let reply = dest_thread.take_reply();
                 src_thread.do_ipc_transfer(dest_thread, Some(self), badge, can_grant);
+                #[cfg(not(feature = "KERNEL_MCS"))]
                 let reply_can_grant = dest_thread.tcbState.get_blocking_ipc_can_grant() != 0;
                 set_thread_state(dest_thread, ThreadState::ThreadStateRunning);
                 possible_switch_to(dest_thread);
                 if do_call {
+                    #[cfg(not(feature = "KERNEL_MCS"))]
                     if can_grant || can_grant_reply {
                         dest_thread.setup_caller_cap(src_thread, reply_can_grant);
                     } else {
                         set_thread_state(src_thread, ThreadState::ThreadStateInactive);
                     }
+                    #[cfg(feature = "KERNEL_MCS")]
+                    match reply {
+                        Some(reply) if can_grant || can_grant_reply => {
+                            reply.push(src_thread, dest_thread)
+                        }
+                        _ => set_thread_state(src_thread, ThreadState::ThreadStateInactive),
+                    };
                 }
             }
         }
@@ -211,13 +224,23 @@ impl endpoint_t {
     /// * `thread` - The thread to receive the IPC
     /// * `is_blocking` - If the IPC is blocking
     /// * `grant` - If the IPC can grant
+    /// # Note
+    /// With MCS, the reply object to receive with is passed in the thread state of the thread
     pub fn receive_ipc(&mut self, thread: &mut tcb_t, is_blocking: bool, grant: bool) {
         if thread.complete_signal() {
+            #[cfg(feature = "KERNEL_MCS")]
+            thread.tcbState.set_reply_object(0);
             return;
         }
         match self.get_state() {
             EPState::Idle | EPState::Recv => {
                 if is_blocking {
+                    #[cfg(feature = "KERNEL_MCS")]
+                    if let Some(reply) = convert_to_option_mut_type_ref::<reply_t>(
+                        thread.tcbState.get_reply_object(),
+                    ) {
+                        reply.replyTCB = thread.get_ptr();
+                    }
                     thread.tcbState.set_blocking_object(self.get_ptr());
                     thread.tcbState.set_blocking_ipc_can_grant(grant as usize);
                     set_thread_state(thread, ThreadState::ThreadStateBlockedOnReceive);
@@ -228,6 +251,8 @@ impl endpoint_t {
                 } else {
                     // NBReceive failed
                     thread.tcbArch.set_register(ArchReg::Badge, 0);
+                    #[cfg(feature = "KERNEL_MCS")]
+                    thread.tcbState.set_reply_object(0);
                 }
             }
             EPState::Send => {
@@ -242,14 +267,22 @@ impl endpoint_t {
                 let badge = sender.tcbState.get_blocking_ipc_badge();
                 let can_grant = sender.tcbState.get_blocking_ipc_can_grant() != 0;
                 let can_grant_reply = sender.tcbState.get_blocking_ipc_can_grant_reply() != 0;
+                #[cfg(feature = "KERNEL_MCS")]
+                let reply = thread.take_reply();
                 sender.do_ipc_transfer(thread, Some(self), badge, can_grant);
                 let do_call = sender.tcbState.get_blocking_ipc_is_call() != 0;
                 if do_call {
+                    #[cfg(not(feature = "KERNEL_MCS"))]
                     if can_grant || can_grant_reply {
                         thread.setup_caller_cap(sender, grant);
                     } else {
                         set_thread_state(sender, ThreadState::ThreadStateInactive);
                     }
+                    #[cfg(feature = "KERNEL_MCS")]
+                    match reply {
+                        Some(reply) if can_grant || can_grant_reply => reply.push(sender, thread),
+                        _ => set_thread_state(sender, ThreadState::ThreadStateInactive),
+                    };
                 } else {
                     set_thread_state(sender, ThreadState::ThreadStateRunning);
                     possible_switch_to(sender);
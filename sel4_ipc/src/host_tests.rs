@@ -0,0 +1,215 @@
+//! Endpoint and notification tests run by `cargo host-test` on the host backend.
+//!
+//! Waking a thread goes through the scheduler globals of `sel4_task`, so every
+//! case holds the kernel state lock and starts from a fresh current thread.
+extern crate std;
+
+use std::boxed::Box;
+use std::sync::MutexGuard;
+use std::vec::Vec;
+
+use sel4_common::arch::{host_lock_kernel_state, ArchReg};
+use sel4_common::message_info::seL4_MessageInfo_t;
+use sel4_common::sel4_config::{seL4_TCBBits, TCB_OFFSET};
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_common::BIT;
+use sel4_task::{
+    get_ks_scheduler_action, set_current_thread, set_ks_scheduler_action, tcb_t,
+    SchedulerAction_ResumeCurrentThread, ThreadState,
+};
+
+use crate::endpoint::{endpoint_t, EPState};
+use crate::notification::{notification_t, NtfnState};
+
+/// The memory of a TCB object, whose CNode slots come before the `tcb_t`
+#[repr(C, align(2048))]
+struct tcb_object([u8; BIT!(seL4_TCBBits)]);
+
+/// The threads of one test case, the first one is the current thread
+struct Threads {
+    objects: Vec<Box<tcb_object>>,
+    _ks: MutexGuard<'static, ()>,
+}
+
+impl Threads {
+    fn new(n: usize) -> Self {
+        let ks = host_lock_kernel_state();
+        let objects = (0..n + 1)
+            .map(|_| Box::new(tcb_object([0; BIT!(seL4_TCBBits)])))
+            .collect();
+        let threads = Self { objects, _ks: ks };
+        set_current_thread(threads.get(0));
+        set_ks_scheduler_action(SchedulerAction_ResumeCurrentThread);
+        threads
+    }
+
+    fn get(&self, i: usize) -> &'static mut tcb_t {
+        let object = &*self.objects[i] as *const tcb_object as usize;
+        convert_to_mut_type_ref::<tcb_t>(object + TCB_OFFSET)
+    }
+}
+
+fn new_endpoint() -> Box<endpoint_t> {
+    Box::new(endpoint_t::new(0, 0, EPState::Idle as usize))
+}
+
+fn new_notification() -> Box<notification_t> {
+    Box::new(notification_t::new(0, 0, 0, 0, NtfnState::Idle as usize))
+}
+
+#[test]
+fn signal_wakes_the_waiting_thread() {
+    let threads = Threads::new(1);
+    let waiter = threads.get(1);
+    let mut ntfn = new_notification();
+
+    ntfn.receive_signal(waiter, true);
+    assert_eq!(ntfn.get_state(), NtfnState::Waiting);
+    assert_eq!(ntfn.get_queue_head(), waiter.get_ptr());
+    assert_eq!(
+        waiter.get_state(),
+        ThreadState::ThreadStateBlockedOnNotification
+    );
+
+    ntfn.send_signal(0b100);
+    assert_eq!(ntfn.get_state(), NtfnState::Idle);
+    assert!(ntfn.get_queue().empty());
+    assert_eq!(waiter.get_state(), ThreadState::ThreadStateRunning);
+    assert_eq!(waiter.tcbArch.get_register(ArchReg::Badge), 0b100);
+    assert_eq!(get_ks_scheduler_action(), waiter.get_ptr());
+}
+
+#[test]
+fn signals_accumulate_while_active() {
+    let threads = Threads::new(1);
+    let receiver = threads.get(1);
+    let mut ntfn = new_notification();
+
+    ntfn.send_signal(0b01);
+    ntfn.send_signal(0b10);
+    assert_eq!(ntfn.get_state(), NtfnState::Active);
+    assert_eq!(ntfn.get_msg_identifier(), 0b11);
+
+    ntfn.receive_signal(receiver, true);
+    assert_eq!(ntfn.get_state(), NtfnState::Idle);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Badge), 0b11);
+    assert_ne!(
+        receiver.get_state(),
+        ThreadState::ThreadStateBlockedOnNotification
+    );
+}
+
+#[test]
+fn poll_on_an_idle_notification_returns_no_badge() {
+    let threads = Threads::new(1);
+    let receiver = threads.get(1);
+    receiver.tcbArch.set_register(ArchReg::Badge, 0xdead);
+    let mut ntfn = new_notification();
+
+    ntfn.receive_signal(receiver, false);
+    assert_eq!(ntfn.get_state(), NtfnState::Idle);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Badge), 0);
+}
+
+#[test]
+fn cancelling_the_last_waiter_idles_the_notification() {
+    let threads = Threads::new(2);
+    let (first, second) = (threads.get(1), threads.get(2));
+    let mut ntfn = new_notification();
+    ntfn.receive_signal(first, true);
+    ntfn.receive_signal(second, true);
+
+    ntfn.cancel_signal(first);
+    assert_eq!(ntfn.get_state(), NtfnState::Waiting);
+    assert_eq!(ntfn.get_queue_head(), second.get_ptr());
+    assert_eq!(first.get_state(), ThreadState::ThreadStateInactive);
+
+    ntfn.cancel_signal(second);
+    assert_eq!(ntfn.get_state(), NtfnState::Idle);
+    assert!(ntfn.get_queue().empty());
+}
+
+#[test]
+fn send_blocks_until_a_receiver_arrives() {
+    let threads = Threads::new(2);
+    let (sender, receiver) = (threads.get(1), threads.get(2));
+    let mut ep = new_endpoint();
+    let tag = seL4_MessageInfo_t::new(0x42, 0, 0, 2);
+    sender.tcbArch.set_register(ArchReg::MsgInfo, tag.to_word());
+    sender.tcbArch.set_register(ArchReg::Msg(0), 0x1111);
+    sender.tcbArch.set_register(ArchReg::Msg(1), 0x2222);
+
+    ep.send_ipc(sender, true, false, false, 0x7, false);
+    assert_eq!(ep.get_state(), EPState::Send);
+    assert_eq!(ep.get_queue_head(), sender.get_ptr());
+    assert_eq!(sender.get_state(), ThreadState::ThreadStateBlockedOnSend);
+    assert_eq!(sender.tcbState.get_blocking_ipc_badge(), 0x7);
+
+    ep.receive_ipc(receiver, true, false);
+    assert_eq!(ep.get_state(), EPState::Idle);
+    assert!(ep.get_queue().empty());
+    assert_eq!(sender.get_state(), ThreadState::ThreadStateRunning);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Badge), 0x7);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Msg(0)), 0x1111);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Msg(1)), 0x2222);
+    let received = seL4_MessageInfo_t::from_word(receiver.tcbArch.get_register(ArchReg::MsgInfo));
+    assert_eq!(received.get_length(), 2);
+    assert_eq!(get_ks_scheduler_action(), sender.get_ptr());
+}
+
+#[test]
+fn receive_blocks_until_a_sender_arrives() {
+    let threads = Threads::new(2);
+    let (sender, receiver) = (threads.get(1), threads.get(2));
+    let mut ep = new_endpoint();
+
+    ep.receive_ipc(receiver, true, false);
+    assert_eq!(ep.get_state(), EPState::Recv);
+    assert_eq!(ep.get_queue_head(), receiver.get_ptr());
+    assert_eq!(
+        receiver.get_state(),
+        ThreadState::ThreadStateBlockedOnReceive
+    );
+
+    let tag = seL4_MessageInfo_t::new(0, 0, 0, 1);
+    sender.tcbArch.set_register(ArchReg::MsgInfo, tag.to_word());
+    sender.tcbArch.set_register(ArchReg::Msg(0), 0x3333);
+    ep.send_ipc(sender, true, false, false, 0x9, false);
+    assert_eq!(ep.get_state(), EPState::Idle);
+    assert_eq!(receiver.get_state(), ThreadState::ThreadStateRunning);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Badge), 0x9);
+    assert_eq!(receiver.tcbArch.get_register(ArchReg::Msg(0)), 0x3333);
+    assert_eq!(get_ks_scheduler_action(), receiver.get_ptr());
+}
+
+#[test]
+fn non_blocking_send_to_an_idle_endpoint_is_dropped() {
+    let threads = Threads::new(1);
+    let sender = threads.get(1);
+    let mut ep = new_endpoint();
+
+    ep.send_ipc(sender, false, false, false, 0, false);
+    assert_eq!(ep.get_state(), EPState::Idle);
+    assert!(ep.get_queue().empty());
+}
+
+#[test]
+fn cancelling_queued_senders_keeps_the_queue_linked() {
+    let threads = Threads::new(3);
+    let (first, second, third) = (threads.get(1), threads.get(2), threads.get(3));
+    let mut ep = new_endpoint();
+    for sender in [&mut *first, &mut *second, &mut *third] {
+        ep.send_ipc(sender, true, false, false, 0, false);
+    }
+
+    ep.cancel_ipc(second);
+    assert_eq!(ep.get_state(), EPState::Send);
+    assert_eq!(second.get_state(), ThreadState::ThreadStateInactive);
+    assert_eq!(first.tcbEPNext, third.get_ptr());
+    assert_eq!(third.tcbEPPrev, first.get_ptr());
+
+    ep.cancel_ipc(first);
+    ep.cancel_ipc(third);
+    assert_eq!(ep.get_state(), EPState::Idle);
+    assert!(ep.get_queue().empty());
+}
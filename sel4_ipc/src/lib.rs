@@ -9,6 +9,8 @@
 #![allow(non_upper_case_globals)]
 
 mod endpoint;
+#[cfg(all(test, not(any(target_arch = "riscv64", target_arch = "aarch64"))))]
+mod host_tests;
 mod notification;
 mod transfer;
 
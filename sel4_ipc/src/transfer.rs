@@ -3,6 +3,8 @@ use core::intrinsics::likely;
 use super::endpoint::*;
 use super::notification::*;
 
+#[cfg(feature = "HARDWARE_DEBUG_API")]
+use sel4_common::arch::seL4_SingleStep;
 use sel4_common::arch::ArchReg;
 use sel4_common::arch::{n_exceptionMessage, n_syscallMessage};
 use sel4_common::fault::*;
@@ -11,6 +13,8 @@ use sel4_common::sel4_config::*;
 use sel4_common::structures::*;
 use sel4_common::utils::*;
 use sel4_cspace::interface::*;
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_task::reply_t;
 use sel4_task::{possible_switch_to, set_thread_state, tcb_t, ThreadState};
 use sel4_vspace::pptr_t;
 
@@ -55,7 +59,11 @@ pub trait Transfer {
         grant: bool,
     );
 
+    #[cfg(not(feature = "KERNEL_MCS"))]
     fn do_reply(&mut self, receiver: &mut tcb_t, slot: &mut cte_t, grant: bool);
+
+    #[cfg(feature = "KERNEL_MCS")]
+    fn do_reply(&mut self, reply: &mut reply_t, grant: bool);
 }
 
 impl Transfer for tcb_t {
@@ -66,6 +74,12 @@ impl Transfer for tcb_t {
                 let ep = convert_to_mut_type_ref::<endpoint_t>(state.get_blocking_object());
                 assert_ne!(ep.get_state(), EPState::Idle);
                 ep.cancel_ipc(self);
+                #[cfg(feature = "KERNEL_MCS")]
+                if let Some(reply) =
+                    convert_to_option_mut_type_ref::<reply_t>(self.tcbState.get_reply_object())
+                {
+                    reply.unlink(self);
+                }
             }
             ThreadState::ThreadStateBlockedOnNotification => {
                 let ntfn = convert_to_mut_type_ref::<notification_t>(state.get_blocking_object());
@@ -74,11 +88,16 @@ impl Transfer for tcb_t {
 
             ThreadState::ThreadStateBlockedOnReply => {
                 self.tcbFault = seL4_Fault_t::new_null_fault();
-                let slot = self.get_cspace(tcbReply);
-                let caller_slot_ptr = slot.cteMDBNode.get_next();
-                if caller_slot_ptr != 0 {
-                    convert_to_mut_type_ref::<cte_t>(caller_slot_ptr).delete_one()
+                #[cfg(not(feature = "KERNEL_MCS"))]
+                {
+                    let slot = self.get_cspace(tcbReply);
+                    let caller_slot_ptr = slot.cteMDBNode.get_next();
+                    if caller_slot_ptr != 0 {
+                        convert_to_mut_type_ref::<cte_t>(caller_slot_ptr).delete_one()
+                    }
                 }
+                #[cfg(feature = "KERNEL_MCS")]
+                convert_to_mut_type_ref::<reply_t>(self.tcbState.get_reply_object()).unlink(self);
             }
             _ => {}
         }
@@ -213,6 +232,48 @@ impl Transfer for tcb_t {
                 );
                 receiver.set_mr(seL4_VMFault_FSR, self.tcbFault.vm_fault_get_fsr())
             }
+            #[cfg(feature = "HARDWARE_DEBUG_API")]
+            FaultType::DebugException => {
+                receiver.set_mr(
+                    seL4_DebugException_FaultIP,
+                    self.tcbArch.get_register(ArchReg::FaultIP),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_ExceptionReason,
+                    self.tcbFault.debug_exception_get_exception_reason(),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_TriggerAddress,
+                    self.tcbFault.debug_exception_get_breakpoint_address(),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_BreakpointNumber,
+                    self.tcbFault.debug_exception_get_breakpoint_number(),
+                )
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VGICMaintenance => {
+                // the handler reads a -1 as no list register with the EOI
+                let idx = match self.tcbFault.vgic_maintenance_get_idx_valid() {
+                    0 => usize::MAX,
+                    _ => self.tcbFault.vgic_maintenance_get_idx(),
+                };
+                receiver.set_mr(seL4_VGICMaintenance_IDX, idx)
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VCPUFault => {
+                receiver.set_mr(seL4_VCPUFault_HSR, self.tcbFault.vcpu_fault_get_hsr())
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VPPIEvent => {
+                receiver.set_mr(seL4_VPPIEvent_IRQ, self.tcbFault.vppi_event_get_irq())
+            }
+            #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+            FaultType::VCPUFault => {
+                receiver.set_mr(seL4_VCPUFault_Cause, self.tcbFault.vcpu_fault_get_cause());
+                receiver.set_mr(seL4_VCPUFault_Addr, self.tcbFault.vcpu_fault_get_address());
+                receiver.set_mr(seL4_VCPUFault_Inst, self.tcbFault.vcpu_fault_get_inst())
+            }
             _ => {
                 panic!("invalid fault")
             }
@@ -268,6 +329,19 @@ impl Transfer for tcb_t {
                 );
                 return label as usize == 0;
             }
+            // the handler of a single-step fault may step the thread again
+            #[cfg(feature = "HARDWARE_DEBUG_API")]
+            FaultType::DebugException => {
+                if receiver.tcbFault.debug_exception_get_exception_reason() == seL4_SingleStep
+                    && length >= 1
+                {
+                    receiver
+                        .tcbArch
+                        .breakpoints
+                        .configure_single_stepping(self.tcbArch.get_register(ArchReg::Msg(0)));
+                }
+                true
+            }
             _ => true,
         }
     }
@@ -300,6 +374,7 @@ impl Transfer for tcb_t {
         }
     }
 
+    #[cfg(not(feature = "KERNEL_MCS"))]
     fn do_reply(&mut self, receiver: &mut tcb_t, slot: &mut cte_t, grant: bool) {
         assert_eq!(receiver.get_state(), ThreadState::ThreadStateBlockedOnReply);
         let fault_type = receiver.tcbFault.get_fault_type();
@@ -318,4 +393,29 @@ impl Transfer for tcb_t {
             }
         }
     }
+
+    #[cfg(feature = "KERNEL_MCS")]
+    fn do_reply(&mut self, reply: &mut reply_t, grant: bool) {
+        let receiver = match convert_to_option_mut_type_ref::<tcb_t>(reply.replyTCB) {
+            Some(receiver) if receiver.get_state() == ThreadState::ThreadStateBlockedOnReply => {
+                receiver
+            }
+            // the caller has gone
+            _ => return,
+        };
+        reply.unlink(receiver);
+        if likely(receiver.tcbFault.get_fault_type() == FaultType::NullFault) {
+            self.do_ipc_transfer(receiver, None, 0, grant);
+            set_thread_state(receiver, ThreadState::ThreadStateRunning);
+        } else if self.do_fault_reply_transfer(receiver) {
+            receiver.tcbFault = seL4_Fault_t::new_null_fault();
+            set_thread_state(receiver, ThreadState::ThreadStateRestart);
+        } else {
+            receiver.tcbFault = seL4_Fault_t::new_null_fault();
+            set_thread_state(receiver, ThreadState::ThreadStateInactive);
+        }
+        if receiver.is_runnable() {
+            possible_switch_to(receiver);
+        }
+    }
 }
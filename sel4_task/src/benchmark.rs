@@ -0,0 +1,118 @@
+//! CPU utilisation accounting of the threads, the `BENCHMARK_TRACK_UTILISATION` of seL4.
+//!
+//! The kernel starts the accounting of a core with `SysBenchmarkResetLog` and stops it with
+//! `SysBenchmarkFinalizeLog`. In between, a thread is charged the cycles since it was switched
+//! to when the kernel switches away from it, and the cycles of each kernel entry are counted
+//! for the thread the kernel returns to as well.
+use core::intrinsics::likely;
+
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+
+use crate::deps::getKernelEntryTime;
+use crate::scheduler::{get_currenct_thread, get_idle_thread};
+use crate::tcb::tcb_t;
+
+/// The utilisation counters of a thread, all in cycles of the cycle counter
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct benchmark_util_t {
+    /// When the thread was last switched to
+    pub schedule_start_time: u64,
+    /// The cycles the thread ran in user mode
+    pub utilisation: u64,
+    /// How many times the thread was switched to
+    pub number_schedules: u64,
+    /// The cycles the kernel spent before returning to the thread
+    pub kernel_utilisation: u64,
+    /// How many times the kernel returned to the thread
+    pub number_kernel_entries: u64,
+}
+
+/// The utilisation counters of a core
+#[derive(Debug, Clone, Copy)]
+pub struct benchmark_core_util_t {
+    /// Set from `SysBenchmarkResetLog` to `SysBenchmarkFinalizeLog`
+    pub enabled: bool,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// The cycles spent in the kernel
+    pub kernel_time: u64,
+    pub number_kernel_entries: u64,
+    pub number_schedules: u64,
+}
+
+static mut ksBenchmarkCoreUtil: [benchmark_core_util_t; CONFIG_MAX_NUM_NODES] =
+    [benchmark_core_util_t {
+        enabled: false,
+        start_time: 0,
+        end_time: 0,
+        kernel_time: 0,
+        number_kernel_entries: 0,
+        number_schedules: 0,
+    }; CONFIG_MAX_NUM_NODES];
+
+#[inline]
+/// Get the utilisation counters of the given core.
+pub fn get_core_utilisation(cpu: usize) -> &'static mut benchmark_core_util_t {
+    unsafe { &mut ksBenchmarkCoreUtil[cpu] }
+}
+
+/// Charge the cycles from the last switch to the kernel entry to `heir`.
+#[inline]
+fn benchmark_utilisation_charge(heir: &mut tcb_t, enter: u64) {
+    heir.benchmark.utilisation += enter.wrapping_sub(heir.benchmark.schedule_start_time);
+}
+
+/// Charge the current thread up to the kernel entry, and start the accounting of `next`.
+pub fn benchmark_utilisation_switch(next: &mut tcb_t) {
+    let core = get_core_utilisation(cpu_id());
+    if likely(core.enabled) {
+        let enter = unsafe { getKernelEntryTime() };
+        benchmark_utilisation_charge(get_currenct_thread(), enter);
+        next.benchmark.schedule_start_time = enter;
+        next.benchmark.number_schedules += 1;
+        core.number_schedules += 1;
+    }
+}
+
+/// Charge the cycles spent in the kernel since `enter` to the thread the kernel returns to.
+pub fn benchmark_utilisation_kentry_stamp(enter: u64, exit: u64) {
+    let core = get_core_utilisation(cpu_id());
+    if likely(core.enabled) {
+        let thread = get_currenct_thread();
+        thread.benchmark.number_kernel_entries += 1;
+        thread.benchmark.kernel_utilisation += exit.wrapping_sub(enter);
+        core.number_kernel_entries += 1;
+        core.kernel_time += exit.wrapping_sub(enter);
+    }
+}
+
+/// Start the accounting of the current core, `enter` is the entry of the starting syscall.
+pub fn benchmark_utilisation_start(enter: u64) {
+    benchmark_track_reset_utilisation(get_idle_thread());
+    let thread = get_currenct_thread();
+    thread.benchmark.schedule_start_time = enter;
+    thread.benchmark.number_schedules += 1;
+    *get_core_utilisation(cpu_id()) = benchmark_core_util_t {
+        enabled: true,
+        start_time: enter,
+        end_time: 0,
+        kernel_time: 0,
+        number_kernel_entries: 0,
+        number_schedules: 1,
+    };
+}
+
+/// Stop the accounting of the current core, the current thread is charged up to `enter`.
+pub fn benchmark_utilisation_finalise(enter: u64) {
+    benchmark_utilisation_charge(get_currenct_thread(), enter);
+    let core = get_core_utilisation(cpu_id());
+    core.end_time = enter;
+    core.enabled = false;
+}
+
+/// Clear the utilisation counters of the thread.
+pub fn benchmark_track_reset_utilisation(tcb: &mut tcb_t) {
+    tcb.benchmark = benchmark_util_t::default();
+}
@@ -2,7 +2,8 @@ use sel4_common::{
     sel4_config::{seL4_TCBBits, CONFIG_MAX_NUM_NODES},
     BIT,
 };
-#[repr(align(2048))]
+#[cfg_attr(not(feature = "HARDWARE_DEBUG_API"), repr(align(2048)))]
+#[cfg_attr(feature = "HARDWARE_DEBUG_API", repr(align(4096)))]
 pub struct ksIdleThreadTCB_data {
     pub data: [[u8; CONFIG_MAX_NUM_NODES]; BIT!(seL4_TCBBits)],
 }
@@ -12,6 +13,23 @@ pub static mut ksIdleThreadTCB: ksIdleThreadTCB_data = ksIdleThreadTCB_data {
     data: [[0; CONFIG_MAX_NUM_NODES]; BIT!(seL4_TCBBits)],
 };
 extern "C" {
+    /// Send a reschedule IPI to the other cores in the mask
     #[cfg(feature = "ENABLE_SMP")]
     pub fn doMaskReschedule(mask: usize);
+    /// Read the timer of the current core in ticks
+    #[cfg(feature = "KERNEL_MCS")]
+    pub fn getCurrentTime() -> usize;
+    /// Raise the timer interrupt of the current core at the given time in ticks
+    #[cfg(feature = "KERNEL_MCS")]
+    pub fn setDeadline(deadline: usize);
+    #[cfg(feature = "KERNEL_MCS")]
+    pub fn usToTicks(us: usize) -> usize;
+    #[cfg(feature = "KERNEL_MCS")]
+    pub fn ticksToUs(ticks: usize) -> usize;
+    /// How early the deadlines are set, in ticks
+    #[cfg(feature = "KERNEL_MCS")]
+    pub fn getTimerPrecision() -> usize;
+    /// The cycle counter of the current core when it last entered the kernel
+    #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+    pub fn getKernelEntryTime() -> u64;
 }
@@ -0,0 +1,126 @@
+//! Ready queue and `tcb_queue_t` tests run by `cargo host-test` on the host backend.
+//!
+//! The ready queues and their bitmaps are kernel globals, so the cases that
+//! touch them hold the kernel state lock while the harness runs the others in parallel.
+extern crate std;
+
+use std::boxed::Box;
+use std::vec::Vec;
+
+use sel4_common::arch::host_lock_kernel_state;
+use sel4_common::sel4_config::{wordBits, CONFIG_NUM_PRIORITIES};
+
+use crate::scheduler::{addToBitmap, isHighestPrio, ready_queues_index, removeFromBitmap};
+use crate::tcb::tcb_t;
+use crate::tcb_queue::tcb_queue_t;
+
+/// TCBs handed to the queues must not move, so they live on the heap
+fn new_tcbs(n: usize, prio: usize) -> Vec<Box<tcb_t>> {
+    (0..n)
+        .map(|_| {
+            let mut tcb: Box<tcb_t> = Box::new(unsafe { core::mem::zeroed() });
+            tcb.tcbPriority = prio;
+            tcb
+        })
+        .collect()
+}
+
+#[test]
+fn bitmap_tracks_the_highest_priority() {
+    let _ks = host_lock_kernel_state();
+    let (low, mid, high) = (3, wordBits + 7, CONFIG_NUM_PRIORITIES - 1);
+    assert!(isHighestPrio(0, 0));
+
+    addToBitmap(0, 0, mid);
+    addToBitmap(0, 0, low);
+    assert!(isHighestPrio(0, mid));
+    assert!(!isHighestPrio(0, low));
+
+    addToBitmap(0, 0, high);
+    assert!(isHighestPrio(0, high));
+    assert!(!isHighestPrio(0, mid));
+
+    removeFromBitmap(0, 0, high);
+    assert!(isHighestPrio(0, mid));
+    removeFromBitmap(0, 0, mid);
+    assert!(isHighestPrio(0, low));
+    assert!(!isHighestPrio(0, low - 1));
+    removeFromBitmap(0, 0, low);
+    assert!(isHighestPrio(0, 0));
+}
+
+#[test]
+fn sched_enqueue_and_dequeue_keep_the_ready_queue_in_order() {
+    let _ks = host_lock_kernel_state();
+    let prio = 42;
+    let mut tcbs = new_tcbs(3, prio);
+    let idx = ready_queues_index(0, prio);
+    for tcb in tcbs.iter_mut() {
+        tcb.sched_enqueue();
+        assert_eq!(tcb.tcbState.get_tcb_queued(), 1);
+    }
+    // Enqueuing a queued thread again is a no-op
+    tcbs[1].sched_enqueue();
+
+    let queue = *tcbs[0].get_sched_queue(idx);
+    assert_eq!(queue.head, tcbs[0].get_ptr());
+    assert_eq!(queue.tail, tcbs[2].get_ptr());
+    assert_eq!(tcbs[0].tcbSchedNext, tcbs[1].get_ptr());
+    assert_eq!(tcbs[1].tcbSchedNext, tcbs[2].get_ptr());
+    assert_eq!(tcbs[2].tcbSchedPrev, tcbs[1].get_ptr());
+    assert!(isHighestPrio(0, prio));
+    assert!(!isHighestPrio(0, prio - 1));
+
+    tcbs[1].sched_dequeue();
+    assert_eq!(tcbs[1].tcbState.get_tcb_queued(), 0);
+    assert_eq!(tcbs[0].tcbSchedNext, tcbs[2].get_ptr());
+    assert_eq!(tcbs[2].tcbSchedPrev, tcbs[0].get_ptr());
+
+    tcbs[0].sched_dequeue();
+    tcbs[2].sched_dequeue();
+    assert!(tcbs[0].get_sched_queue(idx).empty());
+    assert!(isHighestPrio(0, 0));
+}
+
+#[test]
+fn sched_append_puts_the_thread_at_the_tail() {
+    let _ks = host_lock_kernel_state();
+    let prio = 7;
+    let mut tcbs = new_tcbs(2, prio);
+    let idx = ready_queues_index(0, prio);
+    tcbs[0].sched_append();
+    tcbs[1].sched_append();
+    let queue = *tcbs[0].get_sched_queue(idx);
+    assert_eq!(queue.head, tcbs[0].get_ptr());
+    assert_eq!(queue.tail, tcbs[1].get_ptr());
+
+    tcbs[0].sched_dequeue();
+    tcbs[1].sched_dequeue();
+    assert!(tcbs[0].get_sched_queue(idx).empty());
+}
+
+#[test]
+fn ep_queue_appends_and_dequeues_from_anywhere() {
+    let mut tcbs = new_tcbs(3, 0);
+    let mut queue = tcb_queue_t::default();
+    assert!(queue.empty());
+    for tcb in tcbs.iter_mut() {
+        queue.ep_append(tcb);
+    }
+    assert_eq!(queue.head, tcbs[0].get_ptr());
+    assert_eq!(queue.tail, tcbs[2].get_ptr());
+    assert_eq!(tcbs[1].tcbEPPrev, tcbs[0].get_ptr());
+    assert_eq!(tcbs[1].tcbEPNext, tcbs[2].get_ptr());
+
+    queue.ep_dequeue(&mut tcbs[1]);
+    assert_eq!(tcbs[0].tcbEPNext, tcbs[2].get_ptr());
+    assert_eq!(tcbs[2].tcbEPPrev, tcbs[0].get_ptr());
+
+    queue.ep_dequeue(&mut tcbs[0]);
+    assert_eq!(queue.head, tcbs[2].get_ptr());
+    assert_eq!(queue.tail, tcbs[2].get_ptr());
+
+    queue.ep_dequeue(&mut tcbs[2]);
+    assert!(queue.empty());
+    assert_eq!(queue.tail, 0);
+}
@@ -9,13 +9,27 @@
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
 
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+mod benchmark;
 mod deps;
+#[cfg(all(test, not(any(target_arch = "riscv64", target_arch = "aarch64"))))]
+mod host_tests;
+#[cfg(feature = "KERNEL_MCS")]
+mod reply;
+#[cfg(feature = "KERNEL_MCS")]
+mod sched_context;
 mod scheduler;
 mod structures;
 pub mod tcb;
 mod tcb_queue;
 mod thread_state;
 
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+pub use benchmark::*;
+#[cfg(feature = "KERNEL_MCS")]
+pub use reply::*;
+#[cfg(feature = "KERNEL_MCS")]
+pub use sched_context::*;
 pub use scheduler::*;
 pub use structures::*;
 pub use tcb::*;
@@ -0,0 +1,43 @@
+//! Reply objects of the MCS kernel.
+//!
+//! A reply object is bound either to the thread waiting on a receive with it, or to the caller
+//! blocked until the receiver answers through it. The thread keeps the reply object in its
+//! thread state in both cases.
+
+use crate::tcb::{set_thread_state, tcb_t};
+use crate::thread_state::ThreadState;
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+/// Structure for the reply object
+pub struct reply_t {
+    /// The TCB bound to the reply object, 0 if there is none
+    pub replyTCB: usize,
+}
+
+impl reply_t {
+    #[inline]
+    /// Get the pointer of the reply object
+    pub fn get_ptr(&self) -> usize {
+        self as *const reply_t as usize
+    }
+
+    /// Block the caller on the reply object until the callee replies through it.
+    pub fn push(&mut self, caller: &mut tcb_t, callee: &mut tcb_t) {
+        assert_eq!(self.replyTCB, 0);
+        assert_eq!(caller.tcbState.get_reply_object(), 0);
+        callee.tcbState.set_reply_object(0);
+        self.replyTCB = caller.get_ptr();
+        caller.tcbState.set_reply_object(self.get_ptr());
+        set_thread_state(caller, ThreadState::ThreadStateBlockedOnReply);
+    }
+
+    /// Break the binding between the reply object and the TCB, which stops.
+    pub fn unlink(&mut self, tcb: &mut tcb_t) {
+        assert_eq!(self.replyTCB, tcb.get_ptr());
+        assert_eq!(tcb.tcbState.get_reply_object(), self.get_ptr());
+        tcb.tcbState.set_reply_object(0);
+        self.replyTCB = 0;
+        set_thread_state(tcb, ThreadState::ThreadStateInactive);
+    }
+}
@@ -0,0 +1,371 @@
+//! Scheduling contexts of the MCS kernel.
+//!
+//! A scheduling context holds the CPU budget of the thread bound to it. The budget is kept as a
+//! circular buffer of refills placed right after the header, each refill is an amount of time
+//! which becomes usable at its release time. The head refill is the one being consumed, and the
+//! refills of a sporadic server never give more than its budget in any window of its period.
+//!
+//! Scheduling contexts are not donated over IPC, so each thread runs on its own.
+
+use core::intrinsics::unlikely;
+use core::mem::size_of;
+
+use sel4_common::sel4_config::{CONFIG_KERNEL_WCET_US, MAX_PERIOD_US, MIN_BUDGET_US};
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_common::BIT;
+
+use crate::deps::{getTimerPrecision, ticksToUs, usToTicks};
+use crate::scheduler::{
+    ksCurTime, ksReprogram, release_enqueue, release_remove, rescheduleRequired,
+};
+use crate::tcb::tcb_t;
+
+/// Time in timer ticks
+pub type ticks_t = usize;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+/// A budget of `rAmount` ticks usable from `rTime`
+pub struct refill_t {
+    /// The release time of the refill
+    pub rTime: ticks_t,
+    /// The amount of ticks of the refill
+    pub rAmount: ticks_t,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+/// Structure for the scheduling context
+pub struct sched_context_t {
+    /// The period of the scheduling context, 0 for a round robin one
+    pub scPeriod: ticks_t,
+    /// The ticks consumed since the last SchedContextConsumed
+    pub scConsumed: ticks_t,
+    /// The core of the scheduling context
+    pub scCore: usize,
+    /// The TCB bound to the scheduling context
+    pub scTcb: usize,
+    /// The badge delivered with the timeout faults of the scheduling context
+    pub scBadge: usize,
+    /// Whether the refills are only released when the thread unblocks
+    pub scSporadic: bool,
+    /// The size of the refill buffer, 0 if the scheduling context is not configured
+    pub scRefillMax: usize,
+    /// The index of the head refill
+    pub scRefillHead: usize,
+    /// The number of refills in use
+    pub scRefillCount: usize,
+}
+
+#[inline]
+/// Convert microseconds to timer ticks.
+pub fn us_to_ticks(us: usize) -> ticks_t {
+    unsafe { usToTicks(us) }
+}
+
+#[inline]
+/// Convert timer ticks to microseconds.
+pub fn ticks_to_us(ticks: ticks_t) -> usize {
+    unsafe { ticksToUs(ticks) }
+}
+
+#[inline]
+/// Get the worst case execution time of the kernel in ticks.
+pub fn get_kernel_wcet_ticks() -> ticks_t {
+    us_to_ticks(CONFIG_KERNEL_WCET_US)
+}
+
+#[inline]
+/// Get the smallest budget a refill can hold in ticks.
+pub fn min_budget() -> ticks_t {
+    us_to_ticks(MIN_BUDGET_US)
+}
+
+#[inline]
+/// Get how early a deadline is programmed for the timer interrupt to be on time, in ticks.
+pub fn timer_precision() -> ticks_t {
+    unsafe { getTimerPrecision() }
+}
+
+#[inline]
+/// Get the release time after which refills are never released.
+pub fn max_release_time() -> ticks_t {
+    usize::MAX - 5 * us_to_ticks(MAX_PERIOD_US)
+}
+
+#[inline]
+/// Get the number of refills a scheduling context of `2^size_bits` bytes can hold.
+pub fn refill_absolute_max(size_bits: usize) -> usize {
+    (BIT!(size_bits) - size_of::<sched_context_t>()) / size_of::<refill_t>()
+}
+
+impl sched_context_t {
+    #[inline]
+    /// Get the pointer of the scheduling context
+    pub fn get_ptr(&self) -> usize {
+        self as *const sched_context_t as usize
+    }
+
+    #[inline]
+    fn refill_index(&self, index: usize) -> &'static mut refill_t {
+        convert_to_mut_type_ref::<refill_t>(
+            self.get_ptr() + size_of::<sched_context_t>() + index * size_of::<refill_t>(),
+        )
+    }
+
+    #[inline]
+    fn refill_next(&self, index: usize) -> usize {
+        if index == self.scRefillMax - 1 {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    #[inline]
+    fn refill_tail_index(&self) -> usize {
+        let index = self.scRefillHead + self.scRefillCount - 1;
+        if index >= self.scRefillMax {
+            index - self.scRefillMax
+        } else {
+            index
+        }
+    }
+
+    #[inline]
+    /// Get the refill being consumed
+    pub fn refill_head(&self) -> &'static mut refill_t {
+        self.refill_index(self.scRefillHead)
+    }
+
+    #[inline]
+    /// Get the refill released last
+    pub fn refill_tail(&self) -> &'static mut refill_t {
+        self.refill_index(self.refill_tail_index())
+    }
+
+    #[inline]
+    fn refill_single(&self) -> bool {
+        self.scRefillCount == 1
+    }
+
+    #[inline]
+    fn refill_full(&self) -> bool {
+        self.scRefillCount == self.scRefillMax
+    }
+
+    fn refill_pop_head(&mut self) -> refill_t {
+        assert!(!self.refill_single());
+        let refill = *self.refill_head();
+        self.scRefillHead = self.refill_next(self.scRefillHead);
+        self.scRefillCount -= 1;
+        refill
+    }
+
+    fn refill_add_tail(&mut self, refill: refill_t) {
+        assert!(!self.refill_full());
+        let index = self.refill_next(self.refill_tail_index());
+        *self.refill_index(index) = refill;
+        self.scRefillCount += 1;
+    }
+
+    #[inline]
+    /// A round robin scheduling context gets its whole budget back at the end of each timeslice
+    pub fn is_round_robin(&self) -> bool {
+        self.scPeriod == 0
+    }
+
+    #[inline]
+    /// Check if the scheduling context has been configured
+    pub fn is_active(&self) -> bool {
+        self.scRefillMax > 0
+    }
+
+    #[inline]
+    /// Get the budget left in the head refill after `usage` ticks
+    pub fn refill_capacity(&self, usage: ticks_t) -> ticks_t {
+        let head = self.refill_head();
+        if unlikely(usage > head.rAmount) {
+            0
+        } else {
+            head.rAmount - usage
+        }
+    }
+
+    #[inline]
+    /// Check if the head refill can still be used after `usage` ticks
+    pub fn refill_sufficient(&self, usage: ticks_t) -> bool {
+        self.refill_capacity(usage) >= min_budget()
+    }
+
+    #[inline]
+    /// Check if the head refill has been released, allowing for the time spent in the kernel
+    pub fn refill_ready(&self) -> bool {
+        self.refill_head().rTime <= unsafe { ksCurTime } + get_kernel_wcet_ticks()
+    }
+
+    fn maybe_add_empty_tail(&mut self) {
+        if self.is_round_robin() {
+            // the time used by a round robin thread is tracked in the empty tail
+            let head = *self.refill_head();
+            self.refill_add_tail(refill_t {
+                rTime: head.rTime,
+                rAmount: 0,
+            });
+        }
+    }
+
+    /// Give the scheduling context a full budget usable from now.
+    pub fn refill_new(&mut self, max_refills: usize, budget: ticks_t, period: ticks_t) {
+        self.scPeriod = period;
+        self.scRefillHead = 0;
+        self.scRefillCount = 1;
+        self.scRefillMax = max_refills;
+        let head = self.refill_head();
+        head.rAmount = budget;
+        head.rTime = unsafe { ksCurTime };
+        self.maybe_add_empty_tail();
+    }
+
+    /// Change the budget of a scheduling context in use without exceeding the new budget in
+    /// the new period.
+    pub fn refill_update(
+        &mut self,
+        new_period: ticks_t,
+        new_budget: ticks_t,
+        new_max_refills: usize,
+    ) {
+        assert!(self.is_active());
+        // keep only the head, moved to the start of the buffer which may shrink
+        *self.refill_index(0) = *self.refill_head();
+        self.scRefillHead = 0;
+        self.scRefillCount = 1;
+        self.scRefillMax = new_max_refills;
+        self.scPeriod = new_period;
+
+        if self.refill_ready() {
+            self.refill_head().rTime = unsafe { ksCurTime };
+        }
+
+        let head = *self.refill_head();
+        if head.rAmount >= new_budget {
+            self.refill_head().rAmount = new_budget;
+            self.maybe_add_empty_tail();
+        } else {
+            // the rest of the budget comes in the next period
+            self.refill_add_tail(refill_t {
+                rTime: head.rTime + new_period,
+                rAmount: new_budget - head.rAmount,
+            });
+        }
+    }
+
+    /// Schedule a used refill to be released again, merging it into the tail when they overlap
+    /// or when there is no room left.
+    fn schedule_used(&mut self, new: refill_t) {
+        let tail = self.refill_tail();
+        if tail.rTime + tail.rAmount >= new.rTime {
+            tail.rAmount += new.rAmount;
+        } else if !self.refill_full() {
+            self.refill_add_tail(new);
+        } else {
+            tail.rTime = new.rTime - tail.rAmount;
+            tail.rAmount += new.rAmount;
+        }
+    }
+
+    /// Charge `usage` ticks to the refills of a sporadic scheduling context, the used time is
+    /// released again one period later.
+    pub fn refill_budget_check(&mut self, mut usage: ticks_t) {
+        assert!(!self.is_round_robin());
+        while self.refill_head().rAmount <= usage && self.refill_head().rTime < max_release_time() {
+            usage -= self.refill_head().rAmount;
+            if self.refill_single() {
+                self.refill_head().rTime += self.scPeriod;
+            } else {
+                let mut old_head = self.refill_pop_head();
+                old_head.rTime += self.scPeriod;
+                self.schedule_used(old_head);
+            }
+        }
+
+        if usage > 0 && self.refill_head().rTime < max_release_time() {
+            let head = self.refill_head();
+            let used = refill_t {
+                rTime: head.rTime + self.scPeriod,
+                rAmount: usage,
+            };
+            head.rAmount -= usage;
+            head.rTime += usage;
+            self.schedule_used(used);
+        }
+
+        // merge refills until the head is large enough to be used
+        while self.refill_head().rAmount < min_budget() {
+            let refill = self.refill_pop_head();
+            let head = self.refill_head();
+            head.rAmount += refill.rAmount;
+            head.rTime -= refill.rAmount;
+        }
+    }
+
+    /// Move the release of the head refill to now if it has been released, merging the refills
+    /// released since then.
+    pub fn refill_unblock_check(&mut self) {
+        if self.is_round_robin() || !self.refill_ready() {
+            return;
+        }
+        let now = unsafe { ksCurTime };
+        self.refill_head().rTime = now;
+        unsafe {
+            ksReprogram = true;
+        }
+        while !self.refill_single() {
+            let amount = self.refill_head().rAmount;
+            if self.refill_index(self.refill_next(self.scRefillHead)).rTime > now + amount {
+                break;
+            }
+            self.refill_pop_head();
+            let head = self.refill_head();
+            head.rAmount += amount;
+            head.rTime = now;
+        }
+    }
+
+    /// Bind the scheduling context to the TCB and run it if it is runnable.
+    pub fn bind_tcb(&mut self, tcb: &mut tcb_t) {
+        assert_eq!(self.scTcb, 0);
+        assert_eq!(tcb.tcbSchedContext, 0);
+        self.scTcb = tcb.get_ptr();
+        tcb.tcbSchedContext = self.get_ptr();
+        if tcb.is_schedulable() {
+            tcb.sched_enqueue();
+            rescheduleRequired();
+        }
+    }
+
+    /// Unbind the scheduling context from its TCB, which stops running.
+    pub fn unbind_tcb(&mut self) {
+        if self.scTcb == 0 {
+            return;
+        }
+        let tcb = convert_to_mut_type_ref::<tcb_t>(self.scTcb);
+        if tcb.is_current() {
+            rescheduleRequired();
+        }
+        tcb.sched_dequeue();
+        release_remove(tcb);
+        tcb.tcbSchedContext = 0;
+        self.scTcb = 0;
+    }
+
+    /// Make the bound TCB wait in the release queue until the head refill is released.
+    pub fn postpone(&mut self) {
+        let tcb = convert_to_mut_type_ref::<tcb_t>(self.scTcb);
+        tcb.sched_dequeue();
+        release_enqueue(tcb);
+        unsafe {
+            ksReprogram = true;
+        }
+    }
+}
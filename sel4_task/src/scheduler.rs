@@ -5,7 +5,8 @@
 //! new threads to run, managing ready queues, and handling domain scheduling.
 //!
 #[cfg(feature = "ENABLE_SMP")]
-use crate::deps::{doMaskReschedule, kernel_stack_alloc, ksIdleThreadTCB};
+use crate::deps::{doMaskReschedule, ksIdleThreadTCB};
+#[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
 use core::arch::asm;
 use core::intrinsics::{likely, unlikely};
 use sel4_common::arch::ArchReg;
@@ -13,19 +14,29 @@ use sel4_common::arch::ArchReg;
 use sel4_common::sel4_config::{seL4_TCBBits, CONFIG_MAX_NUM_NODES};
 use sel4_common::sel4_config::{
     wordBits, wordRadix, CONFIG_NUM_DOMAINS, CONFIG_NUM_PRIORITIES, CONFIG_TIME_SLICE,
-    L2_BITMAP_SIZE, NUM_READY_QUEUES, TCB_OFFSET,
+    DOMAIN_SCHEDULE, L2_BITMAP_SIZE, NUM_READY_QUEUES, TCB_OFFSET,
 };
 use sel4_common::utils::{convert_to_mut_type_ref, convert_to_mut_type_ref_unsafe};
 use sel4_common::{BIT, MASK};
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 use sel4_vspace::{
     get_arm_global_user_vspace_base, kpptr_to_paddr, setCurrentUserVSpaceRoot, ttbr_new,
 };
 
+#[cfg(feature = "KERNEL_MCS")]
+use crate::deps::{getCurrentTime, setDeadline};
+#[cfg(feature = "KERNEL_MCS")]
+use crate::sched_context::{
+    max_release_time, min_budget, sched_context_t, ticks_t, timer_precision, us_to_ticks,
+};
 use crate::tcb::{set_thread_state, tcb_t};
 use crate::tcb_queue::tcb_queue_t;
 use crate::thread_state::ThreadState;
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ENABLE_SMP")))]
+use sel4_common::sel4_config::{
+    seL4_MinSchedContextBits, CONFIG_BOOT_THREAD_TIME_SLICE, MIN_REFILLS,
+};
 #[cfg(feature = "ENABLE_SMP")]
 use sel4_common::utils::cpu_id;
 
@@ -77,7 +88,20 @@ pub struct dschedule_t {
 
 pub const SchedulerAction_ResumeCurrentThread: usize = 0;
 pub const SchedulerAction_ChooseNewThread: usize = 1;
-pub const ksDomScheduleLength: usize = 1;
+
+pub const ksDomScheduleLength: usize = dom_schedule_length(DOMAIN_SCHEDULE);
+
+// ksDomainTime and ksCurDomain are global: the tick of every core would be taken off the domain
+// time, and only the core which ends the domain would switch, the others would keep running the
+// threads of the previous domain until they reschedule by themselves
+const _: () = assert!(
+    CONFIG_NUM_DOMAINS == 1 || sel4_common::sel4_config::CONFIG_MAX_NUM_NODES == 1,
+    "domain scheduling is not supported with SMP"
+);
+
+// The release queue and the timer state of MCS are only kept for one core
+#[cfg(all(feature = "KERNEL_MCS", feature = "ENABLE_SMP"))]
+compile_error!("KERNEL_MCS is not supported with ENABLE_SMP");
 
 #[no_mangle]
 pub static mut ksDomainTime: usize = 0;
@@ -97,6 +121,9 @@ pub static mut ksIdleThread: usize = 0;
 #[no_mangle]
 pub static mut ksSchedulerAction: usize = 1;
 
+#[no_mangle]
+pub static mut ksDebugTCBs: usize = 0;
+
 #[no_mangle]
 pub static mut ksReadyQueues: [tcb_queue_t; NUM_READY_QUEUES] =
     [tcb_queue_t { head: 0, tail: 0 }; NUM_READY_QUEUES];
@@ -112,11 +139,104 @@ pub static mut ksReadyQueuesL1Bitmap: [usize; CONFIG_NUM_DOMAINS] = [0; CONFIG_N
 // #[link_section = ".boot.bss"]
 pub static mut ksWorkUnitsCompleted: usize = 0;
 
+/// The threads waiting for their budget, sorted by the release time of their head refill
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksReleaseHead: usize = 0;
+
+/// The scheduling context charged for the time spent since the last commit
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksCurSC: usize = 0;
+
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksIdleSC: usize = 0;
+
+/// The ticks consumed by the current scheduling context and not charged yet
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksConsumed: ticks_t = 0;
+
+/// The time of the last kernel entry
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksCurTime: ticks_t = 0;
+
+/// Whether the timer has to be programmed again before leaving the kernel
+#[cfg(feature = "KERNEL_MCS")]
+#[no_mangle]
+pub static mut ksReprogram: bool = false;
+
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ENABLE_SMP")))]
+#[repr(align(256))]
+struct ksIdleSC_data {
+    data: [u8; BIT!(seL4_MinSchedContextBits)],
+}
+
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ENABLE_SMP")))]
+const _: () = assert!(core::mem::align_of::<ksIdleSC_data>() == BIT!(seL4_MinSchedContextBits));
+
+#[cfg(all(feature = "KERNEL_MCS", not(feature = "ENABLE_SMP")))]
+static mut ksIdleSCData: ksIdleSC_data = ksIdleSC_data {
+    data: [0; BIT!(seL4_MinSchedContextBits)],
+};
+
 // #[link_section = ".boot.bss"]
-pub static mut ksDomSchedule: [dschedule_t; ksDomScheduleLength] = [dschedule_t {
-    domain: 0,
-    length: 60,
-}; ksDomScheduleLength];
+pub static mut ksDomSchedule: [dschedule_t; ksDomScheduleLength] =
+    parse_dom_schedule(DOMAIN_SCHEDULE);
+
+/// Get the number of entries in the domain schedule.
+const fn dom_schedule_length(schedule: &str) -> usize {
+    let bytes = schedule.as_bytes();
+    let mut length = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b',' {
+            length += 1;
+        }
+        i += 1;
+    }
+    length
+}
+
+/// Parse the domain schedule at build time, an invalid schedule fails the build.
+const fn parse_dom_schedule(schedule: &str) -> [dschedule_t; ksDomScheduleLength] {
+    let bytes = schedule.as_bytes();
+    let mut dom_schedule = [dschedule_t {
+        domain: 0,
+        length: 0,
+    }; ksDomScheduleLength];
+    let mut entry = 0;
+    let mut value = 0;
+    let mut has_domain = false;
+    let mut i = 0;
+    while i <= bytes.len() {
+        if i == bytes.len() || bytes[i] == b',' {
+            assert!(has_domain, "DOMAIN_SCHEDULE: expected domain:length");
+            assert!(value > 0, "DOMAIN_SCHEDULE: domain length must be positive");
+            dom_schedule[entry].length = value;
+            entry += 1;
+            value = 0;
+            has_domain = false;
+        } else if bytes[i] == b':' {
+            assert!(!has_domain, "DOMAIN_SCHEDULE: expected domain:length");
+            assert!(
+                value < CONFIG_NUM_DOMAINS,
+                "DOMAIN_SCHEDULE: domain out of range"
+            );
+            dom_schedule[entry].domain = value;
+            value = 0;
+            has_domain = true;
+        } else if bytes[i].is_ascii_digit() {
+            value = value * 10 + (bytes[i] - b'0') as usize;
+        } else {
+            assert!(bytes[i] == b' ', "DOMAIN_SCHEDULE: invalid character");
+        }
+        i += 1;
+    }
+    dom_schedule
+}
 
 #[allow(non_camel_case_types)]
 type prio_t = usize;
@@ -200,6 +320,13 @@ pub fn get_currenct_thread_unsafe() -> &'static mut tcb_t {
     }
 }
 
+#[cfg(feature = "KERNEL_MCS")]
+#[inline]
+/// Get the scheduling context charged for the current time.
+pub fn get_current_sc() -> &'static mut sched_context_t {
+    unsafe { convert_to_mut_type_ref::<sched_context_t>(ksCurSC) }
+}
+
 #[inline]
 /// Set the action to be taken by current scheduler.
 pub fn set_current_scheduler_action(action: usize) {
@@ -230,12 +357,52 @@ pub fn set_current_thread(thread: &tcb_t) {
     }
 }
 
+#[inline]
+/// Get the head of the debug TCB list of the given core.
+pub fn get_debug_tcbs_head(_cpu: usize) -> usize {
+    unsafe {
+        #[cfg(feature = "ENABLE_SMP")]
+        {
+            ksSMP[_cpu].ksDebugTCBs
+        }
+        #[cfg(not(feature = "ENABLE_SMP"))]
+        {
+            ksDebugTCBs
+        }
+    }
+}
+
+#[inline]
+/// Set the head of the debug TCB list of the given core.
+pub fn set_debug_tcbs_head(_cpu: usize, head: usize) {
+    unsafe {
+        #[cfg(feature = "ENABLE_SMP")]
+        {
+            ksSMP[_cpu].ksDebugTCBs = head;
+        }
+        #[cfg(not(feature = "ENABLE_SMP"))]
+        {
+            ksDebugTCBs = head
+        }
+    }
+}
+
 #[inline]
 /// Get the current domain.
 pub fn get_current_domain() -> usize {
     unsafe { ksCurDomain }
 }
 
+#[inline]
+/// Get the domain whose ready queues the fastpath checks, which is always 0 with one domain.
+pub fn get_fastpath_domain() -> usize {
+    if CONFIG_NUM_DOMAINS > 1 {
+        get_current_domain()
+    } else {
+        0
+    }
+}
+
 #[inline]
 /// Get the index of the ready queue for the given domain and priority level.
 pub fn ready_queues_index(dom: usize, prio: usize) -> usize {
@@ -355,12 +522,36 @@ fn nextDomain() {
         }
         ksWorkUnitsCompleted = 0;
         ksCurDomain = ksDomSchedule[ksDomScheduleIdx].domain;
-        ksDomainTime = ksDomSchedule[ksDomScheduleIdx].length;
+        ksDomainTime = cur_domain_length();
+        #[cfg(feature = "KERNEL_MCS")]
+        {
+            ksReprogram = true;
+        }
         //FIXME ksWorkUnits not used;
         // ksWorkUnits
     }
 }
 
+/// The length of the current entry of the domain schedule, in timer ticks, or in the ticks of
+/// the kernel timer for MCS.
+pub fn cur_domain_length() -> usize {
+    let length = unsafe { ksDomSchedule[ksDomScheduleIdx].length };
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        us_to_ticks(length * 1000)
+    }
+    #[cfg(not(feature = "KERNEL_MCS"))]
+    {
+        length
+    }
+}
+
+/// Whether the current domain has used up its time.
+#[cfg(feature = "KERNEL_MCS")]
+fn is_cur_domain_expired() -> bool {
+    CONFIG_NUM_DOMAINS > 1 && unsafe { ksDomainTime } == 0
+}
+
 fn scheduleChooseNewThread() {
     // if hart_id() == 0 {
     //     debug!("scheduleChooseNewThread");
@@ -376,7 +567,11 @@ fn scheduleChooseNewThread() {
 
 fn chooseThread() {
     unsafe {
-        let dom = 0;
+        let dom = if CONFIG_NUM_DOMAINS > 1 {
+            ksCurDomain
+        } else {
+            0
+        };
         let ks_l1_bit = {
             #[cfg(feature = "ENABLE_SMP")]
             {
@@ -402,20 +597,27 @@ fn chooseThread() {
             assert_ne!(thread, 0);
             convert_to_mut_type_ref::<tcb_t>(thread).switch_to_this();
         } else {
-            #[cfg(target_arch = "aarch64")]
-            {
-                setCurrentUserVSpaceRoot(ttbr_new(
-                    0,
-                    kpptr_to_paddr(get_arm_global_user_vspace_base()),
-                ));
-                ksCurThread = ksIdleThread;
-            }
-            #[cfg(target_arch = "riscv64")]
-            get_idle_thread().switch_to_this();
+            switch_to_idle_thread();
         }
     }
 }
 
+/// Switch to the idle thread of the current core.
+pub fn switch_to_idle_thread() {
+    #[cfg(not(target_arch = "riscv64"))]
+    {
+        setCurrentUserVSpaceRoot(ttbr_new(
+            0,
+            kpptr_to_paddr(get_arm_global_user_vspace_base()),
+        ));
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        crate::benchmark::benchmark_utilisation_switch(get_idle_thread());
+        set_current_thread(get_idle_thread());
+    }
+    #[cfg(target_arch = "riscv64")]
+    get_idle_thread().switch_to_this();
+}
+
 #[no_mangle]
 /// Reschedule threads, and enqueue the current thread if current ks scheduler action is not to resume the current thread and choose new thread.
 pub fn rescheduleRequired() {
@@ -431,10 +633,16 @@ pub fn rescheduleRequired() {
 #[no_mangle]
 /// Schedule threads.
 pub fn schedule() {
+    #[cfg(feature = "KERNEL_MCS")]
+    awaken();
     if get_ks_scheduler_action() != SchedulerAction_ResumeCurrentThread {
         let was_runnable: bool;
         let current_tcb = get_currenct_thread();
-        if current_tcb.is_runnable() {
+        #[cfg(not(feature = "KERNEL_MCS"))]
+        let current_runnable = current_tcb.is_runnable();
+        #[cfg(feature = "KERNEL_MCS")]
+        let current_runnable = current_tcb.is_schedulable();
+        if current_runnable {
             was_runnable = true;
             current_tcb.sched_enqueue();
         } else {
@@ -468,6 +676,16 @@ pub fn schedule() {
         doMaskReschedule(ksSMP[cpu_id()].ipiReschedulePending);
         ksSMP[cpu_id()].ipiReschedulePending = 0;
     }
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        switch_sched_context();
+        unsafe {
+            if ksReprogram {
+                set_next_interrupt();
+                ksReprogram = false;
+            }
+        }
+    }
 }
 
 #[inline]
@@ -481,25 +699,29 @@ pub fn schedule_tcb(tcb_ref: &tcb_t) {
     }
 }
 
-#[cfg(feature = "ENABLE_SMP")]
 #[inline]
 /// Schedule the given tcb when current tcb is not in the same domain or not in the same cpu or current action is not to resume the current thread.
 pub fn possible_switch_to(target: &mut tcb_t) {
-    if unsafe { ksCurDomain != target.domain || target.tcbAffinity != cpu_id() } {
-        target.sched_enqueue();
-    } else if get_ks_scheduler_action() != SchedulerAction_ResumeCurrentThread {
-        rescheduleRequired();
-        target.sched_enqueue();
-    } else {
-        set_ks_scheduler_action(target.get_ptr());
+    #[cfg(feature = "KERNEL_MCS")]
+    {
+        if target.tcbSchedContext == 0 || target.tcbState.get_tcb_in_release_queue() != 0 {
+            return;
+        }
+        let sc = target.get_sched_context();
+        if sc.scSporadic && sc.get_ptr() != unsafe { ksCurSC } {
+            sc.refill_unblock_check();
+        }
+        if !(sc.refill_ready() && sc.refill_sufficient(0)) {
+            // wait in the release queue
+            target.sched_enqueue();
+            return;
+        }
     }
-}
-
-#[cfg(not(feature = "ENABLE_SMP"))]
-#[inline]
-/// Schedule the given tcb when current tcb is not in the same domain or current action is not to resume the current thread.
-pub fn possible_switch_to(target: &mut tcb_t) {
-    if unsafe { ksCurDomain != target.domain } {
+    #[cfg(feature = "ENABLE_SMP")]
+    let other_cpu = target.tcbAffinity != cpu_id();
+    #[cfg(not(feature = "ENABLE_SMP"))]
+    let other_cpu = false;
+    if unsafe { ksCurDomain != target.domain } || other_cpu {
         target.sched_enqueue();
     } else if get_ks_scheduler_action() != SchedulerAction_ResumeCurrentThread {
         rescheduleRequired();
@@ -533,6 +755,15 @@ pub fn timerTick() {
             rescheduleRequired();
         }
     }
+
+    if CONFIG_NUM_DOMAINS > 1 {
+        unsafe {
+            ksDomainTime -= 1;
+            if ksDomainTime == 0 {
+                rescheduleRequired();
+            }
+        }
+    }
 }
 
 #[no_mangle]
@@ -572,8 +803,21 @@ pub fn create_idle_thread() {
         // let tcb = convert_to_mut_type_ref::<tcb_t>(ksIdleThread as usize);
         let tcb = get_idle_thread();
         // Arch_configureIdleThread(tcb.tcbArch);
-        tcb.tcbArch.config_idle_thread(idle_thread as usize);
+        tcb.tcbArch.config_idle_thread(idle_thread as usize, 0);
         set_thread_state(tcb, ThreadState::ThreadStateIdleThreadState);
+        #[cfg(feature = "KERNEL_MCS")]
+        {
+            ksIdleSC = &mut ksIdleSCData.data[0] as *mut u8 as usize;
+            let sc = convert_to_mut_type_ref::<sched_context_t>(ksIdleSC);
+            // the budget of the idle thread is never charged, it only bounds its timeslice
+            sc.refill_new(
+                MIN_REFILLS,
+                us_to_ticks(CONFIG_BOOT_THREAD_TIME_SLICE * 1000),
+                0,
+            );
+            sc.scTcb = tcb.get_ptr();
+            tcb.tcbSchedContext = ksIdleSC;
+        }
     }
 }
 
@@ -583,31 +827,265 @@ pub fn create_idle_thread() {
     use log::debug;
     unsafe {
         for i in 0..CONFIG_MAX_NUM_NODES {
-            let pptr = (unsafe { &mut ksIdleThreadTCB.data[0][0] as *mut u8 } as usize
+            let pptr = (&mut ksIdleThreadTCB.data[0][0] as *mut u8 as usize
                 + i * BIT!(seL4_TCBBits)) as *mut usize;
             // let pptr = (ksIdleThreadTCB as usize + i * BIT!(seL4_TCBBits)) as *mut usize;
             ksSMP[i].ksIdleThread = pptr.add(TCB_OFFSET) as usize;
             debug!("ksIdleThread: {:#x}", ksSMP[i].ksIdleThread);
             let tcb = convert_to_mut_type_ref::<tcb_t>(ksSMP[i].ksIdleThread);
-            tcb.tcbArch.set_register(NextIP, idle_thread as usize);
-            tcb.tcbArch
-                .set_register(SSTATUS, SSTATUS_SPP | SSTATUS_SPIE);
-            tcb.tcbArch.set_register(
-                sp,
-                unsafe { &kernel_stack_alloc.data[0][0] as *const u8 } as usize
-                    + (i + 1) * BIT!(CONFIG_KERNEL_STACK_BITS),
-            );
+            tcb.tcbArch.config_idle_thread(idle_thread as usize, i);
             set_thread_state(tcb, ThreadState::ThreadStateIdleThreadState);
             tcb.tcbAffinity = i;
         }
     }
 }
 
+#[cfg(feature = "KERNEL_MCS")]
+/// Insert the TCB in the release queue, sorted by the release time of its head refill.
+pub fn release_enqueue(tcb: &mut tcb_t) {
+    assert_eq!(tcb.tcbState.get_tcb_in_release_queue(), 0);
+    assert_eq!(tcb.tcbState.get_tcb_queued(), 0);
+    let time = tcb.get_sched_context().refill_head().rTime;
+    let mut prev = 0;
+    let mut next = unsafe { ksReleaseHead };
+    while next != 0
+        && convert_to_mut_type_ref::<tcb_t>(next)
+            .get_sched_context()
+            .refill_head()
+            .rTime
+            <= time
+    {
+        prev = next;
+        next = convert_to_mut_type_ref::<tcb_t>(next).tcbSchedNext;
+    }
+    if prev == 0 {
+        unsafe {
+            ksReleaseHead = tcb.get_ptr();
+            ksReprogram = true;
+        }
+    } else {
+        convert_to_mut_type_ref::<tcb_t>(prev).tcbSchedNext = tcb.get_ptr();
+    }
+    if next != 0 {
+        convert_to_mut_type_ref::<tcb_t>(next).tcbSchedPrev = tcb.get_ptr();
+    }
+    tcb.tcbSchedPrev = prev;
+    tcb.tcbSchedNext = next;
+    tcb.tcbState.set_tcb_in_release_queue(1);
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Remove the TCB from the release queue if it is in it.
+pub fn release_remove(tcb: &mut tcb_t) {
+    if tcb.tcbState.get_tcb_in_release_queue() == 0 {
+        return;
+    }
+    if tcb.tcbSchedPrev != 0 {
+        convert_to_mut_type_ref::<tcb_t>(tcb.tcbSchedPrev).tcbSchedNext = tcb.tcbSchedNext;
+    } else {
+        unsafe {
+            ksReleaseHead = tcb.tcbSchedNext;
+            ksReprogram = true;
+        }
+    }
+    if tcb.tcbSchedNext != 0 {
+        convert_to_mut_type_ref::<tcb_t>(tcb.tcbSchedNext).tcbSchedPrev = tcb.tcbSchedPrev;
+    }
+    tcb.tcbSchedPrev = 0;
+    tcb.tcbSchedNext = 0;
+    tcb.tcbState.set_tcb_in_release_queue(0);
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Move the threads whose budget has been released from the release queue to the ready queues.
+fn awaken() {
+    unsafe {
+        while ksReleaseHead != 0
+            && convert_to_mut_type_ref::<tcb_t>(ksReleaseHead)
+                .get_sched_context()
+                .refill_ready()
+        {
+            let awakened = convert_to_mut_type_ref::<tcb_t>(ksReleaseHead);
+            release_remove(awakened);
+            assert!(awakened.get_sched_context().refill_sufficient(0));
+            possible_switch_to(awakened);
+        }
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Read the time on kernel entry and add the time since the last entry to the consumed time.
+pub fn update_timestamp() {
+    unsafe {
+        let prev = ksCurTime;
+        ksCurTime = getCurrentTime();
+        assert!(ksCurTime < max_release_time());
+        let consumed = ksCurTime - prev;
+        ksConsumed += consumed;
+        if CONFIG_NUM_DOMAINS > 1 {
+            // what is left of the domain is too short to be used
+            if consumed + min_budget() >= ksDomainTime {
+                ksDomainTime = 0;
+            } else {
+                ksDomainTime -= consumed;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Check if the current scheduling context can pay for the consumed time, and end the timeslice
+/// of the current thread otherwise. The current domain ending also ends it.
+pub fn check_budget() -> bool {
+    let sc = get_current_sc();
+    assert!(sc.refill_ready());
+    if likely(sc.refill_sufficient(unsafe { ksConsumed })) {
+        if unlikely(is_cur_domain_expired()) {
+            unsafe {
+                ksReprogram = true;
+            }
+            rescheduleRequired();
+            return false;
+        }
+        return true;
+    }
+    charge_budget(unsafe { ksConsumed });
+    false
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Check the budget on a syscall, the current thread restarts the syscall once it gets a new
+/// budget.
+pub fn check_budget_restart() -> bool {
+    assert!(get_currenct_thread().is_runnable());
+    let result = check_budget();
+    if !result && get_currenct_thread().is_runnable() {
+        set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    }
+    result
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Charge the consumed time to the current scheduling context and end the timeslice of the
+/// current thread.
+fn charge_budget(consumed: ticks_t) {
+    let sc = get_current_sc();
+    unsafe {
+        if likely(sc.get_ptr() != ksIdleSC && sc.is_active()) {
+            if sc.is_round_robin() {
+                sc.refill_head().rAmount += sc.refill_tail().rAmount;
+                sc.refill_tail().rAmount = 0;
+            } else {
+                sc.refill_budget_check(consumed);
+            }
+            assert!(sc.refill_head().rAmount >= min_budget());
+            sc.scConsumed += consumed;
+        }
+        ksConsumed = 0;
+    }
+    let current = get_currenct_thread();
+    if likely(current.is_schedulable()) {
+        assert_eq!(current.tcbSchedContext, sc.get_ptr());
+        end_timeslice();
+        rescheduleRequired();
+        unsafe {
+            ksReprogram = true;
+        }
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Round robin the current thread if its next refill is available, or wait for it in the
+/// release queue.
+fn end_timeslice() {
+    let sc = get_current_sc();
+    if sc.refill_ready() && sc.refill_sufficient(0) {
+        get_currenct_thread().sched_append();
+    } else {
+        sc.postpone();
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Charge the consumed time to the current scheduling context.
+pub fn commit_time() {
+    let sc = get_current_sc();
+    unsafe {
+        if likely(sc.is_active() && sc.get_ptr() != ksIdleSC) {
+            if likely(ksConsumed > 0) {
+                if sc.is_round_robin() {
+                    sc.refill_head().rAmount -= ksConsumed;
+                    sc.refill_tail().rAmount += ksConsumed;
+                } else {
+                    sc.refill_budget_check(ksConsumed);
+                }
+            }
+            sc.scConsumed += ksConsumed;
+        }
+        ksConsumed = 0;
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Charge the time to the previous scheduling context when the current thread changed.
+fn switch_sched_context() {
+    let thread = get_currenct_thread();
+    unsafe {
+        if unlikely(ksCurSC != thread.tcbSchedContext) {
+            ksReprogram = true;
+            let sc = thread.get_sched_context();
+            if !sc.scSporadic {
+                sc.refill_unblock_check();
+            }
+            assert!(sc.refill_ready());
+            assert!(sc.refill_sufficient(0));
+        }
+        if ksReprogram {
+            // the new time has been acted on, the previous scheduling context pays for it
+            commit_time();
+        }
+        ksCurSC = thread.tcbSchedContext;
+    }
+}
+
+#[cfg(feature = "KERNEL_MCS")]
+/// Program the timer for the end of the budget of the current thread, the end of the current
+/// domain or the next release, whichever comes first.
+fn set_next_interrupt() {
+    unsafe {
+        let mut next_interrupt = ksCurTime
+            + get_currenct_thread()
+                .get_sched_context()
+                .refill_head()
+                .rAmount;
+        if CONFIG_NUM_DOMAINS > 1 {
+            next_interrupt = next_interrupt.min(ksCurTime + ksDomainTime);
+        }
+        if ksReleaseHead != 0 {
+            let release_time = convert_to_mut_type_ref::<tcb_t>(ksReleaseHead)
+                .get_sched_context()
+                .refill_head()
+                .rTime;
+            next_interrupt = next_interrupt.min(release_time);
+        }
+        // ksCurTime lags behind the time the timer is programmed at, never set a deadline before it
+        let deadline = next_interrupt
+            .saturating_sub(timer_precision())
+            .max(ksCurTime);
+        setDeadline(deadline);
+    }
+}
+
 pub fn idle_thread() {
+    #[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
     unsafe {
         loop {
             // debug!("hello idle_thread");
             asm!("wfi");
         }
     }
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+    loop {
+        core::hint::spin_loop();
+    }
 }
@@ -4,12 +4,16 @@ use sel4_common::arch::{
 };
 use sel4_common::fault::*;
 use sel4_common::message_info::seL4_MessageInfo_t;
+#[cfg(feature = "KERNEL_MCS")]
+use sel4_common::utils::convert_to_option_mut_type_ref;
 use sel4_common::utils::{convert_to_mut_type_ref, pageBitsForSize};
 #[cfg(feature = "ENABLE_SMP")]
 use sel4_common::BIT;
 use sel4_common::MASK;
-use sel4_cspace::interface::{cap_t, cte_insert, cte_t, mdb_node_t, resolve_address_bits, CapTag};
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(feature = "KERNEL_MCS"))]
+use sel4_cspace::interface::{cap_t, cte_insert, mdb_node_t};
+use sel4_cspace::interface::{cte_t, resolve_address_bits, CapTag};
+#[cfg(not(target_arch = "riscv64"))]
 use sel4_vspace::{
     find_vspace_for_asid, get_arm_global_user_vspace_base, kpptr_to_paddr,
     setCurrentUserVSpaceRoot, ttbr_new,
@@ -20,14 +24,26 @@ use crate::tcb_queue::tcb_queue_t;
 use sel4_common::sel4_config::*;
 use sel4_common::structures::{exception_t, seL4_IPCBuffer};
 
+#[cfg(feature = "KERNEL_MCS")]
+use super::reply::reply_t;
+#[cfg(feature = "KERNEL_MCS")]
+use super::sched_context::sched_context_t;
 use super::scheduler::{
-    addToBitmap, get_currenct_thread, possible_switch_to, ready_queues_index, removeFromBitmap,
-    rescheduleRequired, schedule_tcb, set_current_thread,
+    addToBitmap, get_currenct_thread, get_debug_tcbs_head, possible_switch_to, ready_queues_index,
+    removeFromBitmap, rescheduleRequired, schedule_tcb, set_current_thread, set_debug_tcbs_head,
 };
+#[cfg(feature = "KERNEL_MCS")]
+use super::scheduler::{release_enqueue, release_remove};
 use super::structures::lookupSlot_raw_ret_t;
+#[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+use crate::benchmark::{benchmark_util_t, benchmark_utilisation_switch};
 
 use super::thread_state::*;
 
+#[cfg(feature = "DEBUG_BUILD")]
+/// The max length of a thread name, including the terminating NUL
+pub const TCB_NAME_LENGTH: usize = 32;
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 /// Structure for the TCB
@@ -57,6 +73,9 @@ pub struct tcb_t {
     /// the affinity of the TCB in SMP
     #[cfg(feature = "ENABLE_SMP")]
     pub tcbAffinity: usize,
+    /// The scheduling context of the TCB, 0 if it has none
+    #[cfg(feature = "KERNEL_MCS")]
+    pub tcbSchedContext: usize,
     /// The next TCB in the scheduling queue
     pub tcbSchedNext: usize,
     /// The previous TCB in the scheduling queue
@@ -65,8 +84,21 @@ pub struct tcb_t {
     pub tcbEPNext: usize,
     /// The previous TCB in the EP queue
     pub tcbEPPrev: usize,
+    /// The next TCB in the debug list
+    pub tcbDebugNext: usize,
+    /// The previous TCB in the debug list
+    pub tcbDebugPrev: usize,
+    /// The name of the TCB, set by SysDebugNameThread
+    #[cfg(feature = "DEBUG_BUILD")]
+    pub tcbName: [u8; TCB_NAME_LENGTH],
+    /// The CPU utilisation of the TCB
+    #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+    pub benchmark: benchmark_util_t,
 }
 
+// the TCB shares its object with the CNode of its caps
+const _: () = assert!(core::mem::size_of::<tcb_t>() <= TCB_OFFSET);
+
 impl tcb_t {
     #[inline]
     /// Get i th cspace of the TCB, unmutable reference
@@ -121,6 +153,52 @@ impl tcb_t {
         }
     }
 
+    #[cfg(feature = "KERNEL_MCS")]
+    #[inline]
+    /// Check if the TCB is runnable and has a configured scheduling context whose budget has
+    /// been released
+    pub fn is_schedulable(&self) -> bool {
+        self.is_runnable()
+            && self.tcbSchedContext != 0
+            && self.get_sched_context().is_active()
+            && self.tcbState.get_tcb_in_release_queue() == 0
+    }
+
+    #[cfg(feature = "KERNEL_MCS")]
+    #[inline]
+    /// Get the scheduling context of the TCB
+    pub fn get_sched_context(&self) -> &'static mut sched_context_t {
+        convert_to_mut_type_ref::<sched_context_t>(self.tcbSchedContext)
+    }
+
+    #[cfg(feature = "KERNEL_MCS")]
+    /// Check if the TCB can join the ready queues, a thread whose budget has not been
+    /// released waits in the release queue instead
+    fn ready_to_queue(&mut self) -> bool {
+        if !self.is_schedulable() {
+            return false;
+        }
+        let sc = self.get_sched_context();
+        if sc.refill_ready() && sc.refill_sufficient(0) {
+            return true;
+        }
+        if self.tcbState.get_tcb_queued() == 0 {
+            release_enqueue(self);
+        }
+        false
+    }
+
+    #[cfg(feature = "KERNEL_MCS")]
+    /// Take the reply object the TCB received with, breaking its binding to the TCB
+    pub fn take_reply(&mut self) -> Option<&'static mut reply_t> {
+        let reply = convert_to_option_mut_type_ref::<reply_t>(self.tcbState.get_reply_object())?;
+        if reply.replyTCB == self.get_ptr() {
+            reply.replyTCB = 0;
+        }
+        self.tcbState.set_reply_object(0);
+        Some(reply)
+    }
+
     #[inline]
     /// Check if the TCB is current by comparing the tcb pointer
     pub fn is_current(&self) -> bool {
@@ -176,6 +254,10 @@ impl tcb_t {
 
     /// Enqueue the TCB to the scheduling queue
     pub fn sched_enqueue(&mut self) {
+        #[cfg(feature = "KERNEL_MCS")]
+        if !self.ready_to_queue() {
+            return;
+        }
         let self_ptr = self as *mut tcb_t;
         if self.tcbState.get_tcb_queued() == 0 {
             let dom = self.domain;
@@ -259,6 +341,10 @@ impl tcb_t {
     /// # Note
     /// This function is as same as `sched_enqueue`, but it is used for the EP queue
     pub fn sched_append(&mut self) {
+        #[cfg(feature = "KERNEL_MCS")]
+        if !self.ready_to_queue() {
+            return;
+        }
         let self_ptr = self as *mut tcb_t;
         if self.tcbState.get_tcb_queued() == 0 {
             let dom = self.domain;
@@ -307,7 +393,7 @@ impl tcb_t {
     pub fn set_vm_root(&mut self) -> Result<(), lookup_fault_t> {
         // let threadRoot = &(*getCSpace(thread as usize, tcbVTable)).cap;
         let thread_root = self.get_cspace(tcbVTable).cap;
-        #[cfg(target_arch = "aarch64")]
+        #[cfg(not(target_arch = "riscv64"))]
         {
             if !thread_root.is_valid_native_root() {
                 setCurrentUserVSpaceRoot(ttbr_new(
@@ -339,6 +425,8 @@ impl tcb_t {
         // if hart_id() == 0 {
         //     debug!("switch_to_this: {:#x}", self.get_ptr());
         // }
+        #[cfg(feature = "BENCHMARK_TRACK_UTILISATION")]
+        benchmark_utilisation_switch(self);
         let _ = self.set_vm_root();
         self.sched_dequeue();
         set_current_thread(self);
@@ -375,6 +463,7 @@ impl tcb_t {
         }
     }
 
+    #[cfg(not(feature = "KERNEL_MCS"))]
     #[inline]
     /// Setup the reply master of the TCB
     pub fn setup_reply_master(&mut self) {
@@ -397,12 +486,15 @@ impl tcb_t {
         // setThreadState(self as *mut Self, ThreadStateInactive);
         set_thread_state(self, ThreadState::ThreadStateInactive);
         self.sched_dequeue();
+        #[cfg(feature = "KERNEL_MCS")]
+        release_remove(self);
     }
 
     #[inline]
     /// Restart the TCB, set the state to ThreadStateRestart and enqueue to the scheduling queue waiting for reschedule
     pub fn restart(&mut self) {
         if self.is_stopped() {
+            #[cfg(not(feature = "KERNEL_MCS"))]
             self.setup_reply_master();
             // setThreadState(self as *mut Self, ThreadStateRestart);
             set_thread_state(self, ThreadState::ThreadStateRestart);
@@ -411,6 +503,7 @@ impl tcb_t {
         }
     }
 
+    #[cfg(not(feature = "KERNEL_MCS"))]
     #[inline]
     /// Setup the caller cap of the TCB
     /// # Arguments
@@ -435,6 +528,7 @@ impl tcb_t {
         );
     }
 
+    #[cfg(not(feature = "KERNEL_MCS"))]
     #[inline]
     /// Delete the caller cap of the TCB
     pub fn delete_caller_cap(&mut self) {
@@ -772,6 +866,48 @@ impl tcb_t {
                 );
                 receiver.set_mr(seL4_VMFault_FSR, self.tcbFault.vm_fault_get_fsr())
             }
+            #[cfg(all(target_arch = "aarch64", feature = "HARDWARE_DEBUG_API"))]
+            FaultType::DebugException => {
+                receiver.set_mr(
+                    seL4_DebugException_FaultIP,
+                    self.tcbArch.get_register(ArchReg::FaultIP),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_ExceptionReason,
+                    self.tcbFault.debug_exception_get_exception_reason(),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_TriggerAddress,
+                    self.tcbFault.debug_exception_get_breakpoint_address(),
+                );
+                receiver.set_mr(
+                    seL4_DebugException_BreakpointNumber,
+                    self.tcbFault.debug_exception_get_breakpoint_number(),
+                )
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VGICMaintenance => {
+                // the handler reads a -1 as no list register with the EOI
+                let idx = match self.tcbFault.vgic_maintenance_get_idx_valid() {
+                    0 => usize::MAX,
+                    _ => self.tcbFault.vgic_maintenance_get_idx(),
+                };
+                receiver.set_mr(seL4_VGICMaintenance_IDX, idx)
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VCPUFault => {
+                receiver.set_mr(seL4_VCPUFault_HSR, self.tcbFault.vcpu_fault_get_hsr())
+            }
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            FaultType::VPPIEvent => {
+                receiver.set_mr(seL4_VPPIEvent_IRQ, self.tcbFault.vppi_event_get_irq())
+            }
+            #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+            FaultType::VCPUFault => {
+                receiver.set_mr(seL4_VCPUFault_Cause, self.tcbFault.vcpu_fault_get_cause());
+                receiver.set_mr(seL4_VCPUFault_Addr, self.tcbFault.vcpu_fault_get_address());
+                receiver.set_mr(seL4_VCPUFault_Inst, self.tcbFault.vcpu_fault_get_inst())
+            }
             _ => {
                 panic!("invalid fault")
             }
@@ -784,8 +920,66 @@ impl tcb_t {
         self.tcbState.set_ts_type(state as usize);
         schedule_tcb(self);
     }
-    pub fn DebugAppend(&mut self) {}
-    pub fn DebugRemove(&mut self) {}
+
+    #[cfg(feature = "DEBUG_BUILD")]
+    /// Set the name of the TCB, truncated to TCB_NAME_LENGTH - 1 bytes without splitting a char
+    /// # Arguments
+    /// * `name` - The name to set, without the terminating NUL
+    pub fn set_name(&mut self, name: &[u8]) {
+        let mut len = core::cmp::min(name.len(), TCB_NAME_LENGTH - 1);
+        if let Ok(name) = core::str::from_utf8(name) {
+            while !name.is_char_boundary(len) {
+                len -= 1;
+            }
+        }
+        self.tcbName[..len].copy_from_slice(&name[..len]);
+        self.tcbName[len..].fill(0);
+    }
+
+    /// Get the name of the TCB, empty if it has never been named
+    pub fn get_name(&self) -> &str {
+        #[cfg(feature = "DEBUG_BUILD")]
+        {
+            let len = self
+                .tcbName
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(TCB_NAME_LENGTH);
+            core::str::from_utf8(&self.tcbName[..len]).unwrap_or("<invalid name>")
+        }
+        #[cfg(not(feature = "DEBUG_BUILD"))]
+        {
+            ""
+        }
+    }
+
+    /// Append the TCB to the debug TCB list of its core
+    pub fn DebugAppend(&mut self) {
+        let cpu = self.get_cpu();
+        let head = get_debug_tcbs_head(cpu);
+        self.tcbDebugPrev = 0;
+        self.tcbDebugNext = head;
+        if head != 0 {
+            convert_to_mut_type_ref::<tcb_t>(head).tcbDebugPrev = self.get_ptr();
+        }
+        set_debug_tcbs_head(cpu, self.get_ptr());
+    }
+
+    /// Remove the TCB from the debug TCB list of its core
+    pub fn DebugRemove(&mut self) {
+        let cpu = self.get_cpu();
+        assert_ne!(get_debug_tcbs_head(cpu), 0);
+        if self.tcbDebugPrev != 0 {
+            convert_to_mut_type_ref::<tcb_t>(self.tcbDebugPrev).tcbDebugNext = self.tcbDebugNext;
+        } else {
+            set_debug_tcbs_head(cpu, self.tcbDebugNext);
+        }
+        if self.tcbDebugNext != 0 {
+            convert_to_mut_type_ref::<tcb_t>(self.tcbDebugNext).tcbDebugPrev = self.tcbDebugPrev;
+        }
+        self.tcbDebugPrev = 0;
+        self.tcbDebugNext = 0;
+    }
 }
 
 #[inline]
@@ -22,6 +22,8 @@ plus_define_bitfield! {
             blocking_ipc_can_grant, get_blocking_ipc_can_grant, set_blocking_ipc_can_grant, 1, 3, 1, 0, false,
             blocking_ipc_can_grant_relpy, get_blocking_ipc_can_grant_reply, set_blocking_ipc_can_grant_reply, 1, 2, 1, 0, false,
             blocking_ipc_is_call, get_blocking_ipc_is_call, set_blocking_ipc_is_call, 1, 1, 1, 0, false,
+            reply_object, get_reply_object, set_reply_object, 1, 5, 34, 5, true,
+            tcb_in_release_queue, get_tcb_in_release_queue, set_tcb_in_release_queue, 1, 4, 1, 0, false,
             tcb_queued, get_tcb_queued, set_tcb_queued, 1, 0, 1, 0, false,
             blocking_object, get_blocking_object, set_blocking_object, 0, 4, 35, 4, true,
             ts_type, get_ts_type, set_ts_type, 0, 0, 4, 0, false
@@ -30,7 +32,7 @@ plus_define_bitfield! {
 }
 
 // FIXED: BF_CANONICAL_RANGE = 48
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 plus_define_bitfield! {
     thread_state_t, 3, 0, 0, 0 => {
         state_new, 0 => {
@@ -38,6 +40,8 @@ plus_define_bitfield! {
             blocking_ipc_can_grant, get_blocking_ipc_can_grant, set_blocking_ipc_can_grant, 1, 3, 1, 0, false,
             blocking_ipc_can_grant_relpy, get_blocking_ipc_can_grant_reply, set_blocking_ipc_can_grant_reply, 1, 2, 1, 0, false,
             blocking_ipc_is_call, get_blocking_ipc_is_call, set_blocking_ipc_is_call, 1, 1, 1, 0, false,
+            reply_object, get_reply_object, set_reply_object, 1, 5, 43, 5, true,
+            tcb_in_release_queue, get_tcb_in_release_queue, set_tcb_in_release_queue, 1, 4, 1, 0, false,
             tcb_queued, get_tcb_queued, set_tcb_queued, 1, 0, 1, 0, false,
             blocking_object, get_blocking_object, set_blocking_object, 0, 4, 44, 4, true,
             ts_type, get_ts_type, set_ts_type, 0, 0, 4, 0, false
@@ -11,7 +11,11 @@ use sel4_cspace::arch::cap_t;
 use crate::{asid_map_t, asid_pool_t, asid_t, findVSpaceForASID_ret, set_vm_root};
 
 use super::asid_pool_from_addr;
-use super::machine::invalidate_local_tlb_asid;
+use super::interface::invalidate_tlb_by_asid;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use super::interface::invalidate_translation_asid;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use sel4_common::sel4_config::asidInvalid;
 
 pub const asid_map_asid_map_none: usize = 0;
 pub const asid_map_asid_map_vspace: usize = 1;
@@ -45,6 +49,96 @@ pub fn find_map_for_asid(asid: usize) -> Option<&'static asid_map_t> {
     None
 }
 
+/// The VMIDs of the 8-bit VMID field of VTTBR_EL2
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const nVMIDs: usize = BIT!(8);
+
+/// The ASID holding each VMID. The VMID 0 is kept for the global user VSpace, so the threads
+/// without a VSpace never hit the entries of another one.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+static mut armKSHWASIDTable: [asid_t; nVMIDs] = [asidInvalid; nVMIDs];
+
+/// Where the search of a free VMID starts, and the VMID taken back when none is free
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+static mut armKSNextASID: usize = 1;
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn find_map_for_asid_mut(asid: usize) -> Option<&'static mut asid_map_t> {
+    convert_to_option_mut_type_ref::<asid_pool_t>(get_asid_pool_by_index(asid >> asidLowBits))
+        .map(|pool| &mut pool[asid & MASK!(asidLowBits)])
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+fn next_vmid(vmid: usize) -> usize {
+    if vmid + 1 == nVMIDs {
+        1
+    } else {
+        vmid + 1
+    }
+}
+
+/// The VMID the ASID holds, if it holds one
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub fn find_hw_asid(asid: asid_t) -> Option<usize> {
+    let asid_map = find_map_for_asid(asid)?;
+    if asid_map.get_type() == asid_map_asid_map_vspace && asid_map.get_stored_vmid_valid() != 0 {
+        Some(asid_map.get_stored_hw_vmid())
+    } else {
+        None
+    }
+}
+
+/// Give the VMID back from the ASID
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn invalidate_asid_entry(asid: asid_t) {
+    if let Some(asid_map) = find_map_for_asid_mut(asid) {
+        if asid_map.get_type() == asid_map_asid_map_vspace && asid_map.get_stored_vmid_valid() != 0
+        {
+            unsafe { armKSHWASIDTable[asid_map.get_stored_hw_vmid()] = asidInvalid };
+            asid_map.set_stored_vmid_valid(0);
+        }
+    }
+}
+
+/// Find a free VMID, the next one is taken back from its ASID when all of them are held
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+fn find_free_hw_asid() -> usize {
+    unsafe {
+        let mut vmid = armKSNextASID;
+        for _ in 1..nVMIDs {
+            if armKSHWASIDTable[vmid] == asidInvalid {
+                return vmid;
+            }
+            vmid = next_vmid(vmid);
+        }
+        let vmid = armKSNextASID;
+        invalidate_asid_entry(armKSHWASIDTable[vmid]);
+        invalidate_translation_asid(vmid);
+        armKSNextASID = next_vmid(vmid);
+        vmid
+    }
+}
+
+/// The VMID the stage 2 root of the VSpace is tagged with, one is taken for the ASID if it
+/// holds none. `None` if the ASID does not map this VSpace.
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub fn get_hw_asid(asid: asid_t, vspace_root: usize) -> Option<usize> {
+    let asid_map = find_map_for_asid_mut(asid)?;
+    if asid_map.get_type() != asid_map_asid_map_vspace || asid_map.get_vspace_root() != vspace_root
+    {
+        return None;
+    }
+    if asid_map.get_stored_vmid_valid() != 0 {
+        return Some(asid_map.get_stored_hw_vmid());
+    }
+    let vmid = find_free_hw_asid();
+    unsafe { armKSHWASIDTable[vmid] = asid };
+    asid_map.set_stored_hw_vmid(vmid);
+    asid_map.set_stored_vmid_valid(1);
+    Some(vmid)
+}
+
 #[no_mangle]
 pub fn find_vspace_for_asid(asid: usize) -> findVSpaceForASID_ret {
     let mut ret: findVSpaceForASID_ret = findVSpaceForASID_ret {
@@ -72,7 +166,9 @@ pub fn delete_asid(asid: usize, vspace: *mut PTE, cap: &cap_t) -> Result<(), loo
         if asid_map.get_type() == asid_map_asid_map_vspace
             && asid_map.get_vspace_root() == vspace as usize
         {
-            invalidate_local_tlb_asid(asid);
+            invalidate_tlb_by_asid(asid);
+            #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+            invalidate_asid_entry(asid);
             pool[asid & MASK!(asidLowBits)] = asid_map_t::new_none();
             return set_vm_root(cap);
         }
@@ -93,7 +189,9 @@ pub fn delete_asid_pool(
         for offset in 0..BIT!(asidLowBits) {
             let asid_map = pool[offset];
             if asid_map.get_type() == asid_map_asid_map_vspace {
-                invalidate_local_tlb_asid(asid_base + offset);
+                invalidate_tlb_by_asid(asid_base + offset);
+                #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+                invalidate_asid_entry(asid_base + offset);
             }
         }
         set_asid_pool_by_index(asid_base >> asidLowBits, 0);
@@ -106,7 +204,7 @@ pub fn delete_asid_pool(
 #[inline]
 pub fn write_it_asid_pool(it_ap_cap: &cap_t, it_vspace_cap: &cap_t) {
     let ap = asid_pool_from_addr(it_ap_cap.get_cap_ptr());
-    let asid_map = asid_map_t::new_vspace(it_vspace_cap.get_vs_base_ptr());
+    let asid_map = asid_map_t::new_vspace(it_vspace_cap.get_vs_base_ptr(), 0, 0);
     ap[IT_ASID] = asid_map;
     set_asid_pool_by_index(IT_ASID >> asidLowBits, ap as *const _ as usize);
 }
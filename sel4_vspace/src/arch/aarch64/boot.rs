@@ -17,7 +17,9 @@ use crate::{
     vptr_t, PTE,
 };
 
-use super::{map_kernel_devices, page_slice};
+use super::page_slice;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use super::pte::S2_MEMATTR_NORMAL;
 
 #[derive(PartialEq, Eq, Debug)]
 enum find_type {
@@ -92,11 +94,16 @@ pub fn rust_map_kernel_window() {
 
     let mut vaddr = PPTR_BASE;
     let mut paddr = PADDR_BASE;
+    // the bit 54 is the XN of the EL2 translation, where the kernel runs its code from the window
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    let uxn = 0;
+    #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+    let uxn = 1;
     while paddr < PADDR_TOP {
         set_kernel_page_directory_by_index(
             VAddr(vaddr).GET_KPT_INDEX(1),
             VAddr(vaddr).GET_KPT_INDEX(2),
-            PTE::pte_new_page(1, paddr, 0, 1, 0, 0, mair_types::NORMAL as usize),
+            PTE::pte_new_page(uxn, paddr, 0, 1, 0, 0, mair_types::NORMAL as usize),
         );
 
         vaddr += BIT!(seL4_LargePageBits);
@@ -124,8 +131,7 @@ pub fn rust_map_kernel_window() {
         BIT!(PUD_INDEX_BITS) - 1,
         PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_table_base())),
     );
-    map_kernel_devices();
-    // ffi_call!(map_kernel_devices());
+    // the kernel devices are mapped by the boot code once it has read the device tree
 }
 
 #[no_mangle]
@@ -208,7 +214,10 @@ pub fn map_it_frame_cap(vspace_cap: &cap_t, frame_cap: &cap_t, exec: bool) {
     ));
     // TODO: Make set_attr usage more efficient.
     // TIPS: exec true will be cast to 1 and false to 0.
+    #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
     pte.set_attr(PTE::pte_new_4k_page((!exec) as usize, 0, 1, 1, 0, 1, 0).0);
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    pte.set_attr(PTE::pte_new_s2_page((!exec) as usize, 0, 1, 0, 0b11, S2_MEMATTR_NORMAL, true).0);
     pte.set_next_level_paddr(pptr_to_paddr(frame_cap.get_frame_base_ptr()));
 }
 
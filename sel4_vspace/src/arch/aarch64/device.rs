@@ -1,13 +1,53 @@
 use super::boot::map_kernel_frame;
 use crate::{paddr_t, pptr_t, vm_attributes_t};
 use sel4_common::arch::vm_rights_t::VMKernelOnly;
+#[cfg(feature = "GIC_V3")]
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
 use sel4_common::{sel4_config::PAGE_BITS, BIT};
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 pub const KDEV_BASE: usize = 0xFFFFFFFFC0000000;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const KDEV_BASE: usize = 0x000000FFC0000000;
+#[cfg(all(not(feature = "GIC_V3"), not(feature = "ARM_HYPERVISOR_SUPPORT")))]
 pub(crate) const NUM_KERNEL_DEVICE_FRAMES: usize = 3;
+/// The virtual interface control of the GICv2 follows the CPU interface
+#[cfg(all(not(feature = "GIC_V3"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub(crate) const NUM_KERNEL_DEVICE_FRAMES: usize = 4;
+#[cfg(feature = "GIC_V3")]
+pub(crate) const NUM_KERNEL_DEVICE_FRAMES: usize =
+    1 + GIC_V3_DISTRIBUTOR_FRAMES + 2 * CONFIG_MAX_NUM_NODES;
 pub(crate) const UART_PPTR: usize = KDEV_BASE + 0x0;
+/// The address the kernel reaches the UART frame at, the kernel page table of the devices maps the
+/// last 2MiB of the address space
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub const KERNEL_UART_VADDR: usize = 0xffffffffffe00000;
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const KERNEL_UART_VADDR: usize = 0x000000ffffe00000;
+#[cfg(not(feature = "GIC_V3"))]
 pub(crate) const GIC_V2_DISTRIBUTOR_PPTR: usize = KDEV_BASE + 0x1000;
+#[cfg(not(feature = "GIC_V3"))]
 pub(crate) const GIC_V2_CONTROLLER_PPTR: usize = KDEV_BASE + 0x2000;
+#[cfg(all(not(feature = "GIC_V3"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub(crate) const GIC_V2_VCPUCTRL_PPTR: usize = KDEV_BASE + 0x3000;
+
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_DISTRIBUTOR_PPTR: usize = KDEV_BASE + 0x1000;
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_DISTRIBUTOR_PADDR: usize = 0x8000000;
+/// The distributor takes 64KiB
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_DISTRIBUTOR_FRAMES: usize = 16;
+/// Only the first page of the RD_base and SGI_base frames of each core is mapped
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_REDISTRIBUTOR_PPTR: usize =
+    GIC_V3_DISTRIBUTOR_PPTR + GIC_V3_DISTRIBUTOR_FRAMES * BIT!(PAGE_BITS);
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_REDISTRIBUTOR_PADDR: usize = 0x80a0000;
+/// The RD_base and SGI_base frames of one core take 128KiB
+#[cfg(feature = "GIC_V3")]
+pub(crate) const GIC_V3_REDISTRIBUTOR_STRIDE: usize = 0x20000;
+
 #[derive(Copy, Clone)]
 struct kernel_frame_t {
     paddr: paddr_t,
@@ -26,8 +66,8 @@ extern "C" {
     pub(self) fn reserve_region(reg: p_region_t) -> bool;
 }
 
+#[cfg(not(feature = "GIC_V3"))]
 #[no_mangle]
-#[link_section = ".boot.text"]
 pub(self) static mut kernel_device_frames: [kernel_frame_t; NUM_KERNEL_DEVICE_FRAMES] = [
     kernel_frame_t {
         paddr: paddr_t(0x9000000),
@@ -47,7 +87,103 @@ pub(self) static mut kernel_device_frames: [kernel_frame_t; NUM_KERNEL_DEVICE_FR
         armExecuteNever: 1,
         userAvailable: 0,
     },
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    kernel_frame_t {
+        paddr: paddr_t(0x8030000),
+        pptr: GIC_V2_VCPUCTRL_PPTR,
+        armExecuteNever: 1,
+        userAvailable: 0,
+    },
 ];
+
+#[cfg(feature = "GIC_V3")]
+#[no_mangle]
+pub(self) static mut kernel_device_frames: [kernel_frame_t; NUM_KERNEL_DEVICE_FRAMES] =
+    gic_v3_kernel_device_frames(GIC_V3_DISTRIBUTOR_PADDR, GIC_V3_REDISTRIBUTOR_PADDR);
+
+/// The UART, the GICv3 distributor and the redistributors of all cores
+#[cfg(feature = "GIC_V3")]
+const fn gic_v3_kernel_device_frames(
+    dist_paddr: usize,
+    redist_paddr: usize,
+) -> [kernel_frame_t; NUM_KERNEL_DEVICE_FRAMES] {
+    let mut frames = [kernel_frame_t {
+        paddr: paddr_t(0x9000000),
+        pptr: UART_PPTR,
+        armExecuteNever: 1,
+        userAvailable: 1,
+    }; NUM_KERNEL_DEVICE_FRAMES];
+    let mut index = 1;
+    let mut i = 0;
+    while i < GIC_V3_DISTRIBUTOR_FRAMES {
+        frames[index] = kernel_frame_t {
+            paddr: paddr_t(dist_paddr + i * BIT!(PAGE_BITS)),
+            pptr: GIC_V3_DISTRIBUTOR_PPTR + i * BIT!(PAGE_BITS),
+            armExecuteNever: 1,
+            userAvailable: 0,
+        };
+        index += 1;
+        i += 1;
+    }
+    let mut cpu = 0;
+    while cpu < CONFIG_MAX_NUM_NODES {
+        // RD_base and SGI_base
+        let mut frame = 0;
+        while frame < 2 {
+            frames[index] = kernel_frame_t {
+                paddr: paddr_t(redist_paddr + cpu * GIC_V3_REDISTRIBUTOR_STRIDE + frame * 0x10000),
+                pptr: GIC_V3_REDISTRIBUTOR_PPTR + (cpu * 2 + frame) * BIT!(PAGE_BITS),
+                armExecuteNever: 1,
+                userAvailable: 0,
+            };
+            index += 1;
+            frame += 1;
+        }
+        cpu += 1;
+    }
+    frames
+}
+
+/// Place the UART frame at the address found in the device tree, before it is mapped, and get
+/// the kernel address of the UART.
+pub fn set_uart_paddr(paddr: usize) -> usize {
+    let offset = paddr & (BIT!(PAGE_BITS) - 1);
+    unsafe {
+        kernel_device_frames[0].paddr = paddr_t(paddr - offset);
+    }
+    KERNEL_UART_VADDR + offset
+}
+
+/// Place the GIC distributor and CPU interface frames at the addresses found in the device
+/// tree, before they are mapped.
+#[cfg(not(feature = "GIC_V3"))]
+pub fn set_gic_paddr(dist_paddr: usize, cpu_paddr: usize) {
+    unsafe {
+        kernel_device_frames[1].paddr = paddr_t(dist_paddr);
+        kernel_device_frames[2].paddr = paddr_t(cpu_paddr);
+    }
+}
+
+/// Place the GIC virtual interface control frame at the address found in the device tree,
+/// before it is mapped.
+#[cfg(all(not(feature = "GIC_V3"), feature = "ARM_HYPERVISOR_SUPPORT"))]
+pub fn set_gic_vcpuctrl_paddr(paddr: usize) {
+    unsafe {
+        kernel_device_frames[3].paddr = paddr_t(paddr);
+    }
+}
+
+/// Place the GIC distributor and redistributor frames at the addresses found in the device
+/// tree, before they are mapped.
+#[cfg(feature = "GIC_V3")]
+pub fn set_gic_paddr(dist_paddr: usize, redist_paddr: usize) {
+    unsafe {
+        let uart = kernel_device_frames[0];
+        kernel_device_frames = gic_v3_kernel_device_frames(dist_paddr, redist_paddr);
+        kernel_device_frames[0] = uart;
+    }
+}
+
 #[no_mangle]
 pub fn map_kernel_devices() {
     unsafe {
@@ -5,6 +5,8 @@ use super::pte::pte_tag_t;
 use super::{kpptr_to_paddr, machine::*, UPT_LEVELS};
 use crate::arch::VAddr;
 use crate::{asid_t, find_vspace_for_asid, paddr_to_pptr, pptr_t, pptr_to_paddr, vptr_t, PTE};
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+use crate::{find_hw_asid, get_hw_asid};
 use sel4_common::arch::MessageLabel;
 use sel4_common::structures::exception_t;
 use sel4_common::utils::{pageBitsForSize, ptr_to_mut};
@@ -91,6 +93,17 @@ pub fn set_kernel_page_directory_by_index(idx1: usize, idx2: usize, pde: PTE) {
     }
 }
 
+/// Map the large page of the kernel log buffer at `vaddr` in the device window of the kernel PD
+pub fn map_kernel_log_frame(paddr: usize, vaddr: usize) {
+    let vaddr = VAddr(vaddr);
+    set_kernel_page_directory_by_index(
+        vaddr.GET_KPT_INDEX(1),
+        vaddr.GET_KPT_INDEX(2),
+        PTE::pte_new_page(1, paddr, 0, 1, 0, 0, mair_types::NORMAL as usize),
+    );
+    invalidate_local_tlb();
+}
+
 #[no_mangle]
 #[link_section = ".page_table"]
 pub(crate) static mut armKSGlobalUserVSpace: PageAligned<PTE> = PageAligned::new(PTE(0));
@@ -119,7 +132,21 @@ pub fn set_kernel_page_table_by_index(idx: usize, pte: PTE) {
 ///
 /// Use page table in vspace_root to set the satp register.
 pub fn set_vm_root(vspace_root: &cap_t) -> Result<(), lookup_fault_t> {
+    #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
     setCurrentUserVSpaceRoot(pptr_to_paddr(vspace_root.get_vs_base_ptr()));
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    match get_hw_asid(
+        vspace_root.get_vs_mapped_asid(),
+        vspace_root.get_vs_base_ptr(),
+    ) {
+        Some(vmid) => {
+            setCurrentUserVSpaceRoot(ttbr_new(vmid, pptr_to_paddr(vspace_root.get_vs_base_ptr())))
+        }
+        None => setCurrentUserVSpaceRoot(ttbr_new(
+            0,
+            kpptr_to_paddr(get_arm_global_user_vspace_base()),
+        )),
+    }
     Ok(())
 }
 
@@ -198,6 +225,10 @@ pub fn set_vm_root_for_flush_with_thread_root(
     }
 
     // armv_context_switch(vspace, asid);
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    let Some(asid) = get_hw_asid(asid, vspace as usize) else {
+        return false;
+    };
     setCurrentUserVSpaceRoot(ttbr_new(asid, vspace as usize));
     true
 }
@@ -269,12 +300,45 @@ pub fn set_vm_root_for_flush_with_thread_root(
 
 #[inline]
 pub fn invalidate_tlb_by_asid(asid: asid_t) {
-    invalidate_local_tlb_asid(asid);
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    let Some(asid) = find_hw_asid(asid) else {
+        return;
+    };
+    invalidate_translation_asid(asid);
 }
 
 #[inline]
 pub fn invalidate_tlb_by_asid_va(asid: asid_t, vaddr: vptr_t) {
-    invalidate_local_tlb_va_asid((asid << 48) | vaddr >> seL4_PageBits);
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    let Some(asid) = find_hw_asid(asid) else {
+        return;
+    };
+    let mva_plus_asid = (asid << 48) | vaddr >> seL4_PageBits;
+    #[cfg(not(feature = "ENABLE_SMP"))]
+    invalidate_local_tlb_va_asid(mva_plus_asid);
+    #[cfg(feature = "ENABLE_SMP")]
+    unsafe {
+        invalidateTranslationSingle(mva_plus_asid)
+    };
+}
+
+/// Invalidate the TLB entries of the hardware ASID on every core, the VMID with the hypervisor
+#[inline]
+pub(super) fn invalidate_translation_asid(hw_asid: usize) {
+    #[cfg(not(feature = "ENABLE_SMP"))]
+    invalidate_local_tlb_asid(hw_asid);
+    #[cfg(feature = "ENABLE_SMP")]
+    unsafe {
+        invalidateTranslationASID(hw_asid)
+    };
+}
+
+#[cfg(feature = "ENABLE_SMP")]
+extern "C" {
+    /// Invalidate the TLB entries of the ASID on every core
+    fn invalidateTranslationASID(asid: usize);
+    /// Invalidate the TLB entry of the address tagged with its ASID on every core
+    fn invalidateTranslationSingle(mva_plus_asid: usize);
 }
 
 // pub fn unmap_page_upper_directory(asid: asid_t, vaddr: vptr_t, pud: &PUDE) {
@@ -1,12 +1,15 @@
 use core::arch::asm;
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 use aarch64_cpu::registers::{Writeable, TTBR0_EL1, TTBR1_EL1};
 use sel4_common::{sel4_config::L1_CACHE_LINE_SIZE_BITS, MASK, ROUND_DOWN};
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn setCurrentKernelVSpaceRoot(val: usize) {
     TTBR1_EL1.set(val as _);
 }
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn setCurrentUserVSpaceRoot(val: usize) {
     TTBR0_EL1.set(val as _);
@@ -14,6 +17,41 @@ pub fn setCurrentUserVSpaceRoot(val: usize) {
     unsafe { core::arch::asm!("tlbi vmalle1; dsb sy; isb") };
 }
 
+/// The kernel at EL2 translates its own addresses with the TTBR0_EL2
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn setCurrentKernelVSpaceRoot(val: usize) {
+    unsafe { asm!("msr ttbr0_el2, {}; isb", in(reg) val) };
+}
+
+/// The user VSpaces are the stage 2 translation of both the native threads and the guests, each
+/// one tagged with its VMID
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn setCurrentUserVSpaceRoot(val: usize) {
+    unsafe { asm!("msr vttbr_el2, {}; isb", in(reg) val) };
+}
+
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+const VTTBR_VMID_SHIFT: usize = 48;
+
+/// Run the TLB maintenance `f` on the VMID, the EL1&0 operations only work on the VMID in
+/// VTTBR_EL2
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+fn with_vmid(vmid: usize, f: impl FnOnce()) {
+    let vttbr: usize;
+    unsafe {
+        asm!("mrs {}, vttbr_el2", out(reg) vttbr);
+        asm!(
+            "msr vttbr_el2, {}; isb",
+            in(reg) (vttbr & !(MASK!(8) << VTTBR_VMID_SHIFT)) | (vmid << VTTBR_VMID_SHIFT)
+        );
+    }
+    f();
+    unsafe { asm!("msr vttbr_el2, {}; isb", in(reg) vttbr) };
+}
+
 #[inline]
 pub const fn ttbr_new(asid: usize, addr: usize) -> usize {
     (asid & 0xffff) << 48 | (addr & 0xffffffffffff)
@@ -44,6 +82,7 @@ pub fn isb() {
     }
 }
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn invalidate_local_tlb_asid(asid: usize) {
     assert!(asid < (1 << 16)); // BIT(16) 相当于 1 << 16
@@ -56,6 +95,7 @@ pub fn invalidate_local_tlb_asid(asid: usize) {
     isb();
 }
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
 #[inline]
 pub fn invalidate_local_tlb_va_asid(mva_plus_asid: usize) {
     dsb();
@@ -66,6 +106,31 @@ pub fn invalidate_local_tlb_va_asid(mva_plus_asid: usize) {
     isb();
 }
 
+/// Drop the stage 1 and stage 2 entries of the VMID
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn invalidate_local_tlb_asid(vmid: usize) {
+    assert!(vmid < (1 << 8));
+
+    dsb();
+    with_vmid(vmid, || unsafe {
+        asm!("tlbi vmalls12e1");
+        asm!("dsb sy; isb");
+    });
+}
+
+/// Drop the stage 2 entry of the IPA, and the stage 1 entries of a guest that may be built on it
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn invalidate_local_tlb_va_asid(mva_plus_vmid: usize) {
+    dsb();
+    with_vmid(mva_plus_vmid >> VTTBR_VMID_SHIFT, || unsafe {
+        asm!("tlbi ipas2e1, {}", in(reg) mva_plus_vmid & MASK!(48));
+        asm!("dsb sy; tlbi vmalle1");
+        asm!("dsb sy; isb");
+    });
+}
+
 #[inline(always)]
 pub fn clean_by_va_pou(vaddr: usize, _paddr: usize) {
     unsafe {
@@ -305,9 +370,14 @@ fn read_clid() -> usize {
 #[inline]
 pub fn invalidate_local_tlb() {
     dsb();
+    #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
     unsafe {
         asm!("tlbi vmalle1");
     }
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    unsafe {
+        asm!("tlbi alle2; tlbi vmalls12e1");
+    }
     dsb();
     isb();
 }
@@ -1,7 +1,10 @@
-use crate::{arch::aarch64::machine::clean_by_va_pou, vm_attributes_t, PTE};
+use super::machine::clean_by_va_pou;
+use crate::{vm_attributes_t, PTE};
 
+#[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
+use super::mair_types;
 use super::utils::paddr_to_pptr;
-use super::{mair_types, seL4_VSpaceIndexBits, UPT_LEVELS};
+use super::{seL4_VSpaceIndexBits, UPT_LEVELS};
 use crate::{lookupPTSlot_ret_t, vptr_t};
 use sel4_common::utils::ptr_to_mut;
 use sel4_common::MASK;
@@ -38,6 +41,13 @@ impl VMPageSize {
 //     pgde_pud = 3,
 // }
 
+/// The stage 2 MemAttr of the device memory, Device-nGnRnE
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const S2_MEMATTR_DEVICE_nGnRnE: usize = 0b0000;
+/// The stage 2 MemAttr of the normal memory, Inner/Outer Write-back
+#[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+pub const S2_MEMATTR_NORMAL: usize = 0b1111;
+
 #[allow(unused)]
 pub enum pte_tag_t {
     pte_table = 3,
@@ -94,6 +104,14 @@ bitflags::bitflags! {
         /// The Execute-never or Unprivileged execute-never field.
         const UXN =         BIT!(54);
 
+        // Attribute fields in stage 2 VMSAv8-64 Block and Page descriptors:
+        /// Stage 2 memory attributes.
+        const S2_MEMATTR =  0b1111 << 2;
+        /// Stage 2 access permission: readable.
+        const S2AP_READ =   BIT!(6);
+        /// Stage 2 access permission: writable.
+        const S2AP_WRITE =  BIT!(7);
+
         // Next-level attributes in stage 1 VMSAv8-64 Table descriptors:
 
         /// PXN limit for subsequent levels of lookup.
@@ -179,6 +197,17 @@ impl PTE {
         }
     }
 
+    /// The stage 2 permissions of the rights, what the native threads and the guests get
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    pub fn s2ap_from_vm_rights_t(rights: vm_rights_t) -> PTEFlags {
+        match rights {
+            vm_rights_t::VMKernelOnly => PTEFlags::empty(),
+            vm_rights_t::VMReadWrite => PTEFlags::S2AP_READ | PTEFlags::S2AP_WRITE,
+            vm_rights_t::VMReadOnly => PTEFlags::S2AP_READ,
+        }
+    }
+
+    #[cfg(not(feature = "ARM_HYPERVISOR_SUPPORT"))]
     pub fn make_user_pte(
         paddr: usize,
         rights: vm_rights_t,
@@ -200,6 +229,57 @@ impl PTE {
         }
     }
 
+    /// The user VSpaces are stage 2 tables under the hypervisor support
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    pub fn make_user_pte(
+        paddr: usize,
+        rights: vm_rights_t,
+        attr: vm_attributes_t,
+        page_size: usize,
+    ) -> Self {
+        let nonexecutable = attr.get_armExecuteNever();
+        let mut memattr = S2_MEMATTR_DEVICE_nGnRnE;
+        if attr.get_armPageCacheable() {
+            memattr = S2_MEMATTR_NORMAL;
+        }
+        let s2ap: usize = Self::s2ap_from_vm_rights_t(rights).bits() >> 6;
+        PTE::pte_new_s2_page(
+            nonexecutable as usize,
+            paddr,
+            1,
+            0,
+            s2ap,
+            memattr,
+            VMPageSize::ARMSmallPage as usize == page_size,
+        )
+    }
+
+    /// A stage 2 block or page descriptor, which has no nG and takes the memory attributes
+    /// themselves instead of an index into the MAIR.
+    #[cfg(feature = "ARM_HYPERVISOR_SUPPORT")]
+    pub fn pte_new_s2_page(
+        XN: usize,
+        page_base_address: usize,
+        AF: usize,
+        SH: usize,
+        S2AP: usize,
+        MemAttr: usize,
+        is_4k_page: bool,
+    ) -> PTE {
+        let val = 0
+            | (XN & 0x1) << 54
+            | (page_base_address & 0xfffffffff000) >> 0
+            | (AF & 0x1) << 10
+            | (SH & 0x3) << 8
+            | (S2AP & 0x3) << 6
+            | (MemAttr & 0xf) << 2;
+        if is_4k_page {
+            PTE(val | 0x400000000000003)
+        } else {
+            PTE(val | (0x1 << 0))
+        }
+    }
+
     pub fn pte_new_table(pt_base_address: usize) -> PTE {
         let val = 0 | (pt_base_address & 0xfffffffff000) | (0x3);
         PTE(val)
@@ -131,7 +131,9 @@ plus_define_bitfield! {
     asid_map_t, 1, 0, 0, 1 => {
         new_none, 0 => {},
         new_vspace, 1 => {
-            vspace_root , get_vspace_root , set_vspace_root , 0, 12, 36, 12 ,true
+            vspace_root , get_vspace_root , set_vspace_root , 0, 12, 36, 12 ,true,
+            stored_hw_vmid, get_stored_hw_vmid, set_stored_hw_vmid, 0, 48, 8, 0, false,
+            stored_vmid_valid, get_stored_vmid_valid, set_stored_vmid_valid, 0, 56, 1, 0, false
         }
     }
 }
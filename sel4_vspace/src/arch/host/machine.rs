@@ -0,0 +1,127 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The simulated `TTBR1_EL1`
+static KERNEL_VSPACE_ROOT: AtomicUsize = AtomicUsize::new(0);
+/// The simulated `TTBR0_EL1`
+static USER_VSPACE_ROOT: AtomicUsize = AtomicUsize::new(0);
+/// Number of TLB invalidations requested so far
+static TLB_FLUSHES: AtomicUsize = AtomicUsize::new(0);
+
+#[inline]
+pub fn setCurrentKernelVSpaceRoot(val: usize) {
+    KERNEL_VSPACE_ROOT.store(val, Ordering::SeqCst);
+}
+
+#[inline]
+pub fn setCurrentUserVSpaceRoot(val: usize) {
+    USER_VSPACE_ROOT.store(val, Ordering::SeqCst);
+    invalidate_local_tlb();
+}
+
+/// Read back the value last written by [`setCurrentKernelVSpaceRoot`]
+pub fn host_get_kernel_vspace_root() -> usize {
+    KERNEL_VSPACE_ROOT.load(Ordering::SeqCst)
+}
+
+/// Read back the value last written by [`setCurrentUserVSpaceRoot`]
+pub fn host_get_user_vspace_root() -> usize {
+    USER_VSPACE_ROOT.load(Ordering::SeqCst)
+}
+
+/// Number of TLB invalidations requested since the start of the test
+pub fn host_tlb_flushes() -> usize {
+    TLB_FLUSHES.load(Ordering::SeqCst)
+}
+
+#[inline]
+pub const fn ttbr_new(asid: usize, addr: usize) -> usize {
+    (asid & 0xffff) << 48 | (addr & 0xffffffffffff)
+}
+
+#[inline]
+pub fn dsb() {
+    core::sync::atomic::fence(Ordering::SeqCst);
+}
+
+#[inline]
+pub fn isb() {}
+
+#[inline]
+pub fn dmb() {
+    core::sync::atomic::fence(Ordering::SeqCst);
+}
+
+#[inline]
+pub fn invalidate_local_tlb() {
+    TLB_FLUSHES.fetch_add(1, Ordering::SeqCst);
+}
+
+#[inline]
+pub fn invalidate_local_tlb_asid(_asid: usize) {
+    invalidate_local_tlb();
+}
+
+#[inline]
+pub fn invalidate_local_tlb_va_asid(_mva_plus_asid: usize) {
+    invalidate_local_tlb();
+}
+
+// The host caches are coherent, so cache maintenance has nothing to do.
+
+#[inline]
+pub fn clean_by_va_pou(_vaddr: usize, _paddr: usize) {}
+
+#[inline]
+pub fn clean_by_va(_vaddr: usize, _paddr: usize) {}
+
+#[inline]
+pub fn invalidate_by_va(_vaddr: usize, _paddr: usize) {}
+
+#[inline]
+pub fn clean_inval_by_va(_vaddr: usize, _paddr: usize) {}
+
+#[inline]
+pub fn invalidate_by_va_i(_vaddr: usize, _paddr: usize) {}
+
+#[inline]
+pub fn clean_cache_range_ram(_start: usize, _end: usize, _pstart: usize) {}
+
+#[inline]
+pub fn invalidate_cache_range_i(_start: usize, _end: usize, _pstart: usize) {}
+
+#[inline]
+pub fn clean_cache_range_poc(_start: usize, _end: usize, _pstart: usize) {}
+
+#[inline]
+pub fn clean_cache_range_pou(_start: usize, _end: usize, _pstart: usize) {}
+
+pub fn plat_clean_l2_range(_pstart: usize, _pend: usize) {}
+
+#[inline]
+pub fn clean_invalidate_cache_range_ram(_start: usize, _end: usize, _pstart: usize) {}
+
+#[inline]
+pub fn invalidate_cache_range_ram(_start: usize, _end: usize, _pstart: usize) {}
+
+pub fn clean_invalidate_l1_caches() {}
+
+#[inline]
+pub fn invalidate_i_pou() {}
+
+pub fn clean_invalidate_d_poc() {}
+
+pub enum arm_cache_type {
+    ARMCacheI = 1,
+    ARMCacheD = 2,
+    ARMCacheID = 3,
+}
+
+/// Same encoding as the aarch64 `MAIR_EL1` slots, the page tables are shared.
+pub enum mair_types {
+    DEVICE_nGnRnE,
+    DEVICE_nGnRE,
+    DEVICE_GRE,
+    NORMAL_NC,
+    NORMAL,
+    NORMAL_WT,
+}
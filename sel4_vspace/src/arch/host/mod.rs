@@ -0,0 +1,31 @@
+//! Simulated vspace of the host backend used by the unit tests.
+//!
+//! The page tables keep the aarch64 layout and live in ordinary memory, so the
+//! table walks, the ASID pools and the frame mappings run the aarch64 code as
+//! is. Only the translation registers, the TLB and the caches are faked in
+//! [`machine`].
+#[path = "../aarch64/asid.rs"]
+mod asid;
+#[path = "../aarch64/boot.rs"]
+mod boot;
+mod device;
+#[path = "../aarch64/interface.rs"]
+mod interface;
+mod machine;
+#[path = "../aarch64/pagetable.rs"]
+mod pagetable;
+#[path = "../aarch64/pte.rs"]
+mod pte;
+#[path = "../aarch64/structures.rs"]
+mod structures;
+#[path = "../aarch64/utils.rs"]
+mod utils;
+pub use asid::*;
+pub use boot::*;
+pub use device::*;
+pub use interface::*;
+pub use machine::*;
+pub use pagetable::create_it_pud_cap;
+pub use pte::{pte_tag_t, PTEFlags};
+pub use structures::*;
+pub use utils::*;
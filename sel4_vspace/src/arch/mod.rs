@@ -10,6 +10,12 @@ pub mod aarch64;
 #[cfg(target_arch = "aarch64")]
 pub use aarch64::*;
 
+#[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+pub mod host;
+
+#[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+pub use host::*;
+
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(target_arch = "riscv64", allow(dead_code))]
 pub struct VAddr(usize);
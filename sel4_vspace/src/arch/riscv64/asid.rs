@@ -127,4 +127,7 @@ pub fn hwASIDFlush(asid: asid_t) {
     unsafe {
         asm!("sfence.vma x0, {0}",in(reg) asid);
     }
+    // a VSpace is also the G-stage tables of the guests running in it
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    super::satp::hfence_gvma();
 }
@@ -1,5 +1,5 @@
 use super::{device::KDEV_BASE, utils::RISCV_GET_LVL_PGSIZE_BITS};
-use crate::arch::riscv64::pagetable::{KERNEL_LEVEL2_PAGE_TABLE, KERNEL_ROOT_PAGE_TABLE};
+use crate::arch::riscv64::pagetable::{KERNEL_DEV_LEVEL2_PAGE_TABLE, KERNEL_ROOT_PAGE_TABLE};
 use crate::{pptr_t, pptr_to_paddr, sfence, PTEFlags, PTE, RISCV_GET_PT_INDEX};
 use sel4_common::{
     arch::vm_rights_t,
@@ -15,7 +15,7 @@ pub fn map_kernel_frame(paddr: usize, vaddr: usize, _vm_rights: vm_rights_t) {
     if vaddr >= KDEV_BASE {
         let paddr = ROUND_DOWN!(paddr, RISCV_GET_LVL_PGSIZE_BITS(1));
         unsafe {
-            KERNEL_LEVEL2_PAGE_TABLE.map_next_table(RISCV_GET_PT_INDEX(vaddr, 0), paddr, true);
+            KERNEL_DEV_LEVEL2_PAGE_TABLE.map_next_table(RISCV_GET_PT_INDEX(vaddr, 1), paddr, true);
         }
     } else {
         let paddr = ROUND_DOWN!(paddr, RISCV_GET_LVL_PGSIZE_BITS(0));
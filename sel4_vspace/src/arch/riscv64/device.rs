@@ -1,10 +1,19 @@
 use super::boot::map_kernel_frame;
+use super::RISCV_GET_LVL_PGSIZE;
 use crate::{paddr_t, pptr_t};
 use sel4_common::arch::vm_rights_t::VMKernelOnly;
-use sel4_common::{sel4_config::PAGE_BITS, BIT};
 
 pub const KDEV_BASE: usize = 0xFFFFFFFFC0000000;
+#[cfg(not(feature = "PLAT_QEMU_RISCV_VIRT"))]
 pub(crate) const NUM_KERNEL_DEVICE_FRAMES: usize = 0;
+/// The PLIC takes two megapages: the priorities and enables, then the contexts. The UART takes
+/// the third one.
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+pub(crate) const NUM_KERNEL_DEVICE_FRAMES: usize = 3;
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+const NUM_PLIC_FRAMES: usize = 2;
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+pub const UART_PPTR: usize = KDEV_BASE + 0x400000;
 #[derive(Copy, Clone)]
 struct kernel_frame_t {
     paddr: paddr_t,
@@ -23,8 +32,50 @@ extern "C" {
 }
 
 #[no_mangle]
-#[link_section = ".boot.text"]
+#[cfg(not(feature = "PLAT_QEMU_RISCV_VIRT"))]
 pub(self) static mut kernel_device_frames: [kernel_frame_t; NUM_KERNEL_DEVICE_FRAMES] = [];
+#[no_mangle]
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+pub(self) static mut kernel_device_frames: [kernel_frame_t; NUM_KERNEL_DEVICE_FRAMES] = [
+    kernel_frame_t {
+        paddr: paddr_t(0xc000000),
+        pptr: KDEV_BASE,
+        userAvailable: 0,
+    },
+    kernel_frame_t {
+        paddr: paddr_t(0xc200000),
+        pptr: KDEV_BASE + 0x200000,
+        userAvailable: 0,
+    },
+    // the virtio devices share the megapage of the UART, the user keeps them
+    kernel_frame_t {
+        paddr: paddr_t(0x10000000),
+        pptr: UART_PPTR,
+        userAvailable: 1,
+    },
+];
+
+/// Place the PLIC frames at the address found in the device tree, before they are mapped.
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+pub fn set_plic_paddr(paddr: usize) {
+    for i in 0..NUM_PLIC_FRAMES {
+        unsafe {
+            kernel_device_frames[i].paddr = paddr_t(paddr + i * RISCV_GET_LVL_PGSIZE(1));
+        }
+    }
+}
+
+/// Place the UART frame at the address found in the device tree, before it is mapped, and get
+/// the kernel address of the UART.
+#[cfg(feature = "PLAT_QEMU_RISCV_VIRT")]
+pub fn set_uart_paddr(paddr: usize) -> usize {
+    let offset = paddr & (RISCV_GET_LVL_PGSIZE(1) - 1);
+    unsafe {
+        kernel_device_frames[NUM_PLIC_FRAMES].paddr = paddr_t(paddr - offset);
+    }
+    UART_PPTR + offset
+}
+
 #[no_mangle]
 pub fn map_kernel_devices() {
     unsafe {
@@ -33,7 +84,7 @@ pub fn map_kernel_devices() {
             if kernel_frame.userAvailable == 0 {
                 reserve_region(p_region_t {
                     start: kernel_frame.paddr.0,
-                    end: kernel_frame.paddr.0 + BIT!(PAGE_BITS),
+                    end: kernel_frame.paddr.0 + RISCV_GET_LVL_PGSIZE(1),
                 });
             }
         }
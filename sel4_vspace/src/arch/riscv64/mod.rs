@@ -12,9 +12,12 @@ pub use boot::*;
 pub use device::*;
 pub use interface::{set_vm_root, unmap_page_table};
 pub use pagetable::{
-    activate_kernel_vspace, copyGlobalMappings, rust_map_kernel_window, unmapPage,
+    activate_kernel_vspace, copyGlobalMappings, map_kernel_log_frame, rust_map_kernel_window,
+    unmapPage,
 };
 pub use pte::PTEFlags;
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+pub use satp::{hfence_gvma, hfence_vvma};
 pub use satp::{setVSpaceRoot, sfence};
 pub use structures::*;
 pub use utils::*;
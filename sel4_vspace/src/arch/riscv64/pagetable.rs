@@ -14,7 +14,8 @@ use sel4_common::{
 };
 
 use super::{
-    kpptr_to_paddr, map_kernel_devices, setVSpaceRoot,
+    device::KDEV_BASE,
+    kpptr_to_paddr, setVSpaceRoot,
     utils::{RISCV_GET_LVL_PGSIZE_BITS, RISCV_GET_PT_INDEX},
     RISCV_GET_LVL_PGSIZE,
 };
@@ -31,8 +32,15 @@ pub(crate) static mut kernel_root_pageTable: [PTE; BIT!(PT_INDEX_BITS)] =
 pub(crate) static mut kernel_image_level2_pt: [PTE; BIT!(PT_INDEX_BITS)] =
     [PTE::pte_invalid(); BIT!(PT_INDEX_BITS)];
 
+///内核设备使用的二级页表，映射`KDEV_BASE`起的`1GB`
+#[no_mangle]
+#[link_section = ".page_table"]
+pub(crate) static mut kernel_image_level2_dev_pt: [PTE; BIT!(PT_INDEX_BITS)] =
+    [PTE::pte_invalid(); BIT!(PT_INDEX_BITS)];
+
 pub(crate) static mut KERNEL_ROOT_PAGE_TABLE: PageTable = PageTable::new(paddr_t(0));
 pub(crate) static mut KERNEL_LEVEL2_PAGE_TABLE: PageTable = PageTable::new(paddr_t(0));
+pub(crate) static mut KERNEL_DEV_LEVEL2_PAGE_TABLE: PageTable = PageTable::new(paddr_t(0));
 
 impl PageTable {
     pub(crate) const PTE_NUM_IN_PAGE: usize = 0x200;
@@ -84,11 +92,16 @@ impl PageTable {
 ///                      virtual address space                          physical address space
 /// ```
 ///
+/// 内核设备不在这里映射，启动代码读完设备树后再调用`map_kernel_devices`
+///
+/// The kernel devices are not mapped here, the boot code maps them with `map_kernel_devices`
+/// once it has read the device tree.
 #[no_mangle]
 pub fn rust_map_kernel_window() {
     unsafe {
         KERNEL_ROOT_PAGE_TABLE.set(kernel_root_pageTable.as_ptr() as usize);
         KERNEL_LEVEL2_PAGE_TABLE.set(kernel_image_level2_pt.as_ptr() as usize);
+        KERNEL_DEV_LEVEL2_PAGE_TABLE.set(kernel_image_level2_dev_pt.as_ptr() as usize);
     }
 
     // 物理地址到内核地址空间的直接映射，用`1GB`大页的方式映射
@@ -127,7 +140,14 @@ pub fn rust_map_kernel_window() {
         paddr += RISCV_GET_LVL_PGSIZE(1);
         index += 1;
     }
-    map_kernel_devices();
+    // 内核设备以`2MB`大页的方式映射在`kernel_image_level2_dev_pt`中
+    unsafe {
+        KERNEL_ROOT_PAGE_TABLE.map_next_table(
+            RISCV_GET_PT_INDEX(KDEV_BASE, 0),
+            kpptr_to_paddr(KERNEL_DEV_LEVEL2_PAGE_TABLE.base()),
+            false,
+        );
+    }
 }
 
 /// 激活内核页表，将`satp`的值设置为内核页表根页表地址
@@ -140,6 +160,17 @@ pub fn activate_kernel_vspace() {
     }
 }
 
+/// 将内核日志缓冲区的`2MB`大页映射到内核设备页表中的`vaddr`处
+///
+/// Map the megapage of the kernel log buffer at `vaddr` in the kernel device window.
+pub fn map_kernel_log_frame(paddr: usize, vaddr: usize) {
+    assert!(vaddr >= KDEV_BASE);
+    unsafe {
+        KERNEL_DEV_LEVEL2_PAGE_TABLE.map_next_table(RISCV_GET_PT_INDEX(vaddr, 1), paddr, true);
+    }
+    sfence();
+}
+
 /// 拷贝内核页表到新给出的页表基地址`Lvl1pt`，当创建一个进程的时候，会拷贝一个新的页表给新创建的进程，新的页表中包含内核地址空间
 ///
 /// Copy the whole kernel page table into a new page table.
@@ -21,19 +21,24 @@ impl satp_t {
 #[cfg(feature = "ENABLE_SMP")]
 #[inline]
 pub fn sfence() {
+    use sel4_common::arch::remote_sfence_vma;
     use sel4_common::smp::get_sbi_mask_for_all_remote_harts;
 
     unsafe {
         core::arch::asm!("fence w, rw");
     }
     sfence_local();
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    hfence_gvma();
     let mask = get_sbi_mask_for_all_remote_harts();
     remote_sfence_vma(mask, 0, 0);
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    sel4_common::arch::remote_hfence_gvma(mask);
 }
 
-#[cfg(feature = "ENABLE_SMP")]
 #[inline]
 pub fn sfence_local() {
+    #[cfg(target_arch = "riscv64")]
     unsafe {
         core::arch::asm!("sfence.vma");
     }
@@ -41,13 +46,40 @@ pub fn sfence_local() {
 
 ///对汇编指令`sfence.vma`的简单封装，清空`cache`、`tlb`
 ///
-/// Risc-v's sfence.vma
+/// Risc-v's sfence.vma, the user page tables are the G-stage tables of the guests as well
 #[cfg(not(feature = "ENABLE_SMP"))]
 #[inline]
 pub fn sfence() {
-    #[cfg(target_arch = "riscv64")]
+    sfence_local();
+    #[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+    hfence_gvma();
+}
+
+/// Flush the G-stage translations of all the VMIDs
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn hfence_gvma() {
     unsafe {
-        core::arch::asm!("sfence.vma");
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +h",
+            "hfence.gvma",
+            ".option pop"
+        );
+    }
+}
+
+/// Flush the VS-stage translations of the guest running on the VMID in hgatp
+#[cfg(feature = "RISCV_HYPERVISOR_SUPPORT")]
+#[inline]
+pub fn hfence_vvma() {
+    unsafe {
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +h",
+            "hfence.vvma",
+            ".option pop"
+        );
     }
 }
 
@@ -59,8 +91,5 @@ pub fn sfence() {
 pub fn setVSpaceRoot(addr: usize, asid: usize) {
     let satp = satp_t::new(8usize, asid, addr >> 12);
     satp::write(satp.words);
-    #[cfg(not(feature = "ENABLE_SMP"))]
-    sfence();
-    #[cfg(feature = "ENABLE_SMP")]
     sfence_local();
 }
@@ -15,6 +15,8 @@ mod utils;
 
 #[cfg(target_arch = "aarch64")]
 pub use arch::aarch64::*;
+#[cfg(not(any(target_arch = "riscv64", target_arch = "aarch64")))]
+pub use arch::host::*;
 #[cfg(target_arch = "riscv64")]
 pub use arch::riscv64::*;
 pub use arch::unmapPage;
@@ -27,7 +27,7 @@ pub struct findVSpaceForASID_ret {
     pub vspace_root: Option<*mut PTE>,
     pub lookup_fault: Option<lookup_fault_t>,
 }
-#[cfg(target_arch = "aarch64")]
+#[cfg(not(target_arch = "riscv64"))]
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct findVSpaceForASID_ret {
@@ -16,4 +16,24 @@ pub trait SerialDriver {
     fn putchar(&self, c: u8);
     /// Get a character from serial
     fn getchar(&self) -> Option<u8>;
+    /// Enable or disable the interrupt raised when a character is received.
+    fn set_rx_interrupt(&self, enable: bool);
+    /// Return true if the receive interrupt is pending.
+    fn is_rx_interrupt(&self) -> bool;
+    /// Clear the pending interrupts.
+    fn ack_interrupts(&self);
+    /// Return true if there is no received character to read.
+    fn rx_fifo_empty(&self) -> bool;
+    /// Return true if the transmit FIFO can not take another character.
+    fn tx_fifo_full(&self) -> bool;
+    /// Put as many characters of `buf` as the transmit FIFO takes without waiting, and return
+    /// how many were put.
+    fn write(&self, buf: &[u8]) -> usize {
+        let mut written = 0;
+        while written < buf.len() && !self.tx_fifo_full() {
+            self.putchar(buf[written]);
+            written += 1;
+        }
+        written
+    }
 }
@@ -0,0 +1,173 @@
+#![no_std]
+
+//! Types and definitions for the 8250/16550 UART family.
+//!
+//! It is the UART of QEMU riscv `virt` and of most SoCs. The registers are spaced by
+//! `1 << reg_shift` as the `reg-shift` property of the device tree gives it, and accessed as bytes
+//! or 32-bit words as `reg-io-width` gives it.
+
+use core::ptr::{read_volatile, write_volatile, NonNull};
+
+use serial_frame::SerialDriver;
+
+/// Receiver Buffer Register when read, Transmitter Holding Register when written
+const RBR_THR: usize = 0;
+/// Interrupt Enable Register
+const IER: usize = 1;
+/// Interrupt Identification Register when read, FIFO Control Register when written
+const IIR_FCR: usize = 2;
+/// Line Control Register
+const LCR: usize = 3;
+/// Modem Control Register
+const MCR: usize = 4;
+/// Line Status Register
+const LSR: usize = 5;
+
+/// Received Data Available interrupt, with the character timeout
+const IER_ERBFI: u8 = 1 << 0;
+/// No interrupt is pending
+const IIR_NO_INT: u8 = 1 << 0;
+/// The interrupt identification
+const IIR_ID_MASK: u8 = 0x0e;
+/// Received Data Available
+const IIR_RDA: u8 = 0x04;
+/// Character Timeout, fewer characters than the trigger level wait
+const IIR_CTI: u8 = 0x0c;
+/// Receiver Line Status, cleared by reading LSR
+const IIR_RLS: u8 = 0x06;
+/// Enable the FIFOs and clear them
+const FCR_FIFO_ENABLE_CLEAR: u8 = 0x07;
+/// 8 bits, no parity, one stop bit
+const LCR_8N1: u8 = 0x03;
+/// DTR, RTS and OUT2, which gates the interrupt line on PCs
+const MCR_DTR_RTS_OUT2: u8 = 0x0b;
+/// Data Ready
+const LSR_DR: u8 = 1 << 0;
+/// Transmitter Holding Register Empty, the whole transmit FIFO is empty
+const LSR_THRE: u8 = 1 << 5;
+/// The depth of the transmit FIFO of a 16550
+const TX_FIFO_DEPTH: usize = 16;
+
+/// The NS16550 Uart
+///
+/// The NS16550 Uart provides a programing interface for:
+/// 1. Construct a new NS16550 UART instance
+/// 2. Initialize the NS16550 UART
+/// 3. Read a char from the UART
+/// 4. Write a char to the UART
+/// 5. Handle a UART IRQ
+pub struct Ns16550Uart {
+    base: NonNull<u8>,
+    reg_shift: usize,
+    reg_io_width: usize,
+}
+
+unsafe impl Send for Ns16550Uart {}
+unsafe impl Sync for Ns16550Uart {}
+
+impl Ns16550Uart {
+    /// The widths in bytes of the register accesses the driver does
+    pub const REG_IO_WIDTHS: &'static [usize] = &[1, 4];
+
+    /// Construct a new NS16550 UART instance whose registers are `1 << reg_shift` bytes apart and
+    /// accessed `reg_io_width` bytes at a time, one of [Self::REG_IO_WIDTHS].
+    pub const fn with_reg_layout(
+        addr: NonNull<usize>,
+        reg_shift: usize,
+        reg_io_width: usize,
+    ) -> Self {
+        assert!(matches!(reg_io_width, 1 | 4));
+        Self {
+            base: addr.cast(),
+            reg_shift,
+            reg_io_width,
+        }
+    }
+
+    fn read_reg(&self, reg: usize) -> u8 {
+        let addr = unsafe { self.base.as_ptr().add(reg << self.reg_shift) };
+        match self.reg_io_width {
+            4 => unsafe { read_volatile(addr as *const u32) as u8 },
+            _ => unsafe { read_volatile(addr) },
+        }
+    }
+
+    fn write_reg(&self, reg: usize, value: u8) {
+        let addr = unsafe { self.base.as_ptr().add(reg << self.reg_shift) };
+        match self.reg_io_width {
+            4 => unsafe { write_volatile(addr as *mut u32, value as u32) },
+            _ => unsafe { write_volatile(addr, value) },
+        }
+    }
+}
+
+impl SerialDriver for Ns16550Uart {
+    /// Construct a new NS16550 UART instance from the base address, with byte registers.
+    fn new(addr: NonNull<usize>) -> Self {
+        Self::with_reg_layout(addr, 0, 1)
+    }
+
+    /// Initializes the NS16550 UART.
+    ///
+    /// It disables the interrupts, sets 8N1 and enables the FIFOs. The baud rate is left as the
+    /// firmware set it.
+    fn init(&self) {
+        self.write_reg(IER, 0);
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(IIR_FCR, FCR_FIFO_ENABLE_CLEAR);
+        self.write_reg(MCR, MCR_DTR_RTS_OUT2);
+    }
+
+    /// Output a char c to the transmitter holding register
+    fn putchar(&self, c: u8) {
+        while self.tx_fifo_full() {}
+        self.write_reg(RBR_THR, c);
+    }
+
+    /// Return a byte if the UART has received one, or it will return `None`.
+    fn getchar(&self) -> Option<u8> {
+        if !self.rx_fifo_empty() {
+            Some(self.read_reg(RBR_THR))
+        } else {
+            None
+        }
+    }
+
+    fn set_rx_interrupt(&self, enable: bool) {
+        self.write_reg(IER, if enable { IER_ERBFI } else { 0 });
+    }
+
+    fn is_rx_interrupt(&self) -> bool {
+        let iir = self.read_reg(IIR_FCR);
+        iir & IIR_NO_INT == 0 && matches!(iir & IIR_ID_MASK, IIR_RDA | IIR_CTI)
+    }
+
+    /// The receive interrupts clear as the characters are read, a line status interrupt clears by
+    /// reading LSR.
+    fn ack_interrupts(&self) {
+        if self.read_reg(IIR_FCR) & IIR_ID_MASK == IIR_RLS {
+            self.read_reg(LSR);
+        }
+    }
+
+    fn rx_fifo_empty(&self) -> bool {
+        self.read_reg(LSR) & LSR_DR == 0
+    }
+
+    /// The 16550 only tells when the transmit FIFO is empty, so it is full otherwise.
+    fn tx_fifo_full(&self) -> bool {
+        self.read_reg(LSR) & LSR_THRE == 0
+    }
+
+    /// Fill the whole transmit FIFO once it is empty.
+    fn write(&self, buf: &[u8]) -> usize {
+        if self.tx_fifo_full() {
+            return 0;
+        }
+        let written = buf.len().min(TX_FIFO_DEPTH);
+        for &c in &buf[..written] {
+            self.write_reg(RBR_THR, c);
+        }
+        written
+    }
+}
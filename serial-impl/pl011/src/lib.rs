@@ -40,6 +40,15 @@ register_structs! {
     }
 }
 
+/// Receive interrupt, raised at the FIFO trigger level
+const RXIM: u32 = 1 << 4;
+/// Receive timeout interrupt, raised when fewer characters than the trigger level wait
+const RTIM: u32 = 1 << 6;
+/// Receive FIFO empty
+const FR_RXFE: u32 = 1 << 4;
+/// Transmit FIFO full
+const FR_TXFF: u32 = 1 << 5;
+
 /// The Pl011 Uart
 ///
 /// The Pl011 Uart provides a programing interface for:
@@ -64,25 +73,20 @@ impl Pl011Uart {
     /// Return true if pl011 has received an interrupt
     pub fn is_receive_interrupt(&self) -> bool {
         let pending = self.regs().mis.get();
-        pending & (1 << 4) != 0
-    }
-
-    /// Clear all interrupts
-    pub fn ack_interrupts(&mut self) {
-        self.regs().icr.set(0x7ff);
+        pending & (RXIM | RTIM) != 0
     }
 }
 
 impl SerialDriver for Pl011Uart {
     /// Output a char c to data register
     fn putchar(&self, c: u8) {
-        while self.regs().fr.get() & (1 << 5) != 0 {}
+        while self.tx_fifo_full() {}
         self.regs().dr.set(c as u32);
     }
 
     /// Return a byte if pl011 has received, or it will return `None`.
     fn getchar(&self) -> Option<u8> {
-        if self.regs().fr.get() & (1 << 4) == 0 {
+        if !self.rx_fifo_empty() {
             Some(self.regs().dr.get() as u8)
         } else {
             None
@@ -105,9 +109,31 @@ impl SerialDriver for Pl011Uart {
         self.regs().ifls.set(0); // 1/8 rxfifo, 1/8 txfifo.
 
         // enable rx interrupt
-        self.regs().imsc.set(1 << 4); // rxim
+        self.regs().imsc.set(RXIM);
 
         // enable receive
         self.regs().cr.set((1 << 0) | (1 << 8) | (1 << 9)); // tx enable, rx enable, uart enable
     }
+
+    /// Enable or disable the receive and receive timeout interrupts.
+    fn set_rx_interrupt(&self, enable: bool) {
+        self.regs().imsc.set(if enable { RXIM | RTIM } else { 0 });
+    }
+
+    fn is_rx_interrupt(&self) -> bool {
+        self.is_receive_interrupt()
+    }
+
+    /// Clear all interrupts
+    fn ack_interrupts(&self) {
+        self.regs().icr.set(0x7ff);
+    }
+
+    fn rx_fifo_empty(&self) -> bool {
+        self.regs().fr.get() & FR_RXFE != 0
+    }
+
+    fn tx_fifo_full(&self) -> bool {
+        self.regs().fr.get() & FR_TXFF != 0
+    }
 }
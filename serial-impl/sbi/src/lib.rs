@@ -49,4 +49,24 @@ impl SerialDriver for SerialSBI {
             None
         }
     }
+
+    /// The legacy SBI console has no interrupt.
+    fn set_rx_interrupt(&self, _enable: bool) {}
+
+    fn is_rx_interrupt(&self) -> bool {
+        false
+    }
+
+    fn ack_interrupts(&self) {}
+
+    /// The legacy SBI console can not tell without taking the character, so the callers always
+    /// try [SerialDriver::getchar].
+    fn rx_fifo_empty(&self) -> bool {
+        false
+    }
+
+    /// The SBI firmware waits for the UART itself.
+    fn tx_fifo_full(&self) -> bool {
+        false
+    }
 }